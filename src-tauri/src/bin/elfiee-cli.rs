@@ -0,0 +1,776 @@
+//! Command-line tools for .elf projects (no GUI).
+//!
+//! Usage:
+//!   elfiee-cli fsck <file.elf> [--repair] [--editor <id>] [--json] [--key-file <path>]
+//!   elfiee-cli verify <file.elf> [--json] [--keys <dir>] [--key-file <path>]
+//!   elfiee-cli encrypt <file.elf> [--key-file <path>] [--new-key-file <path>]
+//!   elfiee-cli decrypt <file.elf> [--key-file <path>]
+//!   elfiee-cli relay <file.elf> [--listen <addr>] [--editor <id>] [--key-file <path>]
+//!   elfiee-cli merge <ours.elf> <theirs.elf> -o <merged.elf> [--prefer ours|theirs] [--editor <id>] [--keys <dir>] [--json] [--key-file <path>]
+//!   elfiee-cli export-site <file.elf> -o <dir> [--editor <id>] [--key-file <path>]
+//!   elfiee-cli export-git <file.elf> -o <dir> [--editor <id>] [--key-file <path>]
+//!   elfiee-cli import-git <repo> -o <new.elf> [--editor <id>]
+//!
+//! Encrypted files ask for their passphrase unless `--key-file` is given;
+//! `ELFIEE_PASSPHRASE` / `ELFIEE_NEW_PASSPHRASE` skip the prompts.
+//! `relay` asks for the sync secret unless `ELFIEE_SYNC_SECRET` is set.
+
+use elfiee_lib::config;
+use elfiee_lib::elf::{
+    encryption, merge, ArchiveKey, ConflictKind, ElfArchive, FsckReport, MergeReport, MergeSide,
+};
+use elfiee_lib::engine::{spawn_engine, verify_events, EventStore, KeyStore, VerifyReport};
+use elfiee_lib::export;
+use elfiee_lib::sync;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: elfiee-cli <command> [options]
+
+Commands:
+  fsck <file.elf>    Check a project for integrity problems
+      --repair       Fix the problems found and save the file
+      --editor <id>  Editor that commits repair events (default: system editor)
+      --json         Print the report as JSON
+  verify <file.elf>  Check the hash chain and signatures of signed events
+      --json         Print the report as JSON
+      --keys <dir>   Directory of trusted editor keys (default: ~/.elf/keys)
+  encrypt <file.elf> Encrypt a project, or change its passphrase or key file
+      --new-key-file <path>  Encrypt with a key file instead of a new passphrase
+  decrypt <file.elf> Remove the encryption of a project
+  relay <file.elf>   Relay sync sessions between running instances through a file
+      --listen <addr>        Address to listen on (default: 127.0.0.1:47300)
+      --editor <id>          Editor that signs received events (default: system editor)
+                             Peers must know the sync secret, which is prompted
+                             for or read from ELFIEE_SYNC_SECRET
+  merge <ours.elf> <theirs.elf>
+                     Merge two copies of a project into a new file
+      -o, --output <path>    File to write the merged project to
+      --prefer <side>        Copy whose writes win conflicts: ours (default) or theirs
+      --editor <id>          Editor that resolves conflicts and signs merged events
+                             (default: system editor)
+      --keys <dir>           Directory of editor keys (default: ~/.elf/keys)
+      --json                 Print the report as JSON
+  export-site <file.elf>
+                     Export a project as a static HTML site
+      -o, --output <dir>     Directory to write the site into
+      --editor <id>          Editor whose read permissions apply (default: system editor)
+  export-git <file.elf>
+                     Export the history of a project as a git repository
+      -o, --output <dir>     Directory to create the repository in (must be empty)
+      --editor <id>          Editor whose read permissions apply (default: system editor)
+  import-git <repo>  Import the history of a git repository into a new project
+      -o, --output <path>    File to write the new project to
+      --editor <id>          Editor that owns the project (default: system editor)
+
+Options for encrypted projects:
+      --key-file <path>      Key file the project is encrypted with
+                             (otherwise the passphrase is prompted for,
+                             or read from ELFIEE_PASSPHRASE)";
+
+/// Environment variable holding the passphrase of an encrypted project
+const PASSPHRASE_ENV: &str = "ELFIEE_PASSPHRASE";
+/// Environment variable holding the new passphrase for `encrypt`
+const NEW_PASSPHRASE_ENV: &str = "ELFIEE_NEW_PASSPHRASE";
+/// Environment variable holding the sync secret for `relay`
+const SYNC_SECRET_ENV: &str = "ELFIEE_SYNC_SECRET";
+/// Address `relay` listens on by default
+const DEFAULT_RELAY_ADDR: &str = "127.0.0.1:47300";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("fsck") => fsck(&args[1..]).await,
+        Some("verify") => verify(&args[1..]).await,
+        Some("encrypt") => encrypt(&args[1..]).await,
+        Some("decrypt") => decrypt(&args[1..]).await,
+        Some("relay") => relay(&args[1..]).await,
+        Some("merge") => merge(&args[1..]).await,
+        Some("export-site") => export_site(&args[1..]).await,
+        Some("export-git") => export_git(&args[1..]).await,
+        Some("import-git") => import_git(&args[1..]).await,
+        Some("-h") | Some("--help") | None => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Some(other) => Err(format!("Unknown command '{}'\n\n{}", other, USAGE)),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// `fsck`: exits with 0 if the project is clean (or was repaired), 1 if problems remain.
+async fn fsck(args: &[String]) -> Result<ExitCode, String> {
+    let mut path: Option<PathBuf> = None;
+    let mut repair = false;
+    let mut json = false;
+    let mut editor_id: Option<String> = None;
+    let mut key_file: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--repair" => repair = true,
+            "--json" => json = true,
+            "--editor" => {
+                editor_id = Some(iter.next().ok_or("--editor requires an editor id")?.clone())
+            }
+            "--key-file" => key_file = Some(key_file_arg(iter.next())?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option '{}'", flag)),
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("fsck requires a .elf file\n\n{}", USAGE))?;
+    let editor_id = match editor_id {
+        Some(id) => id,
+        None => config::get_system_editor_id()?,
+    };
+
+    let archive = open_archive(&path, key_file)?;
+    let event_pool = archive
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to open event store: {}", e))?;
+    let handle = spawn_engine(path.display().to_string(), event_pool).await?;
+
+    let report = handle.check_integrity(repair, editor_id).await?;
+    handle.shutdown().await;
+
+    if report.repaired && !report.is_clean() {
+        archive
+            .save(&path)
+            .await
+            .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+        );
+    } else {
+        print_report(&path, &report);
+    }
+
+    if report.is_clean() || report.repaired {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+fn print_report(path: &std::path::Path, report: &FsckReport) {
+    if report.is_clean() {
+        println!("{}: clean", path.display());
+        return;
+    }
+
+    println!("{}: {} problem(s)", path.display(), report.issues.len());
+    for (category, issues) in report.by_category() {
+        println!("\n{} ({}):", category.label(), issues.len());
+        for issue in issues {
+            println!("  {}: {}", issue.subject, issue.detail);
+        }
+    }
+
+    if report.repaired {
+        println!(
+            "\nrepaired ({} corrective event(s) committed)",
+            report.repair_events
+        );
+    } else {
+        println!("\nrun with --repair to fix");
+    }
+}
+
+/// `verify`: exits with 0 if every signed event checks out, 1 if any is broken.
+async fn verify(args: &[String]) -> Result<ExitCode, String> {
+    let mut path: Option<PathBuf> = None;
+    let mut json = false;
+    let mut keys_dir: Option<PathBuf> = None;
+    let mut key_file: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--keys" => {
+                keys_dir = Some(PathBuf::from(
+                    iter.next().ok_or("--keys requires a directory")?,
+                ))
+            }
+            "--key-file" => key_file = Some(key_file_arg(iter.next())?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option '{}'", flag)),
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("verify requires a .elf file\n\n{}", USAGE))?;
+    let keys = match keys_dir {
+        Some(dir) => KeyStore::new(dir),
+        None => KeyStore::default_location()?,
+    };
+
+    let archive = open_archive(&path, key_file)?;
+    let event_pool = archive
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to open event store: {}", e))?;
+    let events = EventStore::get_all_events(&event_pool.pool)
+        .await
+        .map_err(|e| format!("Failed to load events: {}", e))?;
+    let report = verify_events(&events, &keys);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+        );
+    } else {
+        print_verify_report(&path, &report);
+    }
+
+    if report.is_valid() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+fn print_verify_report(path: &std::path::Path, report: &VerifyReport) {
+    if report.signed_events == 0 {
+        println!("{}: no signed events", path.display());
+        return;
+    }
+    if report.is_valid() {
+        println!(
+            "{}: {} of {} event(s) signed, chain intact",
+            path.display(),
+            report.signed_events,
+            report.total_events
+        );
+        return;
+    }
+
+    println!(
+        "{}: {} broken event(s)",
+        path.display(),
+        report.issues.len()
+    );
+    for issue in &report.issues {
+        println!(
+            "  #{} {} ({:?}): {}",
+            issue.index, issue.event_id, issue.kind, issue.detail
+        );
+    }
+}
+
+/// `encrypt`: encrypts a project, or re-encrypts it with a new passphrase or key file.
+async fn encrypt(args: &[String]) -> Result<ExitCode, String> {
+    let mut path: Option<PathBuf> = None;
+    let mut key_file: Option<PathBuf> = None;
+    let mut new_key_file: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--key-file" => key_file = Some(key_file_arg(iter.next())?),
+            "--new-key-file" => new_key_file = Some(key_file_arg(iter.next())?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option '{}'", flag)),
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("encrypt requires a .elf file\n\n{}", USAGE))?;
+
+    let archive = open_archive(&path, key_file)?;
+    let new_key = match new_key_file {
+        Some(file) => ArchiveKey::KeyFile(file),
+        None => ArchiveKey::Passphrase(read_new_passphrase()?),
+    };
+
+    archive.set_encryption(Some(new_key));
+    archive
+        .save(&path)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+    println!("{}: encrypted", path.display());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `decrypt`: stores an encrypted project as a plain archive again.
+async fn decrypt(args: &[String]) -> Result<ExitCode, String> {
+    let mut path: Option<PathBuf> = None;
+    let mut key_file: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--key-file" => key_file = Some(key_file_arg(iter.next())?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option '{}'", flag)),
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("decrypt requires a .elf file\n\n{}", USAGE))?;
+
+    let archive = open_archive(&path, key_file)?;
+    if !archive.is_encrypted() {
+        return Err(format!("{} is not encrypted", path.display()));
+    }
+
+    archive.set_encryption(None);
+    archive
+        .save(&path)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+    println!("{}: decrypted", path.display());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `relay`: serves sync sessions until interrupted, saving the file after each
+/// session that brought in new events.
+async fn relay(args: &[String]) -> Result<ExitCode, String> {
+    let mut path: Option<PathBuf> = None;
+    let mut listen = DEFAULT_RELAY_ADDR.to_string();
+    let mut editor: Option<String> = None;
+    let mut key_file: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--listen" => listen = iter.next().ok_or("--listen requires an address")?.clone(),
+            "--editor" => {
+                editor = Some(iter.next().ok_or("--editor requires an editor id")?.clone())
+            }
+            "--key-file" => key_file = Some(key_file_arg(iter.next())?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option '{}'", flag)),
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("relay requires a .elf file\n\n{}", USAGE))?;
+    let editor = match editor {
+        Some(editor) => editor,
+        None => config::get_system_editor_id()?,
+    };
+
+    let archive = open_archive(&path, key_file)?;
+    let event_pool = archive
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to open event store: {}", e))?;
+    let handle = spawn_engine(path.display().to_string(), event_pool).await?;
+    let secret = read_passphrase("Sync secret: ", SYNC_SECRET_ENV)?;
+    if secret.chars().count() < sync::MIN_SECRET_LEN {
+        return Err(format!(
+            "The sync secret must be at least {} characters",
+            sync::MIN_SECRET_LEN
+        ));
+    }
+
+    let listener = tokio::net::TcpListener::bind(&listen)
+        .await
+        .map_err(|e| format!("Failed to listen on {}: {}", listen, e))?;
+    println!("{}: relaying on {}", path.display(), listen);
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(connection) => connection,
+                Err(e) => {
+                    eprintln!("error: failed to accept connection: {}", e);
+                    continue;
+                }
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        };
+
+        match sync::sync_session(&handle, stream, &secret, &editor).await {
+            Ok(report) => {
+                println!(
+                    "{}: sent {}, applied {} of {}, rejected {}",
+                    peer,
+                    report.sent,
+                    report.applied,
+                    report.received,
+                    report.rejected.len()
+                );
+                for rejected in &report.rejected {
+                    println!("  rejected {}: {}", rejected.event_id, rejected.reason);
+                }
+                if report.applied > 0 {
+                    archive
+                        .save(&path)
+                        .await
+                        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+                }
+            }
+            Err(e) => eprintln!("error: sync with {} failed: {}", peer, e),
+        }
+    }
+
+    handle.shutdown().await;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `merge`: exits with 0 if the copies merged cleanly, 1 if conflicts were resolved.
+async fn merge(args: &[String]) -> Result<ExitCode, String> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut output: Option<PathBuf> = None;
+    let mut prefer = MergeSide::Ours;
+    let mut editor_id: Option<String> = None;
+    let mut keys_dir: Option<PathBuf> = None;
+    let mut json = false;
+    let mut key_file: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(
+                    iter.next().ok_or("--output requires a path")?,
+                ))
+            }
+            "--prefer" => {
+                prefer = match iter.next().map(String::as_str) {
+                    Some("ours") => MergeSide::Ours,
+                    Some("theirs") => MergeSide::Theirs,
+                    _ => return Err("--prefer must be 'ours' or 'theirs'".to_string()),
+                }
+            }
+            "--editor" => {
+                editor_id = Some(iter.next().ok_or("--editor requires an editor id")?.clone())
+            }
+            "--keys" => {
+                keys_dir = Some(PathBuf::from(
+                    iter.next().ok_or("--keys requires a directory")?,
+                ))
+            }
+            "--json" => json = true,
+            "--key-file" => key_file = Some(key_file_arg(iter.next())?),
+            flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+            file if paths.len() < 2 => paths.push(PathBuf::from(file)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let [ours_path, theirs_path] = <[PathBuf; 2]>::try_from(paths)
+        .map_err(|_| format!("merge requires two .elf files\n\n{}", USAGE))?;
+    let output = output.ok_or_else(|| format!("merge requires --output\n\n{}", USAGE))?;
+    if output.exists() {
+        return Err(format!("{} already exists", output.display()));
+    }
+
+    let editor_id = match editor_id {
+        Some(editor_id) => editor_id,
+        None => config::get_system_editor_id()?,
+    };
+    let keys = match keys_dir {
+        Some(dir) => KeyStore::new(dir),
+        None => KeyStore::default_location()?,
+    };
+
+    let ours = open_archive(&ours_path, key_file.clone())?;
+    let theirs = open_archive(&theirs_path, key_file)?;
+    let (merged, report) = merge::merge_archives(&ours, &theirs, prefer, &editor_id, &keys).await?;
+    merged.set_encryption(ours.encryption_key());
+    merged
+        .save(&output)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", output.display(), e))?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+        );
+    } else {
+        print_merge_report(&output, &report);
+    }
+
+    if report.conflicts.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+fn print_merge_report(path: &Path, report: &MergeReport) {
+    println!(
+        "{}: {} shared event(s), {} from ours, {} from theirs",
+        path.display(),
+        report.common_events,
+        report.ours_events,
+        report.theirs_events
+    );
+    if !report.rejected.is_empty() {
+        println!("\nrejected ({}):", report.rejected.len());
+        for rejected in &report.rejected {
+            println!("  {}: {}", rejected.event_id, rejected.reason);
+        }
+    }
+    if !report.conflicts.is_empty() {
+        println!("\nconflicts ({}):", report.conflicts.len());
+    }
+    for conflict in &report.conflicts {
+        let kind = match conflict.kind {
+            ConflictKind::Write => "written on both sides",
+            ConflictKind::DeleteWrite => "deleted on one side, written on the other",
+        };
+        println!(
+            "  {} {} ({}): kept {:?}",
+            conflict.entity, conflict.capability, kind, conflict.winner
+        );
+    }
+    if report.resolution_events > 0 {
+        println!(
+            "\n{} resolution event(s) committed",
+            report.resolution_events
+        );
+    }
+    if report.relinked_events > 0 {
+        println!(
+            "{} event(s) signed into the merged chain",
+            report.relinked_events
+        );
+    }
+}
+
+/// `export-site`: writes the HTML site; blocks the editor may not read are left out.
+async fn export_site(args: &[String]) -> Result<ExitCode, String> {
+    let mut path: Option<PathBuf> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut editor_id: Option<String> = None;
+    let mut key_file: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(
+                    iter.next().ok_or("--output requires a path")?,
+                ))
+            }
+            "--editor" => {
+                editor_id = Some(iter.next().ok_or("--editor requires an editor id")?.clone())
+            }
+            "--key-file" => key_file = Some(key_file_arg(iter.next())?),
+            flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("export-site requires a .elf file\n\n{}", USAGE))?;
+    let output = output.ok_or_else(|| format!("export-site requires --output\n\n{}", USAGE))?;
+    let editor_id = match editor_id {
+        Some(id) => id,
+        None => config::get_system_editor_id()?,
+    };
+
+    let archive = open_archive(&path, key_file)?;
+    let event_pool = archive
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to open event store: {}", e))?;
+    let handle = spawn_engine(path.display().to_string(), event_pool).await?;
+    let report = export::export_site(&handle, &editor_id, &output).await;
+    handle.shutdown().await;
+    let report = report?;
+
+    println!(
+        "{}: exported {} block(s) to {}",
+        path.display(),
+        report.blocks,
+        output.display()
+    );
+    if !report.skipped.is_empty() {
+        println!("\nleft out (no read permission):");
+        for name in &report.skipped {
+            println!("  {}", name);
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `export-git`: writes one commit per command into a new repository.
+async fn export_git(args: &[String]) -> Result<ExitCode, String> {
+    let mut path: Option<PathBuf> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut editor_id: Option<String> = None;
+    let mut key_file: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(
+                    iter.next().ok_or("--output requires a path")?,
+                ))
+            }
+            "--editor" => {
+                editor_id = Some(iter.next().ok_or("--editor requires an editor id")?.clone())
+            }
+            "--key-file" => key_file = Some(key_file_arg(iter.next())?),
+            flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("export-git requires a .elf file\n\n{}", USAGE))?;
+    let output = output.ok_or_else(|| format!("export-git requires --output\n\n{}", USAGE))?;
+    let editor_id = match editor_id {
+        Some(id) => id,
+        None => config::get_system_editor_id()?,
+    };
+
+    let archive = open_archive(&path, key_file)?;
+    let event_pool = archive
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to open event store: {}", e))?;
+    let handle = spawn_engine(path.display().to_string(), event_pool).await?;
+    let report = export::export_git(&handle, &editor_id, Some(archive.temp_path()), &output).await;
+    handle.shutdown().await;
+    let report = report?;
+
+    println!(
+        "{}: wrote {} commit(s), {} file(s) to {}",
+        path.display(),
+        report.commits,
+        report.files,
+        output.display()
+    );
+    if !report.skipped.is_empty() {
+        println!("\nleft out (no read permission):");
+        for name in &report.skipped {
+            println!("  {}", name);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `import-git`: replays the first-parent history of a repository into a new project.
+async fn import_git(args: &[String]) -> Result<ExitCode, String> {
+    let mut repo: Option<PathBuf> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut editor_id: Option<String> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(
+                    iter.next().ok_or("--output requires a path")?,
+                ))
+            }
+            "--editor" => {
+                editor_id = Some(iter.next().ok_or("--editor requires an editor id")?.clone())
+            }
+            flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+            dir if repo.is_none() => repo = Some(PathBuf::from(dir)),
+            extra => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    }
+    let repo = repo.ok_or_else(|| format!("import-git requires a repository\n\n{}", USAGE))?;
+    let output = output.ok_or_else(|| format!("import-git requires --output\n\n{}", USAGE))?;
+    if output.exists() {
+        return Err(format!("{} already exists", output.display()));
+    }
+    let editor_id = match editor_id {
+        Some(id) => id,
+        None => config::get_system_editor_id()?,
+    };
+
+    let archive = ElfArchive::new()
+        .await
+        .map_err(|e| format!("Failed to create project: {}", e))?;
+    let event_pool = archive
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to open event store: {}", e))?;
+    let handle = spawn_engine(output.display().to_string(), event_pool).await?;
+    let report = export::import_git(&handle, &repo, &editor_id).await;
+    handle.shutdown().await;
+    let report = report?;
+    archive
+        .save(&output)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", output.display(), e))?;
+
+    println!(
+        "{}: replayed {} commit(s) into {} block(s) in {}",
+        repo.display(),
+        report.commits,
+        report.blocks,
+        output.display()
+    );
+    if !report.skipped.is_empty() {
+        println!("\nleft out:");
+        for file in &report.skipped {
+            println!("  {}", file);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn key_file_arg(value: Option<&String>) -> Result<PathBuf, String> {
+    value
+        .map(PathBuf::from)
+        .ok_or_else(|| "--key-file requires a path".to_string())
+}
+
+/// Open a project, asking for its passphrase if it is encrypted and no key file was given.
+fn open_archive(path: &Path, key_file: Option<PathBuf>) -> Result<ElfArchive, String> {
+    let encrypted = encryption::is_encrypted_file(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let key = match key_file {
+        Some(file) => Some(ArchiveKey::KeyFile(file)),
+        None if encrypted => Some(ArchiveKey::Passphrase(read_passphrase(
+            &format!("Passphrase for {}: ", path.display()),
+            PASSPHRASE_ENV,
+        )?)),
+        None => None,
+    };
+
+    ElfArchive::open_with_key(path, key.as_ref())
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+/// Read a passphrase from `env`, or prompt for it without echo.
+fn read_passphrase(prompt: &str, env: &str) -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var(env) {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt).map_err(|e| format!("Failed to read passphrase: {}", e))
+}
+
+/// Read and confirm the new passphrase for `encrypt`.
+fn read_new_passphrase() -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var(NEW_PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    let prompt = |prompt: &str| {
+        rpassword::prompt_password(prompt).map_err(|e| format!("Failed to read passphrase: {}", e))
+    };
+    let passphrase = prompt("New passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".to_string());
+    }
+    if prompt("Repeat passphrase: ")? != passphrase {
+        return Err("Passphrases do not match".to_string());
+    }
+    Ok(passphrase)
+}
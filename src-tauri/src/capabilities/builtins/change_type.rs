@@ -22,6 +22,14 @@ fn handle_change_type(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Eve
         return Err("Block type cannot be empty".to_string());
     }
 
+    // Asset bytes live in the block directory, text lives in contents: no conversion
+    if (block.block_type == "asset") != (block_type == "asset") {
+        return Err(format!(
+            "Cannot change block type from '{}' to '{}': asset blocks hold binary files",
+            block.block_type, block_type
+        ));
+    }
+
     // Update timestamp
     let mut new_metadata = block.metadata.clone();
     new_metadata.touch();
@@ -65,6 +73,35 @@ mod tests {
         assert_eq!(events[0].value["block_type"], "code");
     }
 
+    #[test]
+    fn test_change_type_to_or_from_asset_fails() {
+        let block = Block::new(
+            "notes.md".to_string(),
+            "markdown".to_string(),
+            "alice".to_string(),
+        );
+        let cmd = Command::new(
+            "alice".to_string(),
+            "core.change_type".to_string(),
+            block.block_id.clone(),
+            serde_json::json!({ "block_type": "asset" }),
+        );
+        assert!(handle_change_type(&cmd, Some(&block)).is_err());
+
+        let asset = Block::new(
+            "mock.png".to_string(),
+            "asset".to_string(),
+            "alice".to_string(),
+        );
+        let cmd = Command::new(
+            "alice".to_string(),
+            "core.change_type".to_string(),
+            asset.block_id.clone(),
+            serde_json::json!({ "block_type": "code" }),
+        );
+        assert!(handle_change_type(&cmd, Some(&asset)).is_err());
+    }
+
     #[test]
     fn test_change_type_empty_fails() {
         let block = Block::new(
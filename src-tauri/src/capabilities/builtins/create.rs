@@ -8,6 +8,8 @@ use capability_macros::capability;
 /// Automatically generates created_at and updated_at timestamps.
 ///
 /// Note: The block parameter is None for create since the block doesn't exist yet.
+/// Authorization is project-level: the project owner, or editors granted
+/// core.create on the project.
 #[capability(id = "core.create", target = "core/*")]
 fn handle_create(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
     // Strongly-typed deserialization
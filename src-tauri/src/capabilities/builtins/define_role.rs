@@ -0,0 +1,39 @@
+use crate::capabilities::core::{create_event, CapResult};
+use crate::models::{Block, Command, DefineRolePayload, Event};
+use capability_macros::capability;
+
+/// Handler for core.define_role capability.
+///
+/// Creates a role or replaces an existing role's definition. Editors holding the
+/// role get the new capability set immediately.
+#[capability(id = "core.define_role", target = "core/*")]
+fn handle_define_role(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
+    // Strongly-typed deserialization
+    let payload: DefineRolePayload = serde_json::from_value(cmd.payload.clone())
+        .map_err(|e| format!("Invalid payload for core.define_role: {}", e))?;
+
+    if payload.name.trim().is_empty() {
+        return Err("Role name cannot be empty".to_string());
+    }
+    if payload.capabilities.is_empty() {
+        return Err(format!(
+            "Role '{}' must bundle at least one capability",
+            payload.name
+        ));
+    }
+
+    // Entity is the role name
+    let event = create_event(
+        payload.name.clone(),
+        "core.define_role",
+        serde_json::json!({
+            "name": payload.name,
+            "capabilities": payload.capabilities,
+            "scope": payload.scope,
+        }),
+        &cmd.editor_id,
+        1, // Placeholder - engine actor updates with correct count
+    );
+
+    Ok(vec![event])
+}
@@ -1,5 +1,5 @@
 use crate::capabilities::core::{create_event, CapResult};
-use crate::models::{Block, Command, Event};
+use crate::models::{Block, Command, DeleteBlockPayload, Event};
 use capability_macros::capability;
 
 /// Handler for core.delete capability.
@@ -14,16 +14,25 @@ use capability_macros::capability;
 /// - Complete history including deleted blocks can be reconstructed via event replay
 /// - Enables audit trails and recovery of deleted content
 ///
-/// ## StateProjector Layer (Hard Delete)
-/// - The block is removed from the in-memory active state (`blocks` HashMap)
-/// - Only current active blocks are kept in the projection for query efficiency
+/// ## StateProjector Layer (Trash)
+/// - The block is moved from the active state (`blocks`) into `trash`
+/// - The trash entry remembers its parents and directory entries
 /// - Deleted blocks do not appear in queries of current state
 ///
+/// ## Delete Modes
+/// The payload selects how references to the block are handled (see `DeleteMode`):
+/// - `detach` (default): only this block is deleted
+/// - `refuse`: fail if the block has children or directory entries point at it
+/// - `cascade`: also delete `implement` descendants up to `depth`
+///
+/// This handler only emits the event for the target block. The engine actor plans
+/// the delete against the current state and appends the cascaded `core.delete`,
+/// `core.unlink` and `directory.write` events, attaching the resulting
+/// `DeleteReport` to this event under `affected`.
+///
 /// ## Recovery
-/// To recover a deleted block:
-/// 1. Query Event Store for all events related to the block
-/// 2. Replay events in a temporary StateProjector up to the deletion event
-/// 3. Reconstruct the block's last state before deletion
+/// A trashed block is brought back with `core.restore`, which reattaches it to
+/// surviving parents and directories. `core.purge` removes it from the trash for good.
 ///
 /// This design separates concerns:
 /// - Event = single source of truth (permanently preserved)
@@ -32,12 +41,25 @@ use capability_macros::capability;
 fn handle_delete(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>> {
     let block = block.ok_or("Block required for core.delete")?;
 
+    // Strongly-typed deserialization (empty payload means detach mode)
+    let payload: DeleteBlockPayload = if cmd.payload.is_null() {
+        DeleteBlockPayload::default()
+    } else {
+        serde_json::from_value(cmd.payload.clone())
+            .map_err(|e| format!("Invalid payload for core.delete: {}", e))?
+    };
+
     // Generate deletion event
-    // Empty payload - deletion is signaled by the event type itself
+    // Deletion is signaled by the event type itself; the mode (and cascade depth)
+    // is recorded so the delete can be planned again where the event is received
+    let mut value = serde_json::json!({ "mode": payload.mode });
+    if let Some(depth) = payload.depth {
+        value["depth"] = serde_json::json!(depth);
+    }
     let event = create_event(
         block.block_id.clone(),
         "core.delete",
-        serde_json::json!({}), // Empty payload
+        value,
         &cmd.editor_id,
         1, // Placeholder - updated by engine actor (actor.rs:329)
     );
@@ -0,0 +1,24 @@
+use crate::capabilities::core::{create_event, CapResult};
+use crate::models::{Block, Command, DeleteRolePayload, Event};
+use capability_macros::capability;
+
+/// Handler for core.delete_role capability.
+///
+/// Deletes a role definition. Every editor holding the role loses it.
+#[capability(id = "core.delete_role", target = "core/*")]
+fn handle_delete_role(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
+    // Strongly-typed deserialization
+    let payload: DeleteRolePayload = serde_json::from_value(cmd.payload.clone())
+        .map_err(|e| format!("Invalid payload for core.delete_role: {}", e))?;
+
+    // Entity is the role name
+    let event = create_event(
+        payload.name.clone(),
+        "core.delete_role",
+        serde_json::json!({ "name": payload.name }),
+        &cmd.editor_id,
+        1, // Placeholder - engine actor updates with correct count
+    );
+
+    Ok(vec![event])
+}
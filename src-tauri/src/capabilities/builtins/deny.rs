@@ -0,0 +1,29 @@
+use crate::capabilities::core::{create_event, CapResult};
+use crate::models::{Block, Command, DenyPayload, Event};
+use capability_macros::capability;
+
+/// Handler for core.deny capability.
+///
+/// Adds a deny rule for an editor on a specific block (or wildcard).
+/// Deny rules take precedence over grants and roles.
+#[capability(id = "core.deny", target = "core/*")]
+fn handle_deny(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
+    // Strongly-typed deserialization
+    let payload: DenyPayload = serde_json::from_value(cmd.payload.clone())
+        .map_err(|e| format!("Invalid payload for core.deny: {}", e))?;
+
+    // Entity is the target block (or "*" for wildcard)
+    let event = create_event(
+        payload.target_block.clone(),
+        "core.deny",
+        serde_json::json!({
+            "editor": payload.target_editor,
+            "capability": payload.capability,
+            "block": payload.target_block,
+        }),
+        &cmd.editor_id,
+        1, // Placeholder - engine actor updates with correct count
+    );
+
+    Ok(vec![event])
+}
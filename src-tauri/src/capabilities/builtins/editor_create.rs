@@ -5,8 +5,9 @@ use capability_macros::capability;
 /// Handler for editor.create capability.
 ///
 /// Creates a new editor identity in the file.
-/// This is a system-level operation that doesn't require a target block.
-/// Any editor can create a new editor (no authorization check needed).
+/// This is a project-level operation that doesn't require a target block.
+/// Authorization: the project owner, or editors granted editor.create on the project.
+/// The first editor created becomes the project owner.
 #[capability(id = "editor.create", target = "system")]
 fn handle_editor_create(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
     // Strongly-typed deserialization
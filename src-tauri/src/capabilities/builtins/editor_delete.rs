@@ -5,9 +5,9 @@ use capability_macros::capability;
 /// Handler for editor.delete capability.
 ///
 /// Removes an editor identity from the file.
-/// This is a system-level operation.
-/// Authorization: For now, any editor can delete any editor (MVP).
-/// In production, we would restrict this to admins or the editor themselves.
+/// This is a project-level operation.
+/// Authorization: the project owner, or editors granted editor.delete on the project.
+/// The project owner itself can't be deleted.
 #[capability(id = "editor.delete", target = "system")]
 fn handle_editor_delete(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
     // Strongly-typed deserialization
@@ -4,23 +4,46 @@ use capability_macros::capability;
 
 /// Handler for core.grant capability.
 ///
-/// Grants a capability to an editor for a specific block (or wildcard).
+/// Grants a capability to an editor for a specific block (or wildcard),
+/// optionally limited by an expiry timestamp and a maximum number of uses.
+/// A `grantable` grant lets the target editor delegate it; the engine checks
+/// that the granting editor holds what they grant (see `engine::delegation`).
 #[capability(id = "core.grant", target = "core/*")]
 fn handle_grant(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
     // Strongly-typed deserialization
     let payload: GrantPayload = serde_json::from_value(cmd.payload.clone())
         .map_err(|e| format!("Invalid payload for core.grant: {}", e))?;
 
+    if let Some(expires_at) = &payload.expires_at {
+        chrono::DateTime::parse_from_rfc3339(expires_at)
+            .map_err(|e| format!("Invalid expires_at '{}': {}", expires_at, e))?;
+    }
+    if payload.max_uses == Some(0) {
+        return Err("max_uses must be at least 1".to_string());
+    }
+
+    let mut value = serde_json::json!({
+        "editor": payload.target_editor,
+        "capability": payload.capability,
+        "block": payload.target_block,
+    });
+    // Limits are only recorded when set, so unlimited grants keep their original shape
+    if let Some(expires_at) = payload.expires_at {
+        value["expires_at"] = serde_json::json!(expires_at);
+    }
+    if let Some(max_uses) = payload.max_uses {
+        value["max_uses"] = serde_json::json!(max_uses);
+    }
+    if payload.grantable {
+        value["grantable"] = serde_json::json!(true);
+    }
+
     // Create grant event
     // Entity is the target block (or "*" for wildcard)
     let event = create_event(
         payload.target_block.clone(),
         "core.grant", // cap_id
-        serde_json::json!({
-            "editor": payload.target_editor,
-            "capability": payload.capability,
-            "block": payload.target_block,
-        }),
+        value,
         &cmd.editor_id,
         1, // Placeholder - engine actor updates with correct count (actor.rs:227)
     );
@@ -0,0 +1,28 @@
+use crate::capabilities::core::{create_event, CapResult};
+use crate::models::{Block, Command, Event, GrantRolePayload};
+use capability_macros::capability;
+
+/// Handler for core.grant_role capability.
+///
+/// Gives a role to an editor for a specific block (or wildcard).
+#[capability(id = "core.grant_role", target = "core/*")]
+fn handle_grant_role(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
+    // Strongly-typed deserialization
+    let payload: GrantRolePayload = serde_json::from_value(cmd.payload.clone())
+        .map_err(|e| format!("Invalid payload for core.grant_role: {}", e))?;
+
+    // Entity is the target block (or "*" for wildcard)
+    let event = create_event(
+        payload.target_block.clone(),
+        "core.grant_role",
+        serde_json::json!({
+            "editor": payload.target_editor,
+            "role": payload.role,
+            "block": payload.target_block,
+        }),
+        &cmd.editor_id,
+        1, // Placeholder - engine actor updates with correct count
+    );
+
+    Ok(vec![event])
+}
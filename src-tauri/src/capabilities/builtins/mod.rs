@@ -1,25 +1,41 @@
 mod change_type;
 mod create;
+mod define_role;
 mod delete;
+mod delete_role;
+mod deny;
 mod editor_create;
 mod editor_delete;
 mod grant;
+mod grant_role;
 mod link;
+mod purge;
 mod read;
 mod rename;
+mod restore;
 mod revoke;
+mod revoke_role;
+mod undeny;
 mod unlink;
 mod update_metadata;
 
 pub use change_type::CoreChange_typeCapability;
 pub use create::CoreCreateCapability;
+pub use define_role::CoreDefine_roleCapability;
 pub use delete::CoreDeleteCapability;
+pub use delete_role::CoreDelete_roleCapability;
+pub use deny::CoreDenyCapability;
 pub use editor_create::EditorCreateCapability;
 pub use editor_delete::EditorDeleteCapability;
 pub use grant::CoreGrantCapability;
+pub use grant_role::CoreGrant_roleCapability;
 pub use link::CoreLinkCapability;
+pub use purge::CorePurgeCapability;
 pub use read::CoreReadCapability;
 pub use rename::CoreRenameCapability;
+pub use restore::CoreRestoreCapability;
 pub use revoke::CoreRevokeCapability;
+pub use revoke_role::CoreRevoke_roleCapability;
+pub use undeny::CoreUndenyCapability;
 pub use unlink::CoreUnlinkCapability;
 pub use update_metadata::CoreUpdate_metadataCapability;
@@ -0,0 +1,22 @@
+use crate::capabilities::core::{create_event, CapResult};
+use crate::models::{Block, Command, Event};
+use capability_macros::capability;
+
+/// Handler for core.purge capability.
+///
+/// Permanently removes a block from the trash. Its events stay in the event log,
+/// but the block can no longer be restored and its `block-{id}/` directory is removed.
+#[capability(id = "core.purge", target = "core/*")]
+fn handle_purge(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>> {
+    let block = block.ok_or("Block required for core.purge")?;
+
+    let event = create_event(
+        block.block_id.clone(),
+        "core.purge",
+        serde_json::json!({}),
+        &cmd.editor_id,
+        1, // Placeholder - updated by engine actor
+    );
+
+    Ok(vec![event])
+}
@@ -0,0 +1,34 @@
+use crate::capabilities::core::{create_event, CapResult};
+use crate::models::{Block, Command, Event, RestoreBlockPayload};
+use capability_macros::capability;
+
+/// Handler for core.restore capability.
+///
+/// Brings a block back from the trash with its contents, children and metadata intact.
+///
+/// The engine actor looks the block up in the trash rather than in the active
+/// state, and appends the follow-up events: `core.restore` for cascaded blocks
+/// (when `include_cascaded` is set), `core.link` for parents that still exist and
+/// `directory.write` for directory entries whose path is still free.
+#[capability(id = "core.restore", target = "core/*")]
+fn handle_restore(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>> {
+    let block = block.ok_or("Block required for core.restore")?;
+
+    // Strongly-typed deserialization (empty payload restores only this block)
+    let payload: RestoreBlockPayload = if cmd.payload.is_null() {
+        RestoreBlockPayload::default()
+    } else {
+        serde_json::from_value(cmd.payload.clone())
+            .map_err(|e| format!("Invalid payload for core.restore: {}", e))?
+    };
+
+    let event = create_event(
+        block.block_id.clone(),
+        "core.restore",
+        serde_json::json!({ "include_cascaded": payload.include_cascaded }),
+        &cmd.editor_id,
+        1, // Placeholder - updated by engine actor
+    );
+
+    Ok(vec![event])
+}
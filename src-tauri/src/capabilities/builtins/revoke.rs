@@ -5,22 +5,28 @@ use capability_macros::capability;
 /// Handler for core.revoke capability.
 ///
 /// Revokes a capability from an editor for a specific block (or wildcard).
+/// With `cascade`, the engine also revokes the grants delegated from it.
 #[capability(id = "core.revoke", target = "core/*")]
 fn handle_revoke(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
     // Strongly-typed deserialization
     let payload: RevokePayload = serde_json::from_value(cmd.payload.clone())
         .map_err(|e| format!("Invalid payload for core.revoke: {}", e))?;
 
+    let mut value = serde_json::json!({
+        "editor": payload.target_editor,
+        "capability": payload.capability,
+        "block": payload.target_block,
+    });
+    if payload.cascade {
+        value["cascade"] = serde_json::json!(true);
+    }
+
     // Create revoke event
     // Entity is the target block (or "*" for wildcard)
     let event = create_event(
         payload.target_block.clone(),
         "core.revoke", // cap_id
-        serde_json::json!({
-            "editor": payload.target_editor,
-            "capability": payload.capability,
-            "block": payload.target_block,
-        }),
+        value,
         &cmd.editor_id,
         1, // Placeholder - engine actor updates with correct count (actor.rs:227)
     );
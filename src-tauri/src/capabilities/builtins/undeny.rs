@@ -0,0 +1,28 @@
+use crate::capabilities::core::{create_event, CapResult};
+use crate::models::{Block, Command, Event, UndenyPayload};
+use capability_macros::capability;
+
+/// Handler for core.undeny capability.
+///
+/// Removes a deny rule for an editor on a specific block (or wildcard).
+#[capability(id = "core.undeny", target = "core/*")]
+fn handle_undeny(cmd: &Command, _block: Option<&Block>) -> CapResult<Vec<Event>> {
+    // Strongly-typed deserialization
+    let payload: UndenyPayload = serde_json::from_value(cmd.payload.clone())
+        .map_err(|e| format!("Invalid payload for core.undeny: {}", e))?;
+
+    // Entity is the target block (or "*" for wildcard)
+    let event = create_event(
+        payload.target_block.clone(),
+        "core.undeny",
+        serde_json::json!({
+            "editor": payload.target_editor,
+            "capability": payload.capability,
+            "block": payload.target_block,
+        }),
+        &cmd.editor_id,
+        1, // Placeholder - engine actor updates with correct count
+    );
+
+    Ok(vec![event])
+}
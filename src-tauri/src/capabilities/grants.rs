@@ -1,14 +1,31 @@
-use crate::models::Event;
+use crate::engine::grant_target;
+use crate::models::{
+    capability_matches, BlockContainers, Delegation, Event, GrantLimits, GrantScope,
+};
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 
+/// Key of a single grant: (editor_id, cap_id, block_id)
+pub type GrantKey = (String, String, String);
+
 /// Grants table for Capability-Based Access Control (CBAC).
 ///
-/// Tracks which editors have been granted which capabilities for which blocks.
-/// This table is projected from grant/revoke events in the EventStore.
+/// Tracks which editors have been granted which capabilities for which blocks,
+/// and deny rules that take precedence over any matching grant.
+/// This table is projected from grant/revoke and deny/undeny events in the EventStore.
 #[derive(Debug, Clone)]
 pub struct GrantsTable {
     /// Map: editor_id -> Vec<(cap_id, block_id)>
     grants: HashMap<String, Vec<(String, String)>>,
+
+    /// Map: (editor_id, cap_id, block_id) -> limits, for time/usage-limited grants only
+    limits: HashMap<GrantKey, GrantLimits>,
+
+    /// Map: editor_id -> Vec<(cap_id, block_id)> of deny rules
+    denies: HashMap<String, Vec<(String, String)>>,
+
+    /// Map: (editor_id, cap_id, block_id) -> who issued the grant and whether it may be delegated
+    delegations: HashMap<GrantKey, Delegation>,
 }
 
 impl GrantsTable {
@@ -16,6 +33,59 @@ impl GrantsTable {
     pub fn new() -> Self {
         Self {
             grants: HashMap::new(),
+            limits: HashMap::new(),
+            denies: HashMap::new(),
+            delegations: HashMap::new(),
+        }
+    }
+
+    /// Read the optional `expires_at` / `max_uses` fields of a grant event value.
+    ///
+    /// Returns None for unlimited grants.
+    pub fn limits_from_value(value: &serde_json::Value) -> Option<GrantLimits> {
+        let expires_at = value
+            .get("expires_at")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let max_uses = value
+            .get("max_uses")
+            .and_then(|v| v.as_u64())
+            .map(|n| n.min(u32::MAX as u64) as u32);
+
+        if expires_at.is_none() && max_uses.is_none() {
+            return None;
+        }
+        Some(GrantLimits {
+            expires_at,
+            max_uses,
+            uses: 0,
+        })
+    }
+
+    /// Read the delegation details of a grant event.
+    ///
+    /// The issuer is the editor in the event attribute (`{editor_id}/core.grant`);
+    /// `grantable` and `delegated_from` are optional fields of the value.
+    pub fn delegation_from_event(event: &Event) -> Delegation {
+        let granted_by = event
+            .attribute
+            .split_once('/')
+            .map(|(editor, _)| editor.to_string())
+            .unwrap_or_default();
+        let grantable = event
+            .value
+            .get("grantable")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let delegated_from = event.value.get("delegated_from").and_then(|source| {
+            let field = |name: &str| source.get(name).and_then(|v| v.as_str()).map(String::from);
+            Some((field("editor")?, field("capability")?, field("block")?))
+        });
+
+        Delegation {
+            granted_by,
+            grantable,
+            delegated_from,
         }
     }
 
@@ -23,6 +93,8 @@ impl GrantsTable {
     ///
     /// Processes all grant and revoke events to build the current authorization state.
     /// Events have attribute format `{editor_id}/{cap_id}` where cap_id is "core.grant" or "core.revoke".
+    /// Grant limits are read, but uses are not counted (that needs the full block state;
+    /// see `StateProjector`).
     pub fn from_events(events: &[Event]) -> Self {
         let mut table = Self::new();
 
@@ -45,12 +117,22 @@ impl GrantsTable {
                         .get("block")
                         .and_then(|v| v.as_str())
                         .unwrap_or("*");
+                    let block = grant_target(capability, block);
 
                     if !editor.is_empty() && !capability.is_empty() {
-                        table.add_grant(
+                        table.add_grant_with_limits(
                             editor.to_string(),
                             capability.to_string(),
                             block.to_string(),
+                            Self::limits_from_value(&event.value),
+                        );
+                        table.set_delegation(
+                            (
+                                editor.to_string(),
+                                capability.to_string(),
+                                block.to_string(),
+                            ),
+                            Self::delegation_from_event(event),
                         );
                     }
                 }
@@ -68,9 +150,10 @@ impl GrantsTable {
                         .get("block")
                         .and_then(|v| v.as_str())
                         .unwrap_or("*");
+                    let block = grant_target(capability, block);
 
                     if !editor.is_empty() && !capability.is_empty() {
-                        table.remove_grant(editor, capability, block);
+                        table.remove_grant(editor, capability, &block);
                     }
                 }
             }
@@ -81,6 +164,29 @@ impl GrantsTable {
 
     /// Add a grant to the table.
     pub fn add_grant(&mut self, editor_id: String, cap_id: String, block_id: String) {
+        self.add_grant_with_limits(editor_id, cap_id, block_id, None);
+    }
+
+    /// Add a grant with optional expiry/usage limits.
+    ///
+    /// Granting again replaces the previous limits (and resets the use count).
+    pub fn add_grant_with_limits(
+        &mut self,
+        editor_id: String,
+        cap_id: String,
+        block_id: String,
+        limits: Option<GrantLimits>,
+    ) {
+        let key = (editor_id.clone(), cap_id.clone(), block_id.clone());
+        match limits {
+            Some(limits) => {
+                self.limits.insert(key, limits);
+            }
+            None => {
+                self.limits.remove(&key);
+            }
+        }
+
         let entry = self.grants.entry(editor_id).or_default();
 
         // Avoid duplicates
@@ -90,8 +196,71 @@ impl GrantsTable {
         }
     }
 
+    /// Record who issued a grant and whether it may be delegated.
+    pub fn set_delegation(&mut self, key: GrantKey, delegation: Delegation) {
+        self.delegations.insert(key, delegation);
+    }
+
+    /// Get the delegation details of a grant (None for grants added without them).
+    pub fn get_delegation(&self, key: &GrantKey) -> Option<&Delegation> {
+        self.delegations.get(key)
+    }
+
+    /// Get the delegation details of all grants.
+    pub fn all_delegations(&self) -> &HashMap<GrantKey, Delegation> {
+        &self.delegations
+    }
+
+    /// Grants delegated from `key`, directly or through other delegated grants.
+    ///
+    /// Sorted so that cascading revokes are deterministic.
+    pub fn derived_grants(&self, key: &GrantKey) -> Vec<GrantKey> {
+        let mut derived: Vec<GrantKey> = Vec::new();
+        let mut stack = vec![key.clone()];
+        while let Some(source) = stack.pop() {
+            let mut children: Vec<&GrantKey> = self
+                .delegations
+                .iter()
+                .filter(|(child, d)| {
+                    d.delegated_from.as_ref() == Some(&source)
+                        && *child != key
+                        && !derived.contains(child)
+                })
+                .map(|(child, _)| child)
+                .collect();
+            children.sort();
+            for child in children {
+                derived.push(child.clone());
+                stack.push(child.clone());
+            }
+        }
+        derived.sort();
+        derived
+    }
+
+    /// Grants that may be delegated: the editor's own, then the wildcard editor's.
+    pub fn grantable_grants(&self, editor_id: &str) -> Vec<GrantKey> {
+        let wildcard = if editor_id != "*" { Some("*") } else { None };
+        std::iter::once(editor_id)
+            .chain(wildcard)
+            .filter_map(|editor| self.grants.get(editor).map(|list| (editor, list)))
+            .flat_map(|(editor, list)| {
+                list.iter()
+                    .map(move |(cap, blk)| (editor.to_string(), cap.clone(), blk.clone()))
+            })
+            .filter(|key| self.delegations.get(key).is_some_and(|d| d.grantable))
+            .collect()
+    }
+
     /// Remove a grant from the table.
     pub fn remove_grant(&mut self, editor_id: &str, cap_id: &str, block_id: &str) {
+        let key = (
+            editor_id.to_string(),
+            cap_id.to_string(),
+            block_id.to_string(),
+        );
+        self.limits.remove(&key);
+        self.delegations.remove(&key);
         if let Some(editor_grants) = self.grants.get_mut(editor_id) {
             editor_grants.retain(|(cap, blk)| !(cap == cap_id && blk == block_id));
 
@@ -107,6 +276,69 @@ impl GrantsTable {
     /// This is used when an editor is deleted from the system.
     pub fn remove_all_grants_for_editor(&mut self, editor_id: &str) {
         self.grants.remove(editor_id);
+        self.limits.retain(|(editor, _, _), _| editor != editor_id);
+        self.delegations
+            .retain(|(editor, _, _), _| editor != editor_id);
+        self.denies.remove(editor_id);
+    }
+
+    /// Add a deny rule: editor_id may not use cap_id (or pattern) on the target.
+    pub fn add_deny(&mut self, editor_id: String, cap_id: String, block_id: String) {
+        let entry = self.denies.entry(editor_id).or_default();
+
+        // Avoid duplicates
+        let deny_pair = (cap_id, block_id);
+        if !entry.contains(&deny_pair) {
+            entry.push(deny_pair);
+        }
+    }
+
+    /// Remove a deny rule.
+    pub fn remove_deny(&mut self, editor_id: &str, cap_id: &str, block_id: &str) {
+        if let Some(editor_denies) = self.denies.get_mut(editor_id) {
+            editor_denies.retain(|(cap, blk)| !(cap == cap_id && blk == block_id));
+
+            // Clean up empty entries
+            if editor_denies.is_empty() {
+                self.denies.remove(editor_id);
+            }
+        }
+    }
+
+    /// Get all deny rules as a map: editor_id -> Vec<(cap_id, block_id)>.
+    pub fn denies_map(&self) -> &HashMap<String, Vec<(String, String)>> {
+        &self.denies
+    }
+
+    /// Get the limits of a grant (None for unlimited grants).
+    pub fn get_limits(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+    ) -> Option<&GrantLimits> {
+        self.limits.get(&(
+            editor_id.to_string(),
+            cap_id.to_string(),
+            block_id.to_string(),
+        ))
+    }
+
+    /// Get the limits of all limited grants.
+    pub fn all_limits(&self) -> &HashMap<GrantKey, GrantLimits> {
+        &self.limits
+    }
+
+    /// Check if any grant carries expiry or usage limits.
+    pub fn has_limited_grants(&self) -> bool {
+        !self.limits.is_empty()
+    }
+
+    /// Count one use of a limited grant.
+    pub fn record_use(&mut self, key: &GrantKey) {
+        if let Some(limits) = self.limits.get_mut(key) {
+            limits.uses += 1;
+        }
     }
 
     /// Get all grants for a specific editor.
@@ -123,38 +355,142 @@ impl GrantsTable {
 
     /// Check if an editor has a specific grant.
     ///
+    /// Only flat scopes (a block ID or "*") are resolved and limits are checked
+    /// against the current time; use [`Self::has_grant_in`] to also resolve
+    /// `subtree:` and `descendants:` scopes at a given time.
+    pub fn has_grant(&self, editor_id: &str, cap_id: &str, block_id: &str) -> bool {
+        self.has_grant_in(
+            editor_id,
+            cap_id,
+            block_id,
+            &BlockContainers::default(),
+            Utc::now(),
+        )
+    }
+
+    /// Check if an editor has a specific grant, resolving hierarchical scopes.
+    ///
     /// Matching order:
-    /// 1. Exact match: editor_id has (cap_id, block_id) or (cap_id, "*")
-    /// 2. Wildcard editor: "*" has (cap_id, block_id) or (cap_id, "*")
+    /// 1. Deny rules: a deny for editor_id (or "*") whose capability pattern
+    ///    matches cap_id and whose scope covers block_id always wins
+    /// 2. Exact match: editor_id has a grant whose capability pattern matches
+    ///    cap_id and whose scope covers block_id
+    /// 3. Wildcard editor: "*" has such a grant
     ///
     /// The wildcard editor_id "*" means "all editors have this grant".
-    pub fn has_grant(&self, editor_id: &str, cap_id: &str, block_id: &str) -> bool {
-        let check = |grants: &Vec<(String, String)>| {
-            grants
-                .iter()
-                .any(|(cap, blk)| cap == cap_id && (blk == block_id || blk == "*"))
-        };
-
-        // 1. Exact match on editor_id
-        if let Some(editor_grants) = self.grants.get(editor_id) {
-            if check(editor_grants) {
-                return true;
-            }
-        }
+    /// `containers` lists the directories and implement ancestors of block_id.
+    /// Limited grants only match while they are unexpired at `now` and have uses left.
+    pub fn has_grant_in(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        containers: &BlockContainers,
+        now: DateTime<Utc>,
+    ) -> bool {
+        self.matching_deny(editor_id, cap_id, block_id, containers)
+            .is_none()
+            && self
+                .active_grant(editor_id, cap_id, block_id, containers, now)
+                .is_some()
+    }
 
-        // 2. Wildcard editor_id "*" (grant to all editors)
-        if editor_id != "*" {
-            if let Some(wildcard_grants) = self.grants.get("*") {
-                if check(wildcard_grants) {
-                    return true;
+    /// The grant that allows cap_id on block_id at `now`, ignoring deny rules.
+    ///
+    /// Unlimited grants are preferred, so a use is only counted against a
+    /// limited grant when nothing else allows it.
+    pub fn active_grant(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        containers: &BlockContainers,
+        now: DateTime<Utc>,
+    ) -> Option<GrantKey> {
+        let mut limited = None;
+        for key in matching_entries(&self.grants, editor_id, cap_id, block_id, containers) {
+            match self.limits.get(&key) {
+                None => return Some(key),
+                Some(limits) if limited.is_none() && limits.is_active_at(now) => {
+                    limited = Some(key)
                 }
+                Some(_) => {}
             }
         }
+        limited
+    }
+
+    /// The first deny rule that forbids cap_id on block_id for editor_id.
+    pub fn matching_deny(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        containers: &BlockContainers,
+    ) -> Option<GrantKey> {
+        matching_entries(&self.denies, editor_id, cap_id, block_id, containers).next()
+    }
+
+    /// All grants (limited or not) whose capability pattern and scope match, ignoring deny rules.
+    pub fn matching_grants(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        containers: &BlockContainers,
+    ) -> Vec<GrantKey> {
+        matching_entries(&self.grants, editor_id, cap_id, block_id, containers).collect()
+    }
+
+    /// All deny rules that forbid cap_id on block_id for editor_id.
+    pub fn matching_denies(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        containers: &BlockContainers,
+    ) -> Vec<GrantKey> {
+        matching_entries(&self.denies, editor_id, cap_id, block_id, containers).collect()
+    }
 
-        false
+    /// Check if any grant or deny rule uses a hierarchical (`subtree:` / `descendants:`) scope.
+    pub fn has_hierarchical_scopes(&self) -> bool {
+        self.grants
+            .values()
+            .chain(self.denies.values())
+            .flatten()
+            .any(|(_, blk)| {
+                matches!(
+                    GrantScope::parse(blk),
+                    GrantScope::Subtree(_) | GrantScope::Descendants(_)
+                )
+            })
     }
 }
 
+/// Entries (editor first, then wildcard editor "*") whose capability pattern
+/// matches cap_id and whose scope covers block_id, ignoring limits.
+fn matching_entries<'a>(
+    entries: &'a HashMap<String, Vec<(String, String)>>,
+    editor_id: &'a str,
+    cap_id: &'a str,
+    block_id: &'a str,
+    containers: &'a BlockContainers,
+) -> impl Iterator<Item = GrantKey> + 'a {
+    let wildcard = if editor_id != "*" { Some("*") } else { None };
+    std::iter::once(editor_id)
+        .chain(wildcard)
+        .filter_map(|editor| entries.get(editor).map(|list| (editor, list)))
+        .flat_map(move |(editor, list)| {
+            list.iter()
+                .filter(move |(cap, blk)| {
+                    capability_matches(cap, cap_id)
+                        && GrantScope::parse(blk).covers(block_id, containers)
+                })
+                .map(move |(cap, blk)| (editor.to_string(), cap.clone(), blk.clone()))
+        })
+}
+
 impl Default for GrantsTable {
     fn default() -> Self {
         Self::new()
@@ -392,4 +728,122 @@ mod tests {
         // Wrong capability still doesn't match
         assert!(!table.has_grant("alice", "markdown.write", "any-block"));
     }
+
+    #[test]
+    fn test_has_grant_capability_pattern() {
+        let mut table = GrantsTable::new();
+        table.add_grant(
+            "alice".to_string(),
+            "markdown.*".to_string(),
+            "block1".to_string(),
+        );
+
+        assert!(table.has_grant("alice", "markdown.write", "block1"));
+        assert!(table.has_grant("alice", "markdown.read", "block1"));
+        assert!(!table.has_grant("alice", "code.write", "block1"));
+        assert!(!table.has_grant("alice", "markdown.write", "block2"));
+    }
+
+    #[test]
+    fn test_has_grant_hierarchical_scopes() {
+        let mut table = GrantsTable::new();
+        table.add_grant(
+            "alice".to_string(),
+            "markdown.read".to_string(),
+            "subtree:dir".to_string(),
+        );
+        table.add_grant(
+            "alice".to_string(),
+            "code.write".to_string(),
+            "descendants:spec".to_string(),
+        );
+        assert!(table.has_hierarchical_scopes());
+
+        let mut containers = BlockContainers::default();
+        containers.directories.insert("dir".to_string());
+        containers.implement_ancestors.insert("spec".to_string());
+
+        let now = Utc::now();
+        assert!(table.has_grant_in("alice", "markdown.read", "file", &containers, now));
+        assert!(table.has_grant_in("alice", "code.write", "file", &containers, now));
+        // The scope root itself is covered
+        assert!(table.has_grant("alice", "markdown.read", "dir"));
+        // Without structural context only flat scopes match
+        assert!(!table.has_grant("alice", "markdown.read", "file"));
+        assert!(!table.has_grant_in(
+            "alice",
+            "markdown.read",
+            "file",
+            &BlockContainers::default(),
+            now
+        ));
+    }
+
+    #[test]
+    fn test_limited_grants() {
+        let mut table = GrantsTable::new();
+        let limits = GrantLimits {
+            expires_at: Some("2025-06-02T00:00:00Z".to_string()),
+            max_uses: Some(1),
+            uses: 0,
+        };
+        table.add_grant_with_limits(
+            "bob".to_string(),
+            "markdown.write".to_string(),
+            "block1".to_string(),
+            Some(limits),
+        );
+
+        let none = BlockContainers::default();
+        let before = "2025-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let after = "2025-06-03T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert!(table.has_grant_in("bob", "markdown.write", "block1", &none, before));
+        assert!(!table.has_grant_in("bob", "markdown.write", "block1", &none, after));
+
+        // Using the grant once exhausts it
+        let key = table
+            .active_grant("bob", "markdown.write", "block1", &none, before)
+            .unwrap();
+        table.record_use(&key);
+        assert!(!table.has_grant_in("bob", "markdown.write", "block1", &none, before));
+        assert_eq!(
+            table
+                .get_limits("bob", "markdown.write", "block1")
+                .unwrap()
+                .uses,
+            1
+        );
+
+        // An unlimited grant takes over and is preferred
+        table.add_grant("bob".to_string(), "markdown.*".to_string(), "*".to_string());
+        assert!(table.has_grant_in("bob", "markdown.write", "block1", &none, after));
+        assert_eq!(
+            table.active_grant("bob", "markdown.write", "block1", &none, before),
+            Some(("bob".to_string(), "markdown.*".to_string(), "*".to_string()))
+        );
+
+        // Revoking removes the limits
+        table.remove_grant("bob", "markdown.write", "block1");
+        assert!(!table.has_limited_grants());
+    }
+
+    #[test]
+    fn test_deny_takes_precedence_over_wildcards() {
+        let mut table = GrantsTable::new();
+        table.add_grant("*".to_string(), "*".to_string(), "*".to_string());
+        table.add_deny(
+            "bot".to_string(),
+            "markdown.*".to_string(),
+            "prd".to_string(),
+        );
+
+        assert!(table.has_grant("bot", "markdown.write", "notes"));
+        assert!(!table.has_grant("bot", "markdown.write", "prd"));
+        assert!(table.has_grant("bot", "code.write", "prd"));
+        assert!(table.has_grant("alice", "markdown.write", "prd"));
+
+        table.remove_deny("bot", "markdown.*", "prd");
+        assert!(table.has_grant("bot", "markdown.write", "prd"));
+        assert!(table.denies_map().is_empty());
+    }
 }
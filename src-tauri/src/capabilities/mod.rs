@@ -2,7 +2,9 @@ pub mod builtins;
 pub mod core;
 pub mod grants;
 pub mod registry;
+pub mod roles;
 
 pub use core::{CapResult, CapabilityHandler};
 pub use grants::GrantsTable;
 pub use registry::CapabilityRegistry;
+pub use roles::RolesTable;
@@ -54,8 +54,16 @@ impl CapabilityRegistry {
         self.register(Arc::new(CoreLinkCapability));
         self.register(Arc::new(CoreUnlinkCapability));
         self.register(Arc::new(CoreDeleteCapability));
+        self.register(Arc::new(CoreRestoreCapability));
+        self.register(Arc::new(CorePurgeCapability));
         self.register(Arc::new(CoreGrantCapability));
         self.register(Arc::new(CoreRevokeCapability));
+        self.register(Arc::new(CoreDenyCapability));
+        self.register(Arc::new(CoreUndenyCapability));
+        self.register(Arc::new(CoreDefine_roleCapability));
+        self.register(Arc::new(CoreDelete_roleCapability));
+        self.register(Arc::new(CoreGrant_roleCapability));
+        self.register(Arc::new(CoreRevoke_roleCapability));
         self.register(Arc::new(CoreUpdate_metadataCapability));
         self.register(Arc::new(CoreRenameCapability));
         self.register(Arc::new(CoreChange_typeCapability));
@@ -126,6 +134,14 @@ mod tests {
             registry.get("core.delete").is_some(),
             "core.delete should be registered"
         );
+        assert!(
+            registry.get("core.restore").is_some(),
+            "core.restore should be registered"
+        );
+        assert!(
+            registry.get("core.purge").is_some(),
+            "core.purge should be registered"
+        );
         assert!(
             registry.get("core.grant").is_some(),
             "core.grant should be registered"
@@ -134,6 +150,14 @@ mod tests {
             registry.get("core.revoke").is_some(),
             "core.revoke should be registered"
         );
+        assert!(
+            registry.get("core.deny").is_some(),
+            "core.deny should be registered"
+        );
+        assert!(
+            registry.get("core.grant_role").is_some(),
+            "core.grant_role should be registered"
+        );
 
         // Verify extension capabilities are registered
         assert!(
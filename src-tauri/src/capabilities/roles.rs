@@ -0,0 +1,281 @@
+use crate::models::{capability_matches, BlockContainers, GrantScope, Role, RoleAssignment};
+use std::collections::HashMap;
+
+/// Roles table for Capability-Based Access Control (CBAC).
+///
+/// Holds role definitions (name → capabilities) and which editors hold which
+/// roles on which blocks. Assignments reference roles by name, so redefining a
+/// role takes effect for every holder. Projected from `core.define_role`,
+/// `core.delete_role`, `core.grant_role` and `core.revoke_role` events.
+#[derive(Debug, Clone)]
+pub struct RolesTable {
+    /// Map: role name -> definition
+    roles: HashMap<String, Role>,
+
+    /// Map: editor_id -> Vec<(role name, block_id)>
+    assignments: HashMap<String, Vec<(String, String)>>,
+}
+
+impl RolesTable {
+    /// Create an empty roles table.
+    pub fn new() -> Self {
+        Self {
+            roles: HashMap::new(),
+            assignments: HashMap::new(),
+        }
+    }
+
+    /// Create or replace a role definition.
+    pub fn define_role(&mut self, role: Role) {
+        self.roles.insert(role.name.clone(), role);
+    }
+
+    /// Delete a role and every assignment of it.
+    pub fn delete_role(&mut self, name: &str) {
+        self.roles.remove(name);
+        for assignments in self.assignments.values_mut() {
+            assignments.retain(|(role, _)| role != name);
+        }
+        self.assignments
+            .retain(|_, assignments| !assignments.is_empty());
+    }
+
+    /// Get a role definition by name.
+    pub fn get_role(&self, name: &str) -> Option<&Role> {
+        self.roles.get(name)
+    }
+
+    /// Get all role definitions, sorted by name.
+    pub fn roles(&self) -> Vec<Role> {
+        let mut roles: Vec<Role> = self.roles.values().cloned().collect();
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+        roles
+    }
+
+    /// Give a role to an editor on a block (or "*").
+    pub fn assign(&mut self, editor_id: String, role: String, block_id: String) {
+        let entry = self.assignments.entry(editor_id).or_default();
+
+        // Avoid duplicates
+        let assignment = (role, block_id);
+        if !entry.contains(&assignment) {
+            entry.push(assignment);
+        }
+    }
+
+    /// Take a role from an editor on a block (or "*").
+    pub fn unassign(&mut self, editor_id: &str, role: &str, block_id: &str) {
+        if let Some(editor_roles) = self.assignments.get_mut(editor_id) {
+            editor_roles.retain(|(r, blk)| !(r == role && blk == block_id));
+
+            // Clean up empty entries
+            if editor_roles.is_empty() {
+                self.assignments.remove(editor_id);
+            }
+        }
+    }
+
+    /// Remove all role assignments for a specific editor.
+    ///
+    /// This is used when an editor is deleted from the system.
+    pub fn remove_all_for_editor(&mut self, editor_id: &str) {
+        self.assignments.remove(editor_id);
+    }
+
+    /// Check if any assignment targets a hierarchical (`subtree:` / `descendants:`) scope.
+    pub fn has_hierarchical_scopes(&self) -> bool {
+        self.assignments.values().flatten().any(|(_, blk)| {
+            matches!(
+                GrantScope::parse(blk),
+                GrantScope::Subtree(_) | GrantScope::Descendants(_)
+            )
+        })
+    }
+
+    /// Get all role assignments, sorted by editor, role and block.
+    pub fn assignments(&self) -> Vec<RoleAssignment> {
+        let mut all: Vec<RoleAssignment> = self
+            .assignments
+            .iter()
+            .flat_map(|(editor_id, roles)| {
+                roles.iter().map(move |(role, block_id)| {
+                    RoleAssignment::new(editor_id.clone(), role.clone(), block_id.clone())
+                })
+            })
+            .collect();
+        all.sort_by(|a, b| {
+            (&a.editor_id, &a.role, &a.block_id).cmp(&(&b.editor_id, &b.role, &b.block_id))
+        });
+        all
+    }
+
+    /// Check if an editor holds a role that grants `cap_id` on `block_id`.
+    ///
+    /// A role grants a capability on a block when:
+    /// 1. The role lists the capability (or a pattern such as "markdown.*")
+    /// 2. The assignment's target scope covers the block
+    /// 3. The role's scope covers the block
+    ///
+    /// Assignments to the wildcard editor "*" apply to all editors.
+    /// `containers` resolves `subtree:` and `descendants:` assignment targets.
+    pub fn has_role_grant(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        containers: &BlockContainers,
+    ) -> bool {
+        self.matching_assignment(editor_id, cap_id, block_id, containers)
+            .is_some()
+    }
+
+    /// The first role assignment that grants `cap_id` on `block_id` (see [`Self::has_role_grant`]).
+    pub fn matching_assignment(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        containers: &BlockContainers,
+    ) -> Option<RoleAssignment> {
+        self.matching_assignments(editor_id, cap_id, block_id, containers)
+            .into_iter()
+            .next()
+    }
+
+    /// All role assignments that grant `cap_id` on `block_id`, editor's own first.
+    pub fn matching_assignments(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        containers: &BlockContainers,
+    ) -> Vec<RoleAssignment> {
+        let wildcard = if editor_id != "*" { Some("*") } else { None };
+        std::iter::once(editor_id)
+            .chain(wildcard)
+            .filter_map(|editor| self.assignments.get(editor).map(|list| (editor, list)))
+            .flat_map(|(editor, assignments)| {
+                assignments
+                    .iter()
+                    .filter(|(role_name, blk)| {
+                        GrantScope::parse(blk).covers(block_id, containers)
+                            && self.roles.get(role_name).is_some_and(|role| {
+                                role.applies_to_block(block_id)
+                                    && role
+                                        .capabilities
+                                        .iter()
+                                        .any(|cap| capability_matches(cap, cap_id))
+                            })
+                    })
+                    .map(move |(role, blk)| {
+                        RoleAssignment::new(editor.to_string(), role.clone(), blk.clone())
+                    })
+            })
+            .collect()
+    }
+}
+
+impl Default for RolesTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn none() -> BlockContainers {
+        BlockContainers::default()
+    }
+
+    fn reviewer(scope: Option<&str>) -> Role {
+        Role {
+            name: "reviewer".to_string(),
+            capabilities: vec!["markdown.read".to_string(), "code.read".to_string()],
+            scope: scope.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_role_grants_bundled_capabilities() {
+        let mut table = RolesTable::new();
+        table.define_role(reviewer(None));
+        table.assign("bob".to_string(), "reviewer".to_string(), "*".to_string());
+
+        assert!(table.has_role_grant("bob", "markdown.read", "block1", &none()));
+        assert!(table.has_role_grant("bob", "code.read", "block2", &none()));
+        assert!(!table.has_role_grant("bob", "markdown.write", "block1", &none()));
+        assert!(!table.has_role_grant("alice", "markdown.read", "block1", &none()));
+    }
+
+    #[test]
+    fn test_role_assignment_on_single_block() {
+        let mut table = RolesTable::new();
+        table.define_role(reviewer(None));
+        table.assign(
+            "bob".to_string(),
+            "reviewer".to_string(),
+            "block1".to_string(),
+        );
+
+        assert!(table.has_role_grant("bob", "markdown.read", "block1", &none()));
+        assert!(!table.has_role_grant("bob", "markdown.read", "block2", &none()));
+    }
+
+    #[test]
+    fn test_role_scope_limits_wildcard_assignment() {
+        let mut table = RolesTable::new();
+        table.define_role(reviewer(Some("block1")));
+        table.assign("bob".to_string(), "reviewer".to_string(), "*".to_string());
+
+        assert!(table.has_role_grant("bob", "markdown.read", "block1", &none()));
+        assert!(!table.has_role_grant("bob", "markdown.read", "block2", &none()));
+    }
+
+    #[test]
+    fn test_redefining_role_affects_holders() {
+        let mut table = RolesTable::new();
+        table.define_role(reviewer(None));
+        table.assign("bob".to_string(), "reviewer".to_string(), "*".to_string());
+        assert!(!table.has_role_grant("bob", "core.delete", "block1", &none()));
+
+        let mut redefined = reviewer(None);
+        redefined.capabilities.push("core.delete".to_string());
+        table.define_role(redefined);
+
+        assert!(table.has_role_grant("bob", "core.delete", "block1", &none()));
+    }
+
+    #[test]
+    fn test_delete_role_removes_assignments() {
+        let mut table = RolesTable::new();
+        table.define_role(reviewer(None));
+        table.assign("bob".to_string(), "reviewer".to_string(), "*".to_string());
+        table.delete_role("reviewer");
+
+        assert!(!table.has_role_grant("bob", "markdown.read", "block1", &none()));
+        assert!(table.assignments().is_empty());
+        assert!(table.roles().is_empty());
+    }
+
+    #[test]
+    fn test_wildcard_editor_role() {
+        let mut table = RolesTable::new();
+        table.define_role(reviewer(None));
+        table.assign("*".to_string(), "reviewer".to_string(), "*".to_string());
+
+        assert!(table.has_role_grant("anyone", "code.read", "block1", &none()));
+    }
+
+    #[test]
+    fn test_unassign_role() {
+        let mut table = RolesTable::new();
+        table.define_role(reviewer(None));
+        table.assign("bob".to_string(), "reviewer".to_string(), "*".to_string());
+        table.unassign("bob", "reviewer", "*");
+
+        assert!(!table.has_role_grant("bob", "markdown.read", "block1", &none()));
+        assert!(table.assignments().is_empty());
+    }
+}
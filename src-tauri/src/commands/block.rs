@@ -1,4 +1,5 @@
 use crate::config;
+use crate::engine::{AuthorizationTrace, DanglingReference, TrashedBlock};
 use crate::models::{Block, Command, Event};
 use crate::state::AppState;
 use crate::utils::infer_block_type;
@@ -34,6 +35,32 @@ pub async fn execute_command(
     handle.process_command(cmd).await
 }
 
+/// Dry-run a command: validate its payload and authorization without persisting.
+///
+/// Nothing is written to the event store and the in-memory state is unchanged.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file containing the block
+/// * `cmd` - Command to validate
+///
+/// # Returns
+/// * `Ok(events)` - Events the command would generate
+/// * `Err(message)` - Error the command would fail with
+#[tauri::command]
+#[specta]
+pub async fn dry_run_command(
+    file_id: String,
+    cmd: Command,
+    state: State<'_, AppState>,
+) -> Result<Vec<Event>, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    handle.dry_run_command(cmd).await
+}
+
 /// Get a specific block by ID from a file.
 ///
 /// This command checks read permission before returning the block content.
@@ -425,3 +452,93 @@ pub async fn check_permission(
         .check_grant(effective_editor_id, capability, block_id)
         .await)
 }
+
+/// Explain a permission check: the block owner and every matching deny rule,
+/// grant and role, plus the rule that decides.
+///
+/// Create-style capabilities (e.g. `core.create`) report that no check applies.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `block_id` - Block to check (may be empty for create-style capabilities)
+/// * `capability` - Capability to check
+/// * `editor_id` - Optional editor ID (defaults to active editor)
+///
+/// # Returns
+/// * `Ok(AuthorizationTrace)` - The full decision trace
+/// * `Err(message)` - Error if file is not open or no editor is active
+#[tauri::command]
+#[specta]
+pub async fn explain_permission(
+    file_id: String,
+    block_id: String,
+    capability: String,
+    editor_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AuthorizationTrace, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    let effective_editor_id = match editor_id {
+        Some(id) => id,
+        None => state
+            .get_active_editor(&file_id)
+            .ok_or_else(|| "No active editor".to_string())?,
+    };
+
+    handle
+        .explain_authorization(effective_editor_id, capability, block_id)
+        .await
+}
+
+/// Find references to blocks that no longer exist.
+///
+/// Reports `implement` links and directory entries that point at deleted blocks.
+/// Projects created before `core.delete` cleaned up references may contain these.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(Vec<DanglingReference>)` - Dangling references (empty if the project is consistent)
+/// * `Err(message)` - Error if file is not open
+#[tauri::command]
+#[specta]
+pub async fn validate_references(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<DanglingReference>, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    Ok(handle.find_dangling_references().await)
+}
+
+/// List the blocks in the trash of a file, most recently deleted first.
+///
+/// Trashed blocks can be brought back with `core.restore` or removed for good
+/// with `core.purge` (both via `execute_command`).
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(Vec<TrashedBlock>)` - Trashed blocks with their parents and directory entries at delete time
+/// * `Err(message)` - Error if file is not open
+#[tauri::command]
+#[specta]
+pub async fn list_trash(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<TrashedBlock>, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    Ok(handle.get_trash().await)
+}
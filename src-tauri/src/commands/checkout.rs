@@ -1,6 +1,7 @@
 use crate::extensions::directory::DirectoryExportPayload;
 use crate::models::Command;
 use crate::state::AppState;
+use crate::utils::read_asset;
 use serde_json::json;
 use std::fs;
 use std::path::Path;
@@ -122,13 +123,28 @@ pub async fn checkout_workspace(
                         );
                         continue;
                     }
-                    // Standardized content field access: try 'text' then 'markdown'
-                    let content = child_block
-                        .contents
-                        .get("text")
-                        .or_else(|| child_block.contents.get("markdown"))
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("");
+                    // Asset bytes live in the blob store of the archive holding the block
+                    let content = if child_block.block_type == "asset" {
+                        let archive_dir = child_block
+                            .contents
+                            .get("_block_dir")
+                            .and_then(|v| v.as_str())
+                            .and_then(|dir| Path::new(dir).parent())
+                            .ok_or_else(|| {
+                                format!("Asset '{}' has no archive directory", virtual_path)
+                            })?;
+                        read_asset(archive_dir, &child_block.contents)?
+                    } else {
+                        // Standardized content field access: try 'text' then 'markdown'
+                        child_block
+                            .contents
+                            .get("text")
+                            .or_else(|| child_block.contents.get("markdown"))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("")
+                            .as_bytes()
+                            .to_vec()
+                    };
 
                     // Write to file
                     let file_path = target_root.join(virtual_path);
@@ -170,7 +186,7 @@ mod tests {
         // 1. Setup Engine
         let temp_elf = NamedTempFile::new().unwrap();
         let archive = ElfArchive::new().await.unwrap();
-        archive.save(temp_elf.path()).unwrap();
+        archive.save(temp_elf.path()).await.unwrap();
 
         let file_id = "test-file".to_string();
         let event_pool = archive.event_pool().await.unwrap();
@@ -1,5 +1,5 @@
 use crate::config;
-use crate::models::{Command, Editor, Grant};
+use crate::models::{Command, Editor, Grant, Role, RoleAssignment};
 use crate::state::AppState;
 use log;
 use specta::specta;
@@ -308,7 +308,9 @@ pub async fn get_active_editor(
 ///
 /// This command filters grants based on permissions:
 /// - Only returns grants for blocks where the user has core.read permission
+///   (for `subtree:`/`descendants:` grants, the block the scope is anchored at)
 /// - Wildcard grants (*) are always included as they are file-level
+/// - Expired or used-up grants are included with `expired: true`
 ///
 /// # Arguments
 /// * `file_id` - Unique identifier of the file
@@ -339,30 +341,43 @@ pub async fn list_grants(
             .ok_or_else(|| "No active editor".to_string())?
     };
 
-    // Get all grants from engine actor
+    // Get all grants (with limits and delegation details) from engine actor
     let grants_map = handle.get_all_grants().await;
+    let limits = handle.get_grant_limits().await;
+    let delegations = handle.get_grant_delegations().await;
+    let now = chrono::Utc::now();
 
     // Convert to Grant objects and filter by permission
     let mut grants = Vec::new();
     for (grant_editor_id, grant_list) in grants_map {
         for (cap_id, block_id) in grant_list {
+            let key = (grant_editor_id.clone(), cap_id, block_id);
+            let grant_limits = limits.get(&key).cloned();
+            let delegation = delegations.get(&key).cloned();
+            // Expired grants stay listed, marked as expired
+            let (_, cap_id, block_id) = key;
+            let grant = Grant::new(grant_editor_id.clone(), cap_id, block_id)
+                .with_limits(grant_limits, now)
+                .with_delegation(delegation);
+
             // Wildcard grants are file-level, always visible
-            if block_id == "*" {
-                grants.push(Grant::new(grant_editor_id.clone(), cap_id, block_id));
+            let Some(root) = grant.scope.root() else {
+                grants.push(grant);
                 continue;
-            }
+            };
 
-            // Block-specific grants: check core.read permission
+            // Block-scoped grants (including subtree/descendants): check core.read
+            // permission on the block the scope is anchored at
             let has_core_read = handle
                 .check_grant(
                     effective_editor_id.clone(),
                     "core.read".to_string(),
-                    block_id.clone(),
+                    root.to_string(),
                 )
                 .await;
 
             if has_core_read {
-                grants.push(Grant::new(grant_editor_id.clone(), cap_id, block_id));
+                grants.push(grant);
             }
         }
     }
@@ -372,7 +387,8 @@ pub async fn list_grants(
 
 /// Get grants for a specific block.
 ///
-/// Returns all grants that apply to this block (including wildcard grants).
+/// Returns all grants that apply to this block (including wildcard grants and
+/// subtree/descendants grants whose scope covers it).
 ///
 /// # Arguments
 /// * `file_id` - Unique identifier of the file
@@ -396,16 +412,119 @@ pub async fn get_block_grants(
 
     // Get grants for this block
     let grant_list = handle.get_block_grants(block_id).await;
+    let limits = handle.get_grant_limits().await;
+    let delegations = handle.get_grant_delegations().await;
+    let now = chrono::Utc::now();
 
-    // Convert to Grant objects
+    // Convert to Grant objects (expired grants are kept and marked as expired)
     let grants = grant_list
         .into_iter()
-        .map(|(editor_id, cap_id, block_id)| Grant::new(editor_id, cap_id, block_id))
+        .map(|key| {
+            let grant_limits = limits.get(&key).cloned();
+            let delegation = delegations.get(&key).cloned();
+            let (editor_id, cap_id, block_id) = key;
+            Grant::new(editor_id, cap_id, block_id)
+                .with_limits(grant_limits, now)
+                .with_delegation(delegation)
+        })
         .collect();
 
     Ok(grants)
 }
 
+/// List all deny rules for the specified file.
+///
+/// Deny rules take precedence over grants and roles; each is returned in the
+/// same shape as a grant (editor, capability or pattern, target and scope).
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(Vec<Grant>)` - List of deny rules
+/// * `Err(message)` - Error if file is not open
+#[tauri::command]
+#[specta]
+pub async fn list_denies(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<Grant>, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    let mut denies: Vec<Grant> = handle
+        .get_all_denies()
+        .await
+        .into_iter()
+        .flat_map(|(editor_id, rules)| {
+            rules
+                .into_iter()
+                .map(move |(cap_id, block_id)| Grant::new(editor_id.clone(), cap_id, block_id))
+        })
+        .collect();
+    denies.sort_by(|a, b| {
+        (&a.editor_id, &a.cap_id, &a.block_id).cmp(&(&b.editor_id, &b.cap_id, &b.block_id))
+    });
+
+    Ok(denies)
+}
+
+/// List all role definitions for the specified file.
+///
+/// Roles bundle capabilities under a name; they are defined with
+/// `core.define_role` and given to editors with `core.grant_role`.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(Vec<Role>)` - Role definitions sorted by name
+/// * `Err(message)` - Error if file is not open
+#[tauri::command]
+#[specta]
+pub async fn list_roles(file_id: String, state: State<'_, AppState>) -> Result<Vec<Role>, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    Ok(handle.get_roles().await)
+}
+
+/// List role assignments for the specified file.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `editor_id` - Optional editor ID to only list that editor's roles
+///
+/// # Returns
+/// * `Ok(Vec<RoleAssignment>)` - Which editor holds which role on which block
+/// * `Err(message)` - Error if file is not open
+#[tauri::command]
+#[specta]
+pub async fn list_role_assignments(
+    file_id: String,
+    editor_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<RoleAssignment>, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    let assignments = handle.get_role_assignments().await;
+
+    Ok(match editor_id {
+        Some(id) => assignments
+            .into_iter()
+            .filter(|a| a.editor_id == id)
+            .collect(),
+        None => assignments,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use crate::elf::ElfArchive;
@@ -421,7 +540,7 @@ mod tests {
         let elf_path = temp_elf.path();
 
         let archive = ElfArchive::new().await.unwrap();
-        archive.save(elf_path).unwrap();
+        archive.save(elf_path).await.unwrap();
 
         // Open archive and get event pool
         let archive = ElfArchive::open(elf_path).unwrap();
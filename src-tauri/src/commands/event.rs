@@ -1,6 +1,7 @@
 use crate::engine::StateProjector;
 use crate::models::{Block, Grant};
 use crate::state::AppState;
+use crate::utils::time;
 use serde::{Deserialize, Serialize};
 use specta::specta;
 use specta::Type;
@@ -49,15 +50,24 @@ pub async fn get_state_at_event(
         .ok_or_else(|| format!("Block '{}' not found at event '{}'", block_id, event_id))?
         .clone();
 
-    // 6. Extract grants
+    // 6. Extract grants (limited grants are marked expired as of the target event)
+    let at = time::parse_to_utc(&all_events[target_index].created_at)
+        .unwrap_or_else(|_| chrono::Utc::now());
     let mut grants = Vec::new();
     for (editor_id, pairs) in temp_projector.grants.as_map() {
         for (cap_id, target_block) in pairs {
-            grants.push(Grant::new(
-                editor_id.clone(),
-                cap_id.clone(),
-                target_block.clone(),
-            ));
+            let limits = temp_projector
+                .grants
+                .get_limits(editor_id, cap_id, target_block)
+                .cloned();
+            let key = (editor_id.clone(), cap_id.clone(), target_block.clone());
+            let delegation = temp_projector.grants.get_delegation(&key).cloned();
+            let (editor_id, cap_id, target_block) = key;
+            grants.push(
+                Grant::new(editor_id, cap_id, target_block)
+                    .with_limits(limits, at)
+                    .with_delegation(delegation),
+            );
         }
     }
 
@@ -0,0 +1,119 @@
+use crate::config;
+use crate::elf::ElfArchive;
+use crate::engine::spawn_engine;
+use crate::export::{self, GitExportReport, GitImportReport, SiteReport};
+use crate::state::AppState;
+use specta::specta;
+use std::path::Path;
+use tauri::State;
+
+/// Export an open file as a static HTML site.
+///
+/// Renders every block the active editor may read into `target_path`, with
+/// the directory blocks as navigation and a history page per block.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `target_path` - Directory to write the site into (created if missing)
+///
+/// # Returns
+/// * `Ok(report)` - Number of pages written and blocks left out
+/// * `Err(message)` - Error description if the file is not open or writing fails
+#[tauri::command]
+#[specta]
+pub async fn export_site(
+    file_id: String,
+    target_path: String,
+    state: State<'_, AppState>,
+) -> Result<SiteReport, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+    let editor_id = state
+        .get_active_editor(&file_id)
+        .ok_or_else(|| "No active editor set for this file".to_string())?;
+
+    export::export_site(&handle, &editor_id, Path::new(&target_path)).await
+}
+
+/// Export the history of an open file as a git repository.
+///
+/// Writes one commit per command into a new repository at `target_path`,
+/// authored by the command's editor. Blocks the active editor may not read
+/// are left out, history included.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `target_path` - Directory to create the repository in (must not exist or be empty)
+///
+/// # Returns
+/// * `Ok(report)` - Number of commits and files written, and blocks left out
+/// * `Err(message)` - Error description if the file is not open or git fails
+#[tauri::command]
+#[specta]
+pub async fn export_git(
+    file_id: String,
+    target_path: String,
+    state: State<'_, AppState>,
+) -> Result<GitExportReport, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+    let (_, archive) = state
+        .get_file_info(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+    let editor_id = state
+        .get_active_editor(&file_id)
+        .ok_or_else(|| "No active editor set for this file".to_string())?;
+
+    export::export_git(
+        &handle,
+        &editor_id,
+        Some(archive.temp_path()),
+        Path::new(&target_path),
+    )
+    .await
+}
+
+/// Import the history of a git repository into a new .elf file.
+///
+/// Replays the first-parent history of `HEAD` as commands, creating a
+/// directory block named after the repository and an editor per commit
+/// author. The system editor owns the new project. Open it with `open_file`.
+///
+/// # Arguments
+/// * `repo_path` - Path of the git repository
+/// * `target_path` - Path of the .elf file to create (must not exist)
+///
+/// # Returns
+/// * `Ok(report)` - Number of commits replayed, blocks created and files left out
+/// * `Err(message)` - Error description if the file exists, git fails or saving fails
+#[tauri::command]
+#[specta]
+pub async fn import_git(repo_path: String, target_path: String) -> Result<GitImportReport, String> {
+    let target = Path::new(&target_path);
+    if target.exists() {
+        return Err(format!("{} already exists", target.display()));
+    }
+    let editor_id = config::get_system_editor_id()?;
+
+    let archive = ElfArchive::new()
+        .await
+        .map_err(|e| format!("Failed to create project: {}", e))?;
+    let event_pool = archive
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to open event store: {}", e))?;
+    let handle = spawn_engine(target_path.clone(), event_pool).await?;
+    let report = export::import_git(&handle, Path::new(&repo_path), &editor_id).await;
+    handle.shutdown().await;
+    let report = report?;
+    archive
+        .save(target)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", target.display(), e))?;
+
+    Ok(report)
+}
@@ -1,5 +1,9 @@
 use crate::config;
-use crate::elf::ElfArchive;
+use crate::elf::merge::{self, MergeReport, MergeSide};
+use crate::elf::{
+    encryption, ArchiveKey, AutosavePolicy, ElfArchive, FsckReport, RecoveryInfo, RecoveryJournal,
+};
+use crate::engine::{ExternalChange, KeyStore, ReceiveReport, VerifyReport};
 use crate::models::Command;
 use crate::state::{AppState, FileInfo};
 use crate::utils::time;
@@ -26,7 +30,8 @@ pub struct FileMetadata {
 
 /// Bootstrap the editor system for a file.
 ///
-/// If no editors exist, creates a "system" editor and sets it as active.
+/// If no editors exist, creates a "system" editor and sets it as active (on a
+/// legacy file owned by someone else, it is set as active without being created).
 /// If editors exist but no active editor is set, sets the first editor as active.
 /// This ensures every file always has at least one editor available and selected.
 async fn bootstrap_editors(file_id: &str, state: &AppState) -> Result<(), String> {
@@ -56,14 +61,26 @@ async fn bootstrap_editors(file_id: &str, state: &AppState) -> Result<(), String
             }),
         );
 
-        let events = handle.process_command(cmd).await?;
-
-        // Extract the actual ID from the creation event
-        if let Some(event) = events.first() {
-            let created_editor_id = event.entity.clone();
-
-            // Set as active editor in current session state
-            state.set_active_editor(file_id.to_string(), created_editor_id);
+        // A legacy file without editors belongs to whoever wrote its first event;
+        // if that is someone else, open it without an active editor rather than
+        // act as an editor that doesn't exist in the project
+        match handle.process_command(cmd).await {
+            Ok(events) => {
+                // Extract the actual ID from the creation event
+                if let Some(event) = events.first() {
+                    let created_editor_id = event.entity.clone();
+
+                    // Set as active editor in current session state
+                    state.set_active_editor(file_id.to_string(), created_editor_id);
+                }
+            }
+            Err(e) => {
+                log::warn!(
+                    "Could not create the system editor for {}, leaving no active editor: {}",
+                    file_id,
+                    e
+                );
+            }
         }
     } else {
         // Editors exist - ensure one is set as active
@@ -101,8 +118,12 @@ pub async fn create_file(path: String, state: State<'_, AppState>) -> Result<Str
     // Save to specified path
     archive
         .save(Path::new(&path))
+        .await
         .map_err(|e| format!("Failed to save file: {}", e))?;
 
+    // A journal left by an earlier file at this path has nothing to do with this one
+    RecoveryJournal::remove(Path::new(&path))?;
+
     // Get event pool for this archive
     let event_pool = archive
         .event_pool()
@@ -110,16 +131,22 @@ pub async fn create_file(path: String, state: State<'_, AppState>) -> Result<Str
         .map_err(|e| format!("Failed to get event pool: {}", e))?;
 
     // Spawn engine actor for this file
-    state
+    let handle = state
         .engine_manager
         .spawn_engine(file_id.clone(), event_pool)
         .await?;
 
+    // Attach the file for autosave and crash recovery
+    let archive = Arc::new(archive);
+    handle
+        .attach_file(archive.clone(), PathBuf::from(&path), autosave_policy())
+        .await?;
+
     // Store file info
     state.files.insert(
         file_id.clone(),
         FileInfo {
-            archive: Arc::new(archive),
+            archive,
             path: PathBuf::from(&path),
         },
     );
@@ -135,6 +162,12 @@ pub async fn create_file(path: String, state: State<'_, AppState>) -> Result<Str
 
 /// Open an existing .elf file for editing.
 ///
+/// Encrypted files fail with "Archive is encrypted"; check with `is_file_encrypted`
+/// and open them with `open_encrypted_file`.
+///
+/// If an earlier session ended without saving, its changes are kept aside:
+/// check with `get_recovery_info`, then `restore_recovery` or `discard_recovery`.
+///
 /// # Arguments
 /// * `path` - Absolute path to the .elf file to open
 ///
@@ -144,13 +177,216 @@ pub async fn create_file(path: String, state: State<'_, AppState>) -> Result<Str
 #[tauri::command]
 #[specta]
 pub async fn open_file(path: String, state: State<'_, AppState>) -> Result<String, String> {
-    // Generate unique file ID
-    let file_id = format!("file-{}", uuid::Uuid::new_v4());
-
     // Open existing archive
     let archive =
         ElfArchive::open(Path::new(&path)).map_err(|e| format!("Failed to open file: {}", e))?;
 
+    register_opened_file(path, archive, &state).await
+}
+
+/// Check whether a .elf file is encrypted (and needs a key to open).
+///
+/// # Arguments
+/// * `path` - Absolute path to the .elf file
+///
+/// # Returns
+/// * `Ok(bool)` - Whether the file is encrypted
+/// * `Err(message)` - Error description if the file can't be read
+#[tauri::command]
+#[specta]
+pub async fn is_file_encrypted(path: String) -> Result<bool, String> {
+    encryption::is_encrypted_file(Path::new(&path))
+        .map_err(|e| format!("Failed to read file: {}", e))
+}
+
+/// Open an encrypted .elf file for editing.
+///
+/// The key is kept for the session, so saving encrypts the file again.
+///
+/// # Arguments
+/// * `path` - Absolute path to the .elf file to open
+/// * `key` - Passphrase or key file the file is encrypted with
+///
+/// # Returns
+/// * `Ok(file_id)` - Unique identifier for the opened file
+/// * `Err(message)` - Error description if the key is wrong or opening fails
+#[tauri::command]
+#[specta]
+pub async fn open_encrypted_file(
+    path: String,
+    key: ArchiveKey,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let archive = ElfArchive::open_with_key(Path::new(&path), Some(&key))
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    register_opened_file(path, archive, &state).await
+}
+
+/// Set, change or remove the encryption of an open file.
+///
+/// The file is saved right away so that it no longer sits on disk under the
+/// old key (or unencrypted). This also saves any other unsaved changes.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `key` - New passphrase or key file, or None to store the file unencrypted
+///
+/// # Returns
+/// * `Ok(())` - File saved with the new encryption
+/// * `Err(message)` - Error description if the file is not open or saving fails
+#[tauri::command]
+#[specta]
+pub async fn set_file_encryption(
+    file_id: String,
+    key: Option<ArchiveKey>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let archive = state
+        .files
+        .get(&file_id)
+        .map(|file_info| file_info.archive.clone())
+        .ok_or_else(|| format!("File '{}' not found", file_id))?;
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    archive.set_encryption(key);
+    handle.save_file().await
+}
+
+/// Get a summary of the unsaved changes an earlier session of this file left behind.
+///
+/// Call after opening a file to offer a restore.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(Some(info))` - Number, editors and time span of the recoverable events
+/// * `Ok(None)` - Nothing to recover
+/// * `Err(message)` - Error description if the file is not open
+#[tauri::command]
+#[specta]
+pub async fn get_recovery_info(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<RecoveryInfo>, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    Ok(handle.get_recovery_info().await)
+}
+
+/// Restore the unsaved changes an earlier session of this file left behind.
+///
+/// The restored changes are unsaved until the file is saved (or autosaved).
+/// Recovery has to be decided before editing: the first new change discards them.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(count)` - Number of restored events
+/// * `Err(message)` - Error description if the file is not open or restoring fails
+#[tauri::command]
+#[specta]
+pub async fn restore_recovery(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    handle.restore_recovery().await
+}
+
+/// Throw away the unsaved changes an earlier session of this file left behind.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(count)` - Number of discarded events
+/// * `Err(message)` - Error description if the file is not open
+#[tauri::command]
+#[specta]
+pub async fn discard_recovery(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    handle.discard_recovery().await
+}
+
+/// Get the autosave policy from the global config.
+///
+/// # Returns
+/// * `Ok(policy)` - When open files are saved automatically
+/// * `Err(message)` - Error if config cannot be read
+#[tauri::command]
+#[specta]
+pub async fn get_autosave_policy() -> Result<AutosavePolicy, String> {
+    Ok(config::load_config()?.autosave)
+}
+
+/// Set the autosave policy in the global config and apply it to all open files.
+///
+/// # Arguments
+/// * `policy` - Save every N events and/or N seconds after the first unsaved change
+///
+/// # Returns
+/// * `Ok(())` - Policy stored and applied
+/// * `Err(message)` - Error if the policy is invalid or config cannot be written
+#[tauri::command]
+#[specta]
+pub async fn set_autosave_policy(
+    policy: AutosavePolicy,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if policy.every_events == Some(0) || policy.every_seconds == Some(0) {
+        return Err("Autosave limits must be greater than zero".to_string());
+    }
+
+    let mut global_config = config::load_config()?;
+    global_config.autosave = policy;
+    config::save_config(&global_config)?;
+
+    let file_ids: Vec<String> = state.files.iter().map(|e| e.key().clone()).collect();
+    for file_id in file_ids {
+        if let Some(handle) = state.engine_manager.get_engine(&file_id) {
+            handle.set_autosave_policy(policy).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Autosave policy for newly opened files (off if the config can't be read).
+fn autosave_policy() -> AutosavePolicy {
+    config::load_config()
+        .map(|global_config| global_config.autosave)
+        .unwrap_or_default()
+}
+
+/// Spawn the engine for an opened archive and register it as an open file.
+async fn register_opened_file(
+    path: String,
+    archive: ElfArchive,
+    state: &State<'_, AppState>,
+) -> Result<String, String> {
+    // Generate unique file ID
+    let file_id = format!("file-{}", uuid::Uuid::new_v4());
+
     // Get event pool for this archive
     let event_pool = archive
         .event_pool()
@@ -158,29 +394,41 @@ pub async fn open_file(path: String, state: State<'_, AppState>) -> Result<Strin
         .map_err(|e| format!("Failed to get event pool: {}", e))?;
 
     // Spawn engine actor for this file
-    state
+    let handle = state
         .engine_manager
         .spawn_engine(file_id.clone(), event_pool)
         .await?;
 
+    // Attach the file for autosave, picking up what an earlier session left unsaved
+    let archive = Arc::new(archive);
+    handle
+        .attach_file(archive.clone(), PathBuf::from(&path), autosave_policy())
+        .await?;
+
     // Store file info
     state.files.insert(
         file_id.clone(),
         FileInfo {
-            archive: Arc::new(archive),
+            archive,
             path: PathBuf::from(&path),
         },
     );
 
     // Bootstrap editors (create system editor if none exist)
-    bootstrap_editors(&file_id, &state).await?;
+    bootstrap_editors(&file_id, state).await?;
 
     Ok(file_id)
 }
 
 /// Save the current state of a file to disk.
 ///
-/// This persists all changes made since the file was opened or last saved.
+/// This persists all changes made since the file was opened or last saved. The save
+/// is atomic, and the previous version is kept next to the file as `{name}.elf.bak`.
+/// Saving also clears the file's crash recovery journal.
+///
+/// If another program rewrote the file since it was opened or last saved, the
+/// save fails instead of overwriting those changes; see `check_external_changes`
+/// and `merge_external_changes`.
 ///
 /// # Arguments
 /// * `file_id` - Unique identifier of the file to save
@@ -191,25 +439,81 @@ pub async fn open_file(path: String, state: State<'_, AppState>) -> Result<Strin
 #[tauri::command]
 #[specta]
 pub async fn save_file(file_id: String, state: State<'_, AppState>) -> Result<(), String> {
-    // Get file info
-    let file_info = state
-        .files
-        .get(&file_id)
-        .ok_or_else(|| format!("File '{}' not found", file_id))?;
+    if !state.files.contains_key(&file_id) {
+        return Err(format!("File '{}' not found", file_id));
+    }
 
-    // Save to original path
-    file_info
-        .archive
-        .save(&file_info.path)
-        .map_err(|e| format!("Failed to save file: {}", e))?;
+    // The engine saves to the original path, so it can keep its journal in step
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
 
-    Ok(())
+    handle.save_file().await
+}
+
+/// Check whether another program rewrote a file since it was opened or last saved.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(Some(change))` - Number and editors of the events only the file on disk has
+/// * `Ok(None)` - The file on disk is the one opened or last saved
+/// * `Err(message)` - Error description if the file is not open or cannot be read
+#[tauri::command]
+#[specta]
+pub async fn check_external_changes(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<ExternalChange>, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    handle.check_external_change().await
+}
+
+/// Merge the changes another program wrote to a file into the open project.
+///
+/// The events only the file on disk has are added to this session, after which
+/// the file can be saved again without losing either side's work. They are
+/// checked against this session's grants (and, in a signed file, against the
+/// trusted keys of their authors) first; refused events are reported and
+/// dropped from the file at the next save. In a signed file the active editor
+/// signs the merged events into the chain.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(report)` - Events found on disk, merged and refused
+/// * `Err(message)` - Error description if the file is not open or cannot be read
+#[tauri::command]
+#[specta]
+pub async fn merge_external_changes(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<ReceiveReport, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    // Merged events are signed into the chain in the name of an editor identity.
+    // If no active editor is set, use the system editor as fallback.
+    let merger = state
+        .get_active_editor(&file_id)
+        .unwrap_or_else(|| config::get_system_editor_id().unwrap_or_else(|_| "system".to_string()));
+
+    handle.merge_external_changes(&merger).await
 }
 
 /// Close a file and release associated resources.
 ///
 /// This shuts down the engine actor and removes the file from memory.
-/// Unsaved changes will be lost.
+/// Unsaved changes will be lost, and are not offered for recovery either.
 ///
 /// # Arguments
 /// * `file_id` - Unique identifier of the file to close
@@ -220,6 +524,17 @@ pub async fn save_file(file_id: String, state: State<'_, AppState>) -> Result<()
 #[tauri::command]
 #[specta]
 pub async fn close_file(file_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    // Drop the recovery journal: closing is a deliberate end of the session
+    if let Some(handle) = state.engine_manager.get_engine(&file_id) {
+        handle.detach_file().await?;
+    }
+
+    // Stop accepting sync sessions and watching linked folders
+    state.sync_servers.remove(&file_id);
+    state
+        .link_watchers
+        .retain(|(watched, _), _| watched != &file_id);
+
     // Shutdown engine actor
     state.engine_manager.shutdown_engine(&file_id).await?;
 
@@ -246,6 +561,122 @@ pub async fn list_open_files(state: State<'_, AppState>) -> Result<Vec<String>,
     Ok(file_ids)
 }
 
+/// Check an open file for integrity problems (fsck).
+///
+/// Reports orphan block directories, stale snapshots, dangling directory entries
+/// and relations, and grants for deleted editors or blocks. With `repair`, the
+/// problems are fixed: corrective events are committed as the active editor
+/// (falling back to the system editor) and block files are rewritten or removed.
+/// Save the file afterwards to persist the repair.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `repair` - Whether to repair the problems found
+///
+/// # Returns
+/// * `Ok(FsckReport)` - Problems found (before repair), grouped by category
+/// * `Err(message)` - Error if file is not open or repair fails
+#[tauri::command]
+#[specta]
+pub async fn check_integrity(
+    file_id: String,
+    repair: bool,
+    state: State<'_, AppState>,
+) -> Result<FsckReport, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    let editor_id = match state.get_active_editor(&file_id) {
+        Some(editor_id) => editor_id,
+        None => config::get_system_editor_id()?,
+    };
+
+    handle.check_integrity(repair, editor_id).await
+}
+
+/// Enable signed events for an open file.
+///
+/// Every event persisted from now on links the previous one in a hash chain and
+/// is signed with its editor's key from `~/.elf/keys` (created on first use).
+/// Files that already carry a chain keep signing when reopened.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(())` - Signing is enabled
+/// * `Err(message)` - Error if file is not open or the key directory is unavailable
+#[tauri::command]
+#[specta]
+pub async fn enable_event_signing(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    handle.enable_signing(KeyStore::default_location()?).await
+}
+
+/// Get the public key (hex) an editor signs with, to share with other machines.
+///
+/// # Arguments
+/// * `editor_id` - Editor whose key to look up
+///
+/// # Returns
+/// * `Ok(Some(key))` - The editor's enrolled or trusted public key
+/// * `Ok(None)` - The editor has no key on this machine
+/// * `Err(message)` - Error if the key directory is unavailable
+#[tauri::command]
+#[specta]
+pub async fn get_editor_public_key(editor_id: String) -> Result<Option<String>, String> {
+    KeyStore::default_location()?.public_key(&editor_id)
+}
+
+/// Trust the public key an editor signs with on another machine.
+///
+/// Events signed by that editor are only verified (and accepted from sync
+/// peers) once its key is trusted.
+///
+/// # Arguments
+/// * `editor_id` - Editor the key belongs to
+/// * `public_key` - Hex-encoded ed25519 public key
+///
+/// # Returns
+/// * `Ok(())` - The key is trusted
+/// * `Err(message)` - Error if the key is invalid or the editor signs on this machine
+#[tauri::command]
+#[specta]
+pub async fn trust_editor_key(editor_id: String, public_key: String) -> Result<(), String> {
+    KeyStore::default_location()?.trust(&editor_id, public_key.trim())
+}
+
+/// Verify the hash chain and signatures of an open file's events.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(VerifyReport)` - Every event whose chain link, hash or signature is broken
+/// * `Err(message)` - Error if file is not open
+#[tauri::command]
+#[specta]
+pub async fn verify_events(
+    file_id: String,
+    state: State<'_, AppState>,
+) -> Result<VerifyReport, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+
+    handle.verify_events().await
+}
+
 /// Get all events for a specific file.
 ///
 /// This command filters events based on permissions:
@@ -447,6 +878,11 @@ pub async fn rename_file(
         }
     })?;
 
+    // Move the recovery journal along
+    if let Some(handle) = state.engine_manager.get_engine(&file_id) {
+        handle.set_file_path(new_path.clone()).await?;
+    }
+
     // Update path in state
     if let Some(mut entry) = state.files.get_mut(&file_id) {
         entry.path = new_path;
@@ -513,6 +949,60 @@ pub async fn duplicate_file(file_id: String, state: State<'_, AppState>) -> Resu
     Ok(new_file_id)
 }
 
+/// Merge two divergent copies of the same project into a new .elf file.
+///
+/// The event logs are combined and ordered causally. A block both copies wrote
+/// through the same capability, or one copy deleted and the other wrote, is
+/// reported as a conflict, and the preferred copy's version is kept. Events
+/// that make it win are made by `editor_id`, who must be allowed to; in a
+/// signed project it also signs the merged events into the chain. The merged
+/// file is encrypted like `ours_path`; open it with `open_file` (or
+/// `open_encrypted_file`) afterwards.
+///
+/// # Arguments
+/// * `ours_path` - Absolute path to our copy
+/// * `theirs_path` - Absolute path to their copy
+/// * `output_path` - Absolute path for the merged file (must not exist)
+/// * `prefer` - Copy whose writes win conflicts
+/// * `editor_id` - Editor that resolves conflicts and signs merged events
+/// * `key` - Passphrase or key file, if the copies are encrypted
+///
+/// # Returns
+/// * `Ok(report)` - Shared and new events per side, and the conflicts found
+/// * `Err(message)` - Error description if the copies share no history or merging fails
+#[tauri::command]
+#[specta]
+pub async fn merge_files(
+    ours_path: String,
+    theirs_path: String,
+    output_path: String,
+    prefer: MergeSide,
+    editor_id: String,
+    key: Option<ArchiveKey>,
+) -> Result<MergeReport, String> {
+    let output_path = PathBuf::from(&output_path);
+    if output_path.exists() {
+        return Err(format!("File '{}' already exists", output_path.display()));
+    }
+
+    let open = |path: &str| {
+        ElfArchive::open_with_key(Path::new(path), key.as_ref())
+            .map_err(|e| format!("Failed to open {}: {}", path, e))
+    };
+    let ours = open(&ours_path)?;
+    let theirs = open(&theirs_path)?;
+
+    let keys = KeyStore::default_location()?;
+    let (merged, report) = merge::merge_archives(&ours, &theirs, prefer, &editor_id, &keys).await?;
+    merged.set_encryption(ours.encryption_key());
+    merged
+        .save(&output_path)
+        .await
+        .map_err(|e| format!("Failed to save file: {}", e))?;
+
+    Ok(report)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
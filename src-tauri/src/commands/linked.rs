@@ -0,0 +1,138 @@
+use crate::extensions::directory::linked::{self, LinkSyncOptions, LinkSyncReport, LinkWatcher};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use specta::{specta, Type};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+/// Event emitted when a watched linked directory changed.
+pub const LINKED_DIRECTORY_EVENT: &str = "linked-directory-changed";
+
+/// How often a watched folder is rescanned by default
+const DEFAULT_WATCH_INTERVAL_MS: u32 = 2000;
+/// Shortest interval a folder may be rescanned at
+const MIN_WATCH_INTERVAL_MS: u32 = 250;
+
+/// Payload of the `linked-directory-changed` event.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct LinkedDirectoryChange {
+    pub file_id: String,
+    pub block_id: String,
+    pub report: LinkSyncReport,
+}
+
+/// Sync a directory block with the folder it was imported from.
+///
+/// Picks up files added, changed, renamed and deleted on disk, and with
+/// `options.push` writes blocks edited in Elfiee back to their files.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `block_id` - The directory block (imported with `directory.import`)
+/// * `options` - Whether to push block edits, and which side wins conflicts
+///
+/// # Returns
+/// * `Ok(report)` - What changed on each side, conflicts and skipped files
+/// * `Err(message)` - Error description if the block is not linked or its folder is gone
+#[tauri::command]
+#[specta]
+pub async fn refresh_linked_directory(
+    file_id: String,
+    block_id: String,
+    options: LinkSyncOptions,
+    state: State<'_, AppState>,
+) -> Result<LinkSyncReport, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+    let editor_id = state
+        .get_active_editor(&file_id)
+        .ok_or_else(|| "No active editor set for this file".to_string())?;
+
+    linked::refresh_linked(&handle, &editor_id, &block_id, &options).await
+}
+
+/// Keep a directory block in sync with its folder until unwatched or closed.
+///
+/// The folder is rescanned every `interval_ms` (default 2000) as the active
+/// editor; each refresh that changes something emits `linked-directory-changed`.
+/// Replaces a watcher already running for the block.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `block_id` - The directory block (imported with `directory.import`)
+/// * `options` - Whether to push block edits, and which side wins conflicts
+/// * `interval_ms` - Time between rescans (at least 250)
+#[tauri::command]
+#[specta]
+pub async fn watch_linked_directory(
+    file_id: String,
+    block_id: String,
+    options: LinkSyncOptions,
+    interval_ms: Option<u32>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+    let editor_id = state
+        .get_active_editor(&file_id)
+        .ok_or_else(|| "No active editor set for this file".to_string())?;
+    let interval = interval_ms
+        .unwrap_or(DEFAULT_WATCH_INTERVAL_MS)
+        .max(MIN_WATCH_INTERVAL_MS);
+
+    // Stop the old watcher first, so two never refresh the block at once
+    state
+        .link_watchers
+        .remove(&(file_id.clone(), block_id.clone()));
+
+    // Fail now rather than in the background if the block can't be synced
+    let report = linked::refresh_linked(&handle, &editor_id, &block_id, &options).await?;
+    let change = |report: LinkSyncReport| LinkedDirectoryChange {
+        file_id: file_id.clone(),
+        block_id: block_id.clone(),
+        report,
+    };
+    if !report.is_empty() {
+        let _ = app.emit(LINKED_DIRECTORY_EVENT, change(report));
+    }
+
+    let template = change(LinkSyncReport::default());
+    let watcher = LinkWatcher::start(
+        handle,
+        editor_id,
+        block_id.clone(),
+        options,
+        Duration::from_millis(interval as u64),
+        move |report| {
+            let _ = app.emit(
+                LINKED_DIRECTORY_EVENT,
+                LinkedDirectoryChange {
+                    report,
+                    ..template.clone()
+                },
+            );
+        },
+    );
+    state.link_watchers.insert((file_id, block_id), watcher);
+    Ok(())
+}
+
+/// Stop keeping a directory block in sync with its folder.
+///
+/// # Returns
+/// * `Ok(())` - Watcher stopped, or none was running
+#[tauri::command]
+#[specta]
+pub async fn unwatch_linked_directory(
+    file_id: String,
+    block_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.link_watchers.remove(&(file_id, block_id));
+    Ok(())
+}
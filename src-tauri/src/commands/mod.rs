@@ -2,13 +2,19 @@ pub mod block;
 pub mod checkout;
 pub mod editor;
 pub mod event;
+pub mod export;
 pub mod file;
+pub mod linked;
+pub mod sync;
 
 // Re-export all commands for easy registration
-pub use block::{check_permission, execute_command, get_all_blocks, get_block, rename_block};
+pub use block::{
+    check_permission, execute_command, get_all_blocks, get_block, list_trash, rename_block,
+    validate_references,
+};
 pub use checkout::checkout_workspace;
 pub use event::get_state_at_event;
 pub use file::{
-    close_file, create_file, get_all_events, get_file_info, list_open_files, open_file,
-    rename_file, save_file, FileMetadata,
+    check_integrity, close_file, create_file, get_all_events, get_file_info, list_open_files,
+    open_file, rename_file, save_file, FileMetadata,
 };
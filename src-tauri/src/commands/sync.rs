@@ -0,0 +1,100 @@
+use crate::state::AppState;
+use crate::sync::{self, SyncReport, SyncServer};
+use specta::specta;
+use std::net::SocketAddr;
+use tauri::State;
+
+/// Let peers sync with an open file.
+///
+/// Listens on localhost, or on all interfaces if `public` is true. Replaces a
+/// server already running for the file. Only peers that know `secret` are
+/// served; their events are taken in by the file's active editor, whose key
+/// must be enrolled.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `port` - Port to listen on (None picks a free port)
+/// * `public` - Whether peers on other machines may connect
+/// * `secret` - Sync secret shared with the peers
+///
+/// # Returns
+/// * `Ok(address)` - Address peers connect to, e.g. "127.0.0.1:47300"
+/// * `Err(message)` - Error description if the file is not open or the port is taken
+#[tauri::command]
+#[specta]
+pub async fn start_sync_server(
+    file_id: String,
+    port: Option<u16>,
+    public: bool,
+    secret: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+    let receiver = active_editor(&state, &file_id)?;
+
+    let ip = if public { [0, 0, 0, 0] } else { [127, 0, 0, 1] };
+    let addr = SocketAddr::from((ip, port.unwrap_or(0)));
+
+    // Stop the old server first, so it can be restarted on the same port
+    state.sync_servers.remove(&file_id);
+    let server = SyncServer::start(handle, addr, secret, receiver).await?;
+    let local_addr = server.local_addr().to_string();
+    state.sync_servers.insert(file_id, server);
+
+    Ok(local_addr)
+}
+
+/// Stop accepting sync sessions for a file.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+///
+/// # Returns
+/// * `Ok(())` - Server stopped, or none was running
+#[tauri::command]
+#[specta]
+pub async fn stop_sync_server(file_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state.sync_servers.remove(&file_id);
+    Ok(())
+}
+
+/// Exchange missing events with a peer running the same project.
+///
+/// The file must be signed. Received events are verified, applied and marked
+/// unsaved; events with a bad signature, or whose editor lacks the capability
+/// under this file's grants, are refused and reported.
+///
+/// # Arguments
+/// * `file_id` - Unique identifier of the file
+/// * `address` - Address of the peer, e.g. "192.168.1.20:47300"
+/// * `secret` - Sync secret shared with the peer
+///
+/// # Returns
+/// * `Ok(report)` - Events sent, received, applied and rejected
+/// * `Err(message)` - Error description if the peer can't be reached or has another project
+#[tauri::command]
+#[specta]
+pub async fn sync_with_peer(
+    file_id: String,
+    address: String,
+    secret: String,
+    state: State<'_, AppState>,
+) -> Result<SyncReport, String> {
+    let handle = state
+        .engine_manager
+        .get_engine(&file_id)
+        .ok_or_else(|| format!("File '{}' is not open", file_id))?;
+    let receiver = active_editor(&state, &file_id)?;
+
+    sync::connect(&handle, &address, &secret, &receiver).await
+}
+
+/// Editor that takes in events from peers: the file's active editor.
+fn active_editor(state: &AppState, file_id: &str) -> Result<String, String> {
+    state
+        .get_active_editor(file_id)
+        .ok_or_else(|| format!("File '{}' has no active editor", file_id))
+}
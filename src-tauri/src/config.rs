@@ -2,6 +2,7 @@
 ///
 /// This module handles persistent configuration stored in the user's home directory.
 /// Configuration is stored at: `$USER_HOME/.elf/config.json`
+use crate::elf::recovery::AutosavePolicy;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -12,12 +13,17 @@ pub struct GlobalConfig {
     /// Unique system editor ID for this machine
     /// This ID is generated once and persists across all file operations
     pub system_editor_id: String,
+
+    /// When open files are saved automatically (off unless configured)
+    #[serde(default)]
+    pub autosave: AutosavePolicy,
 }
 
 impl Default for GlobalConfig {
     fn default() -> Self {
         Self {
             system_editor_id: uuid::Uuid::new_v4().to_string(),
+            autosave: AutosavePolicy::default(),
         }
     }
 }
@@ -85,6 +91,28 @@ pub fn save_config(config: &GlobalConfig) -> Result<(), String> {
     Ok(())
 }
 
+/// Get the directory holding per-editor signing keys
+///
+/// Returns: `$USER_HOME/.elf/keys` (next to the config file)
+pub fn get_keys_dir() -> Result<PathBuf, String> {
+    let config_path = get_config_path()?;
+    let config_dir = config_path
+        .parent()
+        .ok_or("Config path has no parent directory")?;
+    Ok(config_dir.join("keys"))
+}
+
+/// Get the directory holding crash recovery journals of open files
+///
+/// Returns: `$USER_HOME/.elf/recovery` (next to the config file)
+pub fn get_recovery_dir() -> Result<PathBuf, String> {
+    let config_path = get_config_path()?;
+    let config_dir = config_path
+        .parent()
+        .ok_or("Config path has no parent directory")?;
+    Ok(config_dir.join("recovery"))
+}
+
 /// Get the system editor ID for this machine
 ///
 /// This is a convenience function that loads the config and returns the system editor ID.
@@ -168,6 +196,7 @@ mod tests {
         with_temp_config(|_temp_dir| {
             let original_config = GlobalConfig {
                 system_editor_id: "test-id-12345".to_string(),
+                autosave: AutosavePolicy::default(),
             };
 
             // Save config
@@ -1,6 +1,13 @@
+use crate::elf::blobs::BlobStore;
+use crate::elf::encryption::{self, ArchiveKey};
+use crate::elf::lazy::{self, LazyEntries};
+use crate::elf::manifest::{self, CompressionPolicy, Manifest, ManifestEntry, MANIFEST_NAME};
 use crate::engine::{EventPoolWithPath, EventStore};
+use sha2::{Digest, Sha256};
 use std::fs::File;
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 use walkdir::WalkDir;
 use zip::{ZipArchive, ZipWriter};
@@ -8,6 +15,24 @@ use zip::{ZipArchive, ZipWriter};
 pub struct ElfArchive {
     temp_dir: TempDir,
     db_path: PathBuf,
+    /// Key the archive is encrypted with on save (None for a plain zip)
+    encryption: Mutex<Option<ArchiveKey>>,
+    /// Event pool handed out by `event_pool`, checkpointed before each save
+    event_pool: Mutex<Option<EventPoolWithPath>>,
+    /// Block directories still in the original zip (None if everything is extracted)
+    lazy: Option<Arc<LazyEntries>>,
+    /// Compression `save` picks for each entry
+    compression: Mutex<CompressionPolicy>,
+}
+
+impl std::fmt::Debug for ElfArchive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ElfArchive")
+            .field("temp_dir", &self.temp_dir.path())
+            .field("db_path", &self.db_path)
+            .field("encrypted", &self.is_encrypted())
+            .finish_non_exhaustive()
+    }
 }
 
 impl ElfArchive {
@@ -17,59 +42,282 @@ impl ElfArchive {
         let db_path = temp_dir.path().join("events.db");
 
         // Initialize empty event store
-        EventStore::create(db_path.to_str().unwrap())
+        let event_pool = EventStore::create(db_path.to_str().unwrap())
             .await
             .map_err(std::io::Error::other)?;
 
-        Ok(Self { temp_dir, db_path })
+        Ok(Self {
+            temp_dir,
+            db_path,
+            encryption: Mutex::new(None),
+            event_pool: Mutex::new(Some(event_pool)),
+            lazy: None,
+            compression: Mutex::new(CompressionPolicy::default()),
+        })
     }
 
     /// Open an existing .elf archive
     ///
-    /// Extracts all files from the archive, including events.db and all block directories.
+    /// Extracts events.db and the other top-level files; block directories are
+    /// extracted when first used (see `block_dir` and `temp_path`).
+    /// Fails with `ErrorKind::PermissionDenied` if the archive is encrypted; use
+    /// `open_with_key` for those. Fails with `ErrorKind::Unsupported` if the
+    /// archive was written in a newer format than this version reads, and with
+    /// `ErrorKind::InvalidData` if its contents don't match its manifest.
     pub fn open(elf_path: &Path) -> std::io::Result<Self> {
-        let file = File::open(elf_path)?;
-        let mut archive = ZipArchive::new(file)?;
+        Self::open_with_key(elf_path, None)
+    }
 
+    /// Open an existing .elf archive that may be encrypted.
+    ///
+    /// The key is only used if the archive is encrypted; it is kept so that
+    /// `save` encrypts the archive again. Encrypted archives are decrypted in
+    /// memory and extracted completely.
+    pub fn open_with_key(elf_path: &Path, key: Option<&ArchiveKey>) -> std::io::Result<Self> {
         let temp_dir = TempDir::new()?;
         let db_path = temp_dir.path().join("events.db");
 
-        // Extract all files from zip
+        let (encryption, lazy) = if encryption::is_encrypted_file(elf_path)? {
+            let data = std::fs::read(elf_path)?;
+            let plain = encryption::decrypt(&data, key)?;
+            Self::extract(ZipArchive::new(Cursor::new(plain))?, temp_dir.path())?;
+            (key.cloned(), None)
+        } else {
+            let source = ZipArchive::new(File::open(elf_path)?)?;
+            let lazy = LazyEntries::open(source, temp_dir.path())?;
+            (None, Some(Arc::new(lazy)))
+        };
+
+        Ok(Self {
+            temp_dir,
+            db_path,
+            encryption: Mutex::new(encryption),
+            event_pool: Mutex::new(None),
+            lazy,
+            compression: Mutex::new(CompressionPolicy::default()),
+        })
+    }
+
+    /// Extract a block directory (`block-{id}`) that is still in the zip.
+    pub fn load_block_dir(&self, name: &str) -> std::io::Result<()> {
+        match &self.lazy {
+            Some(lazy) => lazy.load(name),
+            None => Ok(()),
+        }
+    }
+
+    /// Extract every block directory still in the zip.
+    ///
+    /// Needed before working on the whole archive directory (merge, fsck).
+    pub fn load_all(&self) -> std::io::Result<()> {
+        match &self.lazy {
+            Some(lazy) => lazy.load_all(),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the archive has a top-level entry `name`, extracted or not.
+    pub fn contains(&self, name: &str) -> bool {
+        self.temp_dir.path().join(name).exists()
+            || self
+                .lazy
+                .as_ref()
+                .is_some_and(|lazy| lazy.pending().iter().any(|pending| pending == name))
+    }
+
+    /// Extract all files from a zip archive into `dir`, checking them against its manifest.
+    fn extract<R: Read + Seek>(mut archive: ZipArchive<R>, dir: &Path) -> std::io::Result<()> {
+        let manifest = Manifest::read(&mut archive)?;
         for i in 0..archive.len() {
-            let mut zip_file = archive.by_index(i)?;
-            let outpath = temp_dir.path().join(zip_file.name());
-
-            // Extract file (directories are created automatically by create_dir_all)
-            if zip_file.is_file() {
-                // Create parent directory if needed
-                if let Some(parent) = outpath.parent() {
-                    std::fs::create_dir_all(parent)?;
-                }
-                let mut outfile = File::create(&outpath)?;
-                std::io::copy(&mut zip_file, &mut outfile)?;
-            }
+            lazy::extract_entry(&mut archive, i, dir, manifest.as_ref())?;
         }
 
-        Ok(Self { temp_dir, db_path })
+        Ok(())
     }
 
     /// Get an EventPoolWithPath for reading/writing events
+    ///
+    /// The pool is shared by all callers, so `save` can checkpoint it.
     pub async fn event_pool(&self) -> Result<EventPoolWithPath, sqlx::Error> {
-        EventStore::create(self.db_path.to_str().unwrap()).await
+        if let Some(pool) = self.event_pool.lock().unwrap().clone() {
+            return Ok(pool);
+        }
+        let mut pool = EventStore::create(self.db_path.to_str().unwrap()).await?;
+        pool.block_loader = self.lazy.clone();
+        *self.event_pool.lock().unwrap() = Some(pool.clone());
+        Ok(pool)
+    }
+
+    /// Path of the backup kept next to a .elf file (`{name}.elf.bak`).
+    pub fn backup_path(elf_path: &Path) -> PathBuf {
+        let mut name = elf_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".bak");
+        elf_path.with_file_name(name)
     }
 
     /// Save the archive to a .elf file
     ///
     /// Recursively saves all files in temp_dir, including events.db and all block directories.
-    pub fn save(&self, elf_path: &Path) -> std::io::Result<()> {
-        let file = File::create(elf_path)?;
-        let mut zip = ZipWriter::new(file);
+    /// Block directories never extracted are copied from the original zip as they are.
+    /// The zip is encrypted first if the archive has an encryption key.
+    ///
+    /// The save is crash-safe:
+    /// 1. The event store's write-ahead log is checkpointed into events.db
+    /// 2. The archive is written to a temp file in the same directory and fsynced
+    /// 3. The previous file is kept as `{name}.elf.bak` (replacing the older backup)
+    /// 4. The temp file is atomically renamed over the .elf file
+    ///
+    /// A crash at any point leaves either the old or the new file in place.
+    /// Blobs no longer referenced by any event are dropped before writing.
+    pub async fn save(&self, elf_path: &Path) -> std::io::Result<()> {
+        // 1. Flush the WAL into events.db. The (now empty) WAL file is still archived,
+        //    in case events were committed after the checkpoint
+        let event_pool = self.event_pool.lock().unwrap().clone();
+        if let Some(pool) = &event_pool {
+            EventStore::checkpoint(&pool.pool)
+                .await
+                .map_err(std::io::Error::other)?;
+        }
 
-        let options =
-            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        // 1.5. Garbage-collect the blob store
+        let blob_store = BlobStore::new(self.temp_dir.path());
+        if !blob_store.list()?.is_empty() {
+            let pool = match event_pool {
+                Some(pool) => pool,
+                None => self.event_pool().await.map_err(std::io::Error::other)?,
+            };
+            let referenced = EventStore::referenced_blobs(&pool.pool)
+                .await
+                .map_err(std::io::Error::other)?;
+            let removed = blob_store.collect_garbage(&referenced)?;
+            if removed > 0 {
+                log::debug!("Removed {} unreferenced blobs", removed);
+            }
+        }
+
+        // 2. Write and fsync a temp file next to the target (same filesystem, so rename is atomic)
+        let dir = match elf_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut temp = tempfile::Builder::new()
+            .prefix(".elf-save-")
+            .suffix(".tmp")
+            .tempfile_in(dir)?;
+
+        let key = self.encryption.lock().unwrap().clone();
+        match &key {
+            Some(key) => {
+                let plain = self.write_zip(Cursor::new(Vec::new()))?.into_inner();
+                temp.write_all(&encryption::encrypt(&plain, key)?)?;
+            }
+            None => {
+                self.write_zip(temp.as_file_mut())?;
+            }
+        }
+        temp.as_file().sync_all()?;
+
+        // 3. Keep the previous version as a backup
+        if elf_path.exists() {
+            std::fs::set_permissions(temp.path(), std::fs::metadata(elf_path)?.permissions())?;
+            Self::rotate_backup(elf_path, key.is_some())?;
+        } else {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(temp.path(), std::fs::Permissions::from_mode(0o644))?;
+            }
+        }
+
+        // 4. Atomically replace the .elf file
+        temp.persist(elf_path).map_err(|e| e.error)?;
+        #[cfg(unix)]
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+
+        Ok(())
+    }
+
+    /// Replace `{name}.elf.bak` with the current contents of `elf_path`.
+    ///
+    /// When an encrypted archive replaces a plain one, the plain backup is
+    /// removed instead of kept, so the unencrypted contents don't linger on disk.
+    fn rotate_backup(elf_path: &Path, encrypting: bool) -> std::io::Result<()> {
+        let backup = Self::backup_path(elf_path);
+        match std::fs::remove_file(&backup) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        if encrypting && !encryption::is_encrypted_file(elf_path)? {
+            return Ok(());
+        }
+
+        // Saves never write the .elf file in place, so a hard link keeps the old
+        // contents; fall back to copying where hard links aren't supported
+        if std::fs::hard_link(elf_path, &backup).is_err() {
+            std::fs::copy(elf_path, &backup)?;
+        }
+        Ok(())
+    }
+
+    /// Set, change (Some) or remove (None) the key the archive is encrypted with.
+    ///
+    /// Takes effect on the next `save`.
+    pub fn set_encryption(&self, key: Option<ArchiveKey>) {
+        *self.encryption.lock().unwrap() = key;
+    }
+
+    /// Set the compression `save` uses for each kind of entry.
+    ///
+    /// Takes effect on the next `save`; block directories copied unchanged
+    /// from the original zip keep their compression.
+    pub fn set_compression_policy(&self, policy: CompressionPolicy) {
+        *self.compression.lock().unwrap() = policy;
+    }
+
+    /// The compression `save` uses for each kind of entry.
+    pub fn compression_policy(&self) -> CompressionPolicy {
+        *self.compression.lock().unwrap()
+    }
+
+    /// Whether the archive is encrypted on save.
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.lock().unwrap().is_some()
+    }
+
+    /// The key the archive is encrypted with on save (None for a plain zip).
+    pub fn encryption_key(&self) -> Option<ArchiveKey> {
+        self.encryption.lock().unwrap().clone()
+    }
+
+    /// SHA-256 of a .elf file as it is on disk (None if there is no file).
+    ///
+    /// Used to notice when the file was rewritten by someone else.
+    pub fn content_hash(elf_path: &Path) -> std::io::Result<Option<String>> {
+        match std::fs::read(elf_path) {
+            Ok(data) => Ok(Some(hex::encode(Sha256::digest(&data)))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the contents of temp_dir as a zip archive.
+    ///
+    /// Each entry is compressed according to the compression policy, and a
+    /// `manifest.json` listing every entry is written last.
+    fn write_zip<W: Write + Seek>(&self, writer: W) -> std::io::Result<W> {
+        let mut zip = ZipWriter::new(writer);
+        let policy = self.compression_policy();
+        let mut manifest = Manifest::new();
 
         let temp_path = self.temp_dir.path();
 
+        // Block directories that were never extracted can't have changed
+        if let Some(lazy) = &self.lazy {
+            manifest.entries = lazy.copy_pending(&mut zip)?;
+        }
+
         // Recursively traverse temp_dir and save all files
         for entry in WalkDir::new(temp_path) {
             let entry = entry.map_err(|e| {
@@ -90,23 +338,88 @@ impl ElfArchive {
 
             // Convert to string path (for zip internal path)
             let zip_path = relative_path.to_string_lossy();
+            if zip_path == MANIFEST_NAME || manifest.entries.contains_key(zip_path.as_ref()) {
+                continue;
+            }
+
+            // Pick the compression from the file name and its first bytes
+            let mut file = File::open(path)?;
+            let mut head = Vec::with_capacity(manifest::SNIFF_LEN);
+            (&mut file)
+                .take(manifest::SNIFF_LEN as u64)
+                .read_to_end(&mut head)?;
+            let compression = policy.choose(&zip_path, &head);
 
             // Add file to zip using streaming I/O (avoids loading entire file into memory)
+            let options = zip::write::FileOptions::default()
+                .compression_method(compression.method())
+                .large_file(entry.metadata().is_ok_and(|m| m.len() >= u32::MAX as u64));
             zip.start_file(zip_path.as_ref(), options)?;
-            let mut file = File::open(path)?;
-            std::io::copy(&mut file, &mut zip)?;
+            let (size, sha256) =
+                manifest::copy_hashed(&mut head.as_slice().chain(&mut file), &mut zip)?;
+            manifest.entries.insert(
+                zip_path.into_owned(),
+                ManifestEntry {
+                    sha256,
+                    size,
+                    compression,
+                },
+            );
         }
 
-        zip.finish()?;
-        Ok(())
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file(MANIFEST_NAME, options)?;
+        serde_json::to_writer_pretty(&mut zip, &manifest).map_err(std::io::Error::other)?;
+
+        Ok(zip.finish()?)
     }
 
     /// Get the temporary directory path (useful for adding assets)
+    ///
+    /// Block directories still in the zip are extracted first, so the whole
+    /// archive is on disk under the path. Use `block_dir` to extract a single
+    /// block's directory instead.
     pub fn temp_path(&self) -> &Path {
+        if let Err(e) = self.load_all() {
+            log::warn!("Failed to extract block directories: {}", e);
+        }
+        self.temp_dir.path()
+    }
+
+    /// Directory of a block (`block-{id}`), extracted from the zip on first access.
+    pub fn block_dir(&self, block_id: &str) -> std::io::Result<PathBuf> {
+        let name = format!("{}{}", lazy::BLOCK_DIR_PREFIX, block_id);
+        self.load_block_dir(&name)?;
+        Ok(self.temp_dir.path().join(name))
+    }
+
+    /// The temporary directory as extracted so far, leaving block directories
+    /// that are still in the zip there.
+    pub fn extracted_path(&self) -> &Path {
         self.temp_dir.path()
     }
 }
 
+/// Recursively copy a directory.
+pub(crate) fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry.map_err(std::io::Error::other)?;
+        let target = to.join(
+            entry
+                .path()
+                .strip_prefix(from)
+                .map_err(std::io::Error::other)?,
+        );
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,7 +447,7 @@ mod tests {
             .unwrap();
 
         let temp_elf = NamedTempFile::new().unwrap();
-        archive.save(temp_elf.path()).unwrap();
+        archive.save(temp_elf.path()).await.unwrap();
 
         // Verify the file exists and is not empty
         let metadata = std::fs::metadata(temp_elf.path()).unwrap();
@@ -170,7 +483,7 @@ mod tests {
             .unwrap();
 
         let temp_elf = NamedTempFile::new().unwrap();
-        archive.save(temp_elf.path()).unwrap();
+        archive.save(temp_elf.path()).await.unwrap();
 
         // Open the saved archive
         let opened = ElfArchive::open(temp_elf.path()).unwrap();
@@ -204,7 +517,7 @@ mod tests {
             .unwrap();
 
         let temp_elf = NamedTempFile::new().unwrap();
-        archive1.save(temp_elf.path()).unwrap();
+        archive1.save(temp_elf.path()).await.unwrap();
 
         let archive2 = ElfArchive::open(temp_elf.path()).unwrap();
         let pool2 = archive2.event_pool().await.unwrap();
@@ -254,7 +567,7 @@ mod tests {
 
         // 4. 保存到elf文件
         let temp_elf = NamedTempFile::new().unwrap();
-        archive.save(temp_elf.path()).unwrap();
+        archive.save(temp_elf.path()).await.unwrap();
 
         // 5. 重新打开elf文件
         let opened = ElfArchive::open(temp_elf.path()).unwrap();
@@ -310,7 +623,7 @@ mod tests {
 
         // 保存并重新打开
         let temp_elf = NamedTempFile::new().unwrap();
-        archive.save(temp_elf.path()).unwrap();
+        archive.save(temp_elf.path()).await.unwrap();
         let opened = ElfArchive::open(temp_elf.path()).unwrap();
 
         // 验证所有block目录都存在
@@ -339,7 +652,7 @@ mod tests {
         fs::write(deep_path.join("deep.txt"), "deep content").unwrap();
 
         let temp_elf = NamedTempFile::new().unwrap();
-        archive.save(temp_elf.path()).unwrap();
+        archive.save(temp_elf.path()).await.unwrap();
         let opened = ElfArchive::open(temp_elf.path()).unwrap();
 
         let opened_deep = opened
@@ -353,4 +666,86 @@ mod tests {
         let content = fs::read_to_string(opened_deep).unwrap();
         assert_eq!(content, "deep content");
     }
+
+    #[tokio::test]
+    async fn test_encrypted_round_trip() {
+        // 测试：加密保存后必须提供正确的密钥才能打开，移除加密后恢复为普通 zip
+        use std::fs;
+
+        let archive = ElfArchive::new().await.unwrap();
+        let block_dir = archive.temp_path().join("block-secret");
+        fs::create_dir_all(&block_dir).unwrap();
+        fs::write(block_dir.join("notes.md"), "customer secrets").unwrap();
+
+        let key = ArchiveKey::Passphrase("s3cret".to_string());
+        archive.set_encryption(Some(key.clone()));
+        let temp_elf = NamedTempFile::new().unwrap();
+        archive.save(temp_elf.path()).await.unwrap();
+
+        assert!(encryption::is_encrypted_file(temp_elf.path()).unwrap());
+        assert!(ZipArchive::new(File::open(temp_elf.path()).unwrap()).is_err());
+        let err = ElfArchive::open(temp_elf.path()).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        let wrong = ArchiveKey::Passphrase("guess".to_string());
+        assert!(ElfArchive::open_with_key(temp_elf.path(), Some(&wrong)).is_err());
+
+        let opened = ElfArchive::open_with_key(temp_elf.path(), Some(&key)).unwrap();
+        assert!(opened.is_encrypted());
+        let content = fs::read_to_string(opened.temp_path().join("block-secret/notes.md")).unwrap();
+        assert_eq!(content, "customer secrets");
+
+        // 移除加密
+        opened.set_encryption(None);
+        opened.save(temp_elf.path()).await.unwrap();
+        let plain = ElfArchive::open(temp_elf.path()).unwrap();
+        assert!(!plain.is_encrypted());
+    }
+
+    #[tokio::test]
+    async fn test_save_is_atomic_and_keeps_backup() {
+        // 测试：保存通过临时文件原子替换，上一个版本保留为 .elf.bak
+        let dir = tempfile::TempDir::new().unwrap();
+        let elf_path = dir.path().join("project.elf");
+
+        let archive = ElfArchive::new().await.unwrap();
+        let pool = archive.event_pool().await.unwrap();
+        let event = |name: &str| {
+            let mut timestamp = HashMap::new();
+            timestamp.insert("editor1".to_string(), 1);
+            Event::new(
+                name.to_string(),
+                "name".to_string(),
+                serde_json::json!(name),
+                timestamp,
+            )
+        };
+
+        EventStore::append_events(&pool.pool, &[event("first")])
+            .await
+            .unwrap();
+        archive.save(&elf_path).await.unwrap();
+        assert!(!ElfArchive::backup_path(&elf_path).exists());
+
+        // The pool stays open; the checkpoint makes the saved events.db complete
+        EventStore::append_events(&pool.pool, &[event("second")])
+            .await
+            .unwrap();
+        archive.save(&elf_path).await.unwrap();
+
+        let count = |path: PathBuf| async move {
+            let opened = ElfArchive::open(&path).unwrap();
+            let pool = opened.event_pool().await.unwrap();
+            EventStore::get_all_events(&pool.pool).await.unwrap().len()
+        };
+        assert_eq!(count(elf_path.clone()).await, 2);
+        assert_eq!(count(ElfArchive::backup_path(&elf_path)).await, 1);
+
+        // Only the .elf file and its backup are left behind
+        let mut names: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["project.elf", "project.elf.bak"]);
+    }
 }
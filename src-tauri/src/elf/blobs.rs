@@ -0,0 +1,309 @@
+//! Content-addressed blob store inside a .elf archive.
+//!
+//! Large or binary content is stored once under `blobs/{sha256}` and
+//! referenced by hash, so identical files across blocks and events cost
+//! nothing extra:
+//!
+//! - Asset blocks keep `{ "hash", "mime", "size" }` in their contents.
+//! - Text fields of an event's `contents` above [`INLINE_LIMIT`] bytes are
+//!   replaced by `{ "$blob": "{sha256}" }` in `events.db`. The event store
+//!   swaps them back on read, so events look the same everywhere else
+//!   (including signature verification).
+//!
+//! Only the store writes references: a field the user wrote that looks like
+//! one (a one-key `$blob` or `$literal` object) is stored escaped as
+//! `{ "$literal": field }` and unwrapped on read. A reference whose blob is
+//! missing or corrupted stays in place, so only the block it belongs to is
+//! affected, not the whole log.
+//!
+//! Blobs no longer referenced by any event are removed when the archive is saved.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Directory of the blob store inside the archive
+pub const BLOB_DIR: &str = "blobs";
+
+/// Text fields up to this many bytes stay inline in events
+pub const INLINE_LIMIT: usize = 4 * 1024;
+
+/// Key of the object that replaces an externalized field
+const BLOB_REF_KEY: &str = "$blob";
+
+/// Key of the object that escapes a user field shaped like a stored one
+const LITERAL_KEY: &str = "$literal";
+
+/// The blob store of one extracted archive.
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    /// Blob store of the archive extracted to `archive_dir`.
+    pub fn new(archive_dir: &Path) -> Self {
+        Self {
+            dir: archive_dir.join(BLOB_DIR),
+        }
+    }
+
+    /// SHA-256 (hex) of `bytes`, the name of their blob.
+    pub fn hash(bytes: &[u8]) -> String {
+        hex::encode(Sha256::digest(bytes))
+    }
+
+    /// Path of a blob (which may not exist).
+    pub fn path(&self, hash: &str) -> io::Result<PathBuf> {
+        if !is_hash(hash) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid blob hash '{}'", hash),
+            ));
+        }
+        Ok(self.dir.join(hash))
+    }
+
+    /// Store `bytes` and return their hash. Storing the same bytes twice is free.
+    pub fn put(&self, bytes: &[u8]) -> io::Result<String> {
+        let hash = Self::hash(bytes);
+        let path = self.path(&hash)?;
+        if !path.exists() {
+            std::fs::create_dir_all(&self.dir)?;
+            // Write aside and rename, so a blob is never seen half-written
+            let mut temp = tempfile::NamedTempFile::new_in(&self.dir)?;
+            io::Write::write_all(&mut temp, bytes)?;
+            temp.persist(&path).map_err(|e| e.error)?;
+        }
+        Ok(hash)
+    }
+
+    /// Read a blob, checking it still matches its hash.
+    pub fn get(&self, hash: &str) -> io::Result<Vec<u8>> {
+        let bytes = std::fs::read(self.path(hash)?)?;
+        if Self::hash(&bytes) != hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Blob {} is corrupted", hash),
+            ));
+        }
+        Ok(bytes)
+    }
+
+    /// Hashes of all stored blobs.
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut hashes = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if self.path(&name).is_ok() {
+                hashes.push(name);
+            }
+        }
+        hashes.sort();
+        Ok(hashes)
+    }
+
+    /// Remove every blob not in `referenced`; returns how many were removed.
+    pub fn collect_garbage(&self, referenced: &HashSet<String>) -> io::Result<usize> {
+        let mut removed = 0;
+        for hash in self.list()? {
+            if !referenced.contains(&hash) {
+                std::fs::remove_file(self.path(&hash)?)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Move large text fields of an event value's `contents` into blobs,
+    /// escaping fields that look like stored references.
+    pub fn externalize(&self, value: &mut serde_json::Value) -> io::Result<()> {
+        let Some(contents) = value.get_mut("contents").and_then(|c| c.as_object_mut()) else {
+            return Ok(());
+        };
+        for field in contents.values_mut() {
+            if let Some(text) = field.as_str().filter(|t| t.len() > INLINE_LIMIT) {
+                let hash = self.put(text.as_bytes())?;
+                *field = tagged(BLOB_REF_KEY, hash.into());
+            } else if tag(field).is_some() {
+                *field = tagged(LITERAL_KEY, field.take());
+            }
+        }
+        Ok(())
+    }
+
+    /// Put externalized fields of an event value back inline, and unescape
+    /// the escaped ones.
+    ///
+    /// Returns the fields whose blob couldn't be read; they keep their
+    /// reference.
+    pub fn resolve(&self, value: &mut serde_json::Value) -> Vec<String> {
+        let mut unresolved = Vec::new();
+        let Some(contents) = value.get_mut("contents").and_then(|c| c.as_object_mut()) else {
+            return unresolved;
+        };
+        for (key, field) in contents.iter_mut() {
+            let inline = match tag(field) {
+                Some((LITERAL_KEY, inner)) => inner.clone(),
+                Some((BLOB_REF_KEY, hash)) => match self.get_text(hash.as_str().unwrap_or("")) {
+                    Ok(text) => serde_json::Value::String(text),
+                    Err(e) => {
+                        unresolved.push(format!("{}: {}", key, e));
+                        continue;
+                    }
+                },
+                _ => continue,
+            };
+            *field = inline;
+        }
+        unresolved
+    }
+
+    /// Read a blob holding an externalized text field.
+    fn get_text(&self, hash: &str) -> io::Result<String> {
+        String::from_utf8(self.get(hash)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Whether `hash` is a well-formed blob name (SHA-256 in hex).
+fn is_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// A one-key object `{ key: inner }`.
+fn tagged(key: &str, inner: serde_json::Value) -> serde_json::Value {
+    let mut object = serde_json::Map::new();
+    object.insert(key.to_string(), inner);
+    serde_json::Value::Object(object)
+}
+
+/// The key and inner value of a stored field's tag (`$blob` or `$literal`).
+fn tag(field: &serde_json::Value) -> Option<(&str, &serde_json::Value)> {
+    let (key, inner) = field.as_object().filter(|o| o.len() == 1)?.iter().next()?;
+    [BLOB_REF_KEY, LITERAL_KEY]
+        .into_iter()
+        .find(|tag| *tag == key.as_str())
+        .map(|tag| (tag, inner))
+}
+
+/// Add the blobs a stored event value references to `referenced`:
+/// externalized fields, and the `hash` of asset contents.
+///
+/// Escaped user fields are not references. A `hash` only counts if it is a
+/// well-formed blob name; at worst it keeps an existing blob from being
+/// collected.
+pub fn collect_refs(value: &serde_json::Value, referenced: &mut HashSet<String>) {
+    let Some(contents) = value.get("contents").and_then(|c| c.as_object()) else {
+        return;
+    };
+    for (key, field) in contents {
+        let hash = match tag(field) {
+            Some((BLOB_REF_KEY, hash)) => hash.as_str(),
+            Some(_) => None,
+            None if key == "hash" => field.as_str(),
+            None => None,
+        };
+        if let Some(hash) = hash.filter(|hash| is_hash(hash)) {
+            referenced.insert(hash.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_put_is_content_addressed() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = BlobStore::new(temp.path());
+
+        let a = store.put(b"same bytes").unwrap();
+        let b = store.put(b"same bytes").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(store.list().unwrap(), vec![a.clone()]);
+        assert_eq!(store.get(&a).unwrap(), b"same bytes");
+
+        assert!(store.path("../events.db").is_err());
+    }
+
+    #[test]
+    fn test_externalize_and_resolve() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = BlobStore::new(temp.path());
+        let long = "x".repeat(INLINE_LIMIT + 1);
+        let original = json!({ "contents": { "markdown": long, "source": "linked" } });
+
+        let mut stored = original.clone();
+        store.externalize(&mut stored).unwrap();
+        assert_eq!(stored["contents"]["source"], "linked");
+        let hash = stored["contents"]["markdown"]["$blob"].as_str().unwrap();
+        assert_eq!(hash, BlobStore::hash(long.as_bytes()));
+
+        let mut referenced = HashSet::new();
+        collect_refs(&stored, &mut referenced);
+        assert!(referenced.contains(hash));
+
+        let mut resolved = stored.clone();
+        assert!(store.resolve(&mut resolved).is_empty());
+        assert_eq!(resolved, original);
+    }
+
+    #[test]
+    fn test_user_fields_shaped_like_references_are_escaped() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = BlobStore::new(temp.path());
+        let original = json!({ "contents": {
+            "entries": { "$blob": "x" },
+            "nested": { "$literal": { "$blob": "y" } },
+            "hash": "not a hash",
+        } });
+
+        let mut stored = original.clone();
+        store.externalize(&mut stored).unwrap();
+        assert_eq!(stored["contents"]["entries"]["$literal"]["$blob"], "x");
+        let mut referenced = HashSet::new();
+        collect_refs(&stored, &mut referenced);
+        assert!(referenced.is_empty());
+
+        let mut resolved = stored;
+        assert!(store.resolve(&mut resolved).is_empty());
+        assert_eq!(resolved, original);
+    }
+
+    #[test]
+    fn test_missing_blob_leaves_the_reference() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = BlobStore::new(temp.path());
+        let stored =
+            json!({ "contents": { "markdown": { "$blob": "0".repeat(64) }, "source": "outline" } });
+
+        let mut resolved = stored.clone();
+        let unresolved = store.resolve(&mut resolved);
+        assert_eq!(unresolved.len(), 1);
+        assert!(unresolved[0].starts_with("markdown"), "{}", unresolved[0]);
+        assert_eq!(resolved, stored);
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_referenced() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = BlobStore::new(temp.path());
+        let kept = store.put(b"kept").unwrap();
+        store.put(b"dropped").unwrap();
+
+        let removed = store
+            .collect_garbage(&HashSet::from([kept.clone()]))
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(store.list().unwrap(), vec![kept]);
+    }
+}
@@ -0,0 +1,285 @@
+//! At-rest encryption of .elf archives.
+//!
+//! An encrypted archive is the whole zip encrypted with AES-256-GCM:
+//!
+//! ```text
+//! "ELFCRYPT" | version (1) | kdf (1) | salt (16) | nonce (12) | ciphertext
+//! ```
+//!
+//! The key is derived from a passphrase (Argon2id) or from the contents of a
+//! key file (SHA-256), salted per save. Encrypting the zip as a whole also
+//! hides the names of the block directories inside it.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use specta::Type;
+use std::io::{Error, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of an encrypted archive
+const MAGIC: &[u8; 8] = b"ELFCRYPT";
+/// Current encrypted format version
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 2 + SALT_LEN + NONCE_LEN;
+
+/// Key derivation function byte in the header
+const KDF_PASSPHRASE: u8 = 1;
+const KDF_KEY_FILE: u8 = 2;
+
+/// Secret an encrypted archive is locked with.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum ArchiveKey {
+    /// A passphrase, stretched with Argon2id
+    Passphrase(String),
+    /// A file whose contents are the key material
+    KeyFile(PathBuf),
+}
+
+impl std::fmt::Debug for ArchiveKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveKey::Passphrase(_) => f.write_str("Passphrase(..)"),
+            ArchiveKey::KeyFile(path) => f.debug_tuple("KeyFile").field(path).finish(),
+        }
+    }
+}
+
+impl ArchiveKey {
+    fn kdf(&self) -> u8 {
+        match self {
+            ArchiveKey::Passphrase(_) => KDF_PASSPHRASE,
+            ArchiveKey::KeyFile(_) => KDF_KEY_FILE,
+        }
+    }
+
+    /// Derive the 256-bit encryption key for `salt`.
+    fn derive(&self, salt: &[u8]) -> std::io::Result<[u8; 32]> {
+        let mut key = [0u8; 32];
+        match self {
+            ArchiveKey::Passphrase(passphrase) => {
+                argon2::Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                    .map_err(|e| Error::other(format!("Failed to derive key: {}", e)))?;
+            }
+            ArchiveKey::KeyFile(path) => {
+                let material = std::fs::read(path).map_err(|e| {
+                    Error::new(
+                        e.kind(),
+                        format!("Failed to read key file {}: {}", path.display(), e),
+                    )
+                })?;
+                let mut hasher = Sha256::new();
+                hasher.update(salt);
+                hasher.update(&material);
+                key.copy_from_slice(&hasher.finalize());
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// Check whether the file at `path` is an encrypted archive.
+pub fn is_encrypted_file(path: &Path) -> std::io::Result<bool> {
+    let mut magic = [0u8; MAGIC.len()];
+    let mut file = std::fs::File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Check whether `data` is an encrypted archive.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encrypt a plain zip archive.
+pub fn encrypt(plain: &[u8], key: &ArchiveKey) -> std::io::Result<Vec<u8>> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let cipher = Aes256Gcm::new(&key.derive(&salt)?.into());
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plain)
+        .map_err(|_| Error::other("Failed to encrypt archive"))?;
+
+    let mut data = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    data.extend_from_slice(MAGIC);
+    data.push(VERSION);
+    data.push(key.kdf());
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// Decrypt an encrypted archive back to the plain zip.
+///
+/// A missing key fails with `ErrorKind::PermissionDenied`, so callers can ask
+/// for one; a wrong key fails with `ErrorKind::InvalidInput`.
+pub fn decrypt(data: &[u8], key: Option<&ArchiveKey>) -> std::io::Result<Vec<u8>> {
+    if data.len() < HEADER_LEN || !is_encrypted(data) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Not an encrypted archive",
+        ));
+    }
+    let version = data[MAGIC.len()];
+    if version != VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unsupported encrypted archive version {}", version),
+        ));
+    }
+    let kdf = data[MAGIC.len() + 1];
+    let salt = &data[MAGIC.len() + 2..MAGIC.len() + 2 + SALT_LEN];
+    let nonce = &data[HEADER_LEN - NONCE_LEN..HEADER_LEN];
+
+    let locked_with = if kdf == KDF_KEY_FILE {
+        "a key file"
+    } else {
+        "a passphrase"
+    };
+    let key = key.ok_or_else(|| {
+        Error::new(
+            ErrorKind::PermissionDenied,
+            format!("Archive is encrypted; {} is required", locked_with),
+        )
+    })?;
+    if key.kdf() != kdf {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Archive is encrypted with {}", locked_with),
+        ));
+    }
+
+    let cipher = Aes256Gcm::new(&key.derive(salt)?.into());
+    cipher
+        .decrypt(Nonce::from_slice(nonce), &data[HEADER_LEN..])
+        .map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Wrong passphrase or key file, or the archive is corrupted",
+            )
+        })
+}
+
+/// Encrypts many small records under one archive key.
+///
+/// The key is derived once per salt, so encrypting a record costs no key
+/// derivation. Each sealed record is `nonce (12) | ciphertext`.
+pub struct RecordCipher {
+    cipher: Aes256Gcm,
+    salt: [u8; SALT_LEN],
+}
+
+impl RecordCipher {
+    /// A cipher for `key` with a fresh salt.
+    pub fn new(key: &ArchiveKey) -> std::io::Result<Self> {
+        Self::with_salt(key, rand::random())
+    }
+
+    /// The cipher for `key` that records sealed with `salt` were encrypted with.
+    pub fn with_salt(key: &ArchiveKey, salt: [u8; SALT_LEN]) -> std::io::Result<Self> {
+        Ok(Self {
+            cipher: Aes256Gcm::new(&key.derive(&salt)?.into()),
+            salt,
+        })
+    }
+
+    pub fn salt(&self) -> [u8; SALT_LEN] {
+        self.salt
+    }
+
+    /// Encrypt one record.
+    pub fn seal(&self, plain: &[u8]) -> std::io::Result<Vec<u8>> {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plain)
+            .map_err(|_| Error::other("Failed to encrypt record"))?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        Ok(data)
+    }
+
+    /// Decrypt a record sealed with the same key and salt.
+    pub fn open(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        if data.len() < NONCE_LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "Record is too short"));
+        }
+        self.cipher
+            .decrypt(Nonce::from_slice(&data[..NONCE_LEN]), &data[NONCE_LEN..])
+            .map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    "Wrong key, or the record is corrupted",
+                )
+            })
+    }
+}
+
+impl std::fmt::Debug for RecordCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RecordCipher(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_with_passphrase_and_key_file() {
+        let plain = b"PK\x03\x04 zip bytes".to_vec();
+
+        let passphrase = ArchiveKey::Passphrase("correct horse".to_string());
+        let data = encrypt(&plain, &passphrase).unwrap();
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(9).any(|w| w == b"zip bytes"));
+        assert_eq!(decrypt(&data, Some(&passphrase)).unwrap(), plain);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let key_path = dir.path().join("project.key");
+        std::fs::write(&key_path, b"0123456789abcdef").unwrap();
+        let key_file = ArchiveKey::KeyFile(key_path);
+        let data = encrypt(&plain, &key_file).unwrap();
+        assert_eq!(decrypt(&data, Some(&key_file)).unwrap(), plain);
+    }
+
+    #[test]
+    fn test_records_round_trip_under_one_salt() {
+        let key = ArchiveKey::Passphrase("correct horse".to_string());
+        let cipher = RecordCipher::new(&key).unwrap();
+        let sealed = cipher.seal(b"{\"event\":1}").unwrap();
+        assert!(!sealed.windows(5).any(|w| w == b"event"));
+
+        let again = RecordCipher::with_salt(&key, cipher.salt()).unwrap();
+        assert_eq!(again.open(&sealed).unwrap(), b"{\"event\":1}");
+
+        let wrong = ArchiveKey::Passphrase("guess".to_string());
+        let wrong = RecordCipher::with_salt(&wrong, cipher.salt()).unwrap();
+        assert!(wrong.open(&sealed).is_err());
+    }
+
+    #[test]
+    fn test_missing_or_wrong_key_is_rejected() {
+        let data = encrypt(b"secret", &ArchiveKey::Passphrase("right".to_string())).unwrap();
+
+        let err = decrypt(&data, None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains("passphrase"));
+
+        let err = decrypt(&data, Some(&ArchiveKey::Passphrase("wrong".to_string()))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let err = decrypt(&data, Some(&ArchiveKey::KeyFile("any.key".into()))).unwrap_err();
+        assert!(err.to_string().contains("passphrase"));
+    }
+}
@@ -0,0 +1,475 @@
+//! Integrity checker (fsck) for .elf projects.
+//!
+//! Compares the extracted archive directory (`block-*/` snapshot directories)
+//! against the `StateProjector` projected from `events.db`, and reports problems
+//! by category:
+//!
+//! - `orphan_block_dir`: a `block-{id}/` directory whose block doesn't exist
+//! - `snapshot_mismatch`: a snapshot file that is missing or differs from `contents`
+//! - `dangling_directory_entry`: a directory entry pointing at a missing block
+//! - `dangling_relation`: an `implement` relation targeting a missing block
+//! - `orphan_grant`: a grant for a deleted editor or a deleted block
+//!
+//! Repair fixes the event-level problems with corrective events (`core.unlink`,
+//! `directory.write`, `core.revoke`) and the file-level problems directly on disk.
+
+use crate::capabilities::core::create_event;
+use crate::engine::{DanglingKind, StateProjector};
+use crate::models::{Event, GrantScope, RELATION_IMPLEMENT};
+use crate::utils::snapshot::expected_snapshot;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Prefix of per-block directories inside the archive
+const BLOCK_DIR_PREFIX: &str = "block-";
+
+/// Category of an integrity problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum FsckCategory {
+    OrphanBlockDir,
+    SnapshotMismatch,
+    DanglingDirectoryEntry,
+    DanglingRelation,
+    OrphanGrant,
+}
+
+impl FsckCategory {
+    /// Human-readable label used by the CLI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            FsckCategory::OrphanBlockDir => "orphan block directories",
+            FsckCategory::SnapshotMismatch => "snapshot mismatches",
+            FsckCategory::DanglingDirectoryEntry => "dangling directory entries",
+            FsckCategory::DanglingRelation => "dangling relations",
+            FsckCategory::OrphanGrant => "orphan grants",
+        }
+    }
+}
+
+/// A single integrity problem.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct FsckIssue {
+    pub category: FsckCategory,
+    /// The block, directory or editor the problem is attached to
+    pub subject: String,
+    /// What is wrong
+    pub detail: String,
+}
+
+/// Result of an integrity check.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct FsckReport {
+    /// Problems found, sorted by category then subject
+    pub issues: Vec<FsckIssue>,
+    /// Whether repair mode was requested
+    pub repaired: bool,
+    /// Number of corrective events committed by repair mode
+    pub repair_events: usize,
+}
+
+impl FsckReport {
+    /// True if no problems were found.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Group issues by category.
+    pub fn by_category(&self) -> BTreeMap<FsckCategory, Vec<&FsckIssue>> {
+        let mut grouped: BTreeMap<FsckCategory, Vec<&FsckIssue>> = BTreeMap::new();
+        for issue in &self.issues {
+            grouped.entry(issue.category).or_default().push(issue);
+        }
+        grouped
+    }
+}
+
+/// Check a project for integrity problems.
+///
+/// `archive_dir` is the extracted archive directory (`ElfArchive::temp_path`).
+/// Pass `None` for in-memory projects to skip the file-level checks.
+pub fn check(archive_dir: Option<&Path>, state: &StateProjector) -> FsckReport {
+    let mut issues = Vec::new();
+
+    if let Some(dir) = archive_dir {
+        for block_id in orphan_block_dirs(dir, state) {
+            issues.push(FsckIssue {
+                category: FsckCategory::OrphanBlockDir,
+                detail: format!("{}{}/ has no matching block", BLOCK_DIR_PREFIX, block_id),
+                subject: block_id,
+            });
+        }
+        for (block_id, filename, missing) in snapshot_mismatches(dir, state) {
+            issues.push(FsckIssue {
+                category: FsckCategory::SnapshotMismatch,
+                detail: if missing {
+                    format!("snapshot {} is missing", filename)
+                } else {
+                    format!("snapshot {} does not match contents", filename)
+                },
+                subject: block_id,
+            });
+        }
+    }
+
+    for reference in state.find_dangling_references() {
+        let issue = match reference.kind {
+            DanglingKind::DirectoryEntry => FsckIssue {
+                category: FsckCategory::DanglingDirectoryEntry,
+                detail: format!(
+                    "entry '{}' points at missing block {}",
+                    reference.path.unwrap_or_default(),
+                    reference.target_id
+                ),
+                subject: reference.source_id,
+            },
+            DanglingKind::RelationTarget => FsckIssue {
+                category: FsckCategory::DanglingRelation,
+                detail: format!(
+                    "{} relation targets missing block {}",
+                    RELATION_IMPLEMENT, reference.target_id
+                ),
+                subject: reference.source_id,
+            },
+        };
+        issues.push(issue);
+    }
+
+    for (editor_id, cap_id, block_id, reason) in orphan_grants(state) {
+        issues.push(FsckIssue {
+            category: FsckCategory::OrphanGrant,
+            subject: editor_id,
+            detail: format!("grant {} on {}: {}", cap_id, block_id, reason),
+        });
+    }
+
+    issues.sort_by(|a, b| {
+        (a.category, &a.subject, &a.detail).cmp(&(b.category, &b.subject, &b.detail))
+    });
+
+    FsckReport {
+        issues,
+        repaired: false,
+        repair_events: 0,
+    }
+}
+
+/// Build the corrective events for the event-level problems in `state`.
+///
+/// - Dangling relations: `core.unlink` with the missing targets removed
+/// - Dangling directory entries: `directory.write` with the entries removed
+/// - Orphan grants: `core.revoke`
+pub fn repair_events(state: &StateProjector, editor_id: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    let mut relations: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut entries: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for reference in state.find_dangling_references() {
+        match reference.kind {
+            DanglingKind::RelationTarget => relations
+                .entry(reference.source_id)
+                .or_default()
+                .push(reference.target_id),
+            DanglingKind::DirectoryEntry => entries
+                .entry(reference.source_id)
+                .or_default()
+                .extend(reference.path),
+        }
+    }
+
+    for (source_id, missing) in relations {
+        let Some(block) = state.get_block(&source_id) else {
+            continue;
+        };
+        let mut new_children = block.children.clone();
+        if let Some(targets) = new_children.get_mut(RELATION_IMPLEMENT) {
+            targets.retain(|t| !missing.contains(t));
+            if targets.is_empty() {
+                new_children.remove(RELATION_IMPLEMENT);
+            }
+        }
+        events.push(create_event(
+            source_id,
+            "core.unlink",
+            serde_json::json!({ "children": new_children }),
+            editor_id,
+            1, // Placeholder - updated by engine actor
+        ));
+    }
+
+    for (directory_id, paths) in entries {
+        let Some(current) = state
+            .get_block(&directory_id)
+            .and_then(|b| b.contents.get("entries"))
+            .and_then(|v| v.as_object())
+        else {
+            continue;
+        };
+        let mut new_entries = current.clone();
+        for path in &paths {
+            new_entries.remove(path);
+        }
+        events.push(create_event(
+            directory_id,
+            "directory.write",
+            serde_json::json!({ "contents": { "entries": new_entries } }),
+            editor_id,
+            1,
+        ));
+    }
+
+    for (grant_editor, cap_id, block_id, _) in orphan_grants(state) {
+        events.push(create_event(
+            block_id.clone(),
+            "core.revoke",
+            serde_json::json!({
+                "editor": grant_editor,
+                "capability": cap_id,
+                "block": block_id,
+            }),
+            editor_id,
+            1,
+        ));
+    }
+
+    events
+}
+
+/// Fix the file-level problems in `archive_dir`.
+///
+/// Removes orphan block directories and rewrites missing or stale snapshots.
+pub fn repair_files(archive_dir: &Path, state: &StateProjector) -> Result<(), String> {
+    for block_id in orphan_block_dirs(archive_dir, state) {
+        let path = archive_dir.join(format!("{}{}", BLOCK_DIR_PREFIX, block_id));
+        std::fs::remove_dir_all(&path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+
+    for (block_id, _, _) in snapshot_mismatches(archive_dir, state) {
+        if let Some(block) = state.get_block(&block_id) {
+            crate::utils::write_block_snapshot(
+                archive_dir,
+                &block.block_id,
+                &block.block_type,
+                &block.name,
+                &block.contents,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Block directories whose block is neither active nor in the trash.
+fn orphan_block_dirs(archive_dir: &Path, state: &StateProjector) -> Vec<String> {
+    let Ok(read_dir) = std::fs::read_dir(archive_dir) else {
+        return Vec::new();
+    };
+
+    let mut orphans: Vec<String> = read_dir
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(BLOCK_DIR_PREFIX))
+                .map(String::from)
+        })
+        .filter(|id| !state.blocks.contains_key(id) && !state.trash.contains_key(id))
+        .collect();
+    orphans.sort();
+    orphans
+}
+
+/// Active blocks whose snapshot is missing or stale: `(block_id, filename, missing)`.
+fn snapshot_mismatches(archive_dir: &Path, state: &StateProjector) -> Vec<(String, String, bool)> {
+    let mut mismatches = Vec::new();
+
+    for block in state.blocks.values() {
+        let Some((filename, expected)) =
+            expected_snapshot(&block.block_type, &block.name, &block.contents)
+        else {
+            continue;
+        };
+        let path = archive_dir
+            .join(format!("{}{}", BLOCK_DIR_PREFIX, block.block_id))
+            .join(&filename);
+        match std::fs::read_to_string(&path) {
+            Ok(actual) if actual == expected => {}
+            Ok(_) => mismatches.push((block.block_id.clone(), filename, false)),
+            Err(_) => mismatches.push((block.block_id.clone(), filename, true)),
+        }
+    }
+
+    mismatches.sort();
+    mismatches
+}
+
+/// Grants held by deleted editors or targeting deleted blocks:
+/// `(editor_id, cap_id, block_id, reason)`.
+fn orphan_grants(state: &StateProjector) -> Vec<(String, String, String, &'static str)> {
+    let mut orphans = Vec::new();
+
+    for (editor_id, grants) in state.grants.as_map() {
+        let editor_missing = !state.editors.contains_key(editor_id);
+        for (cap_id, block_id) in grants {
+            let block_missing = GrantScope::parse(block_id).root().is_some_and(|root| {
+                !state.blocks.contains_key(root) && !state.trash.contains_key(root)
+            });
+            let reason = if editor_missing {
+                "editor does not exist"
+            } else if block_missing {
+                "block does not exist"
+            } else {
+                continue;
+            };
+            orphans.push((editor_id.clone(), cap_id.clone(), block_id.clone(), reason));
+        }
+    }
+
+    orphans.sort();
+    orphans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn event(entity: &str, attribute: &str, value: serde_json::Value) -> Event {
+        let mut ts = HashMap::new();
+        ts.insert("alice".to_string(), 1);
+        Event::new(entity.to_string(), attribute.to_string(), value, ts)
+    }
+
+    fn create(state: &mut StateProjector, id: &str, block_type: &str, contents: serde_json::Value) {
+        state.apply_event(&event(
+            id,
+            "alice/core.create",
+            serde_json::json!({
+                "name": id,
+                "type": block_type,
+                "owner": "alice",
+                "contents": contents,
+                "children": {}
+            }),
+        ));
+    }
+
+    /// A project with one of each problem.
+    fn broken_state() -> StateProjector {
+        let mut state = StateProjector::new();
+        state.apply_event(&event(
+            "alice",
+            "alice/editor.create",
+            serde_json::json!({ "editor_id": "alice", "name": "Alice" }),
+        ));
+        create(
+            &mut state,
+            "a",
+            "markdown",
+            serde_json::json!({ "markdown": "# A" }),
+        );
+        create(
+            &mut state,
+            "dir",
+            "directory",
+            serde_json::json!({ "entries": {
+                "gone.md": { "id": "gone", "type": "file", "source": "outline" }
+            } }),
+        );
+        state.apply_event(&event(
+            "a",
+            "alice/core.link",
+            serde_json::json!({ "children": { RELATION_IMPLEMENT: ["gone"] } }),
+        ));
+        state.apply_event(&event(
+            "a",
+            "alice/core.grant",
+            serde_json::json!({ "editor": "ghost", "capability": "markdown.read", "block": "a" }),
+        ));
+        state
+    }
+
+    #[test]
+    fn test_check_reports_event_level_issues() {
+        let state = broken_state();
+        let report = check(None, &state);
+
+        let grouped = report.by_category();
+        assert_eq!(grouped[&FsckCategory::DanglingRelation].len(), 1);
+        assert_eq!(grouped[&FsckCategory::DanglingDirectoryEntry].len(), 1);
+        assert_eq!(grouped[&FsckCategory::OrphanGrant][0].subject, "ghost");
+        assert!(!grouped.contains_key(&FsckCategory::OrphanBlockDir));
+    }
+
+    #[test]
+    fn test_repair_events_fix_event_level_issues() {
+        let mut state = broken_state();
+        let events = repair_events(&state, "alice");
+        assert_eq!(events.len(), 3);
+
+        for e in &events {
+            state.apply_event(e);
+        }
+        assert!(check(None, &state).is_clean());
+    }
+
+    #[test]
+    fn test_file_level_checks_and_repair() {
+        let mut state = StateProjector::new();
+        state.apply_event(&event(
+            "alice",
+            "alice/editor.create",
+            serde_json::json!({ "editor_id": "alice", "name": "Alice" }),
+        ));
+        create(
+            &mut state,
+            "a",
+            "markdown",
+            serde_json::json!({ "markdown": "# A" }),
+        );
+        create(
+            &mut state,
+            "b",
+            "markdown",
+            serde_json::json!({ "markdown": "# B" }),
+        );
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("block-a")).unwrap();
+        std::fs::write(dir.join("block-a/body.md"), "# stale").unwrap();
+        std::fs::create_dir_all(dir.join("block-orphan")).unwrap();
+
+        let report = check(Some(dir), &state);
+        let grouped = report.by_category();
+        assert_eq!(grouped[&FsckCategory::OrphanBlockDir][0].subject, "orphan");
+        // a is stale, b is missing
+        assert_eq!(grouped[&FsckCategory::SnapshotMismatch].len(), 2);
+
+        repair_files(dir, &state).unwrap();
+        assert!(check(Some(dir), &state).is_clean());
+        assert!(!dir.join("block-orphan").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("block-a/body.md")).unwrap(),
+            "# A"
+        );
+    }
+
+    #[test]
+    fn test_trashed_block_dir_is_not_orphan() {
+        let mut state = StateProjector::new();
+        create(&mut state, "a", "markdown", serde_json::json!({}));
+        state.apply_event(&event("a", "alice/core.delete", serde_json::json!({})));
+
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("block-a")).unwrap();
+
+        let report = check(Some(temp.path()), &state);
+        assert!(report.is_clean());
+    }
+}
@@ -0,0 +1,269 @@
+//! Lazy extraction of block directories from a .elf archive.
+//!
+//! Opening an archive extracts the event store (and the other top-level
+//! files) right away, but leaves the `block-*/` directories in the zip until
+//! something uses them. Until then, saving copies their entries straight
+//! from the original zip, without decompressing and compressing them again.
+//!
+//! Extracted files are checked against the hashes in the archive's manifest.
+
+use crate::elf::manifest::{self, EntryCompression, Manifest, ManifestEntry, MANIFEST_NAME};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zip::{ZipArchive, ZipWriter};
+
+/// Prefix of per-block directories inside the archive
+pub(crate) const BLOCK_DIR_PREFIX: &str = "block-";
+
+/// Block directories of an opened archive that are still in the zip.
+pub struct LazyEntries {
+    dir: PathBuf,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    /// The original zip; kept open, so it stays readable after a save replaces the file
+    source: ZipArchive<File>,
+    /// Not yet extracted: block directory name -> indices of its zip entries
+    pending: BTreeMap<String, Vec<usize>>,
+    /// The original zip's manifest (None for archives written before manifests)
+    manifest: Option<Manifest>,
+}
+
+impl std::fmt::Debug for LazyEntries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LazyEntries")
+            .field("dir", &self.dir)
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+impl LazyEntries {
+    /// Extract everything but the block directories of `source` into `dir`.
+    ///
+    /// Fails if the manifest is from a newer format or doesn't match the zip.
+    pub fn open(mut source: ZipArchive<File>, dir: &Path) -> io::Result<Self> {
+        let manifest = Manifest::read(&mut source)?;
+        let mut pending: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for i in 0..source.len() {
+            let name = source.by_index_raw(i)?.name().to_string();
+            match block_dir_name(&name) {
+                Some(block_dir) => pending.entry(block_dir.to_string()).or_default().push(i),
+                None => extract_entry(&mut source, i, dir, manifest.as_ref())?,
+            }
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            inner: Mutex::new(Inner {
+                source,
+                pending,
+                manifest,
+            }),
+        })
+    }
+
+    /// Extract a block directory (`block-{id}`) if it is still in the zip.
+    ///
+    /// Files already written to the directory are kept.
+    pub fn load(&self, block_dir: &str) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(indices) = inner.pending.remove(block_dir) else {
+            return Ok(());
+        };
+        let Inner {
+            source,
+            pending,
+            manifest,
+        } = &mut *inner;
+        for (n, &i) in indices.iter().enumerate() {
+            if let Err(e) = extract_entry(source, i, &self.dir, manifest.as_ref()) {
+                // Keep the rest pending, so a save still copies it from the zip
+                pending.insert(block_dir.to_string(), indices[n..].to_vec());
+                return Err(e);
+            }
+        }
+        log::debug!("Extracted {} on first access", block_dir);
+        Ok(())
+    }
+
+    /// Extract every block directory still in the zip.
+    pub fn load_all(&self) -> io::Result<()> {
+        for block_dir in self.pending() {
+            self.load(&block_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Forget a block directory that was removed, so a save doesn't bring it back.
+    pub fn discard(&self, block_dir: &str) {
+        self.inner.lock().unwrap().pending.remove(block_dir);
+    }
+
+    /// Names of the block directories not extracted yet.
+    pub fn pending(&self) -> Vec<String> {
+        self.inner.lock().unwrap().pending.keys().cloned().collect()
+    }
+
+    /// Copy the entries not extracted yet into `zip` without recompressing them.
+    ///
+    /// Entries whose file has been written in the meantime are skipped: the
+    /// caller archives the file on disk instead. Returns the manifest entries
+    /// of the copied files, taken from the original manifest or, for archives
+    /// without one, hashed from the zip.
+    pub(crate) fn copy_pending<W: Write + Seek>(
+        &self,
+        zip: &mut ZipWriter<W>,
+    ) -> io::Result<BTreeMap<String, ManifestEntry>> {
+        let mut inner = self.inner.lock().unwrap();
+        let Inner {
+            source,
+            pending,
+            manifest,
+        } = &mut *inner;
+        let mut copied = BTreeMap::new();
+        for &i in pending.values().flatten() {
+            let entry = source.by_index_raw(i)?;
+            let name = entry.name().to_string();
+            if entry.is_dir() || self.dir.join(&name).exists() {
+                continue;
+            }
+            zip.raw_copy_file(entry)?;
+
+            let known = manifest.as_ref().and_then(|m| m.entries.get(&name));
+            let manifest_entry = match known {
+                Some(known) => known.clone(),
+                None => {
+                    let mut entry = source.by_index(i)?;
+                    let compression = EntryCompression::from_method(entry.compression())
+                        .unwrap_or(EntryCompression::Deflated);
+                    let (size, sha256) = manifest::copy_hashed(&mut entry, &mut io::sink())?;
+                    ManifestEntry {
+                        sha256,
+                        size,
+                        compression,
+                    }
+                }
+            };
+            copied.insert(name, manifest_entry);
+        }
+        Ok(copied)
+    }
+}
+
+/// The block directory a zip entry belongs to, if any.
+fn block_dir_name(name: &str) -> Option<&str> {
+    let (first, _) = name.split_once('/')?;
+    first.starts_with(BLOCK_DIR_PREFIX).then_some(first)
+}
+
+/// Extract one zip entry into `dir`, unless its file already exists there.
+///
+/// The manifest itself is skipped; files it lists must match their hash.
+pub(crate) fn extract_entry<R: Read + Seek>(
+    source: &mut ZipArchive<R>,
+    index: usize,
+    dir: &Path,
+    manifest: Option<&Manifest>,
+) -> io::Result<()> {
+    let mut zip_file = source.by_index(index)?;
+    // Entry names come from the file, so refuse any that would land outside `dir`
+    let relative = zip_file
+        .enclosed_name()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsafe entry name in archive: {}", zip_file.name()),
+            )
+        })?;
+    let outpath = dir.join(relative);
+
+    if zip_file.is_dir() {
+        return std::fs::create_dir_all(&outpath);
+    }
+    if zip_file.name() == MANIFEST_NAME || outpath.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = outpath.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut outfile = File::create(&outpath)?;
+    let (_, sha256) = manifest::copy_hashed(&mut zip_file, &mut outfile)?;
+    if let Some(manifest) = manifest {
+        if let Err(e) = manifest.verify(zip_file.name(), &sha256) {
+            drop(outfile);
+            let _ = std::fs::remove_file(&outpath);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    /// A zip with an event store and two block directories.
+    fn sample_zip(path: &Path) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::FileOptions::default();
+        for (name, data) in [
+            ("events.db", "db"),
+            ("block-a/body.md", "# A"),
+            ("block-b/body.md", "# B"),
+            ("block-b/sub/x.txt", "x"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_block_dirs_are_extracted_on_demand() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let zip_path = temp.path().join("p.elf");
+        sample_zip(&zip_path);
+        let dir = temp.path().join("out");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let lazy = LazyEntries::open(
+            ZipArchive::new(File::open(&zip_path).unwrap()).unwrap(),
+            &dir,
+        )
+        .unwrap();
+        assert!(dir.join("events.db").exists());
+        assert!(!dir.join("block-a").exists());
+        assert_eq!(lazy.pending(), vec!["block-a", "block-b"]);
+
+        lazy.load("block-b").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("block-b/sub/x.txt")).unwrap(),
+            "x"
+        );
+        assert_eq!(lazy.pending(), vec!["block-a"]);
+
+        // Saving copies the pending entries as they are
+        let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let copied = lazy.copy_pending(&mut zip).unwrap();
+        assert_eq!(copied.keys().collect::<Vec<_>>(), vec!["block-a/body.md"]);
+        assert_eq!(copied["block-a/body.md"].size, 3);
+        let mut written = ZipArchive::new(zip.finish().unwrap()).unwrap();
+        let mut body = String::new();
+        written
+            .by_name("block-a/body.md")
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "# A");
+
+        lazy.discard("block-a");
+        assert!(lazy.pending().is_empty());
+    }
+}
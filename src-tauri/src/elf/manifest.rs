@@ -0,0 +1,377 @@
+//! Archive manifest and per-entry compression policy.
+//!
+//! Every saved .elf archive ends with a `manifest.json` entry:
+//!
+//! ```json
+//! {
+//!   "format_version": 1,
+//!   "creator_version": "0.1.0",
+//!   "entries": {
+//!     "events.db": { "sha256": "…", "size": 40960, "compression": "zstd" }
+//!   }
+//! }
+//! ```
+//!
+//! `format_version` is bumped whenever the layout changes in a way older
+//! versions can't read; `open` refuses archives with a newer version. Archives
+//! written before the manifest existed have none and are read as they are.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use zip::CompressionMethod;
+
+/// Name of the manifest entry inside the archive
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// Newest archive layout this version reads and writes
+pub const FORMAT_VERSION: u32 = 1;
+
+/// How many leading bytes `CompressionPolicy::choose` looks at
+pub const SNIFF_LEN: usize = 16;
+
+/// Compression of a single archive entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryCompression {
+    Stored,
+    Deflated,
+    Zstd,
+}
+
+impl EntryCompression {
+    pub fn method(self) -> CompressionMethod {
+        match self {
+            Self::Stored => CompressionMethod::Stored,
+            Self::Deflated => CompressionMethod::Deflated,
+            Self::Zstd => CompressionMethod::Zstd,
+        }
+    }
+
+    /// The compression of an entry read from a zip, if it is one we write.
+    pub fn from_method(method: CompressionMethod) -> Option<Self> {
+        match method {
+            CompressionMethod::Stored => Some(Self::Stored),
+            CompressionMethod::Deflated => Some(Self::Deflated),
+            CompressionMethod::Zstd => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Which compression `save` uses for each kind of file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionPolicy {
+    /// The event store (SQLite pages compress much better with zstd)
+    pub database: EntryCompression,
+    /// Files that are compressed already (images, video, archives, ...)
+    pub compressed: EntryCompression,
+    /// Everything else (snapshots, text, JSON)
+    pub other: EntryCompression,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self {
+            database: EntryCompression::Zstd,
+            compressed: EntryCompression::Stored,
+            other: EntryCompression::Deflated,
+        }
+    }
+}
+
+impl CompressionPolicy {
+    /// Choose the compression for an entry from its name and first bytes.
+    ///
+    /// Blobs have no extension, so their type is recognised from `head`
+    /// (up to `SNIFF_LEN` bytes). Empty files are always stored.
+    pub fn choose(&self, name: &str, head: &[u8]) -> EntryCompression {
+        if head.is_empty() {
+            return EntryCompression::Stored;
+        }
+        let extension = Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+
+        if file_name.starts_with("events.db")
+            || matches!(extension.as_str(), "db" | "sqlite" | "sqlite3")
+            || head.starts_with(b"SQLite format 3\0")
+        {
+            self.database
+        } else if is_compressed_extension(&extension) || is_compressed_data(head) {
+            self.compressed
+        } else {
+            self.other
+        }
+    }
+}
+
+/// Extensions of file formats that are compressed already.
+fn is_compressed_extension(extension: &str) -> bool {
+    matches!(
+        extension,
+        "png"
+            | "jpg"
+            | "jpeg"
+            | "gif"
+            | "webp"
+            | "mp4"
+            | "mov"
+            | "mkv"
+            | "webm"
+            | "mp3"
+            | "ogg"
+            | "flac"
+            | "pdf"
+            | "zip"
+            | "gz"
+            | "tgz"
+            | "bz2"
+            | "xz"
+            | "zst"
+            | "7z"
+            | "rar"
+            | "woff"
+            | "woff2"
+            | "elf"
+    )
+}
+
+/// Whether `head` starts like a compressed file format.
+fn is_compressed_data(head: &[u8]) -> bool {
+    const MAGIC: &[&[u8]] = &[
+        b"\x89PNG",
+        b"\xff\xd8\xff",
+        b"GIF8",
+        b"PK\x03\x04",
+        b"\x1f\x8b",
+        b"BZh",
+        b"\xfd7zXZ\x00",
+        b"\x28\xb5\x2f\xfd",
+        b"7z\xbc\xaf",
+        b"Rar!",
+        b"%PDF",
+        b"OggS",
+        b"fLaC",
+        b"ID3",
+        b"\x1a\x45\xdf\xa3",
+        b"wOFF",
+        b"wOF2",
+    ];
+    MAGIC.iter().any(|magic| head.starts_with(magic))
+        || (head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP")
+        || (head.len() >= 8 && &head[4..8] == b"ftyp")
+}
+
+/// Size, hash and compression of one archive entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// SHA-256 of the uncompressed contents (hex)
+    pub sha256: String,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    pub compression: EntryCompression,
+}
+
+/// Contents of `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    /// Version of the application that wrote the archive
+    pub creator_version: String,
+    /// Every file entry of the archive but the manifest itself
+    pub entries: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    /// An empty manifest for an archive written by this version.
+    pub fn new() -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            creator_version: env!("CARGO_PKG_VERSION").to_string(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Read the manifest of a zip (None for archives written before manifests).
+    ///
+    /// Fails with `ErrorKind::Unsupported` if the archive uses a newer format,
+    /// and with `ErrorKind::InvalidData` if an entry it lists is missing.
+    pub fn read<R: Read + io::Seek>(zip: &mut zip::ZipArchive<R>) -> io::Result<Option<Self>> {
+        let invalid = |e: serde_json::Error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {}: {}", MANIFEST_NAME, e),
+            )
+        };
+        let value: serde_json::Value = match zip.by_name(MANIFEST_NAME) {
+            Ok(file) => serde_json::from_reader(file).map_err(invalid)?,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Check the version before the rest: a newer format may change the other fields
+        let format_version = value["format_version"].as_u64().unwrap_or(0);
+        if format_version > FORMAT_VERSION as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "This file uses .elf format version {} (written by Elfiee {}), but this \
+                     version of Elfiee ({}) only supports up to format version {}. \
+                     Update Elfiee to open it.",
+                    format_version,
+                    value["creator_version"].as_str().unwrap_or("unknown"),
+                    env!("CARGO_PKG_VERSION"),
+                    FORMAT_VERSION
+                ),
+            ));
+        }
+        let manifest: Self = serde_json::from_value(value).map_err(invalid)?;
+
+        let names: HashSet<&str> = zip.file_names().collect();
+        if let Some(missing) = manifest
+            .entries
+            .keys()
+            .find(|name| !names.contains(name.as_str()))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Archive is missing {} listed in its manifest", missing),
+            ));
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Check extracted contents against the hash recorded for `name`.
+    ///
+    /// Entries the manifest doesn't list are accepted.
+    pub fn verify(&self, name: &str, sha256: &str) -> io::Result<()> {
+        match self.entries.get(name) {
+            Some(entry) if entry.sha256 != sha256 => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Archive entry {} is corrupted (hash mismatch)", name),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copy `reader` into `writer`, returning the number of bytes and their SHA-256.
+pub(crate) fn copy_hashed<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+) -> io::Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+        size += n as u64;
+    }
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use zip::write::FileOptions;
+    use zip::{ZipArchive, ZipWriter};
+
+    #[test]
+    fn test_policy_by_file_type() {
+        let policy = CompressionPolicy::default();
+        assert_eq!(
+            policy.choose("events.db", b"SQLite"),
+            EntryCompression::Zstd
+        );
+        assert_eq!(
+            policy.choose("events.db-wal", b"\x37\x7f\x06\x82"),
+            EntryCompression::Zstd
+        );
+        assert_eq!(
+            policy.choose("block-a/body.md", b"# Title"),
+            EntryCompression::Deflated
+        );
+        assert_eq!(
+            policy.choose("block-a/photo.JPG", b"\xff\xd8\xff\xe0"),
+            EntryCompression::Stored
+        );
+        // Blobs have no extension; the PNG signature gives them away
+        assert_eq!(
+            policy.choose("blobs/ab12", b"\x89PNG\r\n\x1a\n"),
+            EntryCompression::Stored
+        );
+        assert_eq!(
+            policy.choose("blobs/cd34", b"lorem ipsum"),
+            EntryCompression::Deflated
+        );
+        assert_eq!(
+            policy.choose("block-a/empty.txt", b""),
+            EntryCompression::Stored
+        );
+    }
+
+    fn zip_with_manifest(manifest: &Manifest) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("events.db", FileOptions::default()).unwrap();
+        zip.write_all(b"db").unwrap();
+        zip.start_file(MANIFEST_NAME, FileOptions::default())
+            .unwrap();
+        zip.write_all(&serde_json::to_vec(manifest).unwrap())
+            .unwrap();
+        ZipArchive::new(zip.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_read_validates_version_and_entries() {
+        let mut manifest = Manifest::new();
+        let (size, sha256) = copy_hashed(&mut &b"db"[..], &mut io::sink()).unwrap();
+        manifest.entries.insert(
+            "events.db".to_string(),
+            ManifestEntry {
+                sha256: sha256.clone(),
+                size,
+                compression: EntryCompression::Deflated,
+            },
+        );
+        let read = Manifest::read(&mut zip_with_manifest(&manifest))
+            .unwrap()
+            .unwrap();
+        assert_eq!(read, manifest);
+        assert!(read.verify("events.db", &sha256).is_ok());
+        assert!(read.verify("events.db", "0000").is_err());
+
+        let mut newer = manifest.clone();
+        newer.format_version = FORMAT_VERSION + 1;
+        let err = Manifest::read(&mut zip_with_manifest(&newer)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(err.to_string().contains("Update Elfiee"), "{}", err);
+
+        let mut missing = manifest;
+        missing.entries.insert(
+            "block-x/body.md".to_string(),
+            missing.entries["events.db"].clone(),
+        );
+        let err = Manifest::read(&mut zip_with_manifest(&missing)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
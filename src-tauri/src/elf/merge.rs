@@ -0,0 +1,961 @@
+//! Merge two divergent copies of the same .elf project.
+//!
+//! Events have globally unique ids, so two copies that share an ancestor can be
+//! combined by taking the union of their event logs:
+//!
+//! 1. Events present in both logs (the shared history) come first, in our order
+//! 2. The commands each side added since are interleaved causally by vector
+//!    clock, and each is checked against the merged history before it like a
+//!    command received from another instance; refused ones are left out and
+//!    reported
+//! 3. A block written through the same capability on both sides is a conflict;
+//!    the preferred side's last write wins, re-applied with a resolution event
+//!    if the causal order put the other side's write last
+//! 4. A block one side deleted and the other wrote is a conflict too; if the
+//!    deleting side is preferred the block stays deleted, otherwise it is
+//!    restored with the writes the delete hid
+//! 5. Links are merged per (block, target): a target either side linked or
+//!    unlinked since the shared history stays that way, and a resolution event
+//!    sets the combined targets if the last link event applied dropped some
+//!
+//! Resolution events are made by the merging editor, who must be allowed to
+//! make them under the merged grants. The merged log is written to a new
+//! archive, together with the block directories of both copies and fresh
+//! snapshots of the merged projection. A signed log stays verifiable: from the
+//! first event that no longer continues the chain, the merging editor signs
+//! the events back into it, keeping their authors' signatures.
+
+use crate::elf::{copy_dir, ElfArchive};
+use crate::engine::{
+    check_remote_command, check_remote_event, event_hash, split_commands, verify_author,
+    EventSigner, EventStore, KeyStore, RejectedEvent, StateProjector,
+};
+use crate::models::{Event, RELATION_IMPLEMENT};
+use crate::utils::write_block_snapshot;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Capabilities whose events replace a part of a block, so that concurrent
+/// writes on both sides overwrite each other.
+///
+/// Links are not among them: they are merged per target (see [`merge_links`]).
+const CONFLICTING_CAPABILITIES: &[&str] = &[
+    "markdown.write",
+    "code.write",
+    "directory.write",
+    "core.rename",
+    "core.change_type",
+    "core.update_metadata",
+];
+
+/// Capabilities that set a block's `implement` targets
+const LINKING_CAPABILITIES: &[&str] = &["core.link", "core.unlink"];
+
+/// Capabilities that take a block out of the project
+const DELETING_CAPABILITIES: &[&str] = &["core.delete", "core.purge"];
+
+/// Files of the event store, which are rebuilt rather than copied
+const EVENT_STORE_FILES: &[&str] = &["events.db", "events.db-wal", "events.db-shm"];
+
+/// One of the two copies being merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeSide {
+    Ours,
+    Theirs,
+}
+
+/// How the two copies changed a block in ways that can't both be kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Both sides wrote the block through the same capability
+    Write,
+    /// One side deleted the block, the other wrote it
+    DeleteWrite,
+}
+
+/// A block both copies changed in ways that can't both be kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct MergeConflict {
+    pub kind: ConflictKind,
+    /// The block (or other entity) changed on both sides
+    pub entity: String,
+    /// The capability it was written with (on the writing side for `DeleteWrite`)
+    pub capability: String,
+    /// Last write (or the delete) on our side
+    pub ours_event_id: String,
+    /// Last write (or the delete) on their side
+    pub theirs_event_id: String,
+    /// The side whose change the merged project keeps (the deleting side if
+    /// the block was purged there and can't come back)
+    pub winner: MergeSide,
+}
+
+/// Result of merging two copies of a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct MergeReport {
+    /// Events both copies share
+    pub common_events: usize,
+    /// Events only our copy has
+    pub ours_events: usize,
+    /// Events only their copy has
+    pub theirs_events: usize,
+    /// Events added to make the preferred side win a conflict
+    pub resolution_events: usize,
+    /// Events the merging editor signed back into the chain of a signed log
+    pub relinked_events: usize,
+    pub conflicts: Vec<MergeConflict>,
+    /// Events of either side refused under the merged grants, left out of the merge
+    pub rejected: Vec<RejectedEvent>,
+}
+
+/// Merge the event logs of two copies of a project.
+///
+/// Commands either side added are checked in causal order against the merged
+/// history before them (see [`check_remote_command`]); refused ones are left
+/// out and reported in `rejected`. Fails if the logs share no events, since then they are not copies of the
+/// same project, or if `merger` may not make a resolution event a conflict
+/// needs. Returns the merged log in causal order.
+pub fn merge_events(
+    ours: Vec<Event>,
+    theirs: Vec<Event>,
+    prefer: MergeSide,
+    merger: &str,
+) -> Result<(Vec<Event>, MergeReport), String> {
+    let ours_ids: HashSet<&str> = ours.iter().map(|e| e.event_id.as_str()).collect();
+    let theirs_ids: HashSet<&str> = theirs.iter().map(|e| e.event_id.as_str()).collect();
+
+    let (common, ours_only): (Vec<Event>, Vec<Event>) = ours
+        .iter()
+        .cloned()
+        .partition(|e| theirs_ids.contains(e.event_id.as_str()));
+    let theirs_only: Vec<Event> = theirs
+        .iter()
+        .filter(|e| !ours_ids.contains(e.event_id.as_str()))
+        .cloned()
+        .collect();
+    if common.is_empty() && !ours.is_empty() && !theirs.is_empty() {
+        return Err("The files share no history and are not copies of the same project".into());
+    }
+
+    let mut report = MergeReport {
+        common_events: common.len(),
+        ours_events: ours_only.len(),
+        theirs_events: theirs_only.len(),
+        ..Default::default()
+    };
+
+    let mut merged = common;
+    let mut state = StateProjector::new();
+    state.replay(merged.clone());
+    let now = Utc::now();
+
+    // Each added command is checked against the merged history before it
+    let commands = interleave(&split_commands(&ours_only), &split_commands(&theirs_only));
+    let link_base = link_base(&state, &commands);
+    let mut accepted: HashSet<&str> = HashSet::new();
+    for command in commands {
+        if let Err(reason) = check_remote_command(&state, command, now) {
+            report.rejected.extend(
+                command
+                    .iter()
+                    .map(|event| RejectedEvent::new(event, reason.clone())),
+            );
+            continue;
+        }
+        for event in command {
+            state.apply_event(event);
+            accepted.insert(event.event_id.as_str());
+        }
+        merged.extend_from_slice(command);
+    }
+    let ours_only: Vec<&Event> = ours_only
+        .iter()
+        .filter(|e| accepted.contains(e.event_id.as_str()))
+        .collect();
+    let theirs_only: Vec<&Event> = theirs_only
+        .iter()
+        .filter(|e| accepted.contains(e.event_id.as_str()))
+        .collect();
+
+    let position: HashMap<&str, usize> = merged
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (e.event_id.as_str(), i))
+        .collect();
+    let mut resolver = Resolver::new(merger, state, &merged, now);
+    // Writes already re-applied while restoring a deleted block
+    let mut reapplied: HashSet<(String, String)> = HashSet::new();
+
+    // Delete versus write: the preferred side decides whether the block survives
+    for (side, deletes, writers) in [
+        (MergeSide::Ours, last_deletes(&ours_only), &theirs_only),
+        (MergeSide::Theirs, last_deletes(&theirs_only), &ours_only),
+    ] {
+        for (entity, delete) in deletes {
+            let Some(write) = writers
+                .iter()
+                .rev()
+                .find(|e| e.entity == entity && !DELETING_CAPABILITIES.contains(&cap_of(e)))
+            else {
+                continue;
+            };
+            let purged = cap_of(delete) == "core.purge";
+            let winner = if purged { side } else { prefer };
+            let (ours_event, theirs_event) = match side {
+                MergeSide::Ours => (delete, *write),
+                MergeSide::Theirs => (*write, delete),
+            };
+            report.conflicts.push(MergeConflict {
+                kind: ConflictKind::DeleteWrite,
+                entity: entity.to_string(),
+                capability: cap_of(write).to_string(),
+                ours_event_id: ours_event.event_id.clone(),
+                theirs_event_id: theirs_event.event_id.clone(),
+                winner,
+            });
+
+            let alive = resolver.state.blocks.contains_key(entity);
+            if winner == side {
+                // A restore on the writing side may have brought the block back
+                if alive {
+                    resolver.resolve(
+                        entity,
+                        "core.delete",
+                        serde_json::json!({ "mode": "detach" }),
+                    )?;
+                }
+                continue;
+            }
+            if !alive {
+                resolver.resolve(
+                    entity,
+                    "core.restore",
+                    serde_json::json!({ "include_cascaded": false }),
+                )?;
+            }
+            // Writes that came after the delete were dropped; apply them again
+            let deleted_at = position[delete.event_id.as_str()];
+            for ((_, cap_id), write) in last_writes(writers)
+                .into_iter()
+                .filter(|((e, _), w)| *e == entity && position[w.event_id.as_str()] > deleted_at)
+            {
+                resolver.resolve(entity, cap_id, resolution_value(write))?;
+                reapplied.insert((entity.to_string(), cap_id.to_string()));
+            }
+        }
+    }
+
+    // Write versus write: make the preferred side's last write the last one applied
+    let ours_writes = last_writes(&ours_only);
+    let theirs_writes = last_writes(&theirs_only);
+    for (key, ours_event) in &ours_writes {
+        let Some(theirs_event) = theirs_writes.get(key) else {
+            continue;
+        };
+        let (winner, loser) = match prefer {
+            MergeSide::Ours => (ours_event, theirs_event),
+            MergeSide::Theirs => (theirs_event, ours_event),
+        };
+        report.conflicts.push(MergeConflict {
+            kind: ConflictKind::Write,
+            entity: key.0.to_string(),
+            capability: key.1.to_string(),
+            ours_event_id: ours_event.event_id.clone(),
+            theirs_event_id: theirs_event.event_id.clone(),
+            winner: prefer,
+        });
+
+        let (entity, cap_id) = *key;
+        if reapplied.contains(&(entity.to_string(), cap_id.to_string()))
+            || !resolver.state.blocks.contains_key(entity)
+        {
+            continue;
+        }
+        if position[winner.event_id.as_str()] < position[loser.event_id.as_str()] {
+            resolver.resolve(entity, cap_id, resolution_value(winner))?;
+        }
+    }
+
+    merge_links(&mut resolver, &link_base, &ours_only, &theirs_only)?;
+
+    report.resolution_events = resolver.events.len();
+    merged.extend(resolver.events);
+    Ok((merged, report))
+}
+
+/// The `implement` targets, after the shared history, of every block either
+/// side linked or unlinked.
+fn link_base(state: &StateProjector, commands: &[&[Event]]) -> BTreeMap<String, Vec<String>> {
+    commands
+        .iter()
+        .flat_map(|command| command.iter())
+        .filter(|event| LINKING_CAPABILITIES.contains(&cap_of(event)))
+        .map(|event| (event.entity.clone(), state.get_children(&event.entity)))
+        .collect()
+}
+
+/// Combine both sides' link changes per (block, target).
+///
+/// Link events carry a block's whole list of targets, so the last one applied
+/// drops what the other side linked or brings back what it unlinked. A block
+/// both sides relinked gets the shared history's targets, minus those either
+/// side removed, plus those either side added (if they still exist).
+fn merge_links(
+    resolver: &mut Resolver,
+    base: &BTreeMap<String, Vec<String>>,
+    ours: &[&Event],
+    theirs: &[&Event],
+) -> Result<(), String> {
+    for (entity, shared) in base {
+        let (Some(ours_targets), Some(theirs_targets)) =
+            (last_targets(ours, entity), last_targets(theirs, entity))
+        else {
+            continue;
+        };
+        let Some(block) = resolver.state.get_block(entity) else {
+            continue;
+        };
+
+        let mut combined: Vec<String> = shared
+            .iter()
+            .filter(|id| ours_targets.contains(id) && theirs_targets.contains(id))
+            .cloned()
+            .collect();
+        for id in ours_targets.iter().chain(&theirs_targets) {
+            if !shared.contains(id)
+                && !combined.contains(id)
+                && resolver.state.blocks.contains_key(id)
+            {
+                combined.push(id.clone());
+            }
+        }
+
+        let current = resolver.state.get_children(entity);
+        if current.len() == combined.len() && combined.iter().all(|id| current.contains(id)) {
+            continue;
+        }
+        let cap_id = if combined.iter().any(|id| !current.contains(id)) {
+            "core.link"
+        } else {
+            "core.unlink"
+        };
+        let mut children = block.children.clone();
+        if combined.is_empty() {
+            children.remove(RELATION_IMPLEMENT);
+        } else {
+            children.insert(RELATION_IMPLEMENT.to_string(), combined);
+        }
+        resolver.resolve(entity, cap_id, serde_json::json!({ "children": children }))?;
+    }
+    Ok(())
+}
+
+/// A side's `implement` targets for a block, from its last link event on it.
+fn last_targets(events: &[&Event], entity: &str) -> Option<Vec<String>> {
+    let event = events
+        .iter()
+        .rev()
+        .find(|e| e.entity == entity && LINKING_CAPABILITIES.contains(&cap_of(e)))?;
+    Some(
+        event.value["children"][RELATION_IMPLEMENT]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|id| id.as_str().map(String::from))
+            .collect(),
+    )
+}
+
+/// Makes the resolution events of a merge, in the name of the merging editor.
+///
+/// Each one is checked like a command of the merging editor would be, against
+/// the merged projection including the resolutions before it, and records the
+/// use of a limited grant that authorizes it.
+struct Resolver<'a> {
+    merger: &'a str,
+    state: StateProjector,
+    counts: HashMap<String, i64>,
+    now: DateTime<Utc>,
+    events: Vec<Event>,
+}
+
+impl<'a> Resolver<'a> {
+    /// `state` is the projection of `merged`.
+    fn new(merger: &'a str, state: StateProjector, merged: &[Event], now: DateTime<Utc>) -> Self {
+        Self {
+            merger,
+            state,
+            counts: editor_counts(merged),
+            now,
+            events: Vec::new(),
+        }
+    }
+
+    fn resolve(
+        &mut self,
+        entity: &str,
+        cap_id: &str,
+        mut value: serde_json::Value,
+    ) -> Result<(), String> {
+        if let Some((editor, capability, block)) =
+            self.state
+                .limited_grant_for(self.merger, cap_id, entity, self.now)
+        {
+            if let Some(obj) = value.as_object_mut() {
+                obj.insert(
+                    "grant_use".to_string(),
+                    serde_json::json!({
+                        "editor": editor,
+                        "capability": capability,
+                        "block": block,
+                    }),
+                );
+            }
+        }
+        let count = self.counts.entry(self.merger.to_string()).or_insert(0);
+        *count += 1;
+        let event = Event::new(
+            entity.to_string(),
+            format!("{}/{}", self.merger, cap_id),
+            value,
+            self.counts.clone(),
+        );
+        check_remote_event(&self.state, &event, self.now).map_err(|e| {
+            format!(
+                "{} can't resolve the conflict on {}: {}",
+                self.merger, entity, e
+            )
+        })?;
+        self.state.apply_event(&event);
+        self.events.push(event);
+        Ok(())
+    }
+}
+
+/// The value of a winning write, to re-apply it as a resolution.
+///
+/// The record of a limited grant use belongs to the original write.
+fn resolution_value(winner: &Event) -> serde_json::Value {
+    let mut value = winner.value.clone();
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("grant_use");
+    }
+    value
+}
+
+/// Merge two copies of a project into a new archive.
+///
+/// The new archive is not saved yet; block directories come from both copies,
+/// the preferred side's version where both have one. `merger` makes the
+/// resolution events and, if the log is signed, signs the events that no
+/// longer continue the chain back into it with its key in `keys`.
+pub async fn merge_archives(
+    ours: &ElfArchive,
+    theirs: &ElfArchive,
+    prefer: MergeSide,
+    merger: &str,
+    keys: &KeyStore,
+) -> Result<(ElfArchive, MergeReport), String> {
+    let ours_events = read_events(ours).await?;
+    let theirs_events = read_events(theirs).await?;
+    let (mut events, mut report) = merge_events(ours_events, theirs_events, prefer, merger)?;
+    report.relinked_events = relink(&mut events, merger, keys)?;
+
+    let merged = ElfArchive::new()
+        .await
+        .map_err(|e| format!("Failed to create archive: {}", e))?;
+    let pool = merged
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to get event pool: {}", e))?;
+    EventStore::append_events(&pool.pool, &events)
+        .await
+        .map_err(|e| format!("Failed to persist events to database: {}", e))?;
+
+    let mut state = StateProjector::new();
+    state.replay(events);
+
+    // Copy the other side first, so the preferred side's files win
+    let (first, second) = match prefer {
+        MergeSide::Ours => (theirs, ours),
+        MergeSide::Theirs => (ours, theirs),
+    };
+    for archive in [first, second] {
+        archive
+            .load_all()
+            .map_err(|e| format!("Failed to extract block directories: {}", e))?;
+        copy_block_dirs(archive, &merged, &state)
+            .map_err(|e| format!("Failed to copy block directories: {}", e))?;
+    }
+
+    // Snapshots follow the merged projection, not either copy
+    for block in state.blocks.values() {
+        if let Err(e) = write_block_snapshot(
+            merged.temp_path(),
+            &block.block_id,
+            &block.block_type,
+            &block.name,
+            &block.contents,
+        ) {
+            log::warn!("Snapshot error for {}: {}", block.block_id, e);
+        }
+    }
+
+    Ok((merged, report))
+}
+
+/// Sign the events of a signed log back into its chain.
+///
+/// Events are kept as they are while they continue the chain. From the first
+/// one that doesn't, each must carry a valid signature by its author (new
+/// resolution events are signed by their author, the merger) and is linked in
+/// by `merger`. Returns the number of linked events; an unsigned log is left
+/// alone.
+fn relink(events: &mut [Event], merger: &str, keys: &KeyStore) -> Result<usize, String> {
+    if events.iter().all(|event| event.signature.is_none()) {
+        return Ok(0);
+    }
+
+    let mut last_hash = String::new();
+    let mut chained = 0;
+    for event in events.iter() {
+        match &event.signature {
+            Some(sig)
+                if sig.prev_hash == last_hash && sig.hash == event_hash(event, &last_hash) =>
+            {
+                last_hash = sig.hash.clone();
+                chained += 1;
+            }
+            _ => break,
+        }
+    }
+
+    let mut signer = EventSigner::new(keys.clone(), last_hash);
+    for event in &mut events[chained..] {
+        if event.signature.is_some() {
+            verify_author(event, keys).map_err(|e| {
+                format!("Can't sign {} into the merged chain: {}", event.event_id, e)
+            })?;
+        }
+        signer.link(event, merger)?;
+    }
+    Ok(events.len() - chained)
+}
+
+async fn read_events(archive: &ElfArchive) -> Result<Vec<Event>, String> {
+    let pool = archive
+        .event_pool()
+        .await
+        .map_err(|e| format!("Failed to get event pool: {}", e))?;
+    EventStore::get_all_events(&pool.pool)
+        .await
+        .map_err(|e| format!("Failed to get events: {}", e))
+}
+
+/// Copy the files of `from` into `to`, except the event store and the
+/// directories of blocks the merged project no longer has.
+fn copy_block_dirs(
+    from: &ElfArchive,
+    to: &ElfArchive,
+    state: &StateProjector,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(from.temp_path())? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if EVENT_STORE_FILES.contains(&name.as_str()) {
+            continue;
+        }
+        if let Some(block_id) = name.strip_prefix("block-") {
+            if !state.blocks.contains_key(block_id) && !state.trash.contains_key(block_id) {
+                continue;
+            }
+        }
+
+        let target = to.temp_path().join(&name);
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Interleave the commands each side added, keeping both sides' own order.
+///
+/// Whenever one side's next command happened before the other's (by vector
+/// clock) it goes first; concurrent commands go by the smaller clock.
+fn interleave<'a>(ours: &[&'a [Event]], theirs: &[&'a [Event]]) -> Vec<&'a [Event]> {
+    let mut merged = Vec::with_capacity(ours.len() + theirs.len());
+    let (mut i, mut j) = (0, 0);
+
+    while i < ours.len() || j < theirs.len() {
+        let take_ours = match (ours.get(i), theirs.get(j)) {
+            (Some(_), None) => true,
+            (None, _) => false,
+            (Some(a), Some(b)) => match compare_clocks(&a[0].timestamp, &b[0].timestamp) {
+                Some(Ordering::Less) | Some(Ordering::Equal) => true,
+                Some(Ordering::Greater) => false,
+                None => clock_sum(&a[0].timestamp) <= clock_sum(&b[0].timestamp),
+            },
+        };
+        if take_ours {
+            merged.push(ours[i]);
+            i += 1;
+        } else {
+            merged.push(theirs[j]);
+            j += 1;
+        }
+    }
+
+    merged
+}
+
+/// Compare two vector clocks (None if they are concurrent).
+fn compare_clocks(a: &HashMap<String, i64>, b: &HashMap<String, i64>) -> Option<Ordering> {
+    let mut ordering = Ordering::Equal;
+    for editor_id in a.keys().chain(b.keys()) {
+        let left = a.get(editor_id).copied().unwrap_or(0);
+        let right = b.get(editor_id).copied().unwrap_or(0);
+        match (ordering, left.cmp(&right)) {
+            (_, Ordering::Equal) => {}
+            (Ordering::Equal, other) => ordering = other,
+            (current, other) if current != other => return None,
+            _ => {}
+        }
+    }
+    Some(ordering)
+}
+
+fn clock_sum(clock: &HashMap<String, i64>) -> i64 {
+    clock.values().sum()
+}
+
+/// Highest count of every editor across a log.
+fn editor_counts(events: &[Event]) -> HashMap<String, i64> {
+    let mut counts = HashMap::new();
+    for event in events {
+        for (editor_id, count) in &event.timestamp {
+            let current = counts.entry(editor_id.clone()).or_insert(0);
+            *current = (*current).max(*count);
+        }
+    }
+    counts
+}
+
+/// The capability of an event (the part of its attribute after the editor).
+fn cap_of(event: &Event) -> &str {
+    event.attribute.split('/').nth(1).unwrap_or_default()
+}
+
+/// Last delete (or purge) per block.
+fn last_deletes<'a>(events: &[&'a Event]) -> BTreeMap<&'a str, &'a Event> {
+    let mut deletes = BTreeMap::new();
+    for event in events {
+        if DELETING_CAPABILITIES.contains(&cap_of(event)) {
+            deletes.insert(event.entity.as_str(), *event);
+        }
+    }
+    deletes
+}
+
+/// Last event per `(entity, capability)` among the conflicting capabilities.
+fn last_writes<'a>(events: &[&'a Event]) -> BTreeMap<(&'a str, &'a str), &'a Event> {
+    let mut writes = BTreeMap::new();
+    for event in events {
+        let cap_id = cap_of(event);
+        if CONFLICTING_CAPABILITIES.contains(&cap_id) {
+            writes.insert((event.entity.as_str(), cap_id), *event);
+        }
+    }
+    writes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(
+        entity: &str,
+        attribute: &str,
+        value: serde_json::Value,
+        clock: &[(&str, i64)],
+    ) -> Event {
+        let timestamp = clock
+            .iter()
+            .map(|(editor_id, count)| (editor_id.to_string(), *count))
+            .collect();
+        Event::new(entity.to_string(), attribute.to_string(), value, timestamp)
+    }
+
+    /// Value of a core.create event for a markdown block owned by alice.
+    fn create() -> serde_json::Value {
+        json!({ "name": "notes.md", "type": "markdown", "owner": "alice", "contents": {} })
+    }
+
+    /// alice grants `capability` on `block` to bob.
+    fn grant(capability: &str, block: &str) -> Event {
+        event(
+            block,
+            "alice/core.grant",
+            json!({ "editor": "bob", "capability": capability, "block": block }),
+            &[("alice", 1)],
+        )
+    }
+
+    #[test]
+    fn test_compare_clocks() {
+        let clock = |pairs: &[(&str, i64)]| -> HashMap<String, i64> {
+            pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+        };
+        assert_eq!(
+            compare_clocks(&clock(&[("a", 1)]), &clock(&[("a", 2)])),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare_clocks(&clock(&[("a", 2), ("b", 1)]), &clock(&[("a", 2)])),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare_clocks(&clock(&[("a", 2)]), &clock(&[("a", 1), ("b", 1)])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_union_keeps_causal_order() {
+        let base = event("b1", "alice/core.create", create(), &[("alice", 1)]);
+        let may_create = grant("core.create", "@project");
+        let ours_1 = event("b2", "alice/core.create", json!({}), &[("alice", 2)]);
+        let theirs_1 = event(
+            "b3",
+            "bob/core.create",
+            json!({}),
+            &[("alice", 1), ("bob", 1)],
+        );
+        let theirs_2 = event(
+            "b4",
+            "bob/core.create",
+            json!({}),
+            &[("alice", 1), ("bob", 2)],
+        );
+
+        let (merged, report) = merge_events(
+            vec![base.clone(), may_create.clone(), ours_1.clone()],
+            vec![
+                base.clone(),
+                may_create.clone(),
+                theirs_1.clone(),
+                theirs_2.clone(),
+            ],
+            MergeSide::Ours,
+            "alice",
+        )
+        .unwrap();
+
+        let ids: Vec<&str> = merged.iter().map(|e| e.event_id.as_str()).collect();
+        assert_eq!(ids[0], base.event_id);
+        assert_eq!(merged.len(), 5);
+        let pos = |id: &str| ids.iter().position(|x| *x == id).unwrap();
+        assert!(pos(&theirs_1.event_id) < pos(&theirs_2.event_id));
+        assert_eq!(report.common_events, 2);
+        assert_eq!(report.ours_events, 1);
+        assert_eq!(report.theirs_events, 2);
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_writes_follow_preference() {
+        let base = event("b1", "alice/core.create", create(), &[("alice", 1)]);
+        let may_write = grant("markdown.write", "b1");
+        let ours = event(
+            "b1",
+            "bob/markdown.write",
+            json!({ "contents": { "markdown": "ours" } }),
+            &[("alice", 2)],
+        );
+        let theirs = event(
+            "b1",
+            "bob/markdown.write",
+            json!({ "contents": { "markdown": "theirs" } }),
+            &[("alice", 1), ("bob", 5)],
+        );
+
+        let shared = vec![base, may_write];
+        let with = |event: &Event| {
+            let mut events = shared.clone();
+            events.push(event.clone());
+            events
+        };
+
+        // Their write has the larger clock, so it is applied last on its own
+        let (merged, report) =
+            merge_events(with(&ours), with(&theirs), MergeSide::Theirs, "alice").unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].winner, MergeSide::Theirs);
+        assert_eq!(report.resolution_events, 0);
+        assert_eq!(merged.last().unwrap().event_id, theirs.event_id);
+
+        // Preferring ours re-applies our write after theirs, made by the merger
+        let (merged, report) =
+            merge_events(with(&ours), with(&theirs), MergeSide::Ours, "alice").unwrap();
+        assert_eq!(report.resolution_events, 1);
+        let last = merged.last().unwrap();
+        assert_eq!(last.attribute, "alice/markdown.write");
+        assert_eq!(last.value, ours.value);
+        assert_eq!(last.timestamp.get("alice"), Some(&3));
+        assert_eq!(last.timestamp.get("bob"), Some(&5));
+
+        // A merger without write access can't resolve the conflict
+        let err = merge_events(with(&ours), with(&theirs), MergeSide::Ours, "mallory").unwrap_err();
+        assert!(err.contains("can't resolve"), "{}", err);
+    }
+
+    #[test]
+    fn test_delete_versus_write_follows_preference() {
+        let base = event("b1", "alice/core.create", create(), &[("alice", 1)]);
+        let may_write = grant("markdown.write", "b1");
+        let delete = event(
+            "b1",
+            "alice/core.delete",
+            json!({ "mode": "detach" }),
+            &[("alice", 2)],
+        );
+        let write = event(
+            "b1",
+            "bob/markdown.write",
+            json!({ "contents": { "markdown": "theirs" } }),
+            &[("alice", 1), ("bob", 1)],
+        );
+        let merge = |prefer| {
+            merge_events(
+                vec![base.clone(), may_write.clone(), delete.clone()],
+                vec![base.clone(), may_write.clone(), write.clone()],
+                prefer,
+                "alice",
+            )
+            .unwrap()
+        };
+        let project = |events: Vec<Event>| {
+            let mut state = StateProjector::new();
+            state.replay(events);
+            state
+        };
+
+        // The delete goes first, so their write alone would be lost
+        let (merged, report) = merge(MergeSide::Ours);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].kind, ConflictKind::DeleteWrite);
+        assert_eq!(report.conflicts[0].ours_event_id, delete.event_id);
+        assert_eq!(report.conflicts[0].theirs_event_id, write.event_id);
+        assert_eq!(report.resolution_events, 0);
+        assert!(project(merged).get_block("b1").is_none());
+
+        // Preferring the write restores the block and applies the write again
+        let (merged, report) = merge(MergeSide::Theirs);
+        assert_eq!(report.conflicts[0].winner, MergeSide::Theirs);
+        assert_eq!(report.resolution_events, 2);
+        let state = project(merged);
+        assert_eq!(
+            state.get_block("b1").unwrap().contents["markdown"],
+            "theirs"
+        );
+    }
+
+    #[test]
+    fn test_added_events_are_checked_in_causal_order() {
+        let base = event("b1", "alice/core.create", create(), &[("alice", 1)]);
+        let write = |editor: &str, text: &str, clock: &[(&str, i64)]| {
+            event(
+                "b1",
+                &format!("{}/markdown.write", editor),
+                json!({ "contents": { "markdown": text } }),
+                clock,
+            )
+        };
+        // bob's write comes after the grant that allows it; mallory has none
+        let may_write = event(
+            "b1",
+            "alice/core.grant",
+            json!({ "editor": "bob", "capability": "markdown.write", "block": "b1" }),
+            &[("alice", 2)],
+        );
+        let bob = write("bob", "bob", &[("alice", 2), ("bob", 1)]);
+        let mallory = write("mallory", "pwned", &[("alice", 1), ("mallory", 1)]);
+
+        let (merged, report) = merge_events(
+            vec![base.clone(), may_write.clone()],
+            vec![base, bob.clone(), mallory.clone()],
+            MergeSide::Ours,
+            "alice",
+        )
+        .unwrap();
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].event_id, mallory.event_id);
+        assert!(report.conflicts.is_empty());
+        assert!(merged.iter().all(|e| e.event_id != mallory.event_id));
+
+        let mut state = StateProjector::new();
+        state.replay(merged);
+        assert_eq!(state.get_block("b1").unwrap().contents["markdown"], "bob");
+    }
+
+    #[test]
+    fn test_links_are_merged_per_target() {
+        let mut shared: Vec<Event> = ["b1", "b2", "b3", "b4"]
+            .into_iter()
+            .map(|id| event(id, "alice/core.create", create(), &[("alice", 1)]))
+            .collect();
+        shared.push(event(
+            "b1",
+            "alice/core.link",
+            json!({ "children": { "implement": ["b2"] } }),
+            &[("alice", 1)],
+        ));
+        shared.push(grant("core.link", "b1"));
+        let link = |editor: &str, targets: &[&str], clock: &[(&str, i64)]| {
+            event(
+                "b1",
+                &format!("{}/core.link", editor),
+                json!({ "children": { "implement": targets } }),
+                clock,
+            )
+        };
+        let unlink = event(
+            "b1",
+            "alice/core.unlink",
+            json!({ "children": {} }),
+            &[("alice", 2)],
+        );
+        let with = |events: &[Event]| [shared.clone(), events.to_vec()].concat();
+
+        // We unlinked b2 and linked b3; they linked b4, keeping b2
+        let (merged, report) = merge_events(
+            with(&[unlink, link("alice", &["b3"], &[("alice", 3)])]),
+            with(&[link("bob", &["b2", "b4"], &[("alice", 1), ("bob", 1)])]),
+            MergeSide::Ours,
+            "alice",
+        )
+        .unwrap();
+        assert!(report.conflicts.is_empty());
+        assert_eq!(report.resolution_events, 1);
+        let mut state = StateProjector::new();
+        state.replay(merged);
+        let mut children = state.get_children("b1");
+        children.sort();
+        assert_eq!(children, vec!["b3".to_string(), "b4".to_string()]);
+    }
+
+    #[test]
+    fn test_unrelated_logs_are_rejected() {
+        let ours = event("b1", "alice/core.create", create(), &[("alice", 1)]);
+        let theirs = event("b2", "bob/core.create", json!({}), &[("bob", 1)]);
+        assert!(merge_events(vec![ours], vec![theirs], MergeSide::Ours, "alice").is_err());
+    }
+}
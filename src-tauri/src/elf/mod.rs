@@ -1,3 +1,18 @@
 mod archive;
+pub mod blobs;
+pub mod encryption;
+pub mod fsck;
+pub mod lazy;
+pub mod manifest;
+pub mod merge;
+pub mod recovery;
 
+pub(crate) use archive::copy_dir;
 pub use archive::ElfArchive;
+pub use blobs::BlobStore;
+pub use encryption::ArchiveKey;
+pub use fsck::{FsckCategory, FsckIssue, FsckReport};
+pub use lazy::LazyEntries;
+pub use manifest::{CompressionPolicy, EntryCompression, Manifest, ManifestEntry};
+pub use merge::{ConflictKind, MergeConflict, MergeReport, MergeSide};
+pub use recovery::{AutosavePolicy, RecoveryInfo, RecoveryJournal};
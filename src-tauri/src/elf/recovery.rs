@@ -0,0 +1,328 @@
+//! Crash recovery journal and autosave policy for open .elf files.
+//!
+//! Changes to an open file live in its extracted temp directory until the file
+//! is saved, and that directory is gone after a crash. While a file is open,
+//! every committed event is also appended to a journal in a stable location:
+//!
+//! ```text
+//! ~/.elf/recovery/{hash of the .elf path}.jsonl
+//! ```
+//!
+//! The first line records the .elf path; every following line is one event.
+//! The journal of an encrypted archive holds its events encrypted with the
+//! archive key (hex-encoded, one per line), and its first line records the salt.
+//! Saving the file empties the journal and closing it removes the journal, so
+//! a journal that still holds events when the file is opened again belongs to
+//! a session that ended without saving.
+
+use crate::config;
+use crate::elf::encryption::{ArchiveKey, RecordCipher};
+use crate::models::Event;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use specta::Type;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// When the engine saves an open file on its own.
+///
+/// Both limits are optional; with neither set, files are only saved explicitly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct AutosavePolicy {
+    /// Save once this many events are unsaved
+    #[serde(default)]
+    pub every_events: Option<u32>,
+    /// Save this many seconds after the first unsaved change
+    #[serde(default)]
+    pub every_seconds: Option<u32>,
+}
+
+impl AutosavePolicy {
+    /// Whether any autosave trigger is set.
+    pub fn is_enabled(&self) -> bool {
+        self.every_events.is_some() || self.every_seconds.is_some()
+    }
+}
+
+/// Summary of unsaved events left behind by a previous session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct RecoveryInfo {
+    /// Number of events that can be restored
+    pub events: usize,
+    /// Editors that produced them
+    pub editors: Vec<String>,
+    /// Wall clock time of the first and last of them
+    pub first_event_at: String,
+    pub last_event_at: String,
+}
+
+impl RecoveryInfo {
+    /// Summarize recoverable events (None if there are none).
+    pub fn from_events(events: &[Event]) -> Option<Self> {
+        let first = events.first()?;
+        let last = events.last()?;
+        let mut editors: Vec<String> = events
+            .iter()
+            .filter_map(|event| event.attribute.split_once('/'))
+            .map(|(editor_id, _)| editor_id.to_string())
+            .collect();
+        editors.sort();
+        editors.dedup();
+
+        Some(Self {
+            events: events.len(),
+            editors,
+            first_event_at: first.created_at.clone(),
+            last_event_at: last.created_at.clone(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalHeader {
+    path: PathBuf,
+    /// Salt of the key the events are encrypted with (absent in plain journals)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+}
+
+/// Append-only journal of the events committed to an open file since its last save.
+#[derive(Debug)]
+pub struct RecoveryJournal {
+    elf_path: PathBuf,
+    journal_path: PathBuf,
+    file: File,
+    /// Key of the archive (None for a plain archive)
+    key: Option<ArchiveKey>,
+    /// Cipher derived from `key` that the journal is written with
+    cipher: Option<RecordCipher>,
+}
+
+impl RecoveryJournal {
+    /// Journal location for a .elf file.
+    pub fn location(elf_path: &Path) -> Result<PathBuf, String> {
+        let elf_path = std::fs::canonicalize(elf_path).unwrap_or_else(|_| elf_path.to_path_buf());
+        let digest = Sha256::digest(elf_path.to_string_lossy().as_bytes());
+        Ok(config::get_recovery_dir()?.join(format!("{}.jsonl", &hex::encode(digest)[..16])))
+    }
+
+    /// Read the events journaled for a .elf file by an earlier session.
+    ///
+    /// `key` is the key of the archive, needed if the journal is encrypted.
+    /// A torn last line (a crash mid-write) is ignored, and so is a journal
+    /// that can't be decrypted with `key`.
+    pub fn read(elf_path: &Path, key: Option<&ArchiveKey>) -> Result<Vec<Event>, String> {
+        Self::read_journal(&Self::location(elf_path)?, key, None)
+    }
+
+    /// Read a journal, decrypting it with `cipher` if it was written with it
+    /// or else with a cipher derived from `key`.
+    fn read_journal(
+        journal_path: &Path,
+        key: Option<&ArchiveKey>,
+        cipher: Option<&RecordCipher>,
+    ) -> Result<Vec<Event>, String> {
+        let file = match File::open(journal_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read recovery journal: {}", e)),
+        };
+
+        let mut lines = BufReader::new(file).lines();
+        let header = match lines.next() {
+            Some(Ok(header)) => match serde_json::from_str::<JournalHeader>(&header) {
+                Ok(header) => header,
+                Err(_) => return Ok(Vec::new()),
+            },
+            _ => return Ok(Vec::new()),
+        };
+
+        let derived;
+        let cipher = match header.salt.as_deref() {
+            None => None,
+            Some(salt) => {
+                let salt: [u8; 16] = match hex::decode(salt).ok().and_then(|s| s.try_into().ok()) {
+                    Some(salt) => salt,
+                    None => return Ok(Vec::new()),
+                };
+                match (cipher, key) {
+                    (Some(cipher), _) if cipher.salt() == salt => Some(cipher),
+                    (_, Some(key)) => {
+                        derived = RecordCipher::with_salt(key, salt)
+                            .map_err(|e| format!("Failed to read recovery journal: {}", e))?;
+                        Some(&derived)
+                    }
+                    _ => {
+                        log::warn!(
+                            "Recovery journal of {} is encrypted and no key was given",
+                            header.path.display()
+                        );
+                        return Ok(Vec::new());
+                    }
+                }
+            }
+        };
+
+        Ok(lines
+            .map_while(Result::ok)
+            .filter_map(|line| match cipher {
+                Some(cipher) => {
+                    let plain = cipher.open(&hex::decode(line.trim()).ok()?).ok()?;
+                    serde_json::from_slice::<Event>(&plain).ok()
+                }
+                None => serde_json::from_str::<Event>(&line).ok(),
+            })
+            .collect())
+    }
+
+    /// Open the journal of a .elf file for appending, keeping what it holds.
+    ///
+    /// The journal of an archive encrypted with `key` is encrypted with it too.
+    pub fn open(elf_path: &Path, key: Option<&ArchiveKey>) -> Result<Self, String> {
+        let journal_path = Self::location(elf_path)?;
+        let existing = Self::read(elf_path, key)?;
+        let cipher = Self::cipher(key)?;
+        let file = Self::write(&journal_path, elf_path, cipher.as_ref(), &existing)?;
+
+        Ok(Self {
+            elf_path: elf_path.to_path_buf(),
+            journal_path,
+            file,
+            key: key.cloned(),
+            cipher,
+        })
+    }
+
+    /// A cipher with a fresh salt for `key` (None for plain journals).
+    fn cipher(key: Option<&ArchiveKey>) -> Result<Option<RecordCipher>, String> {
+        key.map(RecordCipher::new)
+            .transpose()
+            .map_err(|e| format!("Failed to derive recovery journal key: {}", e))
+    }
+
+    /// One journal line for an event.
+    fn line(cipher: Option<&RecordCipher>, event: &Event) -> Result<String, String> {
+        let json = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        let mut line = match cipher {
+            Some(cipher) => hex::encode(
+                cipher
+                    .seal(json.as_bytes())
+                    .map_err(|e| format!("Failed to write recovery journal: {}", e))?,
+            ),
+            None => json,
+        };
+        line.push('\n');
+        Ok(line)
+    }
+
+    /// Atomically (re)write a journal holding `events`, returning it opened for appending.
+    fn write(
+        journal_path: &Path,
+        elf_path: &Path,
+        cipher: Option<&RecordCipher>,
+        events: &[Event],
+    ) -> Result<File, String> {
+        let header = JournalHeader {
+            path: elf_path.to_path_buf(),
+            salt: cipher.map(|cipher| hex::encode(cipher.salt())),
+        };
+        let mut content = serde_json::to_string(&header).map_err(|e| e.to_string())?;
+        content.push('\n');
+        for event in events {
+            content.push_str(&Self::line(cipher, event)?);
+        }
+
+        if let Some(dir) = journal_path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create recovery directory: {}", e))?;
+        }
+        let temp_path = journal_path.with_extension("jsonl.tmp");
+        std::fs::write(&temp_path, content)
+            .and_then(|_| std::fs::rename(&temp_path, journal_path))
+            .map_err(|e| format!("Failed to write recovery journal: {}", e))?;
+
+        OpenOptions::new()
+            .append(true)
+            .open(journal_path)
+            .map_err(|e| format!("Failed to open recovery journal: {}", e))
+    }
+
+    /// Remove the journal of a .elf file.
+    pub fn remove(elf_path: &Path) -> Result<(), String> {
+        match std::fs::remove_file(Self::location(elf_path)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove recovery journal: {}", e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Append committed events and flush them to disk.
+    pub fn append(&mut self, events: &[Event]) -> Result<(), String> {
+        let mut lines = String::new();
+        for event in events {
+            lines.push_str(&Self::line(self.cipher.as_ref(), event)?);
+        }
+        self.file
+            .write_all(lines.as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .map_err(|e| format!("Failed to write recovery journal: {}", e))
+    }
+
+    /// Rewrite the journal so that it holds exactly `events`.
+    pub fn reset(&mut self, events: &[Event]) -> Result<(), String> {
+        self.file = Self::write(
+            &self.journal_path,
+            &self.elf_path,
+            self.cipher.as_ref(),
+            events,
+        )?;
+        Ok(())
+    }
+
+    /// Switch to the archive's new key (None once it is no longer encrypted),
+    /// re-encrypting what the journal holds.
+    pub fn rekey(&mut self, key: Option<ArchiveKey>) -> Result<(), String> {
+        if key == self.key {
+            return Ok(());
+        }
+        let events = self.events()?;
+        self.cipher = Self::cipher(key.as_ref())?;
+        self.key = key;
+        self.reset(&events)
+    }
+
+    /// The events this journal holds.
+    fn events(&self) -> Result<Vec<Event>, String> {
+        Self::read_journal(&self.journal_path, self.key.as_ref(), self.cipher.as_ref())
+    }
+
+    /// Drop the given events from the journal, keeping the rest.
+    pub fn discard(&mut self, event_ids: &HashSet<String>) -> Result<(), String> {
+        let kept: Vec<Event> = self
+            .events()?
+            .into_iter()
+            .filter(|event| !event_ids.contains(&event.event_id))
+            .collect();
+        self.reset(&kept)
+    }
+
+    /// Move the journal along with its .elf file.
+    pub fn rename(&mut self, new_elf_path: &Path) -> Result<(), String> {
+        let events = self.events()?;
+        let new_journal_path = Self::location(new_elf_path)?;
+        self.file = Self::write(
+            &new_journal_path,
+            new_elf_path,
+            self.cipher.as_ref(),
+            &events,
+        )?;
+        let _ = std::fs::remove_file(&self.journal_path);
+
+        self.elf_path = new_elf_path.to_path_buf();
+        self.journal_path = new_journal_path;
+        Ok(())
+    }
+}
@@ -1,10 +1,27 @@
+use crate::capabilities::grants::GrantKey;
 use crate::capabilities::registry::CapabilityRegistry;
+use crate::elf::fsck::{self, FsckReport};
+use crate::elf::{AutosavePolicy, ElfArchive, RecoveryInfo};
+use crate::engine::authorization::{
+    grant_target, is_project_command, AuthorizationTrace, PROJECT_SCOPE,
+};
+use crate::engine::autosave::{AttachedFile, ExternalChange};
 use crate::engine::event_store::{EventPoolWithPath, EventStore};
+use crate::engine::references::{DanglingReference, DeleteReport};
+use crate::engine::remote::{self, ReceiveReport, RejectedEvent};
+use crate::engine::signing::{self, EventSigner, KeyStore, VerifyReport};
 use crate::engine::state::StateProjector;
-use crate::models::{Block, Command, Editor, Event, LinkBlockPayload, RELATION_IMPLEMENT};
+use crate::engine::trash::TrashedBlock;
+use crate::models::{
+    Block, Command, Delegation, DeleteBlockPayload, Editor, EditorDeletePayload, Event,
+    GrantLimits, GrantPayload, GrantRolePayload, GrantScope, LinkBlockPayload, RestoreBlockPayload,
+    RevokePayload, Role, RoleAssignment,
+};
 use crate::utils::write_block_snapshot;
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
 
 /// Prefix for block-specific directories
@@ -56,6 +73,12 @@ pub enum EngineMessage {
         command: Command,
         response: oneshot::Sender<Result<Vec<Event>, String>>,
     },
+    /// Validate and authorize a command and return the events it would produce,
+    /// without persisting or applying them
+    DryRunCommand {
+        command: Command,
+        response: oneshot::Sender<Result<Vec<Event>, String>>,
+    },
     /// Get a block by ID
     GetBlock {
         block_id: String,
@@ -83,6 +106,18 @@ pub enum EngineMessage {
         block_id: String,
         response: oneshot::Sender<Vec<(String, String, String)>>,
     },
+    /// Get all deny rules as a map: editor_id -> Vec<(cap_id, block_id)>
+    GetAllDenies {
+        response: oneshot::Sender<HashMap<String, Vec<(String, String)>>>,
+    },
+    /// Get the expiry/usage limits of all limited grants
+    GetGrantLimits {
+        response: oneshot::Sender<HashMap<GrantKey, GrantLimits>>,
+    },
+    /// Get who issued each grant and whether it may be delegated
+    GetGrantDelegations {
+        response: oneshot::Sender<HashMap<GrantKey, Delegation>>,
+    },
     /// Check if an editor is authorized for a capability on a block
     CheckGrant {
         editor_id: String,
@@ -90,10 +125,100 @@ pub enum EngineMessage {
         block_id: String,
         response: oneshot::Sender<bool>,
     },
+    /// Trace every rule that applies to an authorization check
+    ExplainAuthorization {
+        editor_id: String,
+        cap_id: String,
+        block_id: String,
+        response: oneshot::Sender<AuthorizationTrace>,
+    },
+    /// Get all role definitions
+    GetRoles {
+        response: oneshot::Sender<Vec<Role>>,
+    },
+    /// Get all role assignments
+    GetRoleAssignments {
+        response: oneshot::Sender<Vec<RoleAssignment>>,
+    },
+    /// Find references to blocks that no longer exist
+    FindDanglingReferences {
+        response: oneshot::Sender<Vec<DanglingReference>>,
+    },
+    /// Get all blocks in the trash
+    GetTrash {
+        response: oneshot::Sender<Vec<TrashedBlock>>,
+    },
+    /// Check project integrity, optionally repairing what can be repaired
+    CheckIntegrity {
+        repair: bool,
+        editor_id: String,
+        response: oneshot::Sender<Result<FsckReport, String>>,
+    },
+    /// Sign every event persisted from now on, using keys from the given store
+    EnableSigning {
+        keys: KeyStore,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    /// Verify the hash chain and signatures of the stored events
+    VerifyEvents {
+        response: oneshot::Sender<Result<VerifyReport, String>>,
+    },
     /// Get all events
     GetAllEvents {
         response: oneshot::Sender<Result<Vec<Event>, String>>,
     },
+    /// Attach the .elf file the engine works on, for autosave and crash recovery
+    AttachFile {
+        archive: Arc<ElfArchive>,
+        path: PathBuf,
+        policy: AutosavePolicy,
+        response: oneshot::Sender<Result<Option<RecoveryInfo>, String>>,
+    },
+    /// Save the attached file
+    SaveFile {
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    /// Follow the attached file to a new path
+    SetFilePath {
+        path: PathBuf,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    /// Change when the attached file is saved automatically
+    SetAutosavePolicy {
+        policy: AutosavePolicy,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    /// Get the unsaved events an earlier session left behind
+    GetRecoveryInfo {
+        response: oneshot::Sender<Option<RecoveryInfo>>,
+    },
+    /// Restore the unsaved events an earlier session left behind
+    RestoreRecovery {
+        response: oneshot::Sender<Result<usize, String>>,
+    },
+    /// Throw away the unsaved events an earlier session left behind
+    DiscardRecovery {
+        response: oneshot::Sender<Result<usize, String>>,
+    },
+    /// Check whether someone else rewrote the attached file since it was opened or saved
+    CheckExternalChange {
+        response: oneshot::Sender<Result<Option<ExternalChange>, String>>,
+    },
+    /// Merge the events someone else wrote to the attached file into this session
+    MergeExternalChanges {
+        merger: String,
+        response: oneshot::Sender<Result<ReceiveReport, String>>,
+    },
+    /// Append events received from another instance of the project
+    ReceiveEvents {
+        events: Vec<Event>,
+        receiver: String,
+        response: oneshot::Sender<Result<ReceiveReport, String>>,
+    },
+    /// Detach the file, removing its recovery journal
+    DetachFile {
+        response: oneshot::Sender<Result<(), String>>,
+    },
     /// Shutdown the actor
     Shutdown,
 }
@@ -116,6 +241,12 @@ pub struct ElfileEngineActor {
     /// Capability registry
     registry: CapabilityRegistry,
 
+    /// Signs persisted events (None if signing is not enabled for this file)
+    signer: Option<EventSigner>,
+
+    /// The .elf file being edited (None until attached, e.g. in tests)
+    file: Option<AttachedFile>,
+
     /// Mailbox for receiving messages
     mailbox: mpsc::UnboundedReceiver<EngineMessage>,
 }
@@ -136,8 +267,18 @@ impl ElfileEngineActor {
         }
     }
 
+    /// Extract a block's directory if the archive was opened lazily and it is still zipped.
+    fn load_block_dir(&self, block_id: &str) {
+        if let Some(loader) = &self.event_pool_with_path.block_loader {
+            if let Err(e) = loader.load(&format!("{}{}", BLOCK_DIR_PREFIX, block_id)) {
+                log::warn!("Failed to extract block directory for {}: {}", block_id, e);
+            }
+        }
+    }
+
     /// Inject _block_dir for a specific block when temp dir is available.
     fn inject_block_dir_if_possible(&self, block: &mut Block) {
+        self.load_block_dir(&block.block_id);
         self.with_temp_dir(|temp_dir| {
             let _ = inject_block_dir(temp_dir, &block.block_id, &mut block.contents);
         });
@@ -147,7 +288,7 @@ impl ElfileEngineActor {
     ///
     /// Called after events are committed and state is projected.
     /// Handles: markdown.write, code.write, directory.write, directory.import,
-    /// directory.create, and core.create (for blocks with content).
+    /// directory.create, core.create and core.restore (for blocks with content).
     fn write_snapshots(&self, events: &[Event]) {
         let temp_dir = match self
             .event_pool_with_path
@@ -161,6 +302,8 @@ impl ElfileEngineActor {
 
         for event in events {
             let cap_id = Self::extract_cap_id(&event.attribute);
+            // Don't let a snapshot shadow the rest of a still zipped block directory
+            self.load_block_dir(&event.entity);
 
             match cap_id {
                 "markdown.write" | "code.write" => {
@@ -191,8 +334,8 @@ impl ElfileEngineActor {
                         }
                     }
                 }
-                "core.create" => {
-                    // New block created: write snapshot if it has content
+                "core.create" | "core.restore" => {
+                    // New or restored block: write snapshot if it has content
                     if let Some(block) = self.state.get_block(&event.entity) {
                         if let Err(e) = write_block_snapshot(
                             temp_dir,
@@ -217,38 +360,167 @@ impl ElfileEngineActor {
         attribute.split('/').nth(1).unwrap_or("")
     }
 
-    /// Check if linking source → target would create a cycle in the DAG.
+    /// Plan a core.delete command against the current state.
     ///
-    /// From target, DFS along `implement` children. If we reach source,
-    /// a cycle would be formed: source → target → ... → source.
-    /// Also rejects self-links (source == target).
-    fn check_link_cycle(&self, source_id: &str, target_id: &str) -> Result<(), String> {
-        // Self-link is always a cycle
-        if source_id == target_id {
-            return Err(format!(
-                "Cycle detected: linking {} → {} would create a self-cycle",
-                source_id, target_id
-            ));
-        }
-
-        let mut visited = HashSet::new();
-        let mut stack = vec![target_id.to_string()];
-
-        while let Some(current) = stack.pop() {
-            if current == source_id {
+    /// Cascaded blocks are deleted on the editor's behalf, so the editor must be
+    /// authorized for `core.delete` on each of them, not just on the target.
+    /// Removing links and directory entries that point at deleted blocks is a
+    /// consequence of the delete and needs no further authorization.
+    fn plan_delete(&self, cmd: &Command, now: DateTime<Utc>) -> Result<DeleteReport, String> {
+        let payload: DeleteBlockPayload = if cmd.payload.is_null() {
+            DeleteBlockPayload::default()
+        } else {
+            serde_json::from_value(cmd.payload.clone())
+                .map_err(|e| format!("Invalid payload for core.delete: {}", e))?
+        };
+        let report = self.state.plan_delete(&cmd.block_id, &payload)?;
+
+        for block_id in report.deleted.iter().skip(1) {
+            if !self
+                .state
+                .is_authorized_at(&cmd.editor_id, "core.delete", block_id, now)
+            {
                 return Err(format!(
-                    "Cycle detected: linking {} → {} would create a cycle",
-                    source_id, target_id
+                    "Authorization failed: {} does not have permission for core.delete on block {} (cascaded from {})",
+                    cmd.editor_id, block_id, cmd.block_id
                 ));
             }
-            if visited.insert(current.clone()) {
-                if let Some(block) = self.state.get_block(&current) {
-                    if let Some(targets) = block.children.get(RELATION_IMPLEMENT) {
-                        stack.extend(targets.iter().cloned());
-                    }
+        }
+
+        Ok(report)
+    }
+
+    /// Plan a core.restore command against the trash.
+    ///
+    /// Returns the blocks to restore, target first. Restoring cascaded blocks
+    /// requires `core.restore` on each of them.
+    fn plan_restore(&self, cmd: &Command, now: DateTime<Utc>) -> Result<Vec<String>, String> {
+        let payload: RestoreBlockPayload = if cmd.payload.is_null() {
+            RestoreBlockPayload::default()
+        } else {
+            serde_json::from_value(cmd.payload.clone())
+                .map_err(|e| format!("Invalid payload for core.restore: {}", e))?
+        };
+        let restored = self
+            .state
+            .plan_restore(&cmd.block_id, payload.include_cascaded)?;
+
+        for block_id in restored.iter().skip(1) {
+            if !self
+                .state
+                .is_authorized_at(&cmd.editor_id, "core.restore", block_id, now)
+            {
+                return Err(format!(
+                    "Authorization failed: {} does not have permission for core.restore on block {} (cascaded from {})",
+                    cmd.editor_id, block_id, cmd.block_id
+                ));
+            }
+        }
+
+        Ok(restored)
+    }
+
+    /// Remove the physical `block-{id}/` directory of a purged block.
+    fn remove_block_dir(&self, block_id: &str) {
+        if let Some(loader) = &self.event_pool_with_path.block_loader {
+            loader.discard(&format!("{}{}", BLOCK_DIR_PREFIX, block_id));
+        }
+        self.with_temp_dir(|temp_dir| {
+            let block_dir = temp_dir.join(format!("{}{}", BLOCK_DIR_PREFIX, block_id));
+            if block_dir.exists() {
+                if let Err(e) = std::fs::remove_dir_all(&block_dir) {
+                    log::warn!("Failed to remove block directory for {}: {}", block_id, e);
                 }
             }
+        });
+    }
+
+    /// Run the integrity checker over the archive directory and current state.
+    ///
+    /// In repair mode, corrective events are committed as `editor_id` and
+    /// file-level problems are fixed on disk. The returned report lists the
+    /// problems found before repair.
+    async fn check_integrity(
+        &mut self,
+        repair: bool,
+        editor_id: &str,
+    ) -> Result<FsckReport, String> {
+        let archive_dir = self
+            .event_pool_with_path
+            .db_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(Path::to_path_buf);
+
+        // fsck compares every block directory, so extract the ones still zipped
+        if let Some(loader) = &self.event_pool_with_path.block_loader {
+            loader
+                .load_all()
+                .map_err(|e| format!("Failed to extract block directories: {}", e))?;
+        }
+
+        let mut report = fsck::check(archive_dir.as_deref(), &self.state);
+        if !repair {
+            return Ok(report);
+        }
+
+        let events = fsck::repair_events(&self.state, editor_id);
+        report.repair_events = events.len();
+        if !events.is_empty() {
+            self.commit_events(editor_id, events).await?;
+        }
+        if let Some(dir) = archive_dir.as_deref() {
+            fsck::repair_files(dir, &self.state)?;
+        }
+        report.repaired = true;
+
+        Ok(report)
+    }
+
+    /// Commit engine-generated events on behalf of an editor.
+    ///
+    /// Stamps the vector clock, persists and applies the events, bypassing
+    /// capability handlers. Used for corrective events that are not the result
+    /// of a single command.
+    async fn commit_events(
+        &mut self,
+        editor_id: &str,
+        mut events: Vec<Event>,
+    ) -> Result<Vec<Event>, String> {
+        let mut full_timestamp = self.state.editor_counts.clone();
+        let new_count = *full_timestamp.get(editor_id).unwrap_or(&0) + 1;
+        full_timestamp.insert(editor_id.to_string(), new_count);
+        for event in &mut events {
+            event.timestamp = full_timestamp.clone();
+        }
+        if let Some(signer) = self.signer.as_mut() {
+            signer.sign(&mut events)?;
+        }
+
+        EventStore::append_events(&self.event_pool_with_path.pool, &events)
+            .await
+            .map_err(|e| format!("Failed to persist events to database: {}", e))?;
+
+        for event in &events {
+            self.state.apply_event(event);
+        }
+        self.write_snapshots(&events);
+        self.after_commit(&events).await;
+
+        Ok(events)
+    }
+
+    /// Enrol the project's editors in `keys` and sign from now on.
+    ///
+    /// An unsigned log starts a new chain; a signed one is already being extended.
+    fn enable_signing(&mut self, keys: KeyStore) -> Result<(), String> {
+        if self.signer.is_some() {
+            return Ok(());
         }
+        for editor_id in self.state.editors.keys() {
+            keys.enrol(editor_id)?;
+        }
+        self.signer = Some(EventSigner::new(keys, String::new()));
         Ok(())
     }
 
@@ -268,6 +540,13 @@ impl ElfileEngineActor {
         let events = EventStore::get_all_events(&event_pool_with_path.pool)
             .await
             .map_err(|e| format!("Failed to load events from database: {}", e))?;
+
+        // A signed log keeps being signed
+        let signer = if events.iter().any(|event| event.signature.is_some()) {
+            EventSigner::resume(KeyStore::default_location()?, &events)
+        } else {
+            None
+        };
         state.replay(events);
 
         Ok(Self {
@@ -275,6 +554,8 @@ impl ElfileEngineActor {
             event_pool_with_path,
             state,
             registry,
+            signer,
+            file: None,
             mailbox,
         })
     }
@@ -283,12 +564,15 @@ impl ElfileEngineActor {
     ///
     /// This processes messages from the mailbox until a Shutdown message is received.
     pub async fn run(mut self) {
-        while let Some(msg) = self.mailbox.recv().await {
+        while let Some(msg) = self.next_message().await {
             match msg {
                 EngineMessage::ProcessCommand { command, response } => {
                     let result = self.process_command(command).await;
                     let _ = response.send(result);
                 }
+                EngineMessage::DryRunCommand { command, response } => {
+                    let _ = response.send(self.prepare_events(&command, false));
+                }
                 EngineMessage::GetBlock { block_id, response } => {
                     let mut block = self.state.get_block(&block_id).cloned();
 
@@ -335,11 +619,12 @@ impl ElfileEngineActor {
                     let _ = response.send(grants);
                 }
                 EngineMessage::GetBlockGrants { block_id, response } => {
-                    // Get all grants and filter those that apply to this block
+                    // Get all grants and filter those whose scope covers this block
+                    let containers = self.state.block_containers(&block_id);
                     let mut block_grants = Vec::new();
                     for (editor_id, grants) in self.state.grants.as_map() {
                         for (cap_id, target_block) in grants {
-                            if target_block == &block_id || target_block == "*" {
+                            if GrantScope::parse(target_block).covers(&block_id, &containers) {
                                 block_grants.push((
                                     editor_id.clone(),
                                     cap_id.clone(),
@@ -350,6 +635,15 @@ impl ElfileEngineActor {
                     }
                     let _ = response.send(block_grants);
                 }
+                EngineMessage::GetAllDenies { response } => {
+                    let _ = response.send(self.state.grants.denies_map().clone());
+                }
+                EngineMessage::GetGrantLimits { response } => {
+                    let _ = response.send(self.state.grants.all_limits().clone());
+                }
+                EngineMessage::GetGrantDelegations { response } => {
+                    let _ = response.send(self.state.grants.all_delegations().clone());
+                }
                 EngineMessage::CheckGrant {
                     editor_id,
                     cap_id,
@@ -359,6 +653,136 @@ impl ElfileEngineActor {
                     let authorized = self.state.is_authorized(&editor_id, &cap_id, &block_id);
                     let _ = response.send(authorized);
                 }
+                EngineMessage::ExplainAuthorization {
+                    editor_id,
+                    cap_id,
+                    block_id,
+                    response,
+                } => {
+                    let trace = self.state.trace_authorization_at(
+                        &editor_id,
+                        &cap_id,
+                        &block_id,
+                        Utc::now(),
+                    );
+                    let _ = response.send(trace);
+                }
+                EngineMessage::GetRoles { response } => {
+                    let _ = response.send(self.state.roles.roles());
+                }
+                EngineMessage::GetRoleAssignments { response } => {
+                    let _ = response.send(self.state.roles.assignments());
+                }
+                EngineMessage::FindDanglingReferences { response } => {
+                    let _ = response.send(self.state.find_dangling_references());
+                }
+                EngineMessage::GetTrash { response } => {
+                    let mut trash: Vec<TrashedBlock> = self.state.trash.values().cloned().collect();
+                    // Most recently deleted first
+                    trash.sort_by(|a, b| {
+                        b.deleted_at
+                            .cmp(&a.deleted_at)
+                            .then_with(|| a.block.block_id.cmp(&b.block.block_id))
+                    });
+                    let _ = response.send(trash);
+                }
+                EngineMessage::CheckIntegrity {
+                    repair,
+                    editor_id,
+                    response,
+                } => {
+                    let result = self.check_integrity(repair, &editor_id).await;
+                    let _ = response.send(result);
+                }
+                EngineMessage::EnableSigning { keys, response } => {
+                    let _ = response.send(self.enable_signing(keys));
+                }
+                EngineMessage::VerifyEvents { response } => {
+                    let result = match self.signer.as_ref() {
+                        Some(signer) => Ok(signer.keys().clone()),
+                        None => KeyStore::default_location(),
+                    };
+                    let result = match result {
+                        Ok(keys) => EventStore::get_all_events(&self.event_pool_with_path.pool)
+                            .await
+                            .map(|events| signing::verify_events(&events, &keys))
+                            .map_err(|e| format!("Failed to get events: {}", e)),
+                        Err(e) => Err(e),
+                    };
+                    let _ = response.send(result);
+                }
+                EngineMessage::AttachFile {
+                    archive,
+                    path,
+                    policy,
+                    response,
+                } => {
+                    let result = self.attach_file(archive, path, policy).await;
+                    let _ = response.send(result);
+                }
+                EngineMessage::SaveFile { response } => {
+                    let result = match self.file.as_mut() {
+                        Some(file) => file.save().await,
+                        None => Err("No file is attached to this engine".to_string()),
+                    };
+                    let _ = response.send(result);
+                }
+                EngineMessage::SetFilePath { path, response } => {
+                    let result = match self.file.as_mut() {
+                        Some(file) => file.set_path(&path),
+                        None => Err("No file is attached to this engine".to_string()),
+                    };
+                    let _ = response.send(result);
+                }
+                EngineMessage::SetAutosavePolicy { policy, response } => {
+                    let result = match self.file.as_mut() {
+                        Some(file) => {
+                            file.set_policy(policy);
+                            Ok(file.save_due())
+                        }
+                        None => Err("No file is attached to this engine".to_string()),
+                    };
+                    let result = match result {
+                        Ok(true) => self.autosave().await,
+                        other => other.map(|_| ()),
+                    };
+                    let _ = response.send(result);
+                }
+                EngineMessage::GetRecoveryInfo { response } => {
+                    let info = self.file.as_ref().and_then(AttachedFile::recovery_info);
+                    let _ = response.send(info);
+                }
+                EngineMessage::RestoreRecovery { response } => {
+                    let result = self.restore_recovery().await;
+                    let _ = response.send(result);
+                }
+                EngineMessage::DiscardRecovery { response } => {
+                    let result = match self.file.as_mut() {
+                        Some(file) => file.discard_recovery(),
+                        None => Ok(0),
+                    };
+                    let _ = response.send(result);
+                }
+                EngineMessage::CheckExternalChange { response } => {
+                    let result = self.check_external_change().await;
+                    let _ = response.send(result);
+                }
+                EngineMessage::MergeExternalChanges { merger, response } => {
+                    let result = self.merge_external_changes(&merger).await;
+                    let _ = response.send(result);
+                }
+                EngineMessage::ReceiveEvents {
+                    events,
+                    receiver,
+                    response,
+                } => {
+                    let result = self.receive_events(events, &receiver).await;
+                    let _ = response.send(result);
+                }
+                EngineMessage::DetachFile { response } => {
+                    let result = self.file.take().map_or(Ok(()), AttachedFile::close);
+                    let _ = response.send(result);
+                }
                 EngineMessage::Shutdown => {
                     break;
                 }
@@ -366,31 +790,287 @@ impl ElfileEngineActor {
         }
     }
 
-    /// Process a command and return resulting events.
+    /// Wait for the next message, autosaving the attached file whenever its timer runs out.
+    async fn next_message(&mut self) -> Option<EngineMessage> {
+        loop {
+            let Some(deadline) = self.file.as_ref().and_then(AttachedFile::deadline) else {
+                return self.mailbox.recv().await;
+            };
+            tokio::select! {
+                msg = self.mailbox.recv() => return msg,
+                _ = tokio::time::sleep_until(deadline) => {}
+            }
+            if let Err(e) = self.autosave().await {
+                log::error!("Autosave failed: {}", e);
+            }
+        }
+    }
+
+    /// Save the attached file (if any).
+    async fn autosave(&mut self) -> Result<(), String> {
+        match self.file.as_mut() {
+            Some(file) => file.save().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Journal events that were just committed, and autosave if that is due.
+    async fn after_commit(&mut self, events: &[Event]) {
+        let due = match self.file.as_mut() {
+            Some(file) => file.record(events),
+            None => false,
+        };
+        if due {
+            if let Err(e) = self.autosave().await {
+                log::error!("Autosave failed: {}", e);
+            }
+        }
+    }
+
+    /// Attach the .elf file and report what an earlier session left unsaved.
+    async fn attach_file(
+        &mut self,
+        archive: Arc<ElfArchive>,
+        path: PathBuf,
+        policy: AutosavePolicy,
+    ) -> Result<Option<RecoveryInfo>, String> {
+        let stored_event_ids = self.stored_event_ids().await?;
+        let file = AttachedFile::attach(archive, path, policy, &stored_event_ids)?;
+        let info = file.recovery_info();
+        self.file = Some(file);
+        Ok(info)
+    }
+
+    /// Bring back the unsaved events an earlier session left behind.
     ///
-    /// This is the core command processing logic:
-    /// 1. Get capability handler
-    /// 2. Get block (None for create, Some for others)
-    /// 3. Check authorization (certificator)
-    /// 4. Execute handler
-    /// 5. Update vector clock
-    /// 6. Check for conflicts (MVP simple version)
-    /// 7. Commit events to EventStore
-    /// 8. Apply events to StateProjector
-    async fn process_command(&mut self, cmd: Command) -> Result<Vec<Event>, String> {
+    /// They are persisted and applied as they were journaled (already stamped
+    /// and signed), so they continue the event log where the saved file ends.
+    async fn restore_recovery(&mut self) -> Result<usize, String> {
+        let events = match self.file.as_mut() {
+            Some(file) => file.take_recovery(),
+            None => return Ok(0),
+        };
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        EventStore::append_events(&self.event_pool_with_path.pool, &events)
+            .await
+            .map_err(|e| format!("Failed to persist events to database: {}", e))?;
+        for event in &events {
+            self.state.apply_event(event);
+        }
+        self.write_snapshots(&events);
+
+        // A signed log keeps being signed, from the last restored event on
+        if let Some(signer) = EventSigner::resume(KeyStore::default_location()?, &events) {
+            self.signer = Some(signer);
+        }
+
+        Ok(events.len())
+    }
+
+    /// IDs of all events in the event store.
+    async fn stored_event_ids(&self) -> Result<HashSet<String>, String> {
+        Ok(EventStore::get_all_events(&self.event_pool_with_path.pool)
+            .await
+            .map_err(|e| format!("Failed to get events: {}", e))?
+            .into_iter()
+            .map(|event| event.event_id)
+            .collect())
+    }
+
+    /// Report the events someone else wrote to the attached file (None if it is unchanged).
+    async fn check_external_change(&mut self) -> Result<Option<ExternalChange>, String> {
+        let known = self.stored_event_ids().await?;
+        let Some(file) = self.file.as_mut() else {
+            return Ok(None);
+        };
+        if !file.is_modified_externally()? {
+            return Ok(None);
+        }
+
+        let external = file.read_external(&known).await?;
+        let editors = RecoveryInfo::from_events(&external.events)
+            .map(|info| info.editors)
+            .unwrap_or_default();
+        Ok(Some(ExternalChange {
+            new_events: external.events.len(),
+            editors,
+        }))
+    }
+
+    /// Merge the events someone else wrote to the attached file into this session.
+    ///
+    /// Events are globally unique, so the logs are combined by appending the
+    /// events this session doesn't have yet. Both sides only added to a shared
+    /// history, so their new events are concurrent and can follow ours. They
+    /// are checked like events from another instance (see [`Self::take_in_events`]);
+    /// in a signed log, `merger` links them into the chain. After merging, the
+    /// file can be saved again, which drops the refused events from it.
+    async fn merge_external_changes(&mut self, merger: &str) -> Result<ReceiveReport, String> {
+        let known = self.stored_event_ids().await?;
+        let Some(file) = self.file.as_mut() else {
+            return Err("No file is attached to this engine".to_string());
+        };
+        if !file.is_modified_externally()? {
+            return Ok(ReceiveReport::default());
+        }
+
+        let mut external = file.read_external(&known).await?;
+        let (report, applied) = self
+            .take_in_events(std::mem::take(&mut external.events), merger)
+            .await?;
+
+        // Only the blocks of merged events are copied over
+        let merged_dirs: HashSet<String> = applied
+            .iter()
+            .map(|event| format!("{}{}", BLOCK_DIR_PREFIX, event.entity))
+            .collect();
+        external
+            .block_dirs
+            .retain(|name| merged_dirs.contains(name.to_string_lossy().as_ref()));
+        if let Some(file) = self.file.as_mut() {
+            file.accept_external(&external)?;
+        }
+
+        self.write_snapshots(&applied);
+        if !applied.is_empty() {
+            self.after_commit(&applied).await;
+        }
+
+        Ok(report)
+    }
+
+    /// Append events received from another instance of the project.
+    ///
+    /// Only signed logs take in remote events; see [`Self::take_in_events`]
+    /// for how they are checked.
+    async fn receive_events(
+        &mut self,
+        events: Vec<Event>,
+        receiver: &str,
+    ) -> Result<ReceiveReport, String> {
+        if self.signer.is_none() {
+            return Err(
+                "Events from other instances are only taken in by signed files; enable signing first"
+                    .to_string(),
+            );
+        }
+
+        let (report, applied) = self.take_in_events(events, receiver).await?;
+        self.write_snapshots(&applied);
+        if !applied.is_empty() {
+            self.after_commit(&applied).await;
+        }
+
+        Ok(report)
+    }
+
+    /// Check events written by another copy of the project and append those
+    /// that pass.
+    ///
+    /// Events are taken in the order they were written, a command (the events
+    /// sharing an editor and vector clock) at a time. Known events are skipped.
+    /// A command is refused as a whole if its editor lacks the capability
+    /// under the local grants, or it fails the checks of the command that made
+    /// it (see [`remote::check_remote_command`]). In a signed log each event
+    /// must also carry its author's signature, made with the key trusted here
+    /// for that editor, and is chained into the local log (signed by
+    /// `receiver`). Accepted commands are persisted and applied one by one, so
+    /// a grant taken in earlier authorizes the commands that follow it.
+    /// Events keep the editor ids and vector clocks they were created with.
+    ///
+    /// Returns the report and the applied events; snapshots and the
+    /// post-commit steps are left to the caller.
+    async fn take_in_events(
+        &mut self,
+        events: Vec<Event>,
+        receiver: &str,
+    ) -> Result<(ReceiveReport, Vec<Event>), String> {
+        let keys = self.signer.as_ref().map(|signer| signer.keys().clone());
+        if let Some(keys) = &keys {
+            keys.signing_key(receiver)?;
+        }
+
+        let known = self.stored_event_ids().await?;
+        let mut report = ReceiveReport {
+            received: events.len(),
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+        let events: Vec<Event> = events
+            .into_iter()
+            .filter(|event| !known.contains(&event.event_id) && seen.insert(event.event_id.clone()))
+            .collect();
+        report.duplicates = report.received - events.len();
+        let mut applied = Vec::new();
+        let now = Utc::now();
+
+        for command in remote::split_commands(&events) {
+            let checked = command
+                .iter()
+                .try_for_each(|event| match &keys {
+                    Some(keys) => signing::verify_author(event, keys),
+                    None => Ok(()),
+                })
+                .and_then(|_| remote::check_remote_command(&self.state, command, now));
+            if let Err(reason) = checked {
+                for event in command {
+                    log::warn!("Rejected event {}: {}", event.event_id, reason);
+                    report
+                        .rejected
+                        .push(RejectedEvent::new(event, reason.clone()));
+                }
+                continue;
+            }
+
+            let mut command = command.to_vec();
+            if let Some(signer) = self.signer.as_mut() {
+                for event in &mut command {
+                    signer.link(event, receiver)?;
+                }
+            }
+            EventStore::append_events(&self.event_pool_with_path.pool, &command)
+                .await
+                .map_err(|e| format!("Failed to persist events to database: {}", e))?;
+            for event in &command {
+                self.state.apply_event(event);
+            }
+            applied.extend(command);
+        }
+
+        report.applied = applied.len();
+        Ok((report, applied))
+    }
+
+    /// Run a command up to (but not including) persistence.
+    ///
+    /// Covers steps 1-5 of [`Self::process_command`]: looks up the handler and
+    /// block, checks authorization and structural constraints, runs the handler
+    /// and stamps the vector clock. Nothing is persisted or applied, so this also
+    /// backs dry runs (with `block_dirs` false, no block directories are created).
+    fn prepare_events(&self, cmd: &Command, block_dirs: bool) -> Result<Vec<Event>, String> {
         // 1. Get capability handler
         let handler = self
             .registry
             .get(&cmd.cap_id)
             .ok_or_else(|| format!("Unknown capability: {}", cmd.cap_id))?;
 
-        // 2. Get block (None for create operations, Some for others)
-        // System-level operations like core.create, editor.create, and editor.delete don't require a block
-        let mut block_opt = if cmd.cap_id == "core.create"
-            || cmd.cap_id == "editor.create"
-            || cmd.cap_id == "editor.delete"
-        {
+        // 2. Get block (None for project-level operations, Some for others)
+        // Project-level operations like core.create, editor.create, editor.delete,
+        // role definitions and grant management without a block don't require a block
+        // Trash operations (core.restore, core.purge) look the block up in the trash
+        let mut block_opt = if is_project_command(&cmd.cap_id, &cmd.block_id) {
             None
+        } else if cmd.cap_id == "core.restore" || cmd.cap_id == "core.purge" {
+            Some(
+                self.state
+                    .get_trashed(&cmd.block_id)
+                    .ok_or_else(|| format!("Block not in trash: {}", cmd.block_id))?
+                    .block
+                    .clone(),
+            )
         } else {
             Some(
                 self.state
@@ -402,7 +1082,12 @@ impl ElfileEngineActor {
 
         // 2.5. Inject _block_dir into block contents (runtime only, not persisted)
         // Skip for :memory: databases used in unit tests (no filesystem access needed)
-        if let Some(ref mut block) = block_opt {
+        // Purged blocks are about to lose their directory, so don't recreate it
+        // Dry runs leave the filesystem alone
+        if let Some(ref mut block) = block_opt
+            .as_mut()
+            .filter(|_| block_dirs && cmd.cap_id != "core.purge")
+        {
             if let Some(temp_dir) = self
                 .event_pool_with_path
                 .db_path
@@ -410,21 +1095,42 @@ impl ElfileEngineActor {
                 .filter(|p| !p.as_os_str().is_empty())
             {
                 // Use helper function to inject _block_dir and create directory
+                self.load_block_dir(&block.block_id);
                 inject_block_dir(temp_dir, &block.block_id, &mut block.contents)?;
             }
         }
 
         // 3. Check authorization (certificator)
-        // Only check if block exists (non-create operations)
-        if let Some(block) = block_opt.as_ref() {
-            if !self
-                .state
-                .is_authorized(&cmd.editor_id, &cmd.cap_id, &block.block_id)
-            {
-                return Err(format!(
-                    "Authorization failed: {} does not have permission for {} on block {}",
-                    cmd.editor_id, cmd.cap_id, cmd.block_id
-                ));
+        // Block operations are checked against the block, project-level operations
+        // against the project pseudo-block (project owner or grants on PROJECT_SCOPE)
+        // Limited grants are checked at the engine's clock: the command's own
+        // timestamp comes from the caller and can't be trusted
+        let now = Utc::now();
+        let target = block_opt
+            .as_ref()
+            .map_or(PROJECT_SCOPE, |block| block.block_id.as_str());
+        match block_opt.as_ref() {
+            Some(block) => {
+                if !self
+                    .state
+                    .is_authorized_at(&cmd.editor_id, &cmd.cap_id, &block.block_id, now)
+                {
+                    return Err(format!(
+                        "Authorization failed: {} does not have permission for {} on block {}",
+                        cmd.editor_id, cmd.cap_id, cmd.block_id
+                    ));
+                }
+            }
+            None => {
+                if !self
+                    .state
+                    .is_authorized_at(&cmd.editor_id, &cmd.cap_id, PROJECT_SCOPE, now)
+                {
+                    return Err(format!(
+                        "Authorization failed: {} does not have project-level permission for {}",
+                        cmd.editor_id, cmd.cap_id
+                    ));
+                }
             }
         }
 
@@ -432,14 +1138,116 @@ impl ElfileEngineActor {
         if cmd.cap_id == "core.link" {
             let payload: LinkBlockPayload = serde_json::from_value(cmd.payload.clone())
                 .map_err(|e| format!("Invalid payload for cycle check: {}", e))?;
-            self.check_link_cycle(&cmd.block_id, &payload.target_id)?;
+            self.state
+                .check_link_cycle(&cmd.block_id, &payload.target_id)?;
         }
 
-        // 4. Execute handler (block now contains _block_dir)
-        let mut events = handler.handler(&cmd, block_opt.as_ref())?;
+        // 3.6. Delete planning for core.delete (refuse / cascade / detach)
+        let delete_report = if cmd.cap_id == "core.delete" {
+            Some(self.plan_delete(cmd, now)?)
+        } else {
+            None
+        };
 
-        // 5. Update vector clock
-        // Get the full current vector clock state and increment the current editor's count
+        // 3.7. Restore planning for core.restore (target plus cascaded blocks)
+        let restore_plan = if cmd.cap_id == "core.restore" {
+            Some(self.plan_restore(cmd, now)?)
+        } else {
+            None
+        };
+
+        // 3.8. Roles must be defined before they can be given to editors
+        if cmd.cap_id == "core.grant_role" {
+            let payload: GrantRolePayload = serde_json::from_value(cmd.payload.clone())
+                .map_err(|e| format!("Invalid payload for core.grant_role: {}", e))?;
+            if self.state.roles.get_role(&payload.role).is_none() {
+                return Err(format!("Unknown role: {}", payload.role));
+            }
+        }
+
+        // 3.9. The project owner can't be deleted, or nobody could manage the project
+        if cmd.cap_id == "editor.delete" {
+            let payload: EditorDeletePayload = serde_json::from_value(cmd.payload.clone())
+                .map_err(|e| format!("Invalid payload for editor.delete: {}", e))?;
+            self.state.check_editor_delete(&payload.editor_id)?;
+        }
+
+        // 3.10. Delegation: editors may only grant what they hold
+        let delegated_from = if cmd.cap_id == "core.grant" {
+            let payload: GrantPayload = serde_json::from_value(cmd.payload.clone())
+                .map_err(|e| format!("Invalid payload for core.grant: {}", e))?;
+            self.state.check_delegation(
+                &cmd.editor_id,
+                &payload.capability,
+                &grant_target(&payload.capability, &payload.target_block),
+                now,
+            )?
+        } else {
+            None
+        };
+
+        // 3.11. A limited grant is used once per command, however many events it makes
+        let grant_use = self
+            .state
+            .limited_grant_for(&cmd.editor_id, &cmd.cap_id, target, now);
+
+        // 4. Execute handler (block now contains _block_dir)
+        let mut events = handler.handler(cmd, block_opt.as_ref())?;
+
+        // 4.5. Append cascade and cleanup events, and record what was affected
+        if let Some(report) = delete_report {
+            if let Some(primary) = events.first_mut() {
+                if let Some(obj) = primary.value.as_object_mut() {
+                    obj.insert("affected".to_string(), serde_json::json!(report));
+                }
+            }
+            events.extend(self.state.delete_followup_events(&report, &cmd.editor_id));
+        }
+        if let Some(restored) = restore_plan {
+            events.extend(
+                self.state
+                    .restore_followup_events(&restored, &cmd.editor_id),
+            );
+        }
+        if let Some((editor, capability, block)) = delegated_from {
+            if let Some(obj) = events.first_mut().and_then(|e| e.value.as_object_mut()) {
+                obj.insert(
+                    "delegated_from".to_string(),
+                    serde_json::json!({
+                        "editor": editor,
+                        "capability": capability,
+                        "block": block,
+                    }),
+                );
+            }
+        }
+        if let Some((editor, capability, block)) = grant_use {
+            if let Some(obj) = events.first_mut().and_then(|e| e.value.as_object_mut()) {
+                obj.insert(
+                    "grant_use".to_string(),
+                    serde_json::json!({
+                        "editor": editor,
+                        "capability": capability,
+                        "block": block,
+                    }),
+                );
+            }
+        }
+        if cmd.cap_id == "core.revoke" {
+            let payload: RevokePayload = serde_json::from_value(cmd.payload.clone())
+                .map_err(|e| format!("Invalid payload for core.revoke: {}", e))?;
+            if payload.cascade {
+                let key = (
+                    payload.target_editor,
+                    payload.capability,
+                    payload.target_block,
+                );
+                events.extend(self.state.revoke_followup_events(&key, &cmd.editor_id));
+            }
+        }
+
+        // 5. Update vector clock
+        // Get the full current vector clock state and increment the current editor's count
         let mut full_timestamp = self.state.editor_counts.clone();
         let current_count = *full_timestamp.get(&cmd.editor_id).unwrap_or(&0);
         let new_count = current_count + 1;
@@ -449,6 +1257,26 @@ impl ElfileEngineActor {
             event.timestamp = full_timestamp.clone();
         }
 
+        Ok(events)
+    }
+
+    /// Process a command and return resulting events.
+    ///
+    /// This is the core command processing logic:
+    /// 1. Get capability handler
+    /// 2. Get block (None for create, Some for others)
+    /// 3. Check authorization (certificator)
+    ///    (plus cycle detection for core.link, delete/restore planning and delegation)
+    /// 4. Execute handler
+    /// 5. Update vector clock
+    /// 6. Check for conflicts (MVP simple version)
+    /// 7. Commit events to EventStore
+    /// 8. Apply events to StateProjector
+    async fn process_command(&mut self, cmd: Command) -> Result<Vec<Event>, String> {
+        // 1-5. Validate, authorize and run the handler
+        let mut events = self.prepare_events(&cmd, true)?;
+        let current_count = self.state.get_editor_count(&cmd.editor_id);
+
         // 5.5. Special handling: inject _block_dir for core.create
         // Skip for :memory: databases used in unit tests
         if cmd.cap_id == "core.create" {
@@ -493,6 +1321,23 @@ impl ElfileEngineActor {
             }
         }
 
+        // 7.5. Extend the hash chain and sign (if signing is enabled).
+        // Editors created while signing are enrolled, so they can sign too,
+        // unless they already sign on another machine with a trusted key.
+        if let Some(signer) = self.signer.as_mut() {
+            if cmd.cap_id == "editor.create" {
+                for event in &events_to_persist {
+                    if signer.keys().public_key(&event.entity)?.is_none() {
+                        signer.keys().enrol(&event.entity)?;
+                    }
+                }
+            }
+            signer.sign(&mut events_to_persist)?;
+            for (event, persisted) in events.iter_mut().zip(&events_to_persist) {
+                event.signature = persisted.signature.clone();
+            }
+        }
+
         // 8. Persist events to database (without runtime fields)
         EventStore::append_events(&self.event_pool_with_path.pool, &events_to_persist)
             .await
@@ -508,6 +1353,14 @@ impl ElfileEngineActor {
         // Errors are logged but do not fail the command.
         self.write_snapshots(&events);
 
+        // 10.5. Purged blocks can't come back, so drop their physical files too (non-critical)
+        if cmd.cap_id == "core.purge" {
+            self.remove_block_dir(&cmd.block_id);
+        }
+
+        // 11. Journal the events for crash recovery and autosave if due
+        self.after_commit(&events_to_persist).await;
+
         // Return original events (with _block_dir) for caller
         Ok(events)
     }
@@ -527,6 +1380,43 @@ impl EngineHandle {
         Self { sender }
     }
 
+    /// Validate and authorize a command without persisting anything.
+    ///
+    /// Returns the events the command would produce, or the error it would fail with.
+    pub async fn dry_run_command(&self, command: Command) -> Result<Vec<Event>, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::DryRunCommand {
+                command,
+                response: tx,
+            })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Trace every rule that applies to an authorization check.
+    pub async fn explain_authorization(
+        &self,
+        editor_id: String,
+        cap_id: String,
+        block_id: String,
+    ) -> Result<AuthorizationTrace, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::ExplainAuthorization {
+                editor_id,
+                cap_id,
+                block_id,
+                response: tx,
+            })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())
+    }
+
     /// Process a command and return resulting events.
     pub async fn process_command(&self, command: Command) -> Result<Vec<Event>, String> {
         let (tx, rx) = oneshot::channel();
@@ -619,7 +1509,9 @@ impl EngineHandle {
 
     /// Get grants for a specific block.
     ///
-    /// Returns Vec<(editor_id, cap_id, block_id)> for all grants that apply to this block.
+    /// Returns Vec<(editor_id, cap_id, block_id)> for all grants that apply to this block,
+    /// including wildcard and `subtree:`/`descendants:` grants that cover it.
+    /// The third element is the grant target as stored.
     pub async fn get_block_grants(&self, block_id: String) -> Vec<(String, String, String)> {
         let (tx, rx) = oneshot::channel();
         if self
@@ -636,6 +1528,54 @@ impl EngineHandle {
         rx.await.unwrap_or_default()
     }
 
+    /// Get all deny rules.
+    ///
+    /// Returns a map of editor_id -> Vec<(cap_id, block_id)>
+    pub async fn get_all_denies(&self) -> HashMap<String, Vec<(String, String)>> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .sender
+            .send(EngineMessage::GetAllDenies { response: tx })
+            .is_err()
+        {
+            return HashMap::new();
+        }
+
+        rx.await.unwrap_or_default()
+    }
+
+    /// Get the expiry/usage limits of all limited grants.
+    ///
+    /// Returns a map of (editor_id, cap_id, block_id) -> limits; unlimited grants are absent.
+    pub async fn get_grant_limits(&self) -> HashMap<GrantKey, GrantLimits> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .sender
+            .send(EngineMessage::GetGrantLimits { response: tx })
+            .is_err()
+        {
+            return HashMap::new();
+        }
+
+        rx.await.unwrap_or_default()
+    }
+
+    /// Get who issued each grant and whether it may be delegated.
+    ///
+    /// Returns a map of (editor_id, cap_id, block_id) -> delegation details.
+    pub async fn get_grant_delegations(&self) -> HashMap<GrantKey, Delegation> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .sender
+            .send(EngineMessage::GetGrantDelegations { response: tx })
+            .is_err()
+        {
+            return HashMap::new();
+        }
+
+        rx.await.unwrap_or_default()
+    }
+
     /// Check if an editor is authorized for a capability on a block.
     pub async fn check_grant(&self, editor_id: String, cap_id: String, block_id: String) -> bool {
         let (tx, rx) = oneshot::channel();
@@ -655,6 +1595,110 @@ impl EngineHandle {
         rx.await.unwrap_or(false)
     }
 
+    /// Find references to blocks that no longer exist.
+    ///
+    /// Returns dangling `implement` links and directory entries.
+    pub async fn find_dangling_references(&self) -> Vec<DanglingReference> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .sender
+            .send(EngineMessage::FindDanglingReferences { response: tx })
+            .is_err()
+        {
+            return Vec::new();
+        }
+
+        rx.await.unwrap_or_default()
+    }
+
+    /// Get all role definitions, sorted by name.
+    pub async fn get_roles(&self) -> Vec<Role> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .sender
+            .send(EngineMessage::GetRoles { response: tx })
+            .is_err()
+        {
+            return Vec::new();
+        }
+
+        rx.await.unwrap_or_default()
+    }
+
+    /// Get all role assignments (which editor holds which role on which block).
+    pub async fn get_role_assignments(&self) -> Vec<RoleAssignment> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .sender
+            .send(EngineMessage::GetRoleAssignments { response: tx })
+            .is_err()
+        {
+            return Vec::new();
+        }
+
+        rx.await.unwrap_or_default()
+    }
+
+    /// Check project integrity (see `elf::fsck`).
+    ///
+    /// With `repair`, corrective events are committed as `editor_id`.
+    pub async fn check_integrity(
+        &self,
+        repair: bool,
+        editor_id: String,
+    ) -> Result<FsckReport, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::CheckIntegrity {
+                repair,
+                editor_id,
+                response: tx,
+            })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Sign every event persisted from now on with per-editor keys from `keys`.
+    ///
+    /// The project's editors are enrolled in `keys`. Enabling signing on a file
+    /// that already signs is a no-op.
+    pub async fn enable_signing(&self, keys: KeyStore) -> Result<(), String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::EnableSigning { keys, response: tx })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Verify the hash chain and signatures of the stored events.
+    pub async fn verify_events(&self) -> Result<VerifyReport, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::VerifyEvents { response: tx })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Get all blocks in the trash, most recently deleted first.
+    pub async fn get_trash(&self) -> Vec<TrashedBlock> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .sender
+            .send(EngineMessage::GetTrash { response: tx })
+            .is_err()
+        {
+            return Vec::new();
+        }
+
+        rx.await.unwrap_or_default()
+    }
+
     /// Get all events.
     ///
     /// Returns all events from the event store for this file.
@@ -672,6 +1716,174 @@ impl EngineHandle {
             .map_err(|_| "Engine actor did not respond".to_string())?
     }
 
+    /// Attach the .elf file this engine works on.
+    ///
+    /// From then on committed events are journaled for crash recovery and the
+    /// file is saved according to `policy`. Returns a summary of the unsaved
+    /// events an earlier session left behind, if any.
+    pub async fn attach_file(
+        &self,
+        archive: Arc<ElfArchive>,
+        path: PathBuf,
+        policy: AutosavePolicy,
+    ) -> Result<Option<RecoveryInfo>, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::AttachFile {
+                archive,
+                path,
+                policy,
+                response: tx,
+            })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Save the attached file and clear its recovery journal.
+    pub async fn save_file(&self) -> Result<(), String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::SaveFile { response: tx })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Tell the engine that the attached file was moved.
+    pub async fn set_file_path(&self, path: PathBuf) -> Result<(), String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::SetFilePath { path, response: tx })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Change when the attached file is saved automatically.
+    pub async fn set_autosave_policy(&self, policy: AutosavePolicy) -> Result<(), String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::SetAutosavePolicy {
+                policy,
+                response: tx,
+            })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Get a summary of the unsaved events an earlier session left behind.
+    pub async fn get_recovery_info(&self) -> Option<RecoveryInfo> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .sender
+            .send(EngineMessage::GetRecoveryInfo { response: tx })
+            .is_err()
+        {
+            return None;
+        }
+
+        rx.await.unwrap_or(None)
+    }
+
+    /// Restore the unsaved events an earlier session left behind.
+    ///
+    /// Returns the number of restored events.
+    pub async fn restore_recovery(&self) -> Result<usize, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::RestoreRecovery { response: tx })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Throw away the unsaved events an earlier session left behind.
+    ///
+    /// Returns the number of discarded events.
+    pub async fn discard_recovery(&self) -> Result<usize, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::DiscardRecovery { response: tx })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Check whether someone else rewrote the attached file since it was opened or saved.
+    pub async fn check_external_change(&self) -> Result<Option<ExternalChange>, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::CheckExternalChange { response: tx })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Merge the events someone else wrote to the attached file into this session.
+    ///
+    /// Events whose editor lacks the capability under this session's grants, or
+    /// (in a signed file) without a valid signature by their author, are
+    /// refused and reported; in a signed file, `merger` signs the merged events
+    /// into the chain.
+    pub async fn merge_external_changes(&self, merger: &str) -> Result<ReceiveReport, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::MergeExternalChanges {
+                merger: merger.to_string(),
+                response: tx,
+            })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Append events received from another instance of the project.
+    ///
+    /// The file must be signed. Events without a valid signature by their
+    /// author, or whose editor lacks the capability under the local grants,
+    /// are refused and reported together with the rest of their command;
+    /// `receiver` signs the accepted events into the local chain.
+    pub async fn receive_events(
+        &self,
+        events: Vec<Event>,
+        receiver: &str,
+    ) -> Result<ReceiveReport, String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::ReceiveEvents {
+                events,
+                receiver: receiver.to_string(),
+                response: tx,
+            })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
+    /// Detach the attached file, removing its recovery journal.
+    ///
+    /// Called when a file is closed; changes not saved by then are gone.
+    pub async fn detach_file(&self) -> Result<(), String> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(EngineMessage::DetachFile { response: tx })
+            .map_err(|_| "Engine actor has shut down".to_string())?;
+
+        rx.await
+            .map_err(|_| "Engine actor did not respond".to_string())?
+    }
+
     /// Shutdown the engine actor.
     pub async fn shutdown(&self) {
         let _ = self.sender.send(EngineMessage::Shutdown);
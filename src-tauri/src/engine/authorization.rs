@@ -0,0 +1,442 @@
+//! Authorization decisions with the rule that made them.
+//!
+//! `StateProjector::is_authorized` answers yes/no; this module explains the
+//! answer. Rules are checked in order:
+//! 1. Owner: the block owner (including of a trashed block) may do anything;
+//!    for project-level checks, the project owner
+//! 2. Deny: a deny rule for the editor (or "*") wins over every grant and role
+//! 3. Grant: an unexpired grant whose capability pattern and scope match
+//! 4. Role: a role the editor holds whose capabilities and scope match
+//!
+//! Project-level commands (see [`is_project_command`]) don't act on an
+//! existing block. They are checked against the project pseudo-block
+//! [`PROJECT_SCOPE`], so only grants and roles on that scope apply to them;
+//! grants on "*" (every block) don't. The project owner is the first editor
+//! created, or for logs that start with another event, that event's editor.
+//! Only an empty project has no owner; it is bootstrapping and project-level
+//! commands are allowed until the first event claims it.
+
+use crate::engine::state::StateProjector;
+pub use crate::models::PROJECT_SCOPE;
+use crate::models::{Grant, RoleAssignment};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Capabilities that act on the project rather than an existing block.
+pub const PROJECT_CAPABILITIES: &[&str] = &[
+    "core.create",
+    "editor.create",
+    "editor.delete",
+    "core.define_role",
+    "core.delete_role",
+];
+
+/// Capabilities that manage grants, deny rules and role assignments.
+///
+/// Issued without a block ID, they act on the project.
+pub const GRANT_MANAGEMENT_CAPABILITIES: &[&str] = &[
+    "core.grant",
+    "core.revoke",
+    "core.deny",
+    "core.undeny",
+    "core.grant_role",
+    "core.revoke_role",
+];
+
+/// Target a grant, deny rule or revoke event applies to.
+///
+/// Before project-level authority had its own scope, project capabilities
+/// were granted on "*". Such grants can only have meant the project, so they
+/// are read as grants on [`PROJECT_SCOPE`].
+pub fn grant_target(capability: &str, target: &str) -> String {
+    if target == "*" && PROJECT_CAPABILITIES.contains(&capability) {
+        PROJECT_SCOPE.to_string()
+    } else {
+        target.to_string()
+    }
+}
+
+/// Check if a command is authorized against the project instead of a block.
+pub fn is_project_command(cap_id: &str, block_id: &str) -> bool {
+    PROJECT_CAPABILITIES.contains(&cap_id)
+        || (block_id.is_empty() && GRANT_MANAGEMENT_CAPABILITIES.contains(&cap_id))
+}
+
+/// The rule that decided an authorization check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuthorizationRule {
+    /// The editor owns the block
+    Owner,
+    /// The editor owns the project (project-level checks only)
+    ProjectOwner,
+    /// The project is empty and has no owner yet, so project-level commands are allowed
+    Bootstrap,
+    /// A deny rule matched (takes precedence over grants and roles)
+    Deny {
+        editor_id: String,
+        cap_id: String,
+        block_id: String,
+    },
+    /// A grant matched
+    Grant {
+        editor_id: String,
+        cap_id: String,
+        block_id: String,
+    },
+    /// A role held by the editor matched
+    Role {
+        editor_id: String,
+        role: String,
+        block_id: String,
+    },
+    /// No rule allowed the check
+    NoMatch,
+}
+
+/// Result of an authorization check for (editor, capability, block).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct AuthorizationDecision {
+    pub editor_id: String,
+    pub cap_id: String,
+    pub block_id: String,
+    /// Whether the editor may use the capability on the block
+    pub allowed: bool,
+    /// The rule that decided it
+    pub rule: AuthorizationRule,
+}
+
+/// Every rule that applies to an authorization check, not just the deciding one.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct AuthorizationTrace {
+    /// The outcome and the rule that decided it
+    pub decision: AuthorizationDecision,
+    /// Whether the check was made against the project (see [`is_project_command`])
+    pub project_level: bool,
+    /// Owner of the block, or of the project for project-level checks
+    /// (None if the block doesn't exist, even in the trash, or the project is bootstrapping)
+    pub block_owner: Option<String>,
+    /// Whether the editor owns the block (or the project)
+    pub owner_match: bool,
+    /// Deny rules that match, including wildcard editors and capability patterns
+    pub matching_denies: Vec<Grant>,
+    /// Grants that match, including expired ones (marked `expired`)
+    pub matching_grants: Vec<Grant>,
+    /// Role assignments that match
+    pub matching_roles: Vec<RoleAssignment>,
+}
+
+impl StateProjector {
+    /// Explain an authorization check at the current time.
+    pub fn explain_authorization(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+    ) -> AuthorizationDecision {
+        self.explain_authorization_at(editor_id, cap_id, block_id, Utc::now())
+    }
+
+    /// Explain an authorization check at a given time.
+    ///
+    /// Limited grants are checked against `now`; `subtree:` and `descendants:`
+    /// scopes are resolved against the current structure. A `block_id` of
+    /// [`PROJECT_SCOPE`] checks project-level authorization.
+    pub fn explain_authorization_at(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        now: DateTime<Utc>,
+    ) -> AuthorizationDecision {
+        let decide = |allowed: bool, rule: AuthorizationRule| AuthorizationDecision {
+            editor_id: editor_id.to_string(),
+            cap_id: cap_id.to_string(),
+            block_id: block_id.to_string(),
+            allowed,
+            rule,
+        };
+
+        // 1. Owner (of the project for project-level checks)
+        if block_id == PROJECT_SCOPE {
+            match self.project_owner.as_deref() {
+                None => return decide(true, AuthorizationRule::Bootstrap),
+                Some(owner) if owner == editor_id => {
+                    return decide(true, AuthorizationRule::ProjectOwner)
+                }
+                Some(_) => {}
+            }
+        } else if self.block_owner(block_id) == Some(editor_id) {
+            return decide(true, AuthorizationRule::Owner);
+        }
+
+        let containers = self.scope_containers(block_id);
+
+        // 2. Deny rules
+        if let Some((editor, cap, blk)) =
+            self.grants
+                .matching_deny(editor_id, cap_id, block_id, &containers)
+        {
+            return decide(
+                false,
+                AuthorizationRule::Deny {
+                    editor_id: editor,
+                    cap_id: cap,
+                    block_id: blk,
+                },
+            );
+        }
+
+        // 3. Grants
+        if let Some((editor, cap, blk)) =
+            self.grants
+                .active_grant(editor_id, cap_id, block_id, &containers, now)
+        {
+            return decide(
+                true,
+                AuthorizationRule::Grant {
+                    editor_id: editor,
+                    cap_id: cap,
+                    block_id: blk,
+                },
+            );
+        }
+
+        // 4. Roles
+        if let Some(assignment) =
+            self.roles
+                .matching_assignment(editor_id, cap_id, block_id, &containers)
+        {
+            return decide(
+                true,
+                AuthorizationRule::Role {
+                    editor_id: assignment.editor_id,
+                    role: assignment.role,
+                    block_id: assignment.block_id,
+                },
+            );
+        }
+
+        decide(false, AuthorizationRule::NoMatch)
+    }
+
+    /// Owner of a block, including a trashed one.
+    pub(crate) fn block_owner(&self, block_id: &str) -> Option<&str> {
+        self.get_block(block_id)
+            .or_else(|| self.trash.get(block_id).map(|t| &t.block))
+            .map(|b| b.owner.as_str())
+    }
+
+    /// Trace an authorization check at a given time.
+    ///
+    /// Unlike [`Self::explain_authorization_at`], which stops at the deciding
+    /// rule, this lists every deny rule, grant and role assignment that matches.
+    /// Project-level commands are traced against [`PROJECT_SCOPE`].
+    pub fn trace_authorization_at(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        now: DateTime<Utc>,
+    ) -> AuthorizationTrace {
+        let project_level = is_project_command(cap_id, block_id);
+        let block_id = if project_level {
+            PROJECT_SCOPE
+        } else {
+            block_id
+        };
+        let block_owner = if project_level {
+            self.project_owner.clone()
+        } else {
+            self.block_owner(block_id).map(str::to_string)
+        };
+        let owner_match = block_owner.as_deref() == Some(editor_id);
+
+        let containers = self.scope_containers(block_id);
+        let to_grant = |(editor, cap, blk): (String, String, String)| Grant::new(editor, cap, blk);
+
+        AuthorizationTrace {
+            decision: self.explain_authorization_at(editor_id, cap_id, block_id, now),
+            project_level,
+            block_owner,
+            owner_match,
+            matching_denies: self
+                .grants
+                .matching_denies(editor_id, cap_id, block_id, &containers)
+                .into_iter()
+                .map(to_grant)
+                .collect(),
+            matching_grants: self
+                .grants
+                .matching_grants(editor_id, cap_id, block_id, &containers)
+                .into_iter()
+                .map(|key| {
+                    let limits = self.grants.get_limits(&key.0, &key.1, &key.2).cloned();
+                    to_grant(key).with_limits(limits, now)
+                })
+                .collect(),
+            matching_roles: self.roles.matching_assignments(
+                editor_id,
+                cap_id,
+                block_id,
+                &containers,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Block, Role};
+
+    fn setup() -> StateProjector {
+        let mut state = StateProjector::new();
+        for id in ["notes", "prd"] {
+            let mut block = Block::new(id.to_string(), "markdown".into(), "alice".into());
+            block.block_id = id.to_string();
+            state.blocks.insert(id.to_string(), block);
+        }
+        state
+    }
+
+    #[test]
+    fn test_explain_reports_deciding_rule() {
+        let mut state = setup();
+        state
+            .grants
+            .add_grant("*".to_string(), "markdown.*".to_string(), "*".to_string());
+        state.grants.add_deny(
+            "bot".to_string(),
+            "markdown.write".to_string(),
+            "prd".to_string(),
+        );
+
+        let decision = state.explain_authorization("alice", "markdown.write", "prd");
+        assert_eq!(decision.rule, AuthorizationRule::Owner);
+
+        let decision = state.explain_authorization("bot", "markdown.write", "notes");
+        assert!(decision.allowed);
+        assert_eq!(
+            decision.rule,
+            AuthorizationRule::Grant {
+                editor_id: "*".to_string(),
+                cap_id: "markdown.*".to_string(),
+                block_id: "*".to_string(),
+            }
+        );
+
+        let decision = state.explain_authorization("bot", "markdown.write", "prd");
+        assert!(!decision.allowed);
+        assert!(matches!(decision.rule, AuthorizationRule::Deny { .. }));
+
+        let decision = state.explain_authorization("bot", "core.delete", "prd");
+        assert_eq!(decision.rule, AuthorizationRule::NoMatch);
+    }
+
+    #[test]
+    fn test_deny_overrides_roles() {
+        let mut state = setup();
+        state.roles.define_role(Role {
+            name: "writer".to_string(),
+            capabilities: vec!["markdown.write".to_string()],
+            scope: None,
+        });
+        state
+            .roles
+            .assign("bot".to_string(), "writer".to_string(), "*".to_string());
+        assert!(matches!(
+            state
+                .explain_authorization("bot", "markdown.write", "prd")
+                .rule,
+            AuthorizationRule::Role { .. }
+        ));
+
+        state
+            .grants
+            .add_deny("*".to_string(), "*".to_string(), "prd".to_string());
+        assert!(!state.is_authorized("bot", "markdown.write", "prd"));
+        assert!(state.is_authorized("bot", "markdown.write", "notes"));
+        // Owners keep full access to their own blocks
+        assert!(state.is_authorized("alice", "markdown.write", "prd"));
+    }
+
+    #[test]
+    fn test_trace_lists_every_matching_rule() {
+        let mut state = setup();
+        state
+            .grants
+            .add_grant("*".to_string(), "markdown.*".to_string(), "*".to_string());
+        state.grants.add_grant(
+            "bot".to_string(),
+            "markdown.write".to_string(),
+            "prd".to_string(),
+        );
+        state.grants.add_deny(
+            "bot".to_string(),
+            "markdown.write".to_string(),
+            "prd".to_string(),
+        );
+
+        let trace = state.trace_authorization_at("bot", "markdown.write", "prd", Utc::now());
+        assert!(!trace.project_level);
+        assert!(!trace.decision.allowed);
+        assert_eq!(trace.block_owner.as_deref(), Some("alice"));
+        assert!(!trace.owner_match);
+        assert_eq!(trace.matching_denies.len(), 1);
+        let editors: Vec<&str> = trace
+            .matching_grants
+            .iter()
+            .map(|g| g.editor_id.as_str())
+            .collect();
+        assert_eq!(editors, vec!["bot", "*"]);
+
+        let trace = state.trace_authorization_at("bot", "core.create", "", Utc::now());
+        assert!(trace.project_level);
+        assert!(trace.decision.allowed);
+        assert_eq!(trace.decision.rule, AuthorizationRule::Bootstrap);
+    }
+
+    #[test]
+    fn test_project_level_authorization() {
+        let mut state = setup();
+        state.project_owner = Some("alice".to_string());
+
+        let decision = state.explain_authorization("alice", "editor.delete", PROJECT_SCOPE);
+        assert_eq!(decision.rule, AuthorizationRule::ProjectOwner);
+        assert!(!state.is_authorized("bot", "editor.delete", PROJECT_SCOPE));
+
+        // Grants on a single block don't reach the project
+        state.grants.add_grant(
+            "bot".to_string(),
+            "editor.delete".to_string(),
+            "notes".to_string(),
+        );
+        assert!(!state.is_authorized("bot", "editor.delete", PROJECT_SCOPE));
+
+        // Nor do grants on every block
+        state
+            .grants
+            .add_grant("bot".to_string(), "core.*".to_string(), "*".to_string());
+        assert!(state.is_authorized("bot", "core.link", "notes"));
+        assert!(!state.is_authorized("bot", "core.create", PROJECT_SCOPE));
+
+        state.grants.add_grant(
+            "bot".to_string(),
+            "core.create".to_string(),
+            PROJECT_SCOPE.to_string(),
+        );
+        assert!(state.is_authorized("bot", "core.create", PROJECT_SCOPE));
+        assert!(!state.is_authorized("bot", "editor.create", PROJECT_SCOPE));
+
+        // Legacy grants of project capabilities on "*" meant the project
+        assert_eq!(grant_target("editor.create", "*"), PROJECT_SCOPE);
+        assert_eq!(grant_target("markdown.write", "*"), "*");
+        assert_eq!(grant_target("editor.create", "notes"), "notes");
+
+        assert!(is_project_command("core.grant", ""));
+        assert!(!is_project_command("core.grant", "notes"));
+        assert!(is_project_command("editor.create", "notes"));
+    }
+}
@@ -0,0 +1,329 @@
+//! Autosave and crash recovery for the .elf file an engine works on.
+//!
+//! The engine knows when events are committed, so it is the one that journals
+//! them and decides when the file is due for a save. It also remembers what the
+//! file on disk looked like when it was opened or last saved, so a save never
+//! overwrites changes someone else wrote to it in the meantime.
+
+use crate::elf::{copy_dir, AutosavePolicy, ElfArchive, RecoveryInfo, RecoveryJournal};
+use crate::engine::EventStore;
+use crate::models::Event;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::time::{Duration, Instant};
+
+/// Changes written to an open .elf file by someone else since it was opened or saved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct ExternalChange {
+    /// Number of events in the file on disk that this session doesn't have
+    pub new_events: usize,
+    /// Editors that produced them
+    pub editors: Vec<String>,
+}
+
+/// Events of the file on disk that this session doesn't have, with the file's stamp.
+pub(crate) struct ExternalEvents {
+    pub events: Vec<Event>,
+    /// Block directories only the file on disk has
+    pub block_dirs: Vec<PathBuf>,
+    pub disk_stamp: Option<DiskStamp>,
+    /// Keeps the extracted file alive until the block directories are copied
+    pub archive: ElfArchive,
+}
+
+/// What a .elf file on disk looked like: its modification time and size, and
+/// the hash of its contents.
+///
+/// The file is only hashed when its modification time or size changed, to tell
+/// a rewrite from a file that was just touched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiskStamp {
+    modified: SystemTime,
+    len: u64,
+    hash: String,
+}
+
+impl DiskStamp {
+    /// Stamp of the file at `path` (None if there is no file).
+    fn take(path: &Path) -> Result<Option<Self>, String> {
+        let Some((modified, len)) = Self::metadata(path)? else {
+            return Ok(None);
+        };
+        let hash = ElfArchive::content_hash(path)
+            .map_err(|e| format!("Failed to read file: {}", e))?
+            .unwrap_or_default();
+        Ok(Some(Self {
+            modified,
+            len,
+            hash,
+        }))
+    }
+
+    fn metadata(path: &Path) -> Result<Option<(SystemTime, u64)>, String> {
+        match std::fs::metadata(path) {
+            Ok(metadata) => {
+                let modified = metadata
+                    .modified()
+                    .map_err(|e| format!("Failed to read file: {}", e))?;
+                Ok(Some((modified, metadata.len())))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read file: {}", e)),
+        }
+    }
+
+    /// Whether the file at `path` has other contents than this stamp.
+    ///
+    /// Returns the file's new stamp if it was only touched, so the next check
+    /// doesn't hash it again.
+    fn check(&self, path: &Path) -> Result<(bool, Option<Self>), String> {
+        match Self::metadata(path)? {
+            None => Ok((false, None)),
+            Some((modified, len)) if modified == self.modified && len == self.len => {
+                Ok((false, None))
+            }
+            Some(_) => match Self::take(path)? {
+                Some(stamp) if stamp.hash == self.hash => Ok((false, Some(stamp))),
+                Some(_) => Ok((true, None)),
+                None => Ok((false, None)),
+            },
+        }
+    }
+}
+
+/// The .elf file an engine is attached to, with its journal and autosave state.
+pub(crate) struct AttachedFile {
+    archive: Arc<ElfArchive>,
+    path: PathBuf,
+    /// The file on disk when it was opened or last saved
+    disk_stamp: Option<DiskStamp>,
+    journal: RecoveryJournal,
+    policy: AutosavePolicy,
+    /// Events committed since the last save
+    unsaved_events: u32,
+    /// When the first of them was committed
+    dirty_since: Option<Instant>,
+    /// Journaled events of an earlier session that never made it into the file
+    pending_recovery: Vec<Event>,
+}
+
+impl AttachedFile {
+    /// Attach to a file, picking up what an earlier session left in its journal.
+    ///
+    /// `stored_event_ids` are the events already in the file; journaled events
+    /// among them were saved and are dropped from the journal.
+    pub fn attach(
+        archive: Arc<ElfArchive>,
+        path: PathBuf,
+        policy: AutosavePolicy,
+        stored_event_ids: &HashSet<String>,
+    ) -> Result<Self, String> {
+        let key = archive.encryption_key();
+        let pending_recovery: Vec<Event> = RecoveryJournal::read(&path, key.as_ref())?
+            .into_iter()
+            .filter(|event| !stored_event_ids.contains(&event.event_id))
+            .collect();
+        let mut journal = RecoveryJournal::open(&path, key.as_ref())?;
+        journal.reset(&pending_recovery)?;
+        let disk_stamp = DiskStamp::take(&path)?;
+
+        Ok(Self {
+            archive,
+            path,
+            disk_stamp,
+            journal,
+            policy,
+            unsaved_events: 0,
+            dirty_since: None,
+            pending_recovery,
+        })
+    }
+
+    /// Journal committed events and return whether an autosave is due.
+    ///
+    /// Editing decides against a pending recovery: the restored events would no
+    /// longer continue the event log, so they are dropped.
+    pub fn record(&mut self, events: &[Event]) -> bool {
+        if !self.pending_recovery.is_empty() {
+            log::warn!(
+                "Dropping {} unrestored events of {} after new changes",
+                self.pending_recovery.len(),
+                self.path.display()
+            );
+            if let Err(e) = self.discard_recovery() {
+                log::error!("{}", e);
+            }
+        }
+        if let Err(e) = self.journal.append(events) {
+            log::error!("{}", e);
+        }
+        self.mark_unsaved(events.len());
+        self.save_due()
+    }
+
+    fn mark_unsaved(&mut self, events: usize) {
+        self.unsaved_events = self.unsaved_events.saturating_add(events as u32);
+        self.dirty_since.get_or_insert_with(Instant::now);
+    }
+
+    /// When the next timed autosave is due (None if nothing is waiting for one).
+    pub fn deadline(&self) -> Option<Instant> {
+        let seconds = self.policy.every_seconds?;
+        self.dirty_since
+            .map(|since| since + Duration::from_secs(u64::from(seconds)))
+    }
+
+    /// Save the file and empty the journal.
+    ///
+    /// Refuses to save over a file someone else changed since it was opened or
+    /// last saved; their events have to be merged first. On failure the timer
+    /// restarts, so a timed autosave is retried later rather than right away.
+    pub async fn save(&mut self) -> Result<(), String> {
+        let result = match self.is_modified_externally() {
+            Ok(true) => Err(format!(
+                "File '{}' was modified by another program; merge its changes before saving",
+                self.path.display()
+            )),
+            Ok(false) => self
+                .archive
+                .save(&self.path)
+                .await
+                .map_err(|e| format!("Failed to save file: {}", e)),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            self.dirty_since = Some(Instant::now());
+            return Err(e);
+        }
+
+        self.disk_stamp = DiskStamp::take(&self.path)?;
+        self.unsaved_events = 0;
+        self.dirty_since = None;
+        // The journal follows the key the file was just saved with
+        self.journal.rekey(self.archive.encryption_key())?;
+        self.journal.reset(&self.pending_recovery)
+    }
+
+    /// Whether the file on disk differs from what was opened or last saved.
+    ///
+    /// A file that is gone is not a conflict: saving just writes it again.
+    /// Unless its modification time or size changed, the file isn't read.
+    pub fn is_modified_externally(&mut self) -> Result<bool, String> {
+        let Some(stamp) = &self.disk_stamp else {
+            return Ok(DiskStamp::metadata(&self.path)?.is_some());
+        };
+        let (modified, touched) = stamp.check(&self.path)?;
+        if touched.is_some() {
+            self.disk_stamp = touched;
+        }
+        Ok(modified)
+    }
+
+    /// Read the events of the file on disk that are not in `known_event_ids`.
+    ///
+    /// The file is opened with this file's key, so an encrypted file can be
+    /// read as long as its key didn't change.
+    pub async fn read_external(
+        &self,
+        known_event_ids: &HashSet<String>,
+    ) -> Result<ExternalEvents, String> {
+        let disk_stamp = DiskStamp::take(&self.path)?;
+        let key = self.archive.encryption_key();
+        let archive = ElfArchive::open_with_key(&self.path, key.as_ref())
+            .map_err(|e| format!("Failed to open file on disk: {}", e))?;
+        let pool = archive
+            .event_pool()
+            .await
+            .map_err(|e| format!("Failed to get event pool: {}", e))?;
+        let events: Vec<Event> = EventStore::get_all_events(&pool.pool)
+            .await
+            .map_err(|e| format!("Failed to get events: {}", e))?
+            .into_iter()
+            .filter(|event| !known_event_ids.contains(&event.event_id))
+            .collect();
+        pool.pool.close().await;
+
+        archive
+            .load_all()
+            .map_err(|e| format!("Failed to read file on disk: {}", e))?;
+        let block_dirs = std::fs::read_dir(archive.temp_path())
+            .map_err(|e| format!("Failed to read file on disk: {}", e))?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| PathBuf::from(entry.file_name()))
+            .filter(|name| !self.archive.contains(&name.to_string_lossy()))
+            .collect();
+
+        Ok(ExternalEvents {
+            events,
+            block_dirs,
+            disk_stamp,
+            archive,
+        })
+    }
+
+    /// Take in the changes read from the file on disk.
+    ///
+    /// Block directories only the file on disk has are copied over, and the
+    /// file on disk counts as the one this session last saved.
+    pub fn accept_external(&mut self, external: &ExternalEvents) -> Result<(), String> {
+        for name in &external.block_dirs {
+            copy_dir(
+                &external.archive.temp_path().join(name),
+                &self.archive.extracted_path().join(name),
+            )
+            .map_err(|e| format!("Failed to copy block directory: {}", e))?;
+        }
+        self.disk_stamp = external.disk_stamp.clone();
+        Ok(())
+    }
+
+    /// Summary of the events that can be restored (None if there are none).
+    pub fn recovery_info(&self) -> Option<RecoveryInfo> {
+        RecoveryInfo::from_events(&self.pending_recovery)
+    }
+
+    /// Hand out the pending events for restoring; they stay journaled as unsaved.
+    pub fn take_recovery(&mut self) -> Vec<Event> {
+        let events = std::mem::take(&mut self.pending_recovery);
+        self.mark_unsaved(events.len());
+        events
+    }
+
+    /// Drop the pending events for good and return how many there were.
+    pub fn discard_recovery(&mut self) -> Result<usize, String> {
+        let events = std::mem::take(&mut self.pending_recovery);
+        let event_ids: HashSet<String> = events.into_iter().map(|e| e.event_id).collect();
+        self.journal.discard(&event_ids)?;
+        Ok(event_ids.len())
+    }
+
+    /// Follow the file to a new path.
+    pub fn set_path(&mut self, path: &Path) -> Result<(), String> {
+        self.journal.rename(path)?;
+        self.path = path.to_path_buf();
+        Ok(())
+    }
+
+    pub fn set_policy(&mut self, policy: AutosavePolicy) {
+        self.policy = policy;
+    }
+
+    /// Whether the event-count limit has been reached.
+    pub fn save_due(&self) -> bool {
+        self.policy
+            .every_events
+            .is_some_and(|limit| self.unsaved_events >= limit)
+    }
+
+    /// Detach from the file, throwing away its journal (unsaved changes are lost).
+    pub fn close(self) -> Result<(), String> {
+        let path = self.path;
+        drop(self.journal);
+        RecoveryJournal::remove(&path)
+    }
+}
@@ -0,0 +1,232 @@
+//! Delegation rules for `core.grant` and cascading `core.revoke`.
+//!
+//! An editor may only grant a capability they hold on the grant's target:
+//! 1. As owner of the target block, or of the root of a `subtree:` /
+//!    `descendants:` target; for "*" and project targets, as project owner
+//! 2. Through an active grant marked `grantable` whose capability pattern and
+//!    scope include the new grant's (and no deny rule forbids it)
+//!
+//! A grant made through a grantable grant records it as `delegated_from`, so
+//! revoking with `cascade` also revokes every grant derived from it.
+
+use crate::capabilities::core::create_event;
+use crate::capabilities::grants::GrantKey;
+use crate::engine::state::StateProjector;
+use crate::models::{capability_matches, Event, GrantScope};
+use chrono::{DateTime, Utc};
+
+impl StateProjector {
+    /// Check that `granter` may grant `capability` on `target` at `now`.
+    ///
+    /// Returns the grantable grant the new grant is delegated from, or None
+    /// when the granter owns the target.
+    pub fn check_delegation(
+        &self,
+        granter: &str,
+        capability: &str,
+        target: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<GrantKey>, String> {
+        let scope = GrantScope::parse(target);
+
+        // 1. Owners hold every capability on what they own (an empty project is
+        //    still bootstrapping, as in `explain_authorization_at`)
+        let owns_target = match scope.root() {
+            Some(root) => self.block_owner(root) == Some(granter),
+            None => self
+                .project_owner
+                .as_deref()
+                .is_none_or(|owner| owner == granter),
+        };
+        if owns_target {
+            return Ok(None);
+        }
+
+        let containers = scope
+            .root()
+            .map(|root| self.scope_containers(root))
+            .unwrap_or_default();
+
+        // 2. A deny rule on the target block also forbids handing the capability on
+        if let GrantScope::Block(block_id) = &scope {
+            if self
+                .grants
+                .matching_deny(granter, capability, block_id, &containers)
+                .is_some()
+            {
+                return Err(format!(
+                    "Delegation failed: {} is denied {} on {}",
+                    granter, capability, target
+                ));
+            }
+        }
+
+        // 3. A grantable grant that includes the new grant
+        self.grants
+            .grantable_grants(granter)
+            .into_iter()
+            .find(|(editor, cap, blk)| {
+                capability_matches(cap, capability)
+                    && GrantScope::parse(blk).includes(&scope, &containers)
+                    && self
+                        .grants
+                        .get_limits(editor, cap, blk)
+                        .is_none_or(|limits| limits.is_active_at(now))
+            })
+            .map(Some)
+            .ok_or_else(|| {
+                format!(
+                    "Delegation failed: {} does not hold a grantable {} on {}",
+                    granter, capability, target
+                )
+            })
+    }
+
+    /// Build `core.revoke` events for every grant derived from `key`.
+    pub fn revoke_followup_events(&self, key: &GrantKey, editor_id: &str) -> Vec<Event> {
+        let (source_editor, source_cap, source_block) = key;
+        self.grants
+            .derived_grants(key)
+            .into_iter()
+            .map(|(editor, capability, block)| {
+                create_event(
+                    block.clone(),
+                    "core.revoke",
+                    serde_json::json!({
+                        "editor": editor,
+                        "capability": capability,
+                        "block": block,
+                        "cascade_from": {
+                            "editor": source_editor,
+                            "capability": source_cap,
+                            "block": source_block,
+                        },
+                    }),
+                    editor_id,
+                    1, // Placeholder - updated by engine actor
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Block, Delegation};
+
+    fn setup() -> StateProjector {
+        let mut state = StateProjector::new();
+        let mut block = Block::new("prd".into(), "markdown".into(), "alice".into());
+        block.block_id = "prd".to_string();
+        state.blocks.insert("prd".to_string(), block);
+        state.project_owner = Some("alice".to_string());
+        state
+    }
+
+    fn grant(state: &mut StateProjector, key: (&str, &str, &str), delegation: Delegation) {
+        let (editor, cap, blk) = key;
+        state
+            .grants
+            .add_grant(editor.to_string(), cap.to_string(), blk.to_string());
+        state.grants.set_delegation(
+            (editor.to_string(), cap.to_string(), blk.to_string()),
+            delegation,
+        );
+    }
+
+    #[test]
+    fn test_only_held_capabilities_can_be_delegated() {
+        let mut state = setup();
+        let now = Utc::now();
+
+        assert_eq!(
+            state.check_delegation("alice", "markdown.write", "prd", now),
+            Ok(None)
+        );
+        assert!(state
+            .check_delegation("bob", "markdown.write", "prd", now)
+            .is_err());
+
+        // A plain grant can't be handed on
+        grant(
+            &mut state,
+            ("bob", "markdown.*", "prd"),
+            Delegation {
+                granted_by: "alice".to_string(),
+                grantable: false,
+                delegated_from: None,
+            },
+        );
+        assert!(state
+            .check_delegation("bob", "markdown.write", "prd", now)
+            .is_err());
+
+        // A grantable one can, but only within its capability pattern and scope
+        grant(
+            &mut state,
+            ("bob", "markdown.*", "prd"),
+            Delegation {
+                granted_by: "alice".to_string(),
+                grantable: true,
+                delegated_from: None,
+            },
+        );
+        let source = (
+            "bob".to_string(),
+            "markdown.*".to_string(),
+            "prd".to_string(),
+        );
+        assert_eq!(
+            state.check_delegation("bob", "markdown.write", "prd", now),
+            Ok(Some(source))
+        );
+        assert!(state
+            .check_delegation("bob", "core.delete", "prd", now)
+            .is_err());
+        assert!(state
+            .check_delegation("bob", "markdown.write", "*", now)
+            .is_err());
+    }
+
+    #[test]
+    fn test_revoke_cascades_to_derived_grants() {
+        let mut state = setup();
+        let delegated = |granted_by: &str, from: Option<(&str, &str, &str)>| Delegation {
+            granted_by: granted_by.to_string(),
+            grantable: true,
+            delegated_from: from.map(|(e, c, b)| (e.to_string(), c.to_string(), b.to_string())),
+        };
+        grant(
+            &mut state,
+            ("bob", "markdown.write", "prd"),
+            delegated("alice", None),
+        );
+        grant(
+            &mut state,
+            ("carol", "markdown.write", "prd"),
+            delegated("bob", Some(("bob", "markdown.write", "prd"))),
+        );
+        grant(
+            &mut state,
+            ("dave", "markdown.write", "prd"),
+            delegated("carol", Some(("carol", "markdown.write", "prd"))),
+        );
+
+        let key = (
+            "bob".to_string(),
+            "markdown.write".to_string(),
+            "prd".to_string(),
+        );
+        let events = state.revoke_followup_events(&key, "alice");
+        let revoked: Vec<&str> = events
+            .iter()
+            .map(|e| e.value["editor"].as_str().unwrap())
+            .collect();
+        assert_eq!(revoked, vec!["carol", "dave"]);
+        assert!(events
+            .iter()
+            .all(|e| e.attribute == "alice/core.revoke"
+                && e.value["cascade_from"]["editor"] == "bob"));
+    }
+}
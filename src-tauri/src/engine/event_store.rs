@@ -1,8 +1,12 @@
+use crate::elf::blobs::{self, BlobStore};
+use crate::elf::LazyEntries;
 use crate::models::Event;
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 use sqlx::Row;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 
 /// Event pool with database file path
 ///
@@ -15,6 +19,9 @@ pub struct EventPoolWithPath {
 
     /// Path to the events.db file (e.g., /tmp/xyz789/events.db)
     pub db_path: PathBuf,
+
+    /// Extracts block directories of a lazily opened archive on first use
+    pub block_loader: Option<Arc<LazyEntries>>,
 }
 
 /// Event store for persisting events to SQLite database.
@@ -32,7 +39,8 @@ impl EventStore {
     ///
     /// Returns an EventPoolWithPath containing both the pool and db_path.
     pub async fn create(path: &str) -> Result<EventPoolWithPath, sqlx::Error> {
-        let connection_string = if path == ":memory:" {
+        let in_memory = path == ":memory:";
+        let connection_string = if in_memory {
             "sqlite::memory:".to_string()
         } else {
             // Ensure parent directory exists
@@ -44,12 +52,16 @@ impl EventStore {
             format!("sqlite://{}", path)
         };
 
+        let mut options = sqlx::sqlite::SqliteConnectOptions::from_str(&connection_string)?
+            .create_if_missing(true);
+        if !in_memory {
+            // Write-ahead logging; `checkpoint` flushes the log before the file is archived
+            options = options.journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
+        }
+
         let pool = SqlitePoolOptions::new()
             .max_connections(5)
-            .connect_with(
-                sqlx::sqlite::SqliteConnectOptions::from_str(&connection_string)?
-                    .create_if_missing(true),
-            )
+            .connect_with(options)
             .await?;
 
         // Initialize schema
@@ -59,6 +71,7 @@ impl EventStore {
         Ok(EventPoolWithPath {
             pool,
             db_path: PathBuf::from(path),
+            block_loader: None,
         })
     }
 
@@ -72,12 +85,25 @@ impl EventStore {
                 attribute TEXT NOT NULL,
                 value TEXT NOT NULL,
                 timestamp TEXT NOT NULL,
-                created_at TEXT NOT NULL
+                created_at TEXT NOT NULL,
+                signature TEXT
             )",
         )
         .execute(pool)
         .await?;
 
+        // Databases created before event signing lack the signature column
+        let has_signature: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('events') WHERE name = 'signature'",
+        )
+        .fetch_one(pool)
+        .await?;
+        if !has_signature {
+            sqlx::query("ALTER TABLE events ADD COLUMN signature TEXT")
+                .execute(pool)
+                .await?;
+        }
+
         // Create index on entity for faster lookups
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_entity ON events(entity)")
             .execute(pool)
@@ -91,17 +117,54 @@ impl EventStore {
         Ok(())
     }
 
+    /// Flush the write-ahead log into the database file and truncate it.
+    pub async fn checkpoint(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The blob store next to the database file (None for in-memory databases).
+    ///
+    /// Large text fields of event contents are kept there instead of in the
+    /// `value` column, see [`crate::elf::blobs`].
+    async fn blob_store(pool: &SqlitePool) -> Result<Option<BlobStore>, sqlx::Error> {
+        let file: String =
+            sqlx::query_scalar("SELECT file FROM pragma_database_list WHERE name = 'main'")
+                .fetch_one(pool)
+                .await?;
+        Ok(Path::new(&file)
+            .parent()
+            .filter(|_| !file.is_empty())
+            .map(BlobStore::new))
+    }
+
     /// Append events to the database.
     pub async fn append_events(pool: &SqlitePool, events: &[Event]) -> Result<(), sqlx::Error> {
+        let blob_store = Self::blob_store(pool).await?;
         for event in events {
             let timestamp_json = serde_json::to_string(&event.timestamp)
                 .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
-            let value_json = serde_json::to_string(&event.value)
+            let value_json = match &blob_store {
+                Some(store) => {
+                    let mut value = event.value.clone();
+                    store.externalize(&mut value).map_err(sqlx::Error::Io)?;
+                    serde_json::to_string(&value)
+                }
+                None => serde_json::to_string(&event.value),
+            }
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+            let signature_json = event
+                .signature
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()
                 .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
 
             sqlx::query(
-                "INSERT INTO events (event_id, entity, attribute, value, timestamp, created_at)
-                 VALUES ($1, $2, $3, $4, $5, $6)",
+                "INSERT INTO events (event_id, entity, attribute, value, timestamp, created_at, signature)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
             )
             .bind(&event.event_id)
             .bind(&event.entity)
@@ -109,6 +172,7 @@ impl EventStore {
             .bind(&value_json)
             .bind(&timestamp_json)
             .bind(&event.created_at)
+            .bind(&signature_json)
             .execute(pool)
             .await?;
         }
@@ -118,16 +182,17 @@ impl EventStore {
     /// Get all events from the database, ordered by insertion order (rowid).
     pub async fn get_all_events(pool: &SqlitePool) -> Result<Vec<Event>, sqlx::Error> {
         let rows = sqlx::query(
-            "SELECT event_id, entity, attribute, value, timestamp, created_at
+            "SELECT event_id, entity, attribute, value, timestamp, created_at, signature
              FROM events
              ORDER BY rowid",
         )
         .fetch_all(pool)
         .await?;
 
+        let blob_store = Self::blob_store(pool).await?;
         let mut events = Vec::new();
         for row in rows {
-            let event = Self::row_to_event(row)?;
+            let event = Self::row_to_event(row, blob_store.as_ref())?;
             events.push(event);
         }
 
@@ -140,7 +205,7 @@ impl EventStore {
         entity: &str,
     ) -> Result<Vec<Event>, sqlx::Error> {
         let rows = sqlx::query(
-            "SELECT event_id, entity, attribute, value, timestamp, created_at
+            "SELECT event_id, entity, attribute, value, timestamp, created_at, signature
              FROM events
              WHERE entity = $1
              ORDER BY rowid",
@@ -149,28 +214,63 @@ impl EventStore {
         .fetch_all(pool)
         .await?;
 
+        let blob_store = Self::blob_store(pool).await?;
         let mut events = Vec::new();
         for row in rows {
-            let event = Self::row_to_event(row)?;
+            let event = Self::row_to_event(row, blob_store.as_ref())?;
             events.push(event);
         }
 
         Ok(events)
     }
 
-    /// Convert a database row to an Event.
-    fn row_to_event(row: sqlx::sqlite::SqliteRow) -> Result<Event, sqlx::Error> {
+    /// Blobs referenced by stored events: externalized fields and asset contents.
+    pub async fn referenced_blobs(pool: &SqlitePool) -> Result<HashSet<String>, sqlx::Error> {
+        let values: Vec<String> = sqlx::query_scalar("SELECT value FROM events")
+            .fetch_all(pool)
+            .await?;
+
+        let mut referenced = HashSet::new();
+        for value_json in values {
+            let value: serde_json::Value =
+                serde_json::from_str(&value_json).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+            blobs::collect_refs(&value, &mut referenced);
+        }
+        Ok(referenced)
+    }
+
+    /// Convert a database row to an Event, putting externalized fields back inline.
+    fn row_to_event(
+        row: sqlx::sqlite::SqliteRow,
+        blob_store: Option<&BlobStore>,
+    ) -> Result<Event, sqlx::Error> {
         let event_id: String = row.try_get(0)?;
         let entity: String = row.try_get(1)?;
         let attribute: String = row.try_get(2)?;
         let value_json: String = row.try_get(3)?;
         let timestamp_json: String = row.try_get(4)?;
         let created_at: String = row.try_get(5)?;
+        let signature_json: Option<String> = row.try_get(6)?;
 
-        let value: serde_json::Value =
+        let mut value: serde_json::Value =
             serde_json::from_str(&value_json).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+        if let Some(store) = blob_store {
+            // A missing blob only affects the block the event belongs to
+            for problem in store.resolve(&mut value) {
+                log::warn!(
+                    "Event {} on {} keeps an unresolved blob reference: {}",
+                    event_id,
+                    entity,
+                    problem
+                );
+            }
+        }
         let timestamp =
             serde_json::from_str(&timestamp_json).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+        let signature = signature_json
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
 
         Ok(Event {
             event_id,
@@ -179,6 +279,7 @@ impl EventStore {
             value,
             timestamp,
             created_at,
+            signature,
         })
     }
 }
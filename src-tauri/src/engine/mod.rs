@@ -1,9 +1,30 @@
 mod actor;
+mod authorization;
+mod autosave;
+mod delegation;
 mod event_store;
 mod manager;
+mod references;
+mod remote;
+mod signing;
 mod state;
+mod trash;
 
 pub use actor::{spawn_engine, EngineHandle, EngineMessage};
+pub use authorization::{
+    grant_target, is_project_command, AuthorizationDecision, AuthorizationRule, AuthorizationTrace,
+    GRANT_MANAGEMENT_CAPABILITIES, PROJECT_CAPABILITIES, PROJECT_SCOPE,
+};
+pub use autosave::ExternalChange;
 pub use event_store::{EventPoolWithPath, EventStore};
 pub use manager::EngineManager;
+pub use references::{DanglingKind, DanglingReference, DeleteReport, DirectoryEntryRef};
+pub use remote::{
+    check_remote_command, check_remote_event, split_commands, ReceiveReport, RejectedEvent,
+};
+pub use signing::{
+    event_hash, verify_author, verify_events, EventSigner, KeyStore, VerifyIssue, VerifyIssueKind,
+    VerifyReport,
+};
 pub use state::StateProjector;
+pub use trash::{TrashedBlock, TrashedEntry};
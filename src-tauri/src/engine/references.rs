@@ -0,0 +1,535 @@
+//! Reference tracking between blocks.
+//!
+//! Blocks reference each other in two ways:
+//! - `implement` relations (`Block.children`, indexed in reverse by `StateProjector.parents`)
+//! - Directory entries (`contents.entries[path].id` of directory blocks)
+//!
+//! This module plans `core.delete` against those references (refuse / cascade / detach)
+//! and validates an existing projection for references to blocks that no longer exist.
+
+use crate::capabilities::core::create_event;
+use crate::engine::state::StateProjector;
+use crate::models::{DeleteBlockPayload, DeleteMode, Event, RELATION_IMPLEMENT};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A directory entry that points at a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct DirectoryEntryRef {
+    /// The directory block holding the entry
+    pub directory_id: String,
+    /// Virtual path of the entry inside the directory
+    pub path: String,
+    /// The block the entry points at
+    pub block_id: String,
+}
+
+/// Every block affected by a `core.delete` command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct DeleteReport {
+    /// The mode the delete was planned with
+    pub mode: DeleteMode,
+    /// Deleted blocks; the first entry is the command's target block
+    pub deleted: Vec<String>,
+    /// Surviving blocks whose `implement` children pointed at a deleted block
+    pub unlinked_parents: Vec<String>,
+    /// Directory entries removed because they pointed at a deleted block
+    pub directory_entries: Vec<DirectoryEntryRef>,
+}
+
+/// Kind of a dangling reference found by `StateProjector::find_dangling_references`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum DanglingKind {
+    /// An `implement` relation targets a block that does not exist
+    RelationTarget,
+    /// A directory file entry points at a block that does not exist
+    DirectoryEntry,
+}
+
+/// A reference from an existing block to a block that does not exist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct DanglingReference {
+    pub kind: DanglingKind,
+    /// The block holding the reference
+    pub source_id: String,
+    /// The missing block
+    pub target_id: String,
+    /// Entry path for directory references
+    pub path: Option<String>,
+}
+
+impl StateProjector {
+    /// Check if linking source → target would create a cycle in the DAG.
+    ///
+    /// From target, DFS along `implement` children. If we reach source,
+    /// a cycle would be formed: source → target → ... → source.
+    /// Also rejects self-links (source == target).
+    pub fn check_link_cycle(&self, source_id: &str, target_id: &str) -> Result<(), String> {
+        // Self-link is always a cycle
+        if source_id == target_id {
+            return Err(format!(
+                "Cycle detected: linking {} → {} would create a self-cycle",
+                source_id, target_id
+            ));
+        }
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![target_id.to_string()];
+
+        while let Some(current) = stack.pop() {
+            if current == source_id {
+                return Err(format!(
+                    "Cycle detected: linking {} → {} would create a cycle",
+                    source_id, target_id
+                ));
+            }
+            if visited.insert(current.clone()) {
+                if let Some(block) = self.get_block(&current) {
+                    if let Some(targets) = block.children.get(RELATION_IMPLEMENT) {
+                        stack.extend(targets.iter().cloned());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get all directory file entries that point at one of the given blocks.
+    ///
+    /// Entries held by directories inside `targets` are skipped, since those
+    /// directories are going away themselves.
+    pub fn directory_references(&self, targets: &HashSet<String>) -> Vec<DirectoryEntryRef> {
+        let mut refs = Vec::new();
+        for block in self.blocks.values() {
+            if block.block_type != "directory" || targets.contains(&block.block_id) {
+                continue;
+            }
+            let Some(entries) = block.contents.get("entries").and_then(|v| v.as_object()) else {
+                continue;
+            };
+            for (path, entry) in entries {
+                if entry.get("type").and_then(|v| v.as_str()) != Some("file") {
+                    continue;
+                }
+                if let Some(id) = entry.get("id").and_then(|v| v.as_str()) {
+                    if targets.contains(id) {
+                        refs.push(DirectoryEntryRef {
+                            directory_id: block.block_id.clone(),
+                            path: path.clone(),
+                            block_id: id.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        refs.sort_by(|a, b| (&a.directory_id, &a.path).cmp(&(&b.directory_id, &b.path)));
+        refs
+    }
+
+    /// Plan a `core.delete` of `block_id` without modifying state.
+    ///
+    /// Returns the full set of affected blocks, or an error if the block does not
+    /// exist or `refuse` mode finds references to it.
+    pub fn plan_delete(
+        &self,
+        block_id: &str,
+        payload: &DeleteBlockPayload,
+    ) -> Result<DeleteReport, String> {
+        if !self.blocks.contains_key(block_id) {
+            return Err(format!("Block not found: {}", block_id));
+        }
+
+        let deleted = match payload.mode {
+            DeleteMode::Refuse => {
+                let children = self.get_children(block_id);
+                let target: HashSet<String> = [block_id.to_string()].into();
+                let entries = self.directory_references(&target);
+                if !children.is_empty() || !entries.is_empty() {
+                    return Err(format!(
+                        "Cannot delete block {}: it has {} implement children and is referenced by {} directory entries",
+                        block_id,
+                        children.len(),
+                        entries.len()
+                    ));
+                }
+                vec![block_id.to_string()]
+            }
+            DeleteMode::Detach => vec![block_id.to_string()],
+            DeleteMode::Cascade => self.cascade_set(block_id, payload.depth),
+        };
+
+        let deleted_set: HashSet<String> = deleted.iter().cloned().collect();
+
+        let mut unlinked_parents = Vec::new();
+        for id in &deleted {
+            for parent in self.get_parents(id) {
+                if !deleted_set.contains(&parent) && !unlinked_parents.contains(&parent) {
+                    unlinked_parents.push(parent);
+                }
+            }
+        }
+
+        Ok(DeleteReport {
+            mode: payload.mode,
+            deleted,
+            unlinked_parents,
+            directory_entries: self.directory_references(&deleted_set),
+        })
+    }
+
+    /// Collect `block_id` and its `implement` descendants up to `depth` levels.
+    ///
+    /// A descendant is only included if every one of its parents is included too,
+    /// so blocks shared with another surviving parent are detached, not deleted.
+    fn cascade_set(&self, block_id: &str, depth: Option<u32>) -> Vec<String> {
+        // Breadth-first walk, remembering discovery order
+        let mut order = vec![block_id.to_string()];
+        let mut seen: HashSet<String> = [block_id.to_string()].into();
+        let mut queue = VecDeque::from([(block_id.to_string(), 0u32)]);
+        while let Some((current, level)) = queue.pop_front() {
+            if depth.is_some_and(|max| level >= max) {
+                continue;
+            }
+            for child in self.get_children(&current) {
+                if self.blocks.contains_key(&child) && seen.insert(child.clone()) {
+                    order.push(child.clone());
+                    queue.push_back((child, level + 1));
+                }
+            }
+        }
+
+        // Drop descendants that still have a parent outside the set, until stable
+        loop {
+            let before = seen.len();
+            let shared: Vec<String> = order
+                .iter()
+                .skip(1)
+                .filter(|id| seen.contains(*id))
+                .filter(|id| self.get_parents(id).iter().any(|p| !seen.contains(p)))
+                .cloned()
+                .collect();
+            for id in shared {
+                seen.remove(&id);
+            }
+            if seen.len() == before {
+                break;
+            }
+        }
+
+        order.retain(|id| seen.contains(id));
+        order
+    }
+
+    /// Build the follow-up events that carry out a planned delete.
+    ///
+    /// The target block's own `core.delete` event is produced by the capability
+    /// handler; this adds `core.delete` for cascaded blocks, `core.unlink` for
+    /// surviving parents and `directory.write` for directories whose entries
+    /// pointed at deleted blocks.
+    pub fn delete_followup_events(&self, report: &DeleteReport, editor_id: &str) -> Vec<Event> {
+        let deleted_set: HashSet<&String> = report.deleted.iter().collect();
+        let mut events = Vec::new();
+
+        let Some(target_id) = report.deleted.first() else {
+            return events;
+        };
+
+        for id in report.deleted.iter().skip(1) {
+            events.push(create_event(
+                id.clone(),
+                "core.delete",
+                serde_json::json!({ "mode": report.mode, "cascade_from": target_id }),
+                editor_id,
+                1, // Placeholder - updated by engine actor
+            ));
+        }
+
+        for parent_id in &report.unlinked_parents {
+            if let Some(parent) = self.get_block(parent_id) {
+                let mut new_children = parent.children.clone();
+                if let Some(targets) = new_children.get_mut(RELATION_IMPLEMENT) {
+                    targets.retain(|id| !deleted_set.contains(id));
+                    if targets.is_empty() {
+                        new_children.remove(RELATION_IMPLEMENT);
+                    }
+                }
+                events.push(create_event(
+                    parent_id.clone(),
+                    "core.unlink",
+                    serde_json::json!({ "children": new_children }),
+                    editor_id,
+                    1,
+                ));
+            }
+        }
+
+        let mut removed_paths: HashMap<&String, Vec<&String>> = HashMap::new();
+        for entry in &report.directory_entries {
+            removed_paths
+                .entry(&entry.directory_id)
+                .or_default()
+                .push(&entry.path);
+        }
+        let mut directory_ids: Vec<&&String> = removed_paths.keys().collect();
+        directory_ids.sort();
+        for directory_id in directory_ids {
+            let Some(entries) = self
+                .get_block(directory_id)
+                .and_then(|b| b.contents.get("entries"))
+                .and_then(|v| v.as_object())
+            else {
+                continue;
+            };
+            let mut new_entries = entries.clone();
+            for path in &removed_paths[*directory_id] {
+                new_entries.remove(*path);
+            }
+            events.push(create_event(
+                (*directory_id).clone(),
+                "directory.write",
+                serde_json::json!({ "contents": { "entries": new_entries } }),
+                editor_id,
+                1,
+            ));
+        }
+
+        events
+    }
+
+    /// Find references from existing blocks to blocks that no longer exist.
+    ///
+    /// Projects written before delete cleanup existed can contain `implement`
+    /// links and directory entries pointing at deleted blocks.
+    pub fn find_dangling_references(&self) -> Vec<DanglingReference> {
+        let mut dangling = Vec::new();
+
+        for block in self.blocks.values() {
+            if let Some(targets) = block.children.get(RELATION_IMPLEMENT) {
+                for target in targets {
+                    if !self.blocks.contains_key(target) {
+                        dangling.push(DanglingReference {
+                            kind: DanglingKind::RelationTarget,
+                            source_id: block.block_id.clone(),
+                            target_id: target.clone(),
+                            path: None,
+                        });
+                    }
+                }
+            }
+
+            if block.block_type != "directory" {
+                continue;
+            }
+            let Some(entries) = block.contents.get("entries").and_then(|v| v.as_object()) else {
+                continue;
+            };
+            for (path, entry) in entries {
+                if entry.get("type").and_then(|v| v.as_str()) != Some("file") {
+                    continue;
+                }
+                if let Some(id) = entry.get("id").and_then(|v| v.as_str()) {
+                    if !self.blocks.contains_key(id) {
+                        dangling.push(DanglingReference {
+                            kind: DanglingKind::DirectoryEntry,
+                            source_id: block.block_id.clone(),
+                            target_id: id.to_string(),
+                            path: Some(path.clone()),
+                        });
+                    }
+                }
+            }
+        }
+
+        dangling.sort_by(|a, b| {
+            (&a.source_id, &a.target_id, &a.path).cmp(&(&b.source_id, &b.target_id, &b.path))
+        });
+        dangling
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap as StdHashMap;
+
+    fn create_block_event(entity: &str, block_type: &str) -> Event {
+        let mut ts = StdHashMap::new();
+        ts.insert("alice".to_string(), 1);
+        Event::new(
+            entity.to_string(),
+            "alice/core.create".to_string(),
+            serde_json::json!({
+                "name": entity,
+                "type": block_type,
+                "owner": "alice",
+                "contents": {},
+                "children": {}
+            }),
+            ts,
+        )
+    }
+
+    fn link(state: &mut StateProjector, source: &str, targets: &[&str]) {
+        let mut ts = StdHashMap::new();
+        ts.insert("alice".to_string(), 1);
+        state.apply_event(&Event::new(
+            source.to_string(),
+            "alice/core.link".to_string(),
+            serde_json::json!({ "children": { RELATION_IMPLEMENT: targets } }),
+            ts,
+        ));
+    }
+
+    fn write_entries(state: &mut StateProjector, dir: &str, entries: serde_json::Value) {
+        let mut ts = StdHashMap::new();
+        ts.insert("alice".to_string(), 1);
+        state.apply_event(&Event::new(
+            dir.to_string(),
+            "alice/directory.write".to_string(),
+            serde_json::json!({ "contents": { "entries": entries } }),
+            ts,
+        ));
+    }
+
+    fn payload(mode: DeleteMode, depth: Option<u32>) -> DeleteBlockPayload {
+        DeleteBlockPayload { mode, depth }
+    }
+
+    /// a → b → c, plus d → c, and a directory entry pointing at b
+    fn setup() -> StateProjector {
+        let mut state = StateProjector::new();
+        for id in ["a", "b", "c", "d"] {
+            state.apply_event(&create_block_event(id, "markdown"));
+        }
+        state.apply_event(&create_block_event("dir", "directory"));
+        link(&mut state, "a", &["b"]);
+        link(&mut state, "b", &["c"]);
+        link(&mut state, "d", &["c"]);
+        write_entries(
+            &mut state,
+            "dir",
+            serde_json::json!({
+                "docs": { "id": "dir-1", "type": "directory" },
+                "docs/b.md": { "id": "b", "type": "file" }
+            }),
+        );
+        state
+    }
+
+    #[test]
+    fn test_refuse_rejects_referenced_block() {
+        let state = setup();
+        let result = state.plan_delete("b", &payload(DeleteMode::Refuse, None));
+        assert!(result.unwrap_err().contains("Cannot delete block b"));
+    }
+
+    #[test]
+    fn test_refuse_allows_leaf_without_entries() {
+        let state = setup();
+        let report = state
+            .plan_delete("c", &payload(DeleteMode::Refuse, None))
+            .unwrap();
+        assert_eq!(report.deleted, vec!["c".to_string()]);
+        let mut parents = report.unlinked_parents.clone();
+        parents.sort();
+        assert_eq!(parents, vec!["b".to_string(), "d".to_string()]);
+    }
+
+    #[test]
+    fn test_detach_reports_parents_and_entries() {
+        let state = setup();
+        let report = state
+            .plan_delete("b", &payload(DeleteMode::Detach, None))
+            .unwrap();
+        assert_eq!(report.deleted, vec!["b".to_string()]);
+        assert_eq!(report.unlinked_parents, vec!["a".to_string()]);
+        assert_eq!(report.directory_entries.len(), 1);
+        assert_eq!(report.directory_entries[0].path, "docs/b.md");
+    }
+
+    #[test]
+    fn test_cascade_keeps_shared_descendants() {
+        let state = setup();
+        let report = state
+            .plan_delete("a", &payload(DeleteMode::Cascade, None))
+            .unwrap();
+        // c is also a child of d, so only a and b are deleted
+        assert_eq!(report.deleted, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(report.directory_entries.len(), 1);
+    }
+
+    #[test]
+    fn test_cascade_respects_depth() {
+        let mut state = setup();
+        // With d → c gone, c is only reachable through a → b → c
+        link(&mut state, "d", &[]);
+        let report = state
+            .plan_delete("a", &payload(DeleteMode::Cascade, Some(1)))
+            .unwrap();
+        // depth 1 takes a's direct children only: b is deleted, c (two levels down) is kept
+        assert_eq!(report.deleted, vec!["a".to_string(), "b".to_string()]);
+        // Only surviving parents of deleted blocks are unlinked; a and b have none
+        assert!(report.unlinked_parents.is_empty());
+
+        let report = state
+            .plan_delete("a", &payload(DeleteMode::Cascade, None))
+            .unwrap();
+        // Without a depth limit the whole chain a, b, c goes
+        assert_eq!(report.deleted.len(), 3);
+    }
+
+    #[test]
+    fn test_followup_events_leave_no_dangling_references() {
+        let mut state = setup();
+        let report = state
+            .plan_delete("b", &payload(DeleteMode::Detach, None))
+            .unwrap();
+        let events = state.delete_followup_events(&report, "alice");
+        assert_eq!(events.len(), 2);
+
+        let mut ts = StdHashMap::new();
+        ts.insert("alice".to_string(), 2);
+        state.apply_event(&Event::new(
+            "b".to_string(),
+            "alice/core.delete".to_string(),
+            serde_json::json!({}),
+            ts,
+        ));
+        for event in &events {
+            state.apply_event(event);
+        }
+
+        // c now only has d as parent and b's children are gone with it
+        assert!(state.find_dangling_references().is_empty());
+        assert!(state.get_children("a").is_empty());
+    }
+
+    #[test]
+    fn test_find_dangling_references() {
+        let mut state = setup();
+        let mut ts = StdHashMap::new();
+        ts.insert("alice".to_string(), 2);
+        // Raw delete event without follow-up cleanup (legacy behaviour)
+        state.apply_event(&Event::new(
+            "b".to_string(),
+            "alice/core.delete".to_string(),
+            serde_json::json!({}),
+            ts,
+        ));
+
+        let dangling = state.find_dangling_references();
+        assert_eq!(dangling.len(), 2);
+        assert!(dangling
+            .iter()
+            .any(|d| d.kind == DanglingKind::RelationTarget
+                && d.source_id == "a"
+                && d.target_id == "b"));
+        assert!(dangling
+            .iter()
+            .any(|d| d.kind == DanglingKind::DirectoryEntry
+                && d.source_id == "dir"
+                && d.path.as_deref() == Some("docs/b.md")));
+    }
+}
@@ -0,0 +1,534 @@
+//! Events received from another instance of the same project.
+//!
+//! Remote events were authorized by the sending engine, which can't be trusted
+//! here. Their signatures are verified first (see `signing::verify_author`).
+//! Before an event is appended, its editor is checked against the local
+//! grants, exactly like a local command would be: project-level capabilities
+//! against the project, grant management against the granted block (or the
+//! project), everything else against the block the event changes. The event
+//! then has to pass the same structural checks as the command that made it.
+//!
+//! A command can make several events, which share the editor and vector clock
+//! (see [`split_commands`]). They are checked as one: the first event is the
+//! command's own and is authorized as above. Follow-ups that the engine derives
+//! from it (cascaded deletes and restores, parent links, directory entries,
+//! revokes of delegated grants) are accepted if they are exactly what the local
+//! planner derives from the current state; any other event of the command has
+//! to be authorized on its own. If one event is refused, the whole command is.
+
+use crate::capabilities::grants::GrantKey;
+use crate::engine::authorization::{
+    grant_target, GRANT_MANAGEMENT_CAPABILITIES, PROJECT_CAPABILITIES, PROJECT_SCOPE,
+};
+use crate::engine::state::StateProjector;
+use crate::models::{DeleteBlockPayload, Event, RELATION_IMPLEMENT};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// An event that was refused: bad signature, missing capability or failed check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct RejectedEvent {
+    pub event_id: String,
+    pub entity: String,
+    pub attribute: String,
+    /// Why the event was refused
+    pub reason: String,
+}
+
+/// Result of receiving events from another instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct ReceiveReport {
+    /// Events received
+    pub received: usize,
+    /// Events appended and applied
+    pub applied: usize,
+    /// Events that were already known
+    pub duplicates: usize,
+    pub rejected: Vec<RejectedEvent>,
+}
+
+impl RejectedEvent {
+    pub fn new(event: &Event, reason: String) -> Self {
+        Self {
+            event_id: event.event_id.clone(),
+            entity: event.entity.clone(),
+            attribute: event.attribute.clone(),
+            reason,
+        }
+    }
+}
+
+/// Split a log into commands: runs of events with the same editor and vector clock.
+pub fn split_commands(events: &[Event]) -> Vec<&[Event]> {
+    let editor = |event: &Event| event.attribute.split_once('/').map(|(editor, _)| editor);
+    let mut commands = Vec::new();
+    let mut start = 0;
+    for i in 1..=events.len() {
+        if i == events.len()
+            || editor(&events[i]) != editor(&events[start])
+            || events[i].timestamp != events[start].timestamp
+        {
+            if start < i {
+                commands.push(&events[start..i]);
+            }
+            start = i;
+        }
+    }
+    commands
+}
+
+/// Check the events of one remote command against `state`, the projection
+/// before the command.
+///
+/// The first event is checked with [`check_remote_event`]. Each follow-up must
+/// either match one the local planner derives from the first event or be
+/// authorized on its own. Only the first event may record a grant use.
+pub fn check_remote_command(
+    state: &StateProjector,
+    events: &[Event],
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let Some((first, followups)) = events.split_first() else {
+        return Ok(());
+    };
+    check_remote_event(state, first, now)?;
+    let (editor_id, cap_id) = split_attribute(first)?;
+
+    let mut derived = derived_followups(state, first, editor_id, cap_id, now)?;
+    for event in followups {
+        if event.value.get("grant_use").is_some() {
+            return Err(format!(
+                "{} records a grant use on a follow-up event",
+                event.attribute
+            ));
+        }
+        let matched = derived.iter().position(|expected| {
+            expected.entity == event.entity
+                && expected.attribute == event.attribute
+                && expected.value == event.value
+        });
+        match matched {
+            Some(i) => {
+                derived.swap_remove(i);
+            }
+            None => {
+                check_event(state, event, now)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check a remote event the way the command that made it is checked locally.
+///
+/// The editor must hold the capability under the local grants at `now`, the
+/// time the event is received (its `created_at` comes from the sender). Links
+/// must not close a cycle, the project owner can't be deleted, roles must be
+/// defined, and grants must be delegable by their granter. An event authorized
+/// by a limited grant must record its use of that grant.
+pub fn check_remote_event(
+    state: &StateProjector,
+    event: &Event,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let (editor_id, cap_id, scope) = check_event(state, event, now)?;
+
+    // A limited grant use must be charged to the grant the editor holds here
+    let used = marker(&event.value, "grant_use");
+    match (state.limited_grant_for(editor_id, cap_id, scope, now), used) {
+        (None, None) => Ok(()),
+        (Some(limited), Some(used)) if limited == used => Ok(()),
+        (Some(_), None) => Err(format!(
+            "{} doesn't record the use of the limited grant that authorizes it",
+            event.attribute
+        )),
+        _ => Err(format!(
+            "{} records the use of a grant {} doesn't hold",
+            event.attribute, editor_id
+        )),
+    }
+}
+
+/// Authorization and the command validators, without the grant use.
+///
+/// Returns the editor, the capability and the scope it was authorized against.
+fn check_event<'a>(
+    state: &StateProjector,
+    event: &'a Event,
+    now: DateTime<Utc>,
+) -> Result<(&'a str, &'a str, &'a str), String> {
+    let (editor_id, cap_id) = split_attribute(event)?;
+    let scope = authorization_scope(state, cap_id, &event.entity);
+    check_authorized(state, editor_id, cap_id, scope, now)?;
+    match cap_id {
+        "core.link" => {
+            let current = state.get_children(&event.entity);
+            let linked = event.value["children"][RELATION_IMPLEMENT]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|target| target.as_str());
+            for target in linked.filter(|target| !current.iter().any(|c| c == target)) {
+                state.check_link_cycle(&event.entity, target)?;
+            }
+        }
+        "editor.delete" => state.check_editor_delete(&event.entity)?,
+        "core.grant_role" => {
+            let role = event.value["role"].as_str().unwrap_or_default();
+            if state.roles.get_role(role).is_none() {
+                return Err(format!("Unknown role: {}", role));
+            }
+        }
+        "core.grant" => {
+            let capability = event.value["capability"].as_str().unwrap_or_default();
+            let block = event.value["block"].as_str().unwrap_or_default();
+            let delegated_from = state.check_delegation(
+                editor_id,
+                capability,
+                &grant_target(capability, block),
+                now,
+            )?;
+            if marker(&event.value, "delegated_from") != delegated_from {
+                return Err(format!(
+                    "{} records another delegation than the local grants allow",
+                    event.attribute
+                ));
+            }
+        }
+        _ => {}
+    }
+
+    Ok((editor_id, cap_id, scope))
+}
+
+fn check_authorized(
+    state: &StateProjector,
+    editor_id: &str,
+    cap_id: &str,
+    scope: &str,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    if state.is_authorized_at(editor_id, cap_id, scope, now) {
+        return Ok(());
+    }
+    Err(if scope == PROJECT_SCOPE {
+        format!(
+            "{} does not have project-level permission for {}",
+            editor_id, cap_id
+        )
+    } else {
+        format!(
+            "{} does not have permission for {} on block {}",
+            editor_id, cap_id, scope
+        )
+    })
+}
+
+/// The follow-up events the engine appends to a command's first event,
+/// planned against `state` like a local command.
+///
+/// As locally, cascading a delete or restore to other blocks needs the
+/// capability on each of them.
+fn derived_followups(
+    state: &StateProjector,
+    event: &Event,
+    editor_id: &str,
+    cap_id: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<Event>, String> {
+    Ok(match cap_id {
+        "core.delete" => {
+            let payload: DeleteBlockPayload = serde_json::from_value(event.value.clone())
+                .map_err(|e| format!("Invalid value for core.delete: {}", e))?;
+            let report = state.plan_delete(&event.entity, &payload)?;
+            for block_id in report.deleted.iter().skip(1) {
+                check_authorized(state, editor_id, cap_id, block_id, now)?;
+            }
+            state.delete_followup_events(&report, editor_id)
+        }
+        "core.restore" => {
+            let include_cascaded = event.value["include_cascaded"].as_bool().unwrap_or(false);
+            let restored = state.plan_restore(&event.entity, include_cascaded)?;
+            for block_id in restored.iter().skip(1) {
+                check_authorized(state, editor_id, cap_id, block_id, now)?;
+            }
+            state.restore_followup_events(&restored, editor_id)
+        }
+        "core.revoke" if event.value["cascade"].as_bool().unwrap_or(false) => {
+            let field = |name: &str| event.value[name].as_str().unwrap_or_default().to_string();
+            let key = (field("editor"), field("capability"), field("block"));
+            state.revoke_followup_events(&key, editor_id)
+        }
+        _ => Vec::new(),
+    })
+}
+
+fn split_attribute(event: &Event) -> Result<(&str, &str), String> {
+    event
+        .attribute
+        .split_once('/')
+        .ok_or_else(|| format!("Malformed attribute '{}'", event.attribute))
+}
+
+/// A grant recorded in an event's value (`delegated_from` or `grant_use`).
+fn marker(value: &serde_json::Value, name: &str) -> Option<GrantKey> {
+    let marker = value.get(name)?;
+    Some((
+        marker["editor"].as_str()?.to_string(),
+        marker["capability"].as_str()?.to_string(),
+        marker["block"].as_str()?.to_string(),
+    ))
+}
+
+/// The block (or the project) an event's capability is authorized against.
+///
+/// Project-level events are about an entity that isn't an existing block: the
+/// block being created, an editor, a role, or a wildcard grant.
+fn authorization_scope<'a>(state: &StateProjector, cap_id: &str, entity: &'a str) -> &'a str {
+    let is_block = state.blocks.contains_key(entity) || state.trash.contains_key(entity);
+    let project_level =
+        PROJECT_CAPABILITIES.contains(&cap_id) || GRANT_MANAGEMENT_CAPABILITIES.contains(&cap_id);
+    if project_level && !is_block {
+        PROJECT_SCOPE
+    } else {
+        entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::core::create_event;
+
+    #[test]
+    fn test_remote_events_are_checked_against_local_grants() {
+        let mut state = StateProjector::new();
+        state.apply_event(&create_event(
+            "alice".to_string(),
+            "editor.create",
+            serde_json::json!({ "editor_id": "alice", "name": "Alice" }),
+            "alice",
+            1,
+        ));
+        state.apply_event(&create_event(
+            "block1".to_string(),
+            "core.create",
+            serde_json::json!({ "name": "a.md", "type": "markdown", "owner": "alice", "contents": {} }),
+            "alice",
+            2,
+        ));
+
+        let write = |editor_id: &str| {
+            create_event(
+                "block1".to_string(),
+                "markdown.write",
+                serde_json::json!({ "contents": { "markdown": "hi" } }),
+                editor_id,
+                1,
+            )
+        };
+        assert!(check_remote_event(&state, &write("alice"), Utc::now()).is_ok());
+        let err = check_remote_event(&state, &write("mallory"), Utc::now()).unwrap_err();
+        assert!(err.contains("block1"), "{}", err);
+
+        // Project-level: only the project owner may create editors
+        let create_editor = create_event(
+            "eve".to_string(),
+            "editor.create",
+            serde_json::json!({ "editor_id": "eve", "name": "Eve" }),
+            "mallory",
+            1,
+        );
+        let err = check_remote_event(&state, &create_editor, Utc::now()).unwrap_err();
+        assert!(err.contains("project-level"), "{}", err);
+
+        // A malformed attribute is refused outright
+        let mut malformed = write("alice");
+        malformed.attribute = "markdown.write".to_string();
+        assert!(check_remote_event(&state, &malformed, Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_remote_events_pass_the_command_validators() {
+        let mut state = StateProjector::new();
+        state.apply_event(&create_event(
+            "alice".to_string(),
+            "editor.create",
+            serde_json::json!({ "editor_id": "alice", "name": "Alice" }),
+            "alice",
+            1,
+        ));
+        for block_id in ["a", "b"] {
+            state.apply_event(&create_event(
+                block_id.to_string(),
+                "core.create",
+                serde_json::json!({ "name": block_id, "type": "markdown", "owner": "alice", "contents": {} }),
+                "alice",
+                2,
+            ));
+        }
+        state.apply_event(&create_event(
+            "a".to_string(),
+            "core.link",
+            serde_json::json!({ "children": { "implement": ["b"] } }),
+            "alice",
+            3,
+        ));
+        state.apply_event(&create_event(
+            "a".to_string(),
+            "core.grant",
+            serde_json::json!({ "editor": "bob", "capability": "core.grant", "block": "a" }),
+            "alice",
+            4,
+        ));
+        state.apply_event(&create_event(
+            "a".to_string(),
+            "core.grant",
+            serde_json::json!({ "editor": "bob", "capability": "markdown.write", "block": "a" }),
+            "alice",
+            5,
+        ));
+        let now = Utc::now();
+
+        // b → a closes the cycle a → b → a
+        let cycle = create_event(
+            "b".to_string(),
+            "core.link",
+            serde_json::json!({ "children": { "implement": ["a"] } }),
+            "alice",
+            6,
+        );
+        let err = check_remote_event(&state, &cycle, now).unwrap_err();
+        assert!(err.contains("Cycle"), "{}", err);
+
+        let delete_owner = create_event(
+            "alice".to_string(),
+            "editor.delete",
+            serde_json::json!({}),
+            "alice",
+            6,
+        );
+        let err = check_remote_event(&state, &delete_owner, now).unwrap_err();
+        assert!(err.contains("project owner"), "{}", err);
+
+        // bob holds core.grant on a, but markdown.write isn't grantable
+        let escalate = create_event(
+            "a".to_string(),
+            "core.grant",
+            serde_json::json!({ "editor": "carol", "capability": "markdown.write", "block": "a" }),
+            "bob",
+            1,
+        );
+        let err = check_remote_event(&state, &escalate, now).unwrap_err();
+        assert!(err.contains("Delegation failed"), "{}", err);
+
+        // The owner may grant, but not claim a delegation that doesn't exist
+        let mut grant = create_event(
+            "a".to_string(),
+            "core.grant",
+            serde_json::json!({ "editor": "carol", "capability": "markdown.write", "block": "a" }),
+            "alice",
+            6,
+        );
+        assert!(check_remote_event(&state, &grant, now).is_ok());
+        grant.value["delegated_from"] =
+            serde_json::json!({ "editor": "bob", "capability": "markdown.write", "block": "a" });
+        assert!(check_remote_event(&state, &grant, now).is_err());
+    }
+
+    #[test]
+    fn test_limited_grant_use_must_be_recorded() {
+        let mut state = StateProjector::new();
+        state.apply_event(&create_event(
+            "block1".to_string(),
+            "core.create",
+            serde_json::json!({ "name": "a.md", "type": "markdown", "owner": "alice", "contents": {} }),
+            "alice",
+            1,
+        ));
+        state.apply_event(&create_event(
+            "block1".to_string(),
+            "core.grant",
+            serde_json::json!({
+                "editor": "bob",
+                "capability": "markdown.write",
+                "block": "block1",
+                "max_uses": 1
+            }),
+            "alice",
+            2,
+        ));
+        let mut write = create_event(
+            "block1".to_string(),
+            "markdown.write",
+            serde_json::json!({ "contents": { "markdown": "hi" } }),
+            "bob",
+            1,
+        );
+        let err = check_remote_event(&state, &write, Utc::now()).unwrap_err();
+        assert!(err.contains("limited grant"), "{}", err);
+
+        write.value["grant_use"] = serde_json::json!({ "editor": "bob", "capability": "markdown.write", "block": "block1" });
+        assert!(check_remote_event(&state, &write, Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn test_delete_followups_are_checked_with_the_command() {
+        let mut state = StateProjector::new();
+        for block_id in ["parent", "child"] {
+            state.apply_event(&create_event(
+                block_id.to_string(),
+                "core.create",
+                serde_json::json!({ "name": block_id, "type": "markdown", "owner": "alice", "contents": {} }),
+                "alice",
+                1,
+            ));
+        }
+        state.apply_event(&create_event(
+            "parent".to_string(),
+            "core.link",
+            serde_json::json!({ "children": { "implement": ["child"] } }),
+            "alice",
+            2,
+        ));
+        state.apply_event(&create_event(
+            "child".to_string(),
+            "core.grant",
+            serde_json::json!({ "editor": "bob", "capability": "core.delete", "block": "child" }),
+            "alice",
+            3,
+        ));
+        let now = Utc::now();
+
+        // bob may delete the child; the unlink of its parent comes with the delete
+        let delete = create_event(
+            "child".to_string(),
+            "core.delete",
+            serde_json::json!({ "mode": "detach" }),
+            "bob",
+            1,
+        );
+        let unlink = |children: serde_json::Value| {
+            create_event(
+                "parent".to_string(),
+                "core.unlink",
+                serde_json::json!({ "children": children }),
+                "bob",
+                1,
+            )
+        };
+        let command = [delete.clone(), unlink(serde_json::json!({}))];
+        assert!(check_remote_command(&state, &command, now).is_ok());
+
+        // An unlink the delete doesn't derive needs core.unlink on the parent
+        let command = [
+            delete.clone(),
+            unlink(serde_json::json!({ "implement": ["x"] })),
+        ];
+        let err = check_remote_command(&state, &command, now).unwrap_err();
+        assert!(err.contains("core.unlink on block parent"), "{}", err);
+        assert!(check_remote_event(&state, &unlink(serde_json::json!({})), now).is_err());
+    }
+}
@@ -0,0 +1,702 @@
+//! Signed events for tamper-evident history.
+//!
+//! Once signing is enabled for a project, every persisted event carries an
+//! `EventSignature`:
+//! - `hash`: SHA-256 over the event's fields and `prev_hash`, so each signed
+//!   event links the one before it and edits, insertions or removals in the
+//!   middle of the log break the chain
+//! - `signature`: ed25519 signature of `hash` by the editor that produced the event
+//!
+//! Events created in another copy of the project (sync, merges) keep their
+//! author's signature as `origin`, and the editor that took them in signs the
+//! link into this log.
+//!
+//! Keys are per editor and live in `~/.elf/keys/{editor_id}.key`. Only enrolled
+//! editors have one: the engine enrols the editors of a project when signing is
+//! enabled and each editor created afterwards. Editors of other machines are
+//! trusted by recording their public key (`{editor_id}.pub`).
+//!
+//! Verification checks every signature against the key store, not against keys
+//! found in the log, so rewriting and re-signing the log with new keys is reported.
+
+use crate::config;
+use crate::models::{Event, EventSignature, OriginSignature};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use specta::Type;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Directory of per-editor ed25519 signing keys.
+#[derive(Debug, Clone)]
+pub struct KeyStore {
+    dir: PathBuf,
+}
+
+impl KeyStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The key store in the user's home directory (`~/.elf/keys`).
+    pub fn default_location() -> Result<Self, String> {
+        Ok(Self::new(config::get_keys_dir()?))
+    }
+
+    /// Path of an editor's key file (`extension` is "key" or "pub").
+    /// Editor IDs that aren't safe file names are hex-encoded.
+    fn key_path(&self, editor_id: &str, extension: &str) -> PathBuf {
+        let safe = !editor_id.is_empty()
+            && !editor_id.starts_with('.')
+            && editor_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'));
+        let name = if safe {
+            editor_id.to_string()
+        } else {
+            hex::encode(editor_id)
+        };
+        self.dir.join(format!("{}.{}", name, extension))
+    }
+
+    /// Read a hex-encoded 32-byte key file, if it exists.
+    fn read_key_file(&self, editor_id: &str, extension: &str) -> Result<Option<[u8; 32]>, String> {
+        let path = self.key_path(editor_id, extension);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read key for {}: {}", editor_id, e))?;
+        hex::decode(content.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Some)
+            .ok_or_else(|| format!("Invalid key file for {}", editor_id))
+    }
+
+    /// Load an enrolled editor's signing key.
+    pub fn signing_key(&self, editor_id: &str) -> Result<SigningKey, String> {
+        self.read_key_file(editor_id, "key")?
+            .map(|bytes| SigningKey::from_bytes(&bytes))
+            .ok_or_else(|| format!("Editor {} has no enrolled signing key", editor_id))
+    }
+
+    /// Enrol an editor for signing, creating its key unless it already has one.
+    pub fn enrol(&self, editor_id: &str) -> Result<SigningKey, String> {
+        if let Some(bytes) = self.read_key_file(editor_id, "key")? {
+            return Ok(SigningKey::from_bytes(&bytes));
+        }
+        if self.key_path(editor_id, "pub").exists() {
+            return Err(format!(
+                "Editor {} signs on another machine and can't be enrolled here",
+                editor_id
+            ));
+        }
+
+        let key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let path = self.key_path(editor_id, "key");
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create key directory: {}", e))?;
+        std::fs::write(&path, hex::encode(key.to_bytes()))
+            .map_err(|e| format!("Failed to write key for {}: {}", editor_id, e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+        }
+
+        Ok(key)
+    }
+
+    /// Trust the public key (hex) an editor signs with on another machine.
+    pub fn trust(&self, editor_id: &str, public_key: &str) -> Result<(), String> {
+        hex::decode(public_key)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| format!("Invalid public key for {}", editor_id))?;
+        if self.key_path(editor_id, "key").exists() {
+            return Err(format!(
+                "Editor {} already signs on this machine",
+                editor_id
+            ));
+        }
+
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create key directory: {}", e))?;
+        std::fs::write(self.key_path(editor_id, "pub"), public_key.to_lowercase())
+            .map_err(|e| format!("Failed to write public key for {}: {}", editor_id, e))
+    }
+
+    /// The public key (hex) trusted for an editor: that of its enrolled signing
+    /// key, or the one recorded with `trust`.
+    pub fn public_key(&self, editor_id: &str) -> Result<Option<String>, String> {
+        if let Some(bytes) = self.read_key_file(editor_id, "key")? {
+            let key = SigningKey::from_bytes(&bytes);
+            return Ok(Some(hex::encode(key.verifying_key().to_bytes())));
+        }
+        Ok(self.read_key_file(editor_id, "pub")?.map(hex::encode))
+    }
+}
+
+/// Signs events as they are persisted, extending the project's hash chain.
+#[derive(Debug, Clone)]
+pub struct EventSigner {
+    keys: KeyStore,
+    /// Hash of the last signed event ("" before the first one)
+    last_hash: String,
+}
+
+impl EventSigner {
+    pub fn new(keys: KeyStore, last_hash: String) -> Self {
+        Self { keys, last_hash }
+    }
+
+    /// The key store the signer signs from.
+    pub fn keys(&self) -> &KeyStore {
+        &self.keys
+    }
+
+    /// Resume the chain of an existing log, if it has one.
+    pub fn resume(keys: KeyStore, events: &[Event]) -> Option<Self> {
+        events
+            .iter()
+            .rev()
+            .find_map(|event| event.signature.as_ref())
+            .map(|signature| Self::new(keys, signature.hash.clone()))
+    }
+
+    /// Sign `events` in order, each one linking the previous.
+    pub fn sign(&mut self, events: &mut [Event]) -> Result<(), String> {
+        for event in events {
+            let signer = signer_of(event).to_string();
+            self.sign_link(event, &signer)?;
+        }
+        Ok(())
+    }
+
+    /// Chain an event created in another copy of the project into this log.
+    ///
+    /// The author's signature is kept as the origin and `receiver` signs the
+    /// link. An event without a signature can only be chained by its author.
+    pub fn link(&mut self, event: &mut Event, receiver: &str) -> Result<(), String> {
+        let Some(origin) = event.signature.take().map(OriginSignature::from) else {
+            return self.sign(std::slice::from_mut(event));
+        };
+        self.sign_link(event, receiver)?;
+        if let Some(signature) = event.signature.as_mut() {
+            signature.origin = Some(origin);
+        }
+        Ok(())
+    }
+
+    /// Sign an event as `signer`, extending the chain.
+    fn sign_link(&mut self, event: &mut Event, signer: &str) -> Result<(), String> {
+        let key = self.keys.signing_key(signer)?;
+        let hash = event_hash(event, &self.last_hash);
+        let signature = key.sign(hash.as_bytes());
+
+        event.signature = Some(EventSignature {
+            prev_hash: self.last_hash.clone(),
+            hash: hash.clone(),
+            signer: signer.to_string(),
+            public_key: hex::encode(key.verifying_key().to_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+            origin: None,
+        });
+        self.last_hash = hash;
+        Ok(())
+    }
+}
+
+/// The editor an event is attributed to (the `{editor_id}/` prefix of its attribute).
+pub fn signer_of(event: &Event) -> &str {
+    event
+        .attribute
+        .split_once('/')
+        .map(|(editor_id, _)| editor_id)
+        .unwrap_or("")
+}
+
+/// SHA-256 (hex) of an event's fields chained to `prev_hash`.
+pub fn event_hash(event: &Event, prev_hash: &str) -> String {
+    let canonical = canonical_json(&serde_json::json!([
+        prev_hash,
+        event.event_id,
+        event.entity,
+        event.attribute,
+        event.value,
+        event.timestamp,
+        event.created_at,
+    ]));
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+/// Serialize JSON with object keys sorted, so the hash doesn't depend on key order.
+fn canonical_json(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .map(|key| {
+                    format!(
+                        "{}:{}",
+                        serde_json::Value::String(key.clone()),
+                        canonical_json(&map[key])
+                    )
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        serde_json::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// What is wrong with an event in a signed log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyIssueKind {
+    /// An event after the start of the chain has no signature
+    Unsigned,
+    /// `prev_hash` doesn't match the previous signed event
+    BrokenChain,
+    /// The event's fields don't match its hash
+    HashMismatch,
+    /// The signature doesn't verify, or was made for another editor
+    /// (an event's author must sign it, at least as its origin)
+    BadSignature,
+    /// The event was signed with a key other than the editor's trusted key
+    KeyMismatch,
+    /// No key is trusted for the editor that signed the event
+    UntrustedSigner,
+}
+
+/// A single event that failed verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct VerifyIssue {
+    /// Position of the event in the log
+    pub index: usize,
+    pub event_id: String,
+    pub kind: VerifyIssueKind,
+    pub detail: String,
+}
+
+/// Result of verifying an event log.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct VerifyReport {
+    pub total_events: usize,
+    pub signed_events: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    /// True if every signed event checks out and the chain is unbroken.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Verify the hash chain and signatures of an event log (in log order).
+///
+/// Events before the first signed event are legacy history and are not
+/// reported; every event after it must be signed, with the key `keys` trusts
+/// for its editor.
+pub fn verify_events(events: &[Event], keys: &KeyStore) -> VerifyReport {
+    let mut report = VerifyReport {
+        total_events: events.len(),
+        ..Default::default()
+    };
+    let mut prev_hash: Option<String> = None;
+    let mut trusted = TrustedKeys::new(keys);
+
+    for (index, event) in events.iter().enumerate() {
+        let mut issue = |kind: VerifyIssueKind, detail: String| {
+            report.issues.push(VerifyIssue {
+                index,
+                event_id: event.event_id.clone(),
+                kind,
+                detail,
+            });
+        };
+
+        let Some(signature) = &event.signature else {
+            if prev_hash.is_some() {
+                issue(
+                    VerifyIssueKind::Unsigned,
+                    format!("{} is not signed", event.attribute),
+                );
+            }
+            continue;
+        };
+
+        let expected_prev = prev_hash.as_deref().unwrap_or("");
+        if signature.prev_hash != expected_prev {
+            issue(
+                VerifyIssueKind::BrokenChain,
+                "does not link the previous signed event".to_string(),
+            );
+        }
+
+        if event_hash(event, &signature.prev_hash) != signature.hash {
+            issue(
+                VerifyIssueKind::HashMismatch,
+                "contents do not match the signed hash".to_string(),
+            );
+        }
+
+        match &signature.origin {
+            None if signature.signer != signer_of(event) => issue(
+                VerifyIssueKind::BadSignature,
+                format!(
+                    "signed by {} but attributed to {}",
+                    signature.signer,
+                    signer_of(event)
+                ),
+            ),
+            None => {
+                if let Err((kind, detail)) = trusted.check(
+                    &signature.signer,
+                    &signature.public_key,
+                    &signature.hash,
+                    &signature.signature,
+                ) {
+                    issue(kind, detail);
+                }
+            }
+            Some(origin) => {
+                // Chained in by another editor: both the link and the author's
+                // own signature have to check out
+                if let Err((kind, detail)) = trusted.check(
+                    &signature.signer,
+                    &signature.public_key,
+                    &signature.hash,
+                    &signature.signature,
+                ) {
+                    issue(kind, detail);
+                }
+                if let Err((kind, detail)) = check_author(event, origin, &mut trusted) {
+                    issue(kind, detail);
+                }
+            }
+        }
+
+        report.signed_events += 1;
+        prev_hash = Some(signature.hash.clone());
+    }
+
+    report
+}
+
+/// Check that an event carries a valid signature by its author, made with the
+/// key `keys` trusts for them. Used for events that come from another copy of
+/// the project.
+pub fn verify_author(event: &Event, keys: &KeyStore) -> Result<(), String> {
+    let signature = event
+        .signature
+        .clone()
+        .ok_or_else(|| format!("{} is not signed", event.attribute))?;
+    check_author(
+        event,
+        &OriginSignature::from(signature),
+        &mut TrustedKeys::new(keys),
+    )
+    .map_err(|(_, detail)| detail)
+}
+
+/// Check the author's signature of an event.
+fn check_author(
+    event: &Event,
+    author: &OriginSignature,
+    trusted: &mut TrustedKeys,
+) -> Result<(), (VerifyIssueKind, String)> {
+    if author.signer != signer_of(event) {
+        return Err((
+            VerifyIssueKind::BadSignature,
+            format!(
+                "signed by {} but attributed to {}",
+                author.signer,
+                signer_of(event)
+            ),
+        ));
+    }
+    if event_hash(event, &author.prev_hash) != author.hash {
+        return Err((
+            VerifyIssueKind::HashMismatch,
+            "contents do not match the hash its author signed".to_string(),
+        ));
+    }
+    trusted.check(
+        &author.signer,
+        &author.public_key,
+        &author.hash,
+        &author.signature,
+    )
+}
+
+/// The keys a key store trusts, looked up once per editor.
+struct TrustedKeys<'a> {
+    keys: &'a KeyStore,
+    cache: HashMap<String, Option<String>>,
+}
+
+impl<'a> TrustedKeys<'a> {
+    fn new(keys: &'a KeyStore) -> Self {
+        Self {
+            keys,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Check that `signer` signed `hash` with its trusted key.
+    fn check(
+        &mut self,
+        signer: &str,
+        public_key: &str,
+        hash: &str,
+        signature: &str,
+    ) -> Result<(), (VerifyIssueKind, String)> {
+        let keys = self.keys;
+        let trusted = self
+            .cache
+            .entry(signer.to_string())
+            .or_insert_with(|| keys.public_key(signer).ok().flatten());
+        match trusted {
+            None => Err((
+                VerifyIssueKind::UntrustedSigner,
+                format!("no key is trusted for {}", signer),
+            )),
+            Some(trusted) if !trusted.eq_ignore_ascii_case(public_key) => Err((
+                VerifyIssueKind::KeyMismatch,
+                format!("not signed with the trusted key of {}", signer),
+            )),
+            Some(_) if !signature_verifies(public_key, hash, signature) => Err((
+                VerifyIssueKind::BadSignature,
+                format!("signature by {} does not verify", signer),
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+/// Check a signature of `hash` against a public key (both hex).
+fn signature_verifies(public_key: &str, hash: &str, signature: &str) -> bool {
+    let key = hex::decode(public_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+    let sig = hex::decode(signature)
+        .ok()
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        .map(|bytes| Signature::from_bytes(&bytes));
+
+    match (key, sig) {
+        (Some(key), Some(sig)) => key.verify(hash.as_bytes(), &sig).is_ok(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn event(attribute: &str, value: serde_json::Value) -> Event {
+        let mut timestamp = HashMap::new();
+        timestamp.insert("alice".to_string(), 1);
+        Event::new(
+            "block1".to_string(),
+            attribute.to_string(),
+            value,
+            timestamp,
+        )
+    }
+
+    fn signed_log(keys: &KeyStore) -> Vec<Event> {
+        keys.enrol("alice").unwrap();
+        keys.enrol("bob").unwrap();
+        let mut events = vec![
+            event("alice/core.create", serde_json::json!({ "name": "a" })),
+            event("bob/markdown.write", serde_json::json!({ "b": 1, "a": 2 })),
+            event(
+                "alice/markdown.write",
+                serde_json::json!({ "content": "x" }),
+            ),
+        ];
+        EventSigner::new(keys.clone(), String::new())
+            .sign(&mut events)
+            .unwrap();
+        events
+    }
+
+    #[test]
+    fn test_signed_log_verifies() {
+        let dir = TempDir::new().unwrap();
+        let keys = KeyStore::new(dir.path().to_path_buf());
+        let events = signed_log(&keys);
+
+        let report = verify_events(&events, &keys);
+        assert!(report.is_valid(), "{:?}", report.issues);
+        assert_eq!(report.signed_events, 3);
+        assert_eq!(
+            events[1].signature.as_ref().unwrap().prev_hash,
+            events[0].signature.as_ref().unwrap().hash
+        );
+
+        // Keys are reused across runs
+        assert!(dir.path().join("alice.key").exists());
+        let again = signed_log(&keys);
+        assert_eq!(
+            again[0].signature.as_ref().unwrap().public_key,
+            events[0].signature.as_ref().unwrap().public_key
+        );
+    }
+
+    #[test]
+    fn test_tampering_is_reported() {
+        let dir = TempDir::new().unwrap();
+        let keys = KeyStore::new(dir.path().to_path_buf());
+
+        // Edited value
+        let mut events = signed_log(&keys);
+        events[1].value = serde_json::json!({ "b": 1, "a": 3 });
+        let report = verify_events(&events, &keys);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, VerifyIssueKind::HashMismatch);
+        assert_eq!(report.issues[0].index, 1);
+
+        // Removed event
+        let mut events = signed_log(&keys);
+        events.remove(1);
+        let report = verify_events(&events, &keys);
+        assert_eq!(report.issues[0].kind, VerifyIssueKind::BrokenChain);
+
+        // Unsigned event appended after the chain started
+        let mut events = signed_log(&keys);
+        events.push(event("mallory/core.delete", serde_json::json!({})));
+        let report = verify_events(&events, &keys);
+        assert_eq!(report.issues[0].kind, VerifyIssueKind::Unsigned);
+
+        // Continued by someone holding a different key for bob
+        let mut events = signed_log(&keys);
+        let last_hash = events[2].signature.as_ref().unwrap().hash.clone();
+        let mut forged = vec![event("bob/core.delete", serde_json::json!({}))];
+        let other = KeyStore::new(dir.path().join("other"));
+        other.enrol("bob").unwrap();
+        EventSigner::new(other.clone(), last_hash)
+            .sign(&mut forged)
+            .unwrap();
+        events.extend(forged);
+        let report = verify_events(&events, &keys);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, VerifyIssueKind::KeyMismatch);
+
+        // The whole log rewritten and re-signed with fresh keys
+        let mut events = signed_log(&keys);
+        events[1].value = serde_json::json!({ "b": 1, "a": 3 });
+        other.enrol("alice").unwrap();
+        EventSigner::new(other, String::new())
+            .sign(&mut events)
+            .unwrap();
+        let report = verify_events(&events, &keys);
+        assert_eq!(report.issues.len(), 3);
+        assert!(report
+            .issues
+            .iter()
+            .all(|issue| issue.kind == VerifyIssueKind::KeyMismatch));
+    }
+
+    #[test]
+    fn test_events_from_another_copy_are_linked_in() {
+        let dir = TempDir::new().unwrap();
+        let ours = KeyStore::new(dir.path().join("ours"));
+        let theirs = KeyStore::new(dir.path().join("theirs"));
+        let alice = hex::encode(ours.enrol("alice").unwrap().verifying_key().to_bytes());
+        let bob = hex::encode(theirs.enrol("bob").unwrap().verifying_key().to_bytes());
+        ours.trust("bob", &bob).unwrap();
+        theirs.trust("alice", &alice).unwrap();
+
+        // bob writes in his copy, and alice's copy links the event into its own chain
+        let mut remote = vec![event("bob/markdown.write", serde_json::json!({ "x": 1 }))];
+        EventSigner::new(theirs, "their-head".to_string())
+            .sign(&mut remote)
+            .unwrap();
+        assert!(verify_author(&remote[0], &ours).is_ok());
+
+        let mut events = vec![event("alice/core.create", serde_json::json!({}))];
+        let mut signer = EventSigner::new(ours.clone(), String::new());
+        signer.sign(&mut events).unwrap();
+        let mut received = remote[0].clone();
+        signer.link(&mut received, "alice").unwrap();
+        let signature = received.signature.as_ref().unwrap();
+        assert_eq!(signature.signer, "alice");
+        assert_eq!(signature.origin.as_ref().unwrap().signer, "bob");
+        events.push(received);
+        let report = verify_events(&events, &ours);
+        assert!(report.is_valid(), "{:?}", report.issues);
+
+        // Forwarding it again keeps bob's signature as the origin
+        let mut forwarded = events[1].clone();
+        EventSigner::new(ours.clone(), String::new())
+            .link(&mut forwarded, "alice")
+            .unwrap();
+        assert_eq!(
+            forwarded
+                .signature
+                .as_ref()
+                .unwrap()
+                .origin
+                .as_ref()
+                .unwrap()
+                .signer,
+            "bob"
+        );
+
+        // Whoever chains an event in can't change what its author signed
+        let mut tampered = remote[0].clone();
+        tampered.value = serde_json::json!({ "x": 2 });
+        assert!(verify_author(&tampered, &ours).is_err());
+        signer.link(&mut tampered, "alice").unwrap();
+        events.push(tampered);
+        let report = verify_events(&events, &ours);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, VerifyIssueKind::HashMismatch);
+    }
+
+    #[test]
+    fn test_only_enrolled_or_trusted_keys_count() {
+        let dir = TempDir::new().unwrap();
+        let keys = KeyStore::new(dir.path().to_path_buf());
+
+        // Signing doesn't create keys
+        let mut events = vec![event("alice/core.create", serde_json::json!({}))];
+        let err = EventSigner::new(keys.clone(), String::new())
+            .sign(&mut events)
+            .unwrap_err();
+        assert!(err.contains("no enrolled signing key"), "got: {}", err);
+        assert!(!dir.path().join("alice.key").exists());
+
+        // An editor signing on another machine is verified against its trusted key
+        let remote = KeyStore::new(dir.path().join("remote"));
+        let public_key = hex::encode(remote.enrol("bob").unwrap().verifying_key().to_bytes());
+        let mut events = vec![event("bob/markdown.write", serde_json::json!({}))];
+        EventSigner::new(remote, String::new())
+            .sign(&mut events)
+            .unwrap();
+
+        let report = verify_events(&events, &keys);
+        assert_eq!(report.issues[0].kind, VerifyIssueKind::UntrustedSigner);
+
+        keys.trust("bob", &public_key).unwrap();
+        assert!(verify_events(&events, &keys).is_valid());
+        assert_eq!(keys.public_key("bob").unwrap(), Some(public_key));
+        assert!(keys.enrol("bob").is_err());
+        assert!(keys.trust("bob", "not a key").is_err());
+    }
+}
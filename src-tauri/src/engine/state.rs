@@ -1,5 +1,11 @@
-use crate::capabilities::grants::GrantsTable;
-use crate::models::{Block, BlockMetadata, Editor, EditorType, Event, RELATION_IMPLEMENT};
+use crate::capabilities::grants::{GrantKey, GrantsTable};
+use crate::capabilities::roles::RolesTable;
+use crate::engine::authorization::{grant_target, AuthorizationRule};
+use crate::engine::trash::TrashedBlock;
+use crate::models::{
+    Block, BlockContainers, BlockMetadata, Editor, EditorType, Event, Role, RELATION_IMPLEMENT,
+};
+use chrono::{DateTime, Utc};
 use log;
 use std::collections::HashMap;
 
@@ -17,14 +23,26 @@ pub struct StateProjector {
     /// Grants table for authorization (reuses existing implementation)
     pub grants: GrantsTable,
 
+    /// Role definitions and role assignments (resolved alongside grants)
+    pub roles: RolesTable,
+
     /// Vector clock counts for each editor (for conflict detection)
     pub editor_counts: HashMap<String, i64>,
 
     /// Reverse index: child_block_id → list of parent_block_ids
     ///
     /// Maintained for `implement` relations only (the sole relation type).
-    /// Updated on core.link, core.unlink, and core.delete events.
+    /// Updated on core.link, core.unlink, core.delete and core.restore events.
     pub parents: HashMap<String, Vec<String>>,
+
+    /// Deleted blocks indexed by block_id, kept until restored or purged
+    pub trash: HashMap<String, TrashedBlock>,
+
+    /// The first editor created in the project, or the editor of the first
+    /// event if the log starts with something else (None while still empty)
+    ///
+    /// Holds every project-level capability, like a block owner does for its block.
+    pub project_owner: Option<String>,
 }
 
 impl StateProjector {
@@ -34,8 +52,11 @@ impl StateProjector {
             blocks: HashMap::new(),
             editors: HashMap::new(),
             grants: GrantsTable::new(),
+            roles: RolesTable::new(),
             editor_counts: HashMap::new(),
             parents: HashMap::new(),
+            trash: HashMap::new(),
+            project_owner: None,
         }
     }
 
@@ -66,6 +87,15 @@ impl StateProjector {
         }
         let cap_id = parts[1];
 
+        // A log that doesn't start by creating an editor belongs to whoever
+        // wrote its first event (the first editor created claims it below)
+        if self.project_owner.is_none() && cap_id != "editor.create" {
+            self.project_owner = Some(parts[0].to_string());
+        }
+
+        // Count a use of the limited grant that authorized the event's command
+        self.record_grant_use(event);
+
         // Handle different event types based on capability
         match cap_id {
             // Block creation
@@ -234,25 +264,17 @@ impl StateProjector {
                 }
             }
 
-            // Block deletion
+            // Block deletion: the block moves to the trash
             "core.delete" => {
-                // Clean up reverse index: remove this block as a parent of its children
-                if let Some(block) = self.blocks.get(&event.entity) {
-                    if let Some(targets) = block.children.get(RELATION_IMPLEMENT) {
-                        for target in targets {
-                            if let Some(parent_list) = self.parents.get_mut(target) {
-                                parent_list.retain(|id| id != &event.entity);
-                                if parent_list.is_empty() {
-                                    self.parents.remove(target);
-                                }
-                            }
-                        }
-                    }
-                }
-                // Also remove this block's own parents entry
-                self.parents.remove(&event.entity);
+                self.move_to_trash(event);
+            }
 
-                self.blocks.remove(&event.entity);
+            // Trash: bring a deleted block back, or drop it for good
+            "core.restore" => {
+                self.restore_from_trash(&event.entity);
+            }
+            "core.purge" => {
+                self.trash.remove(&event.entity);
             }
 
             // Block rename
@@ -325,12 +347,22 @@ impl StateProjector {
                             .get("block")
                             .and_then(|v| v.as_str())
                             .unwrap_or("*");
+                        let block = grant_target(capability, block);
 
                         if !editor.is_empty() && !capability.is_empty() {
-                            self.grants.add_grant(
+                            self.grants.add_grant_with_limits(
                                 editor.to_string(),
                                 capability.to_string(),
                                 block.to_string(),
+                                GrantsTable::limits_from_value(&event.value),
+                            );
+                            self.grants.set_delegation(
+                                (
+                                    editor.to_string(),
+                                    capability.to_string(),
+                                    block.to_string(),
+                                ),
+                                GrantsTable::delegation_from_event(event),
                             );
                         }
                     }
@@ -349,9 +381,76 @@ impl StateProjector {
                             .get("block")
                             .and_then(|v| v.as_str())
                             .unwrap_or("*");
+                        let block = grant_target(capability, block);
 
                         if !editor.is_empty() && !capability.is_empty() {
-                            self.grants.remove_grant(editor, capability, block);
+                            self.grants.remove_grant(editor, capability, &block);
+                        }
+                    }
+                }
+            }
+
+            // Deny rules - take precedence over grants and roles
+            "core.deny" | "core.undeny" => {
+                if let Some(deny_obj) = event.value.as_object() {
+                    let editor = deny_obj
+                        .get("editor")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    let capability = deny_obj
+                        .get("capability")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    let block = deny_obj
+                        .get("block")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("*");
+                    let block = grant_target(capability, block);
+
+                    if !editor.is_empty() && !capability.is_empty() {
+                        if cap_id == "core.deny" {
+                            self.grants.add_deny(
+                                editor.to_string(),
+                                capability.to_string(),
+                                block.to_string(),
+                            );
+                        } else {
+                            self.grants.remove_deny(editor, capability, &block);
+                        }
+                    }
+                }
+            }
+
+            // Role definitions
+            "core.define_role" => match serde_json::from_value::<Role>(event.value.clone()) {
+                Ok(role) if !role.name.is_empty() => self.roles.define_role(role),
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!("Failed to parse role in core.define_role event: {}", e);
+                }
+            },
+            "core.delete_role" => {
+                if let Some(name) = event.value.get("name").and_then(|v| v.as_str()) {
+                    self.roles.delete_role(name);
+                }
+            }
+
+            // Role assignments
+            "core.grant_role" | "core.revoke_role" => {
+                if let Some(obj) = event.value.as_object() {
+                    let editor = obj.get("editor").and_then(|v| v.as_str()).unwrap_or("");
+                    let role = obj.get("role").and_then(|v| v.as_str()).unwrap_or("");
+                    let block = obj.get("block").and_then(|v| v.as_str()).unwrap_or("*");
+
+                    if !editor.is_empty() && !role.is_empty() {
+                        if cap_id == "core.grant_role" {
+                            self.roles.assign(
+                                editor.to_string(),
+                                role.to_string(),
+                                block.to_string(),
+                            );
+                        } else {
+                            self.roles.unassign(editor, role, block);
                         }
                     }
                 }
@@ -374,6 +473,11 @@ impl StateProjector {
                         .unwrap_or("Human");
 
                     if !editor_id.is_empty() && !name.is_empty() {
+                        // The first editor created owns a project that has no owner yet
+                        if self.project_owner.is_none() {
+                            self.project_owner = Some(editor_id.to_string());
+                        }
+
                         let editor_type = match editor_type_str {
                             "Bot" => EditorType::Bot,
                             _ => EditorType::Human,
@@ -396,6 +500,7 @@ impl StateProjector {
                 self.editors.remove(&event.entity);
                 // Also remove all grants for this editor to prevent leaks in GrantsTable
                 self.grants.remove_all_grants_for_editor(&event.entity);
+                self.roles.remove_all_for_editor(&event.entity);
             }
 
             _ => {
@@ -427,23 +532,150 @@ impl StateProjector {
             .unwrap_or_default()
     }
 
+    /// Check that an editor may be deleted: the project owner can't be, or
+    /// nobody could manage the project.
+    pub fn check_editor_delete(&self, editor_id: &str) -> Result<(), String> {
+        if self.project_owner.as_deref() == Some(editor_id) {
+            return Err(format!("Cannot delete the project owner: {}", editor_id));
+        }
+        Ok(())
+    }
+
     /// Check if an editor is authorized to execute a capability on a block.
     ///
     /// Authorization logic:
-    /// 1. Block owner always has all permissions on their own block.
-    /// 2. Otherwise, check the grants table for explicit authorization.
+    /// 1. Block owner always has all permissions on their own block
+    ///    (including trashed blocks, so owners can restore or purge them).
+    ///    For the project pseudo-block [`crate::models::PROJECT_SCOPE`], the
+    ///    project owner does (and anyone while the project is still empty).
+    /// 2. Otherwise, a matching deny rule refuses the check.
+    /// 3. Otherwise, check the grants table for explicit authorization.
+    /// 4. Otherwise, check the roles the editor holds on the block.
+    ///
+    /// Time-limited grants are checked against the current time; use
+    /// [`Self::is_authorized_at`] to check at a command's timestamp, and
+    /// `explain_authorization` to see which rule decided.
     pub fn is_authorized(&self, editor_id: &str, cap_id: &str, block_id: &str) -> bool {
-        // Special case: core.create and editor.create are usually handled at a higher level
-        // or have implicit permissions for any registered editor in this simple version.
-        // But for block-level capabilities:
-        if let Some(block) = self.get_block(block_id) {
-            if block.owner == editor_id {
-                return true;
+        self.is_authorized_at(editor_id, cap_id, block_id, Utc::now())
+    }
+
+    /// Check authorization at a given time (see [`Self::is_authorized`]).
+    pub fn is_authorized_at(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        block_id: &str,
+        now: DateTime<Utc>,
+    ) -> bool {
+        self.explain_authorization_at(editor_id, cap_id, block_id, now)
+            .allowed
+    }
+
+    /// The limited grant a command would be authorized by, if any.
+    ///
+    /// Nothing is returned for the block owner or when an unlimited grant also
+    /// applies. The engine records the result as `grant_use` on the first event
+    /// of the command, so the grant is used once per command, however many
+    /// events the command makes.
+    pub fn limited_grant_for(
+        &self,
+        editor_id: &str,
+        cap_id: &str,
+        target: &str,
+        now: DateTime<Utc>,
+    ) -> Option<GrantKey> {
+        if !self.grants.has_limited_grants() {
+            return None;
+        }
+        match self
+            .explain_authorization_at(editor_id, cap_id, target, now)
+            .rule
+        {
+            AuthorizationRule::Grant {
+                editor_id,
+                cap_id,
+                block_id,
+            } => {
+                let key = (editor_id, cap_id, block_id);
+                self.grants.all_limits().contains_key(&key).then_some(key)
+            }
+            _ => None,
+        }
+    }
+
+    /// Count the use recorded by the engine as `grant_use` on an event.
+    ///
+    /// Reading the recorded grant instead of re-checking authorization keeps
+    /// replay deterministic and independent of the events' wall-clock times.
+    fn record_grant_use(&mut self, event: &Event) {
+        let Some(source) = event.value.get("grant_use") else {
+            return;
+        };
+        let field = |name: &str| source.get(name).and_then(|v| v.as_str()).map(String::from);
+        if let (Some(editor), Some(capability), Some(block)) =
+            (field("editor"), field("capability"), field("block"))
+        {
+            self.grants.record_use(&(editor, capability, block));
+        }
+    }
+
+    /// Structural ancestors of a block, computed only when some grant or role
+    /// assignment uses a hierarchical scope.
+    pub(crate) fn scope_containers(&self, block_id: &str) -> BlockContainers {
+        if self.grants.has_hierarchical_scopes() || self.roles.has_hierarchical_scopes() {
+            self.block_containers(block_id)
+        } else {
+            BlockContainers::default()
+        }
+    }
+
+    /// Collect the structural ancestors of a block.
+    ///
+    /// - `directories`: directory blocks with a file entry pointing at the block,
+    ///   followed upwards through directory blocks that are themselves entries
+    /// - `implement_ancestors`: blocks that reach the block via `implement` links
+    pub fn block_containers(&self, block_id: &str) -> BlockContainers {
+        let mut containers = BlockContainers::default();
+
+        // Implement ancestors via the reverse index
+        let mut stack = vec![block_id.to_string()];
+        while let Some(current) = stack.pop() {
+            for parent in self.parents.get(&current).into_iter().flatten() {
+                if parent != block_id && containers.implement_ancestors.insert(parent.clone()) {
+                    stack.push(parent.clone());
+                }
             }
         }
 
-        // Check explicit grants
-        self.grants.has_grant(editor_id, cap_id, block_id)
+        // Directories whose entries point at the block (or at a containing directory)
+        let mut stack = vec![block_id.to_string()];
+        while let Some(current) = stack.pop() {
+            for dir in self.blocks.values() {
+                if dir.block_type != "directory"
+                    || dir.block_id == block_id
+                    || containers.directories.contains(&dir.block_id)
+                {
+                    continue;
+                }
+                let points_at_current = dir
+                    .contents
+                    .get("entries")
+                    .and_then(|v| v.as_object())
+                    .is_some_and(|entries| {
+                        entries.values().any(|entry| {
+                            entry.get("type").and_then(|v| v.as_str()) == Some("file")
+                                && entry.get("id").and_then(|v| v.as_str())
+                                    == Some(current.as_str())
+                        })
+                    });
+                if points_at_current {
+                    containers.directories.insert(dir.block_id.clone());
+                    stack.push(dir.block_id.clone());
+                }
+            }
+        }
+
+        containers
     }
 
     /// Get the current transaction count for an editor.
@@ -470,6 +702,7 @@ impl Default for StateProjector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::time;
     use std::collections::HashMap as StdHashMap;
 
     #[test]
@@ -929,6 +1162,237 @@ mod tests {
         assert!(state.grants.has_grant("bob", "markdown.read", "*"));
     }
 
+    fn role_event(attribute: &str, entity: &str, value: serde_json::Value) -> Event {
+        let mut ts = StdHashMap::new();
+        ts.insert("alice".to_string(), 1);
+        Event::new(
+            entity.to_string(),
+            format!("alice/{}", attribute),
+            value,
+            ts,
+        )
+    }
+
+    #[test]
+    fn test_role_grant_authorizes_bundled_capabilities() {
+        let mut state = StateProjector::new();
+        state.apply_event(&role_event(
+            "core.define_role",
+            "reviewer",
+            serde_json::json!({
+                "name": "reviewer",
+                "capabilities": ["markdown.read", "code.read"],
+                "scope": null
+            }),
+        ));
+        state.apply_event(&role_event(
+            "core.grant_role",
+            "*",
+            serde_json::json!({ "editor": "bob", "role": "reviewer", "block": "*" }),
+        ));
+
+        assert!(state.is_authorized("bob", "markdown.read", "block1"));
+        assert!(state.is_authorized("bob", "code.read", "block1"));
+        assert!(!state.is_authorized("bob", "markdown.write", "block1"));
+
+        // Redefining the role takes effect for existing holders
+        state.apply_event(&role_event(
+            "core.define_role",
+            "reviewer",
+            serde_json::json!({ "name": "reviewer", "capabilities": ["markdown.write"] }),
+        ));
+        assert!(!state.is_authorized("bob", "markdown.read", "block1"));
+        assert!(state.is_authorized("bob", "markdown.write", "block1"));
+    }
+
+    #[test]
+    fn test_revoke_role_and_delete_editor_remove_assignments() {
+        let mut state = StateProjector::new();
+        state.apply_event(&role_event(
+            "core.define_role",
+            "reviewer",
+            serde_json::json!({ "name": "reviewer", "capabilities": ["markdown.read"] }),
+        ));
+        for editor in ["bob", "carol"] {
+            state.apply_event(&role_event(
+                "core.grant_role",
+                "block1",
+                serde_json::json!({ "editor": editor, "role": "reviewer", "block": "block1" }),
+            ));
+        }
+
+        state.apply_event(&role_event(
+            "core.revoke_role",
+            "block1",
+            serde_json::json!({ "editor": "bob", "role": "reviewer", "block": "block1" }),
+        ));
+        assert!(!state.is_authorized("bob", "markdown.read", "block1"));
+
+        state.apply_event(&role_event("editor.delete", "carol", serde_json::json!({})));
+        assert!(!state.is_authorized("carol", "markdown.read", "block1"));
+        assert!(state.roles.assignments().is_empty());
+    }
+
+    #[test]
+    fn test_limited_grant_uses_replay_deterministically() {
+        let create = role_event(
+            "core.create",
+            "block1",
+            serde_json::json!({ "name": "notes", "type": "markdown", "owner": "alice" }),
+        );
+        let grant = role_event(
+            "core.grant",
+            "block1",
+            serde_json::json!({
+                "editor": "bob",
+                "capability": "markdown.write",
+                "block": "block1",
+                "expires_at": "2999-01-01T00:00:00Z",
+                "max_uses": 2
+            }),
+        );
+        let write = |n: i64, grant_use: bool| {
+            let mut ts = StdHashMap::new();
+            ts.insert("bob".to_string(), n);
+            let mut value = serde_json::json!({ "contents": { "markdown": n.to_string() } });
+            if grant_use {
+                value["grant_use"] = serde_json::json!({
+                    "editor": "bob",
+                    "capability": "markdown.write",
+                    "block": "block1"
+                });
+            }
+            Event::new(
+                "block1".to_string(),
+                "bob/markdown.write".to_string(),
+                value,
+                ts,
+            )
+        };
+        // The second event of the first command carries no grant_use: one use per command
+        let events = vec![
+            create,
+            grant,
+            write(1, true),
+            write(1, false),
+            write(2, true),
+        ];
+
+        let mut state = StateProjector::new();
+        state.apply_event(&events[0]);
+        state.apply_event(&events[1]);
+        assert_eq!(
+            state.limited_grant_for("bob", "markdown.write", "block1", Utc::now()),
+            Some((
+                "bob".to_string(),
+                "markdown.write".to_string(),
+                "block1".to_string()
+            ))
+        );
+        assert_eq!(
+            state.limited_grant_for("alice", "markdown.write", "block1", Utc::now()),
+            None
+        );
+        state.apply_event(&events[2]);
+        state.apply_event(&events[3]);
+        assert!(state.is_authorized("bob", "markdown.write", "block1"));
+        state.apply_event(&events[4]);
+        assert!(!state.is_authorized("bob", "markdown.write", "block1"));
+
+        // Replaying the same events yields the same use count
+        let mut replayed = StateProjector::new();
+        replayed.replay(events);
+        let limits = replayed
+            .grants
+            .get_limits("bob", "markdown.write", "block1")
+            .unwrap();
+        assert_eq!(limits.uses, 2);
+        assert!(!replayed.is_authorized("bob", "markdown.write", "block1"));
+
+        // Expiry is checked at the given time
+        let late = time::parse_to_utc("3000-01-01T00:00:00Z").unwrap();
+        let mut fresh = StateProjector::new();
+        fresh.replay(vec![
+            role_event(
+                "core.create",
+                "block1",
+                serde_json::json!({ "name": "notes", "type": "markdown", "owner": "alice" }),
+            ),
+            role_event(
+                "core.grant",
+                "block1",
+                serde_json::json!({
+                    "editor": "bob",
+                    "capability": "markdown.write",
+                    "block": "block1",
+                    "expires_at": "2999-01-01T00:00:00Z"
+                }),
+            ),
+        ]);
+        assert!(fresh.is_authorized("bob", "markdown.write", "block1"));
+        assert!(!fresh.is_authorized_at("bob", "markdown.write", "block1", late));
+    }
+
+    #[test]
+    fn test_hierarchical_grant_scopes() {
+        let mut state = StateProjector::new();
+        for (id, block_type) in [
+            ("dir", "directory"),
+            ("nested", "directory"),
+            ("a", "markdown"),
+            ("b", "markdown"),
+            ("spec", "markdown"),
+            ("impl", "code"),
+        ] {
+            let mut block = Block::new(id.to_string(), block_type.to_string(), "alice".into());
+            block.block_id = id.to_string();
+            state.blocks.insert(id.to_string(), block);
+        }
+        let entries = |ids: &[&str]| {
+            let map: serde_json::Map<String, serde_json::Value> = ids
+                .iter()
+                .map(|id| {
+                    (
+                        format!("{}.md", id),
+                        serde_json::json!({ "id": id, "type": "file" }),
+                    )
+                })
+                .collect();
+            serde_json::json!({ "entries": map })
+        };
+        state.blocks.get_mut("dir").unwrap().contents = entries(&["a", "nested"]);
+        state.blocks.get_mut("nested").unwrap().contents = entries(&["b"]);
+        state
+            .parents
+            .insert("impl".to_string(), vec!["spec".to_string()]);
+
+        let containers = state.block_containers("b");
+        assert!(containers.directories.contains("dir"));
+        assert!(containers.directories.contains("nested"));
+
+        state.apply_event(&role_event(
+            "core.grant",
+            "subtree:dir",
+            serde_json::json!({ "editor": "bob", "capability": "markdown.*", "block": "subtree:dir" }),
+        ));
+        state.apply_event(&role_event(
+            "core.grant",
+            "descendants:spec",
+            serde_json::json!({ "editor": "bob", "capability": "code.write", "block": "descendants:spec" }),
+        ));
+
+        assert!(state.is_authorized("bob", "markdown.write", "a"));
+        assert!(state.is_authorized("bob", "markdown.read", "b"));
+        assert!(!state.is_authorized("bob", "markdown.read", "spec"));
+        assert!(state.is_authorized("bob", "code.write", "impl"));
+        assert!(state.is_authorized("bob", "code.write", "spec"));
+        assert!(!state.is_authorized("bob", "code.write", "a"));
+
+        // Blocks added to the directory later are covered too
+        state.blocks.get_mut("dir").unwrap().contents = entries(&["a", "nested", "spec"]);
+        assert!(state.is_authorized("bob", "markdown.read", "spec"));
+    }
+
     #[test]
     fn test_editor_create_event_adds_to_state() {
         let mut state = StateProjector::new();
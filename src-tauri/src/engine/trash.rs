@@ -0,0 +1,413 @@
+//! Trash for deleted blocks.
+//!
+//! `core.delete` moves a block out of `StateProjector.blocks` into
+//! `StateProjector.trash`, together with the parents and directory entries that
+//! pointed at it. `core.restore` brings it back intact and `core.purge` drops it
+//! for good. The event log is unaffected either way; the trash only changes what
+//! the projection keeps around.
+
+use crate::capabilities::core::create_event;
+use crate::engine::state::StateProjector;
+use crate::models::{Block, Event, RELATION_IMPLEMENT};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashSet;
+
+/// A directory entry that pointed at a block when it was deleted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct TrashedEntry {
+    /// The directory block holding the entry
+    pub directory_id: String,
+    /// Virtual path of the entry
+    pub path: String,
+    /// The entry as it was stored in `contents.entries`
+    pub entry: serde_json::Value,
+}
+
+/// A deleted block kept in the trash with everything needed to restore it.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct TrashedBlock {
+    /// The block as it was right before deletion (contents and children intact)
+    pub block: Block,
+    /// Blocks whose `implement` relation pointed at this block
+    pub parents: Vec<String>,
+    /// Directory entries that pointed at this block
+    pub directory_entries: Vec<TrashedEntry>,
+    /// The command target this delete cascaded from, if any
+    pub cascade_from: Option<String>,
+    /// Editor who deleted the block
+    pub deleted_by: String,
+    /// Wall clock time of the delete event
+    pub deleted_at: String,
+}
+
+impl StateProjector {
+    /// Move a block into the trash (applies a `core.delete` event).
+    ///
+    /// Records the block's current parents and the directory entries pointing at
+    /// it before the delete's follow-up events remove them.
+    pub(crate) fn move_to_trash(&mut self, event: &Event) {
+        let Some(block) = self.blocks.remove(&event.entity) else {
+            return;
+        };
+
+        let parents = self.parents.remove(&event.entity).unwrap_or_default();
+
+        // Clean up reverse index: remove this block as a parent of its children
+        if let Some(targets) = block.children.get(RELATION_IMPLEMENT) {
+            for target in targets {
+                self.remove_parent_entry(target, &event.entity);
+            }
+        }
+
+        let mut directory_entries = Vec::new();
+        for dir in self.blocks.values() {
+            if dir.block_type != "directory" {
+                continue;
+            }
+            let Some(entries) = dir.contents.get("entries").and_then(|v| v.as_object()) else {
+                continue;
+            };
+            for (path, entry) in entries {
+                if entry.get("type").and_then(|v| v.as_str()) == Some("file")
+                    && entry.get("id").and_then(|v| v.as_str()) == Some(event.entity.as_str())
+                {
+                    directory_entries.push(TrashedEntry {
+                        directory_id: dir.block_id.clone(),
+                        path: path.clone(),
+                        entry: entry.clone(),
+                    });
+                }
+            }
+        }
+        directory_entries
+            .sort_by(|a, b| (&a.directory_id, &a.path).cmp(&(&b.directory_id, &b.path)));
+
+        let deleted_by = event.attribute.split('/').next().unwrap_or("").to_string();
+
+        self.trash.insert(
+            event.entity.clone(),
+            TrashedBlock {
+                block,
+                parents,
+                directory_entries,
+                cascade_from: event
+                    .value
+                    .get("cascade_from")
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+                deleted_by,
+                deleted_at: event.created_at.clone(),
+            },
+        );
+    }
+
+    /// Move a block from the trash back into the projection (applies a `core.restore` event).
+    ///
+    /// Only the block itself and the reverse index for its own children are
+    /// restored here; links from parents and directory entries are re-added by
+    /// the restore's follow-up `core.link` and `directory.write` events.
+    pub(crate) fn restore_from_trash(&mut self, block_id: &str) {
+        let Some(trashed) = self.trash.remove(block_id) else {
+            return;
+        };
+
+        if let Some(targets) = trashed.block.children.get(RELATION_IMPLEMENT) {
+            for target in targets {
+                let parents = self.parents.entry(target.clone()).or_default();
+                if !parents.contains(&trashed.block.block_id) {
+                    parents.push(trashed.block.block_id.clone());
+                }
+            }
+        }
+
+        self.blocks.insert(block_id.to_string(), trashed.block);
+    }
+
+    /// Get a trashed block by ID.
+    pub fn get_trashed(&self, block_id: &str) -> Option<&TrashedBlock> {
+        self.trash.get(block_id)
+    }
+
+    /// Get the blocks a `core.restore` of `block_id` brings back.
+    ///
+    /// The target comes first. With `include_cascaded`, blocks that were deleted
+    /// by a cascade from the target follow.
+    pub fn plan_restore(
+        &self,
+        block_id: &str,
+        include_cascaded: bool,
+    ) -> Result<Vec<String>, String> {
+        if !self.trash.contains_key(block_id) {
+            return Err(format!("Block not in trash: {}", block_id));
+        }
+
+        let mut restored = vec![block_id.to_string()];
+        if include_cascaded {
+            let mut cascaded: Vec<String> = self
+                .trash
+                .iter()
+                .filter(|(_, t)| t.cascade_from.as_deref() == Some(block_id))
+                .map(|(id, _)| id.clone())
+                .collect();
+            cascaded.sort();
+            restored.extend(cascaded);
+        }
+        Ok(restored)
+    }
+
+    /// Build the follow-up events that reattach restored blocks.
+    ///
+    /// Emits `core.restore` for cascaded blocks, `core.link` for parents that
+    /// still exist and `directory.write` for directories that still exist and
+    /// whose entry path is free. References that can't be re-added are skipped.
+    pub fn restore_followup_events(&self, restored: &[String], editor_id: &str) -> Vec<Event> {
+        let restored_set: HashSet<&String> = restored.iter().collect();
+        let mut events = Vec::new();
+
+        let Some(target_id) = restored.first() else {
+            return events;
+        };
+
+        for id in restored.iter().skip(1) {
+            events.push(create_event(
+                id.clone(),
+                "core.restore",
+                serde_json::json!({ "cascade_from": target_id }),
+                editor_id,
+                1, // Placeholder - updated by engine actor
+            ));
+        }
+
+        // Parent links, grouped per parent so each parent gets a single event
+        let mut parent_links: Vec<(String, Vec<String>)> = Vec::new();
+        for id in restored {
+            let Some(trashed) = self.trash.get(id) else {
+                continue;
+            };
+            for parent_id in &trashed.parents {
+                if restored_set.contains(parent_id) || !self.blocks.contains_key(parent_id) {
+                    continue;
+                }
+                match parent_links.iter_mut().find(|(p, _)| p == parent_id) {
+                    Some((_, targets)) => targets.push(id.clone()),
+                    None => parent_links.push((parent_id.clone(), vec![id.clone()])),
+                }
+            }
+        }
+        for (parent_id, targets) in parent_links {
+            let Some(parent) = self.get_block(&parent_id) else {
+                continue;
+            };
+            let mut new_children = parent.children.clone();
+            let implement = new_children
+                .entry(RELATION_IMPLEMENT.to_string())
+                .or_default();
+            for target in targets {
+                if !implement.contains(&target) {
+                    implement.push(target);
+                }
+            }
+            events.push(create_event(
+                parent_id,
+                "core.link",
+                serde_json::json!({ "children": new_children }),
+                editor_id,
+                1,
+            ));
+        }
+
+        // Directory entries, grouped per directory
+        let mut directory_ids: Vec<&String> = restored
+            .iter()
+            .filter_map(|id| self.trash.get(id))
+            .flat_map(|t| t.directory_entries.iter().map(|e| &e.directory_id))
+            .collect();
+        directory_ids.sort();
+        directory_ids.dedup();
+        for directory_id in directory_ids {
+            let Some(entries) = self
+                .get_block(directory_id)
+                .and_then(|b| b.contents.get("entries"))
+                .and_then(|v| v.as_object())
+            else {
+                continue;
+            };
+            let mut new_entries = entries.clone();
+            let mut changed = false;
+            for trashed in restored.iter().filter_map(|id| self.trash.get(id)) {
+                for entry in &trashed.directory_entries {
+                    if &entry.directory_id == directory_id && !new_entries.contains_key(&entry.path)
+                    {
+                        new_entries.insert(entry.path.clone(), entry.entry.clone());
+                        changed = true;
+                    }
+                }
+            }
+            if changed {
+                events.push(create_event(
+                    directory_id.clone(),
+                    "directory.write",
+                    serde_json::json!({ "contents": { "entries": new_entries } }),
+                    editor_id,
+                    1,
+                ));
+            }
+        }
+
+        events
+    }
+
+    /// Remove `parent_id` from the reverse index entry of `child_id`.
+    pub(crate) fn remove_parent_entry(&mut self, child_id: &str, parent_id: &str) {
+        if let Some(parent_list) = self.parents.get_mut(child_id) {
+            parent_list.retain(|id| id != parent_id);
+            if parent_list.is_empty() {
+                self.parents.remove(child_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap as StdHashMap;
+
+    fn event(entity: &str, cap: &str, value: serde_json::Value) -> Event {
+        let mut ts = StdHashMap::new();
+        ts.insert("alice".to_string(), 1);
+        Event::new(entity.to_string(), format!("alice/{}", cap), value, ts)
+    }
+
+    fn create(state: &mut StateProjector, id: &str, block_type: &str) {
+        state.apply_event(&event(
+            id,
+            "core.create",
+            serde_json::json!({
+                "name": id,
+                "type": block_type,
+                "owner": "alice",
+                "contents": { "markdown": format!("# {}", id) },
+                "children": {}
+            }),
+        ));
+    }
+
+    /// a → b, dir has an entry for b
+    fn setup() -> StateProjector {
+        let mut state = StateProjector::new();
+        create(&mut state, "a", "markdown");
+        create(&mut state, "b", "markdown");
+        create(&mut state, "dir", "directory");
+        state.apply_event(&event(
+            "a",
+            "core.link",
+            serde_json::json!({ "children": { RELATION_IMPLEMENT: ["b"] } }),
+        ));
+        state.apply_event(&event(
+            "dir",
+            "directory.write",
+            serde_json::json!({ "contents": { "entries": {
+                "b.md": { "id": "b", "type": "file", "source": "outline" }
+            } } }),
+        ));
+        state
+    }
+
+    /// Apply a delete of `id` the way the engine does: delete, then unlink and entry cleanup.
+    fn delete(state: &mut StateProjector, id: &str) {
+        state.apply_event(&event(id, "core.delete", serde_json::json!({})));
+        state.apply_event(&event(
+            "a",
+            "core.unlink",
+            serde_json::json!({ "children": {} }),
+        ));
+        state.apply_event(&event(
+            "dir",
+            "directory.write",
+            serde_json::json!({ "contents": { "entries": {} } }),
+        ));
+    }
+
+    #[test]
+    fn test_delete_moves_block_to_trash() {
+        let mut state = setup();
+        delete(&mut state, "b");
+
+        assert!(state.get_block("b").is_none());
+        let trashed = state.get_trashed("b").unwrap();
+        assert_eq!(trashed.block.contents["markdown"], "# b");
+        assert_eq!(trashed.parents, vec!["a".to_string()]);
+        assert_eq!(trashed.directory_entries.len(), 1);
+        assert_eq!(trashed.directory_entries[0].path, "b.md");
+        assert_eq!(trashed.deleted_by, "alice");
+    }
+
+    #[test]
+    fn test_restore_reattaches_parents_and_entries() {
+        let mut state = setup();
+        delete(&mut state, "b");
+
+        let restored = state.plan_restore("b", false).unwrap();
+        let followups = state.restore_followup_events(&restored, "alice");
+        assert_eq!(followups.len(), 2);
+
+        state.apply_event(&event("b", "core.restore", serde_json::json!({})));
+        for e in &followups {
+            state.apply_event(e);
+        }
+
+        assert!(state.get_trashed("b").is_none());
+        assert_eq!(state.get_block("b").unwrap().contents["markdown"], "# b");
+        assert_eq!(state.get_children("a"), vec!["b".to_string()]);
+        assert_eq!(state.get_parents("b"), vec!["a".to_string()]);
+        assert!(state.get_block("dir").unwrap().contents["entries"]
+            .get("b.md")
+            .is_some());
+    }
+
+    #[test]
+    fn test_restore_skips_missing_parent() {
+        let mut state = setup();
+        delete(&mut state, "b");
+        state.apply_event(&event("a", "core.delete", serde_json::json!({})));
+
+        let restored = state.plan_restore("b", false).unwrap();
+        let followups = state.restore_followup_events(&restored, "alice");
+        // Only the directory entry comes back; parent a is itself in the trash
+        assert_eq!(followups.len(), 1);
+        assert!(followups[0].attribute.ends_with("/directory.write"));
+    }
+
+    #[test]
+    fn test_plan_restore_includes_cascaded() {
+        let mut state = setup();
+        state.apply_event(&event("a", "core.delete", serde_json::json!({})));
+        state.apply_event(&event(
+            "b",
+            "core.delete",
+            serde_json::json!({ "cascade_from": "a" }),
+        ));
+
+        assert_eq!(
+            state.plan_restore("a", false).unwrap(),
+            vec!["a".to_string()]
+        );
+        assert_eq!(
+            state.plan_restore("a", true).unwrap(),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert!(state.plan_restore("missing", false).is_err());
+    }
+
+    #[test]
+    fn test_purge_removes_from_trash() {
+        let mut state = setup();
+        delete(&mut state, "b");
+        state.apply_event(&event("b", "core.purge", serde_json::json!({})));
+
+        assert!(state.get_trashed("b").is_none());
+        assert!(state.get_block("b").is_none());
+    }
+}
@@ -0,0 +1,774 @@
+//! Export and import of a project's history as a plain git repository.
+//!
+//! Export replays the event log and writes one commit per command, leaving
+//! out the blocks the exporting editor may not read. Each commit is authored
+//! by the command's editor (`{name} <{editor_id}@elfiee>`), dated with the
+//! command's `created_at`, and lists its events as trailers.
+//! Files are laid out as `checkout_workspace` writes them, one folder per
+//! directory block:
+//!
+//! ```text
+//! {directory block name}/{entry path}   markdown, code and asset blocks
+//! {block name}                          blocks not in any directory
+//! ```
+//!
+//! Import goes the other way: the first-parent history of `HEAD` becomes a
+//! directory block named after the repository, and every commit becomes the
+//! commands that turn the previous tree into its tree, issued by an editor
+//! for the commit's author. Authors are granted what replaying needs on the
+//! imported directory block and its files, and nothing else.
+//!
+//! Both directions run the `git` command-line tool.
+
+use super::may_read;
+use crate::engine::{split_commands, EngineHandle, StateProjector};
+use crate::models::{Block, Command, Event, SCOPE_SUBTREE_PREFIX};
+use crate::utils::{infer_block_type, read_asset};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Domain of the e-mail addresses given to editors in exported commits
+const EMAIL_DOMAIN: &str = "elfiee";
+
+/// Capabilities commit authors get on an imported repository's blocks:
+/// adding files and folders, writing them and deleting them
+const AUTHOR_CAPABILITIES: &[&str] = &[
+    "directory.create",
+    "markdown.write",
+    "code.write",
+    "core.delete",
+];
+
+/// Capabilities the importer gets on every block, as the imported files
+/// belong to their authors
+const READ_CAPABILITIES: &[&str] = &["core.read", "markdown.read", "code.read"];
+
+/// Result of a git export.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct GitExportReport {
+    /// Commits written (one per command)
+    pub commits: usize,
+    /// Files in the last commit
+    pub files: usize,
+    /// Names of blocks left out because the editor may not read them
+    pub skipped: Vec<String>,
+}
+
+/// Result of a git import.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct GitImportReport {
+    /// Commits replayed
+    pub commits: usize,
+    /// Blocks created for files
+    pub blocks: usize,
+    /// Files left out, with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Run git in `repo` and return its standard output.
+fn git(repo: &Path, args: &[&str], env: &[(&str, &str)]) -> Result<Vec<u8>, String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["-c", "commit.gpgsign=false", "-c", "core.autocrlf=false"])
+        .args(args)
+        .envs(env.iter().copied())
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Export the history of the blocks `editor_id` may read as a new git
+/// repository at `repo`.
+///
+/// `repo` must not exist or be empty. `archive_dir` is where the archive is
+/// extracted; without it, asset blocks are left out.
+pub async fn export_git(
+    handle: &EngineHandle,
+    editor_id: &str,
+    archive_dir: Option<&Path>,
+    repo: &Path,
+) -> Result<GitExportReport, String> {
+    let (events, skipped) = readable_history(handle, editor_id).await?;
+    let mut report = write_history(&events, archive_dir, repo)?;
+    report.skipped = skipped;
+    Ok(report)
+}
+
+/// The events `editor_id` may see: those about blocks they may read (deleted
+/// blocks included), and those about no block at all, like editors and roles.
+///
+/// Also returns the names of the blocks left out, sorted.
+async fn readable_history(
+    handle: &EngineHandle,
+    editor_id: &str,
+) -> Result<(Vec<Event>, Vec<String>), String> {
+    let events = handle.get_all_events().await?;
+    let current = handle.get_all_blocks().await;
+
+    let mut checked: HashSet<&str> = HashSet::new();
+    let mut unreadable: HashSet<&str> = HashSet::new();
+    let mut skipped = Vec::new();
+    for event in events
+        .iter()
+        .filter(|e| e.attribute.ends_with("/core.create"))
+    {
+        let block_id = event.entity.as_str();
+        if !checked.insert(block_id) {
+            continue;
+        }
+        // Blocks no longer in the project are known by their creation event
+        let (name, block_type) = match current.get(block_id) {
+            Some(block) => (block.name.as_str(), block.block_type.as_str()),
+            None => (
+                event.value["name"].as_str().unwrap_or(block_id),
+                event.value["type"].as_str().unwrap_or_default(),
+            ),
+        };
+        if !may_read(handle, editor_id, block_id, block_type).await {
+            unreadable.insert(block_id);
+            skipped.push(name.to_string());
+        }
+    }
+    skipped.sort();
+
+    let readable = events
+        .iter()
+        .filter(|e| !unreadable.contains(e.entity.as_str()))
+        .cloned()
+        .collect();
+    Ok((readable, skipped))
+}
+
+/// Write `events` as a new git repository at `repo`, one commit per command.
+fn write_history(
+    events: &[Event],
+    archive_dir: Option<&Path>,
+    repo: &Path,
+) -> Result<GitExportReport, String> {
+    if repo.exists()
+        && fs::read_dir(repo)
+            .map_err(|e| format!("Failed to read {}: {}", repo.display(), e))?
+            .next()
+            .is_some()
+    {
+        return Err(format!("{} is not empty", repo.display()));
+    }
+    fs::create_dir_all(repo).map_err(|e| format!("Failed to create {}: {}", repo.display(), e))?;
+    git(repo, &["init", "-q"], &[])?;
+
+    let mut report = GitExportReport::default();
+    let mut state = StateProjector::new();
+    let mut worktree = Worktree::new(repo, archive_dir);
+
+    for command in split_commands(events) {
+        // The target's name before the command, in case the command removes it
+        let entity = command[0].entity.as_str();
+        let before = state.get_block(entity).map(|b| b.name.clone());
+        for event in command {
+            state.apply_event(event);
+        }
+        worktree.update(&state, command)?;
+
+        let (editor_id, cap_id) = command[0]
+            .attribute
+            .split_once('/')
+            .unwrap_or(("", command[0].attribute.as_str()));
+        let target = state
+            .get_block(entity)
+            .map(|b| b.name.clone())
+            .or(before)
+            .unwrap_or_else(|| entity.to_string());
+        let mut message = format!("{} {}\n\nElfiee-Editor: {}\n", cap_id, target, editor_id);
+        for event in command {
+            message.push_str(&format!("Elfiee-Event: {}\n", event.event_id));
+        }
+
+        let name = state
+            .editors
+            .get(editor_id)
+            .map_or(editor_id, |editor| editor.name.as_str());
+        let name = if name.is_empty() { "unknown" } else { name };
+        let email = editor_email(editor_id);
+        let date = command[0].created_at.as_str();
+        let env = [
+            ("GIT_AUTHOR_NAME", name),
+            ("GIT_AUTHOR_EMAIL", email.as_str()),
+            ("GIT_AUTHOR_DATE", date),
+            ("GIT_COMMITTER_NAME", name),
+            ("GIT_COMMITTER_EMAIL", email.as_str()),
+            ("GIT_COMMITTER_DATE", date),
+        ];
+        git(repo, &["add", "-A"], &[])?;
+        git(
+            repo,
+            &["commit", "-q", "--allow-empty", "-m", message.as_str()],
+            &env,
+        )?;
+        report.commits += 1;
+    }
+
+    report.files = worktree.paths.len();
+    Ok(report)
+}
+
+/// E-mail address of an editor in exported commits.
+///
+/// Editors imported from git keep their author address as id; any other id
+/// becomes `{id}@elfiee`, keeping only what is safe in an address.
+fn editor_email(editor_id: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "._-+".contains(c);
+    if let Some((local, domain)) = editor_id.split_once('@') {
+        if !local.is_empty() && !domain.is_empty() && local.chars().chain(domain.chars()).all(safe)
+        {
+            return editor_id.to_string();
+        }
+    }
+    let local: String = editor_id.chars().filter(|&c| safe(c)).collect();
+    let local = if local.is_empty() { "editor" } else { &local };
+    format!("{}@{}", local, EMAIL_DOMAIN)
+}
+
+/// A relative path made of normal components only, not inside `.git`.
+fn safe_path(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let normal = path.components().all(|c| matches!(c, Component::Normal(_)));
+    let in_git = path
+        .components()
+        .next()
+        .is_some_and(|c| c.as_os_str() == ".git");
+    (normal && !in_git && path.components().next().is_some()).then(|| path.to_path_buf())
+}
+
+/// Bytes a block is checked out as (None for blocks without a file form).
+fn file_contents(block: &Block, archive_dir: Option<&Path>) -> Option<Vec<u8>> {
+    match block.block_type.as_str() {
+        // Same field order as checkout: `text`, then `markdown`
+        "markdown" | "code" => Some(
+            block.contents["text"]
+                .as_str()
+                .or(block.contents["markdown"].as_str())
+                .unwrap_or_default()
+                .as_bytes()
+                .to_vec(),
+        ),
+        "asset" => archive_dir.and_then(|dir| read_asset(dir, &block.contents).ok()),
+        _ => None,
+    }
+}
+
+/// Capabilities that change a block's file but not where it is checked out
+const CONTENT_CAPABILITIES: &[&str] = &["markdown.write", "code.write"];
+
+/// The files of an exported repository, kept in step with the replayed state.
+///
+/// Contents are read once per block and again only after a command touches
+/// the block, so assets aren't re-read for every commit.
+struct Worktree<'a> {
+    repo: &'a Path,
+    archive_dir: Option<&'a Path>,
+    /// Checked-out files and the block each one holds
+    paths: BTreeMap<PathBuf, String>,
+    /// Contents of the blocks read so far (None for blocks without a file form)
+    contents: HashMap<String, Option<Vec<u8>>>,
+}
+
+impl<'a> Worktree<'a> {
+    fn new(repo: &'a Path, archive_dir: Option<&'a Path>) -> Self {
+        Self {
+            repo,
+            archive_dir,
+            paths: BTreeMap::new(),
+            contents: HashMap::new(),
+        }
+    }
+
+    /// Bring the files up to date with `state` after `command` was applied.
+    fn update(&mut self, state: &StateProjector, command: &[Event]) -> Result<(), String> {
+        let touched: HashSet<&str> = command.iter().map(|e| e.entity.as_str()).collect();
+        for id in &touched {
+            self.contents.remove(*id);
+        }
+
+        // Writing to checked-out blocks leaves every file where it is
+        let moves_files = command.iter().any(|event| {
+            let cap_id = event.attribute.split_once('/').map_or("", |(_, cap)| cap);
+            !CONTENT_CAPABILITIES.contains(&cap_id)
+                || !self.paths.values().any(|id| *id == event.entity)
+        });
+        let old = if moves_files {
+            let next = self.place(state);
+            std::mem::replace(&mut self.paths, next)
+        } else {
+            self.paths.clone()
+        };
+
+        for path in old.keys().filter(|path| !self.paths.contains_key(*path)) {
+            self.remove(path)?;
+        }
+        let writes: Vec<(PathBuf, String)> = self
+            .paths
+            .iter()
+            .filter(|(path, id)| old.get(*path) != Some(*id) || touched.contains(id.as_str()))
+            .map(|(path, id)| (path.clone(), id.clone()))
+            .collect();
+        for (path, id) in writes {
+            let Some(block) = state.get_block(&id) else {
+                continue;
+            };
+            let bytes = self.contents(block).unwrap_or_default().to_vec();
+            let full = self.repo.join(&path);
+            if let Some(parent) = full.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            fs::write(&full, bytes)
+                .map_err(|e| format!("Failed to write {}: {}", full.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Contents of a block, read on first use.
+    fn contents(&mut self, block: &Block) -> Option<&[u8]> {
+        let archive_dir = self.archive_dir;
+        self.contents
+            .entry(block.block_id.clone())
+            .or_insert_with(|| file_contents(block, archive_dir))
+            .as_deref()
+    }
+
+    /// Remove a file, and the folders it leaves empty.
+    fn remove(&self, path: &Path) -> Result<(), String> {
+        let full = self.repo.join(path);
+        fs::remove_file(&full)
+            .map_err(|e| format!("Failed to remove {}: {}", full.display(), e))?;
+        for parent in path
+            .ancestors()
+            .skip(1)
+            .filter(|p| !p.as_os_str().is_empty())
+        {
+            let dir = self.repo.join(parent);
+            let empty = fs::read_dir(&dir)
+                .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+                .next()
+                .is_none();
+            if !empty {
+                break;
+            }
+            fs::remove_dir(&dir)
+                .map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Where each block of the current state is checked out.
+    fn place(&mut self, state: &StateProjector) -> BTreeMap<PathBuf, String> {
+        let mut paths = BTreeMap::new();
+        let mut placed: HashSet<&str> = HashSet::new();
+
+        let mut dirs: Vec<&Block> = state
+            .blocks
+            .values()
+            .filter(|b| b.block_type == "directory")
+            .collect();
+        dirs.sort_by(|a, b| (&a.name, &a.block_id).cmp(&(&b.name, &b.block_id)));
+        for dir in dirs {
+            let Some(root) = safe_path(&dir.name) else {
+                continue;
+            };
+            let Some(entries) = dir.contents["entries"].as_object() else {
+                continue;
+            };
+            for (virtual_path, entry) in entries {
+                let id = entry["id"].as_str().unwrap_or("");
+                let (Some(block), Some(path)) = (state.get_block(id), safe_path(virtual_path))
+                else {
+                    continue;
+                };
+                if entry["type"] != "file" || self.contents(block).is_none() {
+                    continue;
+                }
+                let path = root.join(path);
+                if !collides(&paths, &path) {
+                    paths.insert(path, id.to_string());
+                    placed.insert(id);
+                }
+            }
+        }
+
+        let mut loose: Vec<&Block> = state
+            .blocks
+            .values()
+            .filter(|b| !placed.contains(b.block_id.as_str()))
+            .collect();
+        loose.sort_by(|a, b| (&a.name, &a.block_id).cmp(&(&b.name, &b.block_id)));
+        for block in loose {
+            if self.contents(block).is_none() {
+                continue;
+            }
+            let Some(mut path) = safe_path(&block.name) else {
+                continue;
+            };
+            if collides(&paths, &path) {
+                let short = &block.block_id[..block.block_id.len().min(8)];
+                path = PathBuf::from(format!("{}-{}", block.name, short));
+                if collides(&paths, &path) {
+                    continue;
+                }
+            }
+            paths.insert(path, block.block_id.clone());
+        }
+        paths
+    }
+}
+
+/// Whether `path` can't be checked out next to `paths`: it is taken, a file
+/// lies inside it, or it lies inside a file.
+fn collides(paths: &BTreeMap<PathBuf, String>, path: &Path) -> bool {
+    // Paths order component-wise, so anything inside `path` comes right after it
+    let inside = paths
+        .range(path.to_path_buf()..)
+        .next()
+        .is_some_and(|(other, _)| other.starts_with(path));
+    inside
+        || path
+            .ancestors()
+            .skip(1)
+            .any(|parent| paths.contains_key(parent))
+}
+
+/// A file of an imported repository and the block holding it.
+struct ImportedFile {
+    blob: String,
+    block_id: String,
+    block_type: String,
+}
+
+/// Import the first-parent history of `repo`'s `HEAD` into an engine.
+///
+/// Meant for a new project: `importer_id` becomes the project owner if the
+/// project has none and creates an editor for each commit author. Authors
+/// are granted [`AUTHOR_CAPABILITIES`] on the imported directory block and
+/// the files in it, as anyone with commit access could change any file of
+/// the repository; the rest of the project stays out of their reach. The
+/// importer is granted [`READ_CAPABILITIES`] on every block. Binary files are
+/// left out.
+pub async fn import_git(
+    handle: &EngineHandle,
+    repo: &Path,
+    importer_id: &str,
+) -> Result<GitImportReport, String> {
+    let commits = String::from_utf8_lossy(&git(
+        repo,
+        &["rev-list", "--reverse", "--first-parent", "HEAD"],
+        &[],
+    )?)
+    .lines()
+    .map(str::to_string)
+    .collect::<Vec<_>>();
+
+    let run = |editor: &str, cap_id: &str, block_id: &str, payload: serde_json::Value| {
+        let cmd = Command::new(
+            editor.to_string(),
+            cap_id.to_string(),
+            block_id.to_string(),
+            payload,
+        );
+        handle.process_command(cmd)
+    };
+
+    let mut editors: HashSet<String> = handle.get_all_editors().await.into_keys().collect();
+    if !editors.contains(importer_id) {
+        run(
+            importer_id,
+            "editor.create",
+            "",
+            serde_json::json!({ "editor_id": importer_id, "name": importer_id }),
+        )
+        .await?;
+        editors.insert(importer_id.to_string());
+    }
+
+    let repo_name = fs::canonicalize(repo)
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "repository".to_string());
+    let dir_id = run(
+        importer_id,
+        "core.create",
+        "",
+        serde_json::json!({ "name": repo_name, "block_type": "directory" }),
+    )
+    .await?[0]
+        .entity
+        .clone();
+
+    // Files are created by their authors; the importer keeps reading them,
+    // including the ones deleted later, which no longer are in the directory
+    for capability in READ_CAPABILITIES {
+        run(
+            importer_id,
+            "core.grant",
+            "",
+            serde_json::json!({
+                "target_editor": importer_id,
+                "capability": capability,
+                "target_block": "*",
+            }),
+        )
+        .await?;
+    }
+    let imported_scope = format!("{}{}", SCOPE_SUBTREE_PREFIX, dir_id);
+
+    let mut report = GitImportReport::default();
+    let mut files: HashMap<String, ImportedFile> = HashMap::new();
+    let mut skipped: BTreeMap<String, String> = BTreeMap::new();
+    let mut folders: HashSet<String> = HashSet::new();
+
+    for commit in &commits {
+        let author = String::from_utf8_lossy(&git(
+            repo,
+            &["show", "-s", "--format=%an%x00%ae", commit],
+            &[],
+        )?)
+        .trim_end()
+        .to_string();
+        let (name, email) = author.split_once('\0').unwrap_or((&author, ""));
+        let editor_id = match email.strip_suffix(&format!("@{}", EMAIL_DOMAIN)) {
+            Some(id) if !id.is_empty() => id.to_string(),
+            _ if !email.is_empty() => email.to_string(),
+            _ => name.to_string(),
+        };
+        if !editors.contains(&editor_id) {
+            run(
+                importer_id,
+                "editor.create",
+                "",
+                serde_json::json!({ "editor_id": editor_id, "name": name }),
+            )
+            .await?;
+            for capability in AUTHOR_CAPABILITIES {
+                run(
+                    importer_id,
+                    "core.grant",
+                    "",
+                    serde_json::json!({
+                        "target_editor": editor_id,
+                        "capability": capability,
+                        "target_block": imported_scope,
+                    }),
+                )
+                .await?;
+            }
+            editors.insert(editor_id.clone());
+        }
+        let editor = editor_id.as_str();
+
+        let tree = ls_tree(repo, commit)?;
+
+        // Files removed in this commit (or that can't be kept up to date)
+        let removed: Vec<String> = files
+            .keys()
+            .filter(|path| !tree.contains_key(*path))
+            .cloned()
+            .collect();
+        for path in removed {
+            let file = files.remove(&path).unwrap();
+            // Detach mode also removes the block's entry from the directory
+            run(editor, "core.delete", &file.block_id, serde_json::json!({})).await?;
+        }
+        skipped.retain(|path, _| tree.contains_key(path));
+
+        for (path, blob) in &tree {
+            if files.get(path).is_some_and(|file| &file.blob == blob) {
+                continue;
+            }
+            let bytes = git(repo, &["cat-file", "blob", blob], &[])?;
+            let extension = Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("");
+            let block_type = match infer_block_type(extension).as_deref() {
+                Some("markdown") => "markdown",
+                Some("asset") => {
+                    skipped.insert(path.clone(), "binary file".to_string());
+                    continue;
+                }
+                Some(_) => "code",
+                None => {
+                    skipped.insert(path.clone(), "unsupported file type".to_string());
+                    continue;
+                }
+            };
+            let Ok(content) = String::from_utf8(bytes) else {
+                skipped.insert(path.clone(), "binary file".to_string());
+                continue;
+            };
+
+            match files.get_mut(path) {
+                Some(file) => {
+                    let cap_id = format!("{}.write", file.block_type);
+                    run(
+                        editor,
+                        &cap_id,
+                        &file.block_id,
+                        serde_json::json!({ "content": content }),
+                    )
+                    .await?;
+                    file.blob = blob.clone();
+                }
+                None => {
+                    // Parent folders first, so the directory block has them as entries
+                    let mut folder = String::new();
+                    for segment in path.split('/').collect::<Vec<_>>().split_last().unwrap().1 {
+                        if !folder.is_empty() {
+                            folder.push('/');
+                        }
+                        folder.push_str(segment);
+                        if folders.insert(folder.clone()) {
+                            run(
+                                editor,
+                                "directory.create",
+                                &dir_id,
+                                serde_json::json!({
+                                    "path": folder,
+                                    "type": "directory",
+                                    "source": "outline",
+                                }),
+                            )
+                            .await?;
+                        }
+                    }
+                    let created = run(
+                        editor,
+                        "directory.create",
+                        &dir_id,
+                        serde_json::json!({
+                            "path": path,
+                            "type": "file",
+                            "source": "outline",
+                            "content": content,
+                            "block_type": block_type,
+                        }),
+                    )
+                    .await;
+                    let block_id = match created {
+                        Ok(events) => events
+                            .iter()
+                            .find(|e| e.attribute.ends_with("/core.create"))
+                            .map(|e| e.entity.clone()),
+                        Err(e) => {
+                            skipped.insert(path.clone(), e);
+                            continue;
+                        }
+                    };
+                    let Some(block_id) = block_id else {
+                        continue;
+                    };
+                    files.insert(
+                        path.clone(),
+                        ImportedFile {
+                            blob: blob.clone(),
+                            block_id,
+                            block_type: block_type.to_string(),
+                        },
+                    );
+                    skipped.remove(path);
+                    report.blocks += 1;
+                }
+            }
+        }
+        report.commits += 1;
+    }
+
+    report.skipped = skipped
+        .into_iter()
+        .map(|(path, reason)| format!("{}: {}", path, reason))
+        .collect();
+    Ok(report)
+}
+
+/// Files of a commit: path -> blob hash (symlinks and submodules left out).
+fn ls_tree(repo: &Path, commit: &str) -> Result<BTreeMap<String, String>, String> {
+    let output = git(repo, &["ls-tree", "-r", "-z", commit], &[])?;
+    let mut tree = BTreeMap::new();
+    for record in output.split(|&b| b == 0).filter(|r| !r.is_empty()) {
+        let record = String::from_utf8_lossy(record);
+        let Some((meta, path)) = record.split_once('\t') else {
+            continue;
+        };
+        let mut fields = meta.split(' ');
+        let (Some(mode), Some(kind), Some(hash)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if kind == "blob" && mode != "120000" {
+            tree.insert(path.to_string(), hash.to_string());
+        }
+    }
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(editor: &str, count: i64) -> Event {
+        Event::new(
+            "block".to_string(),
+            format!("{}/markdown.write", editor),
+            serde_json::json!({}),
+            HashMap::from([(editor.to_string(), count)]),
+        )
+    }
+
+    #[test]
+    fn test_commands_group_events_by_editor_and_clock() {
+        let events = vec![
+            event("alice", 1),
+            event("alice", 1),
+            event("alice", 2),
+            event("bob", 2),
+        ];
+        let sizes: Vec<usize> = split_commands(&events).iter().map(|c| c.len()).collect();
+        assert_eq!(sizes, vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn test_editor_email() {
+        assert_eq!(editor_email("alice"), "alice@elfiee");
+        assert_eq!(editor_email("bob@example.com"), "bob@example.com");
+        assert_eq!(editor_email("a <b>"), "ab@elfiee");
+        assert_eq!(editor_email(""), "editor@elfiee");
+    }
+
+    #[test]
+    fn test_safe_path() {
+        assert_eq!(safe_path("docs/a.md"), Some(PathBuf::from("docs/a.md")));
+        assert_eq!(safe_path("../a.md"), None);
+        assert_eq!(safe_path("/etc/passwd"), None);
+        assert_eq!(safe_path(".git/config"), None);
+        assert_eq!(safe_path(""), None);
+    }
+
+    #[test]
+    fn test_collides_with_files_and_folders() {
+        let paths = BTreeMap::from([(PathBuf::from("docs/a.md"), "a".to_string())]);
+        assert!(collides(&paths, Path::new("docs/a.md")));
+        // A file named like the folder, or a file inside the file
+        assert!(collides(&paths, Path::new("docs")));
+        assert!(collides(&paths, Path::new("docs/a.md/b.md")));
+        assert!(!collides(&paths, Path::new("docs-1234")));
+        assert!(!collides(&paths, Path::new("docs/b.md")));
+    }
+}
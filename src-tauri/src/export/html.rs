@@ -0,0 +1,399 @@
+//! Static HTML site export.
+//!
+//! Layout of the target directory:
+//!
+//! ```text
+//! index.html            every block, grouped by type
+//! style.css
+//! blocks/{id}.html      rendered contents and implement cross-links
+//! history/{id}.html     the block's events, oldest first
+//! assets/{id}/{name}    bytes of asset blocks
+//! ```
+//!
+//! Directory blocks' `entries` trees form the navigation shown on every page.
+//! Only blocks the exporting editor may read are exported.
+
+use super::markdown::{escape, render};
+use super::may_read;
+use crate::engine::EngineHandle;
+use crate::models::{Block, Editor, Event, RELATION_IMPLEMENT};
+use crate::utils::read_asset;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+const STYLE: &str = "\
+body { margin: 0; display: flex; font: 15px/1.5 system-ui, sans-serif; color: #222; }
+nav { width: 260px; min-height: 100vh; padding: 16px; background: #f6f6f4; border-right: 1px solid #ddd; }
+nav ul { list-style: none; padding-left: 14px; margin: 2px 0; }
+main { flex: 1; max-width: 860px; padding: 16px 32px; }
+a { color: #1a5fb4; text-decoration: none; }
+a:hover { text-decoration: underline; }
+pre { background: #f6f6f4; padding: 12px; overflow-x: auto; }
+pre.code .ln { display: inline-block; width: 3em; margin-right: 1em; color: #999; text-align: right; user-select: none; }
+.meta, .empty { color: #777; }
+table { border-collapse: collapse; width: 100%; }
+td, th { border-bottom: 1px solid #eee; padding: 4px 8px; text-align: left; vertical-align: top; }
+img { max-width: 100%; }
+";
+
+/// Result of a site export.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct SiteReport {
+    /// Blocks written as pages
+    pub blocks: usize,
+    /// Names of blocks left out because the editor may not read them
+    pub skipped: Vec<String>,
+}
+
+/// Export every block `editor_id` may read as a static site into `target`.
+///
+/// Existing files in `target` are overwritten; other files are left alone.
+pub async fn export_site(
+    handle: &EngineHandle,
+    editor_id: &str,
+    target: &Path,
+) -> Result<SiteReport, String> {
+    let mut report = SiteReport::default();
+    let mut blocks: BTreeMap<String, Block> = BTreeMap::new();
+    for (block_id, block) in handle.get_all_blocks().await {
+        if may_read(handle, editor_id, &block_id, &block.block_type).await {
+            blocks.insert(block_id, block);
+        } else {
+            report.skipped.push(block.name);
+        }
+    }
+    report.skipped.sort();
+
+    let events = handle.get_all_events().await?;
+    let editors = handle.get_all_editors().await;
+    let site = Site::new(&blocks, &editors);
+
+    for dir in ["blocks", "history", "assets"] {
+        fs::create_dir_all(target.join(dir))
+            .map_err(|e| format!("Failed to create {}: {}", target.join(dir).display(), e))?;
+    }
+    write(&target.join("style.css"), STYLE)?;
+    write(&target.join("index.html"), &site.index())?;
+
+    for block in blocks.values() {
+        let page = page_name(&block.block_id);
+        let contents = site.contents(block, target)?;
+        write(
+            &target.join("blocks").join(format!("{}.html", page)),
+            &site.block_page(block, &contents),
+        )?;
+        let history: Vec<&Event> = events
+            .iter()
+            .filter(|e| e.entity == block.block_id)
+            .collect();
+        write(
+            &target.join("history").join(format!("{}.html", page)),
+            &site.history_page(block, &history),
+        )?;
+        report.blocks += 1;
+    }
+
+    Ok(report)
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// File name for a block's pages (block ids are UUIDs, but come from events).
+fn page_name(block_id: &str) -> String {
+    block_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A directory block's `entries`, nested by path segment.
+#[derive(Default)]
+struct Tree {
+    children: BTreeMap<String, Tree>,
+    block_id: Option<String>,
+}
+
+impl Tree {
+    fn from_entries(entries: &serde_json::Map<String, serde_json::Value>) -> Self {
+        let mut tree = Tree::default();
+        for (path, entry) in entries {
+            let mut node = &mut tree;
+            for segment in path.split('/').filter(|s| !s.is_empty()) {
+                node = node.children.entry(segment.to_string()).or_default();
+            }
+            if entry["type"] == "file" {
+                node.block_id = entry["id"].as_str().map(str::to_string);
+            }
+        }
+        tree
+    }
+
+    /// Nested lists; files link to their block page if it was exported.
+    fn render(&self, root: &str, blocks: &BTreeMap<String, Block>) -> String {
+        if self.children.is_empty() {
+            return String::new();
+        }
+        let mut out = String::from("<ul>");
+        for (name, node) in &self.children {
+            let label = match node
+                .block_id
+                .as_deref()
+                .filter(|id| blocks.contains_key(*id))
+            {
+                Some(id) => format!(
+                    "<a href=\"{}blocks/{}.html\">{}</a>",
+                    root,
+                    page_name(id),
+                    escape(name)
+                ),
+                None => escape(name),
+            };
+            out.push_str(&format!("<li>{}{}</li>", label, node.render(root, blocks)));
+        }
+        out.push_str("</ul>");
+        out
+    }
+}
+
+/// What every page of the export needs.
+struct Site<'a> {
+    blocks: &'a BTreeMap<String, Block>,
+    editors: &'a HashMap<String, Editor>,
+    /// block id -> exported blocks with an `implement` relation to it
+    parents: HashMap<&'a str, Vec<&'a Block>>,
+}
+
+impl<'a> Site<'a> {
+    fn new(blocks: &'a BTreeMap<String, Block>, editors: &'a HashMap<String, Editor>) -> Self {
+        let mut parents: HashMap<&str, Vec<&Block>> = HashMap::new();
+        for block in blocks.values() {
+            for child in block.children.get(RELATION_IMPLEMENT).into_iter().flatten() {
+                parents.entry(child.as_str()).or_default().push(block);
+            }
+        }
+        Self {
+            blocks,
+            editors,
+            parents,
+        }
+    }
+
+    fn editor_name(&self, editor_id: &str) -> String {
+        self.editors
+            .get(editor_id)
+            .map_or(editor_id, |editor| editor.name.as_str())
+            .to_string()
+    }
+
+    fn link(&self, root: &str, block: &Block) -> String {
+        format!(
+            "<a href=\"{}blocks/{}.html\">{}</a>",
+            root,
+            page_name(&block.block_id),
+            escape(&block.name)
+        )
+    }
+
+    /// The navigation: one tree per directory block.
+    fn nav(&self, root: &str) -> String {
+        let mut out = format!("<p><a href=\"{}index.html\">Index</a></p>", root);
+        for block in self.blocks.values().filter(|b| b.block_type == "directory") {
+            let entries = block.contents["entries"].as_object();
+            out.push_str(&format!(
+                "<p>{}</p>{}",
+                self.link(root, block),
+                entries.map_or(String::new(), |entries| Tree::from_entries(entries)
+                    .render(root, self.blocks))
+            ));
+        }
+        out
+    }
+
+    fn page(&self, title: &str, root: &str, body: &str) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<link rel=\"stylesheet\" href=\"{root}style.css\">\n\
+             </head>\n<body>\n<nav>{nav}</nav>\n<main>\n{body}</main>\n</body>\n</html>\n",
+            title = escape(title),
+            root = root,
+            nav = self.nav(root),
+            body = body
+        )
+    }
+
+    fn index(&self) -> String {
+        let mut by_type: BTreeMap<&str, Vec<&Block>> = BTreeMap::new();
+        for block in self.blocks.values() {
+            by_type.entry(&block.block_type).or_default().push(block);
+        }
+        let mut body = String::from("<h1>Index</h1>\n");
+        for (block_type, mut blocks) in by_type {
+            blocks.sort_by(|a, b| a.name.cmp(&b.name));
+            body.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape(block_type)));
+            for block in blocks {
+                body.push_str(&format!("<li>{}</li>\n", self.link("", block)));
+            }
+            body.push_str("</ul>\n");
+        }
+        self.page("Index", "", &body)
+    }
+
+    /// HTML for a block's contents; asset bytes are copied to `assets/{id}/`.
+    fn contents(&self, block: &Block, target: &Path) -> Result<String, String> {
+        let text = |key: &str| block.contents[key].as_str().unwrap_or("").to_string();
+        Ok(match block.block_type.as_str() {
+            "markdown" => render(&text("markdown")),
+            "code" => {
+                let language = Path::new(&block.name)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or("");
+                let mut out = format!(
+                    "<pre class=\"code\"><code class=\"language-{}\">",
+                    escape(language)
+                );
+                for (n, line) in text("text").lines().enumerate() {
+                    out.push_str(&format!(
+                        "<span class=\"ln\">{}</span>{}\n",
+                        n + 1,
+                        escape(line)
+                    ));
+                }
+                out.push_str("</code></pre>\n");
+                out
+            }
+            "directory" => match block.contents["entries"].as_object() {
+                Some(entries) => Tree::from_entries(entries).render("../", self.blocks),
+                None => String::new(),
+            },
+            "asset" => self.asset(block, target)?,
+            other => format!(
+                "<p class=\"empty\">No preview for {} blocks.</p>\n",
+                escape(other)
+            ),
+        })
+    }
+
+    fn asset(&self, block: &Block, target: &Path) -> Result<String, String> {
+        let archive_dir = block.contents["_block_dir"]
+            .as_str()
+            .and_then(|dir| Path::new(dir).parent());
+        let Some(archive_dir) = archive_dir else {
+            return Ok("<p class=\"empty\">Asset not available.</p>\n".to_string());
+        };
+        let bytes = read_asset(archive_dir, &block.contents)?;
+
+        let file_name = Path::new(&block.name)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .filter(|n| !n.is_empty() && *n != "." && *n != "..")
+            .unwrap_or("asset");
+        let dir = target.join("assets").join(page_name(&block.block_id));
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        fs::write(dir.join(file_name), &bytes)
+            .map_err(|e| format!("Failed to write asset {}: {}", block.name, e))?;
+
+        let href = format!(
+            "../assets/{}/{}",
+            page_name(&block.block_id),
+            escape(file_name)
+        );
+        let mime = block.contents["mime"].as_str().unwrap_or("");
+        Ok(if mime.starts_with("image/") {
+            format!(
+                "<p><img src=\"{}\" alt=\"{}\"></p>\n",
+                href,
+                escape(&block.name)
+            )
+        } else {
+            format!(
+                "<p><a href=\"{}\">Download {}</a> ({}, {} bytes)</p>\n",
+                href,
+                escape(file_name),
+                escape(mime),
+                bytes.len()
+            )
+        })
+    }
+
+    fn block_page(&self, block: &Block, contents: &str) -> String {
+        let mut body = format!(
+            "<h1>{}</h1>\n<p class=\"meta\">{} block, owned by {} · \
+             <a href=\"../history/{}.html\">History</a></p>\n{}",
+            escape(&block.name),
+            escape(&block.block_type),
+            escape(&self.editor_name(&block.owner)),
+            page_name(&block.block_id),
+            contents
+        );
+
+        let implements: Vec<&Block> = block
+            .children
+            .get(RELATION_IMPLEMENT)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.blocks.get(id))
+            .collect();
+        let implemented_by = self
+            .parents
+            .get(block.block_id.as_str())
+            .cloned()
+            .unwrap_or_default();
+        for (title, related) in [
+            ("Implements", implements),
+            ("Implemented by", implemented_by),
+        ] {
+            if related.is_empty() {
+                continue;
+            }
+            body.push_str(&format!("<h2>{}</h2>\n<ul>\n", title));
+            for other in related {
+                body.push_str(&format!("<li>{}</li>\n", self.link("../", other)));
+            }
+            body.push_str("</ul>\n");
+        }
+
+        self.page(&block.name, "../", &body)
+    }
+
+    fn history_page(&self, block: &Block, events: &[&Event]) -> String {
+        let mut body = format!(
+            "<h1>History of {}</h1>\n<table>\n<tr><th>Time</th><th>Editor</th>\
+             <th>Capability</th><th>Value</th></tr>\n",
+            self.link("../", block)
+        );
+        for event in events {
+            let (editor_id, cap_id) = event
+                .attribute
+                .split_once('/')
+                .unwrap_or(("", event.attribute.as_str()));
+            let mut value = event.value.clone();
+            if let Some(contents) = value.get_mut("contents").and_then(|c| c.as_object_mut()) {
+                contents.remove("_block_dir");
+            }
+            let value = serde_json::to_string_pretty(&value).unwrap_or_default();
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td>\
+                 <td><details><summary>show</summary><pre>{}</pre></details></td></tr>\n",
+                escape(&event.created_at),
+                escape(&self.editor_name(editor_id)),
+                escape(cap_id),
+                escape(&value)
+            ));
+        }
+        body.push_str("</table>\n");
+        self.page(&format!("History of {}", block.name), "../", &body)
+    }
+}
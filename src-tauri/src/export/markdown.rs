@@ -0,0 +1,300 @@
+//! A small Markdown to HTML renderer for the site export.
+//!
+//! Covers what specs are written with: ATX headings, paragraphs, fenced code,
+//! block quotes, flat lists, rules, and inline code, emphasis, links and
+//! images. Everything else comes out as escaped text.
+
+/// Escape text for use in HTML content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Render a Markdown document to an HTML fragment.
+pub fn render(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut list: Option<&str> = None;
+    let mut lines = markdown.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        // Fenced code block: copy lines verbatim up to the closing fence
+        if let Some(lang) = trimmed.strip_prefix("```") {
+            flush_paragraph(&mut out, &mut paragraph);
+            close_list(&mut out, &mut list);
+            let mut code = String::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    break;
+                }
+                code.push_str(line);
+                code.push('\n');
+            }
+            let class = match lang.trim() {
+                "" => String::new(),
+                lang => format!(" class=\"language-{}\"", escape(lang)),
+            };
+            out.push_str(&format!(
+                "<pre><code{}>{}</code></pre>\n",
+                class,
+                escape(&code)
+            ));
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut out, &mut paragraph);
+            close_list(&mut out, &mut list);
+            continue;
+        }
+
+        if let Some((level, text)) = heading(trimmed) {
+            flush_paragraph(&mut out, &mut paragraph);
+            close_list(&mut out, &mut list);
+            out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline(text)));
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            flush_paragraph(&mut out, &mut paragraph);
+            close_list(&mut out, &mut list);
+            out.push_str("<hr>\n");
+            continue;
+        }
+
+        if let Some(quoted) = trimmed.strip_prefix('>') {
+            flush_paragraph(&mut out, &mut paragraph);
+            close_list(&mut out, &mut list);
+            let mut quote = vec![quoted.trim_start()];
+            while let Some(next) = lines
+                .peek()
+                .copied()
+                .and_then(|l| l.trim().strip_prefix('>'))
+            {
+                quote.push(next.trim_start());
+                lines.next();
+            }
+            out.push_str(&format!(
+                "<blockquote>\n{}</blockquote>\n",
+                render(&quote.join("\n"))
+            ));
+            continue;
+        }
+
+        if let Some((tag, item)) = list_item(trimmed) {
+            flush_paragraph(&mut out, &mut paragraph);
+            if list != Some(tag) {
+                close_list(&mut out, &mut list);
+                out.push_str(&format!("<{}>\n", tag));
+                list = Some(tag);
+            }
+            out.push_str(&format!("<li>{}</li>\n", inline(item)));
+            continue;
+        }
+
+        close_list(&mut out, &mut list);
+        paragraph.push(trimmed);
+    }
+
+    flush_paragraph(&mut out, &mut paragraph);
+    close_list(&mut out, &mut list);
+    out
+}
+
+fn flush_paragraph(out: &mut String, paragraph: &mut Vec<&str>) {
+    if !paragraph.is_empty() {
+        out.push_str(&format!("<p>{}</p>\n", inline(&paragraph.join("\n"))));
+        paragraph.clear();
+    }
+}
+
+fn close_list(out: &mut String, list: &mut Option<&str>) {
+    if let Some(tag) = list.take() {
+        out.push_str(&format!("</{}>\n", tag));
+    }
+}
+
+/// `# Title` -> (1, "Title")
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let text = &line[level..];
+    if (1..=6).contains(&level) && (text.is_empty() || text.starts_with(' ')) {
+        Some((level, text.trim().trim_end_matches('#').trim_end()))
+    } else {
+        None
+    }
+}
+
+/// `---`, `***` or `___`
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&c| compact.chars().all(|x| x == c))
+}
+
+/// `- item` -> ("ul", "item"), `1. item` -> ("ol", "item")
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some(("ul", item));
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        if let Some(item) = line[digits..].strip_prefix(". ") {
+            return Some(("ol", item));
+        }
+    }
+    None
+}
+
+/// Render inline Markdown: code spans, links, images and emphasis.
+fn inline(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                out.push_str(&format!("<code>{}</code>", escape(&rest[1..1 + end])));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+
+        let image = rest.starts_with("![");
+        if image || c == '[' {
+            let start = if image { 2 } else { 1 };
+            if let Some((label, url, len)) = link(&rest[start..]) {
+                let url = safe_url(url);
+                if image {
+                    out.push_str(&format!(
+                        "<img src=\"{}\" alt=\"{}\">",
+                        escape(url),
+                        escape(label)
+                    ));
+                } else {
+                    out.push_str(&format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape(url),
+                        inline(label)
+                    ));
+                }
+                rest = &rest[start + len..];
+                continue;
+            }
+        }
+
+        // `_` only emphasizes at the start of a word, so snake_case stays as it is
+        let word_start = !text[..text.len() - rest.len()]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        let strong = ["**", "__"]
+            .into_iter()
+            .filter(|marker| word_start || marker.starts_with('*'))
+            .find_map(|marker| delimited(rest, marker).map(|inner| (marker, inner)));
+        if let Some((marker, inner)) = strong {
+            out.push_str(&format!("<strong>{}</strong>", inline(inner)));
+            rest = &rest[inner.len() + 2 * marker.len()..];
+            continue;
+        }
+        if c == '*' || (c == '_' && word_start) {
+            if let Some(inner) = delimited(rest, &rest[..1]) {
+                out.push_str(&format!("<em>{}</em>", inline(inner)));
+                rest = &rest[inner.len() + 2..];
+                continue;
+            }
+        }
+
+        out.push_str(&escape(&rest[..c.len_utf8()]));
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// `label](url)...` -> (label, url, length consumed)
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let close = text.find("](")?;
+    let end = text[close + 2..].find(')')?;
+    let label = &text[..close];
+    let url = text[close + 2..close + 2 + end].trim();
+    Some((label, url, close + 3 + end))
+}
+
+/// `{marker}inner{marker}...` -> inner, if it is not empty or padded with spaces
+fn delimited<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    let body = text.strip_prefix(marker)?;
+    let end = body.find(marker)?;
+    let inner = &body[..end];
+    (!inner.is_empty() && !inner.starts_with(' ') && !inner.ends_with(' ')).then_some(inner)
+}
+
+/// Drop `javascript:` and similar URLs, which would run in the exported page.
+fn safe_url(url: &str) -> &str {
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    match scheme.as_deref() {
+        Some("http") | Some("https") | Some("mailto") | None => url,
+        Some(scheme) if scheme.contains('/') || scheme.contains('#') => url,
+        _ => "#",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_elements() {
+        let html =
+            render("# Spec\n\nFirst line\nsecond line\n\n- a\n- b\n\n1. one\n\n---\n\n> quoted");
+        assert_eq!(
+            html,
+            "<h1>Spec</h1>\n<p>First line\nsecond line</p>\n<ul>\n<li>a</li>\n<li>b</li>\n</ul>\n\
+             <ol>\n<li>one</li>\n</ol>\n<hr>\n<blockquote>\n<p>quoted</p>\n</blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn test_fenced_code_is_escaped_verbatim() {
+        let html = render("```rust\nlet x = a < b && **c**;\n```\nafter");
+        assert_eq!(
+            html,
+            "<pre><code class=\"language-rust\">let x = a &lt; b &amp;&amp; **c**;\n</code></pre>\n\
+             <p>after</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_elements() {
+        assert_eq!(
+            inline("**bold** and *em* with `a<b` [link](https://x.y) ![img](a.png)"),
+            "<strong>bold</strong> and <em>em</em> with <code>a&lt;b</code> \
+             <a href=\"https://x.y\">link</a> <img src=\"a.png\" alt=\"img\">"
+        );
+        assert_eq!(inline("a * b * c"), "a * b * c");
+        assert_eq!(inline("snake_case_name"), "snake_case_name");
+        assert_eq!(
+            inline("_em_ and __strong__"),
+            "<em>em</em> and <strong>strong</strong>"
+        );
+        assert_eq!(inline("[x](javascript:alert(1))"), "<a href=\"#\">x</a>)");
+        assert_eq!(inline("<script>"), "&lt;script&gt;");
+    }
+}
@@ -0,0 +1,36 @@
+//! Exports of a project for people who don't run Elfiee.
+//!
+//! - `html`: a static site with a page and a history page per block
+//! - `git`: a git repository with a commit per command, and the way back
+
+pub mod git;
+pub mod html;
+pub mod markdown;
+
+pub use git::{export_git, import_git, GitExportReport, GitImportReport};
+pub use html::{export_site, SiteReport};
+
+use crate::engine::EngineHandle;
+
+/// Whether `editor_id` may read a block, and so see it in an export.
+///
+/// Blocks are checked for the read capability of their type.
+pub(crate) async fn may_read(
+    handle: &EngineHandle,
+    editor_id: &str,
+    block_id: &str,
+    block_type: &str,
+) -> bool {
+    let read_cap = match block_type {
+        "markdown" => "markdown.read",
+        "code" => "code.read",
+        _ => "core.read",
+    };
+    handle
+        .check_grant(
+            editor_id.to_string(),
+            read_cap.to_string(),
+            block_id.to_string(),
+        )
+        .await
+}
@@ -3,22 +3,36 @@
 /// Imports files from external directory into the Directory Block.
 use super::DirectoryImportPayload;
 use crate::capabilities::core::{create_event, CapResult};
+use crate::elf::BlobStore;
 use crate::models::{Block, Command, Event};
 use crate::utils::time::now_utc;
-use crate::utils::{infer_block_type, is_safe_path, scan_directory, ScanOptions};
+use crate::utils::{
+    infer_block_type, is_safe_path, scan_directory_with_skipped, store_asset, SkippedFile,
+};
 use capability_macros::capability;
 use serde_json::json;
 use std::fs;
 use std::path::Path;
 
+/// Virtual path of a file of the linked folder, under the import's target path.
+pub(crate) fn linked_path(target_prefix: &str, relative_path: &str) -> String {
+    if target_prefix == "/" || target_prefix.is_empty() {
+        relative_path.to_string()
+    } else {
+        format!("{}/{}", target_prefix, relative_path)
+    }
+}
+
 /// Handler for directory.import capability.
 ///
 /// Imports files and directories from external file system into the virtual file system.
-/// - Scans external directory with filtering
+/// - Scans external directory with filtering (`payload.options`, ignore files)
 /// - Infers Block types based on file extensions
 /// - Creates Content Blocks for each file
+/// - Stores binary files as `asset` blocks, with their bytes in the archive's blob store
 /// - Updates Directory entries
-/// - Records external_root_path in metadata
+/// - Reports skipped files with reasons (`skipped` in the directory.write event)
+/// - Records external_root_path and the scan options in metadata
 ///
 /// # Arguments
 /// * `cmd` - The command containing the payload
@@ -56,9 +70,9 @@ fn handle_import(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>>
     }
 
     // Step 4: Scan external directory
-    let options = ScanOptions::default();
-    let files =
-        scan_directory(source, &options).map_err(|e| format!("Failed to scan directory: {}", e))?;
+    let scan_options = payload.options.clone().unwrap_or_default();
+    let scan = scan_directory_with_skipped(source, &scan_options.to_scan_options())
+        .map_err(|e| format!("Failed to scan directory: {}", e))?;
 
     let mut entries = serde_json::Map::new();
     let target_prefix = payload
@@ -74,14 +88,11 @@ fn handle_import(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>>
 
     let mut events = Vec::new();
     let mut warnings = Vec::new();
+    let mut skipped = scan.skipped;
 
     // Step 6: Process each file
-    for file_info in files {
-        let virtual_path = if target_prefix == "/" || target_prefix.is_empty() {
-            file_info.relative_path.clone()
-        } else {
-            format!("{}/{}", target_prefix, file_info.relative_path)
-        };
+    for file_info in scan.files {
+        let virtual_path = linked_path(&target_prefix, &file_info.relative_path);
 
         if file_info.is_directory {
             // Add directory entry (virtual, no Block)
@@ -103,28 +114,69 @@ fn handle_import(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>>
                 None => {
                     log::warn!("Skipping unsupported file: {:?}", file_info.absolute_path);
                     warnings.push(format!("Skipped unsupported file type: {}", virtual_path));
+                    skipped.push(SkippedFile {
+                        path: file_info.relative_path,
+                        reason: "unsupported file type".to_string(),
+                    });
                     continue;
                 }
             };
 
-            // Read file content
-            let content = fs::read_to_string(&file_info.absolute_path)
-                .map_err(|e| format!("Failed to read file {:?}: {}", file_info.absolute_path, e))?;
-
             // Create Content Block
             let file_block_id = uuid::Uuid::new_v4().to_string();
 
-            // Unified field logic: markdown for markdown, text for others
-            let contents = if block_type == "markdown" {
-                json!({
-                    "markdown": content,
-                    "source": "linked"
-                })
+            // `hash` is what the file looked like when it was last synced (see `linked`)
+            let (contents, hash) = if block_type == "asset" {
+                // Asset bytes go to the blob store of the archive holding `block-{id}/`.
+                // Without an archive directory (in-memory engine, dry run) they can't be kept.
+                let archive_dir = block
+                    .contents
+                    .get("_block_dir")
+                    .and_then(|v| v.as_str())
+                    .and_then(|dir| Path::new(dir).parent());
+                let archive_dir = match archive_dir {
+                    Some(dir) => dir,
+                    None => {
+                        warnings.push(format!(
+                            "Skipped asset without archive storage: {}",
+                            virtual_path
+                        ));
+                        skipped.push(SkippedFile {
+                            path: file_info.relative_path,
+                            reason: "binary file without archive storage".to_string(),
+                        });
+                        continue;
+                    }
+                };
+
+                let bytes = fs::read(&file_info.absolute_path).map_err(|e| {
+                    format!("Failed to read file {:?}: {}", file_info.absolute_path, e)
+                })?;
+                let mut contents = store_asset(archive_dir, &file_info.file_name, &bytes)?;
+                contents["source"] = json!("linked");
+                let hash = BlobStore::hash(&bytes);
+                (contents, hash)
             } else {
-                json!({
-                    "text": content,
-                    "source": "linked"
-                })
+                // Read file content
+                let content = fs::read_to_string(&file_info.absolute_path).map_err(|e| {
+                    format!("Failed to read file {:?}: {}", file_info.absolute_path, e)
+                })?;
+
+                let hash = BlobStore::hash(content.as_bytes());
+
+                // Unified field logic: markdown for markdown, text for others
+                let contents = if block_type == "markdown" {
+                    json!({
+                        "markdown": content,
+                        "source": "linked"
+                    })
+                } else {
+                    json!({
+                        "text": content,
+                        "source": "linked"
+                    })
+                };
+                (contents, hash)
             };
 
             // NOTE: count=1 is a placeholder. Engine actor will update it with correct vector clock.
@@ -153,6 +205,7 @@ fn handle_import(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>>
                     // external_path: Stored for future use in conflict detection bypass and auditing.
                     // It tracks the original location from which the file was imported.
                     "external_path": file_info.absolute_path.to_string_lossy(),
+                    "hash": hash,
                     "updated_at": now_utc()
                 }),
             );
@@ -160,7 +213,8 @@ fn handle_import(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>>
     }
 
     // Step 7: Update Directory Block contents
-    // Recording warnings in the payload so users/UI can see what was skipped.
+    // Recording warnings and skipped files (paths relative to the source folder)
+    // in the payload so users/UI can see what was left out and why.
     events.push(create_event(
         block.block_id.clone(),
         "directory.write",
@@ -169,7 +223,8 @@ fn handle_import(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>>
                 "entries": entries,
                 "source": "linked"
             },
-            "warnings": warnings
+            "warnings": warnings,
+            "skipped": skipped
         }),
         &cmd.editor_id,
         1,
@@ -182,6 +237,9 @@ fn handle_import(cmd: &Command, block: Option<&Block>) -> CapResult<Vec<Event>>
         json!({
             "metadata": {
                 "external_root_path": payload.source_path,
+                "external_target_path": target_prefix,
+                // Refreshes of the linked folder scan it the same way
+                "scan_options": scan_options,
                 "last_import": now_utc()
             }
         }),
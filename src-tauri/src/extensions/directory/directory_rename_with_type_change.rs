@@ -107,8 +107,8 @@ fn handle_rename_with_type_change(cmd: &Command, block: Option<&Block>) -> CapRe
         // Case 1: Direct type specification
         Some(t.clone())
     } else if let Some(ext) = &payload.file_extension {
-        // Case 2: Infer from extension
-        infer_block_type(ext)
+        // Case 2: Infer from extension (a rename can't turn text into a binary asset)
+        infer_block_type(ext).filter(|t| t != "asset")
     } else {
         // No type change requested
         None
@@ -0,0 +1,614 @@
+/// Two-way sync of a directory block with the folder it was imported from.
+///
+/// `directory.import` records the folder in the block's metadata
+/// (`external_root_path`) and, for each file entry, the file it came from
+/// (`external_path`) and the SHA-256 of its bytes at the last sync (`hash`).
+/// A refresh rescans the folder and compares both sides with that hash:
+///
+/// - file changed, block not: the block is rewritten (`markdown.write` / `code.write`)
+/// - block changed, file not: the file is rewritten if pushing is enabled
+/// - both changed: a conflict, unless the options pick a side
+/// - file gone, its bytes under another name: `directory.rename`
+/// - file gone: `core.delete` (the block goes to the trash)
+/// - new file: `directory.create`
+///
+/// The folder is scanned with the options recorded by the import
+/// (`scan_options`), so ignored and excluded files stay out.
+///
+/// Every change goes through the engine as a command of the syncing editor,
+/// so grants apply as for edits made by hand. Binary files are only read by
+/// `directory.import`; a refresh reports new or changed ones as skipped.
+use super::directory_import::linked_path;
+use super::DirectoryScanOptions;
+use crate::elf::BlobStore;
+use crate::engine::EngineHandle;
+use crate::models::{Block, Command};
+use crate::utils::time::now_utc;
+use crate::utils::{infer_block_type, read_asset, scan_directory};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Side whose version wins when a file changed on disk and in Elfiee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkSide {
+    Disk,
+    Elfiee,
+}
+
+/// Options of a refresh.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct LinkSyncOptions {
+    /// Write blocks edited in Elfiee back to their files (needs `directory.export`)
+    #[serde(default)]
+    pub push: bool,
+    /// Side that wins a conflict (None leaves both sides as they are and reports it)
+    #[serde(default)]
+    pub prefer: Option<LinkSide>,
+}
+
+/// A file that changed on both sides since the last sync.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct LinkConflict {
+    pub path: String,
+    pub reason: String,
+}
+
+/// A file renamed on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct LinkRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// What a refresh changed, by virtual path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct LinkSyncReport {
+    /// New files, now blocks
+    pub added: Vec<String>,
+    /// Blocks rewritten from their file
+    pub changed: Vec<String>,
+    /// Blocks whose file is gone, moved to the trash
+    pub deleted: Vec<String>,
+    pub renamed: Vec<LinkRename>,
+    /// Files rewritten from their block
+    pub pushed: Vec<String>,
+    pub conflicts: Vec<LinkConflict>,
+    /// Files left alone, with the reason
+    pub skipped: Vec<String>,
+}
+
+impl LinkSyncReport {
+    /// Whether the refresh found nothing to report.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.changed.is_empty()
+            && self.deleted.is_empty()
+            && self.renamed.is_empty()
+            && self.pushed.is_empty()
+            && self.conflicts.is_empty()
+            && self.skipped.is_empty()
+    }
+}
+
+/// A file entry of the directory that came from the linked folder.
+struct LinkedEntry {
+    path: String,
+    block_id: String,
+    external_path: String,
+    /// Hash of the file at the last sync
+    hash: Option<String>,
+}
+
+/// A file in the linked folder.
+struct DiskFile {
+    relative_path: String,
+    extension: String,
+    bytes: Vec<u8>,
+    hash: String,
+}
+
+/// Hash of a block's contents as they would be written to its file.
+fn block_hash(block: &Block) -> Option<String> {
+    match block.block_type.as_str() {
+        "asset" => block.contents["hash"].as_str().map(str::to_string),
+        _ => block_text(block).map(|text| BlobStore::hash(text.as_bytes())),
+    }
+}
+
+/// Text of a markdown or code block (same field order as checkout).
+fn block_text(block: &Block) -> Option<&str> {
+    block.contents["text"]
+        .as_str()
+        .or(block.contents["markdown"].as_str())
+}
+
+/// Run a command as `editor_id`.
+async fn run(
+    handle: &EngineHandle,
+    editor_id: &str,
+    cap_id: &str,
+    block_id: &str,
+    payload: serde_json::Value,
+) -> Result<(), String> {
+    let cmd = Command::new(
+        editor_id.to_string(),
+        cap_id.to_string(),
+        block_id.to_string(),
+        payload,
+    );
+    handle.process_command(cmd).await.map(|_| ())
+}
+
+/// Sync a linked directory block with its folder once.
+///
+/// # Arguments
+/// * `handle` - Engine of the project
+/// * `editor_id` - Editor the changes are made as
+/// * `block_id` - The directory block (imported with `directory.import`)
+/// * `options` - Whether to push block edits, and which side wins conflicts
+pub async fn refresh_linked(
+    handle: &EngineHandle,
+    editor_id: &str,
+    block_id: &str,
+    options: &LinkSyncOptions,
+) -> Result<LinkSyncReport, String> {
+    let dir = handle
+        .get_block(block_id.to_string())
+        .await
+        .ok_or_else(|| format!("Directory block '{}' not found", block_id))?;
+    if dir.block_type != "directory" {
+        return Err(format!(
+            "Expected block_type 'directory', got '{}'",
+            dir.block_type
+        ));
+    }
+    let metadata = |key: &str| {
+        dir.metadata
+            .custom
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    let root = metadata("external_root_path")
+        .ok_or_else(|| format!("Directory '{}' is not linked to a folder", dir.name))?;
+    let target_prefix = metadata("external_target_path").unwrap_or_default();
+    // Scan with the options of the import, so files it left out don't show up as added
+    let scan_options: DirectoryScanOptions = dir
+        .metadata
+        .custom
+        .get("scan_options")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+
+    // A missing folder (unmounted drive, ...) must not read as every file deleted
+    let root_path = Path::new(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Linked folder not found: {}", root));
+    }
+    let may_write_files = handle
+        .check_grant(
+            editor_id.to_string(),
+            "directory.export".to_string(),
+            block_id.to_string(),
+        )
+        .await;
+    if options.push && !may_write_files {
+        return Err(format!(
+            "Pushing to the linked folder requires directory.export on '{}'",
+            dir.name
+        ));
+    }
+
+    // Step 1: Scan the folder
+    let mut disk: BTreeMap<String, DiskFile> = BTreeMap::new();
+    let mut folders: HashSet<String> = HashSet::new();
+    for info in scan_directory(root_path, &scan_options.to_scan_options())? {
+        if info.is_directory {
+            folders.insert(linked_path(&target_prefix, &info.relative_path));
+            continue;
+        }
+        let bytes = fs::read(&info.absolute_path)
+            .map_err(|e| format!("Failed to read file {:?}: {}", info.absolute_path, e))?;
+        disk.insert(
+            info.absolute_path.to_string_lossy().to_string(),
+            DiskFile {
+                relative_path: info.relative_path,
+                extension: info.extension,
+                hash: BlobStore::hash(&bytes),
+                bytes,
+            },
+        );
+    }
+
+    // Step 2: File entries that came from this folder
+    let entries = dir.contents["entries"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    let linked: Vec<LinkedEntry> = entries
+        .iter()
+        .filter(|(_, entry)| entry["type"] == "file")
+        .filter_map(|(path, entry)| {
+            let external = entry["external_path"].as_str()?;
+            Path::new(external)
+                .starts_with(root_path)
+                .then(|| LinkedEntry {
+                    path: path.clone(),
+                    block_id: entry["id"].as_str().unwrap_or("").to_string(),
+                    external_path: external.to_string(),
+                    hash: entry["hash"].as_str().map(str::to_string),
+                })
+        })
+        .collect();
+    let mut unmatched: BTreeMap<&String, &DiskFile> = disk
+        .iter()
+        .filter(|(external, _)| !linked.iter().any(|e| &e.external_path == *external))
+        .collect();
+
+    let mut report = LinkSyncReport::default();
+    // Entries to record as synced: path -> (external path, hash)
+    let mut synced: HashMap<String, (String, String)> = HashMap::new();
+
+    // Step 3: Compare each entry with its file
+    for LinkedEntry {
+        path,
+        block_id: child_id,
+        external_path: external,
+        hash: base,
+    } in linked
+    {
+        let Some(block) = handle.get_block(child_id.clone()).await else {
+            continue;
+        };
+        let current = block_hash(&block);
+        // Entries imported before hashes were recorded count as unedited
+        let base = base.or_else(|| current.clone());
+        let edited = current != base;
+
+        let on_disk = disk.get(&external);
+        let (path, external, file) = match on_disk {
+            Some(file) => (path, external, file),
+            None => {
+                let renamed_to = base.as_ref().and_then(|base| {
+                    unmatched
+                        .iter()
+                        .find(|(_, file)| &file.hash == base)
+                        .map(|(external, _)| (*external).clone())
+                });
+                match renamed_to {
+                    Some(new_external) => {
+                        let file = unmatched.remove(&new_external).unwrap();
+                        let new_path = linked_path(&target_prefix, &file.relative_path);
+                        let renamed = if infer_block_type(&file.extension).as_deref()
+                            == Some(block.block_type.as_str())
+                        {
+                            run(
+                                handle,
+                                editor_id,
+                                "directory.rename",
+                                block_id,
+                                json!({ "old_path": path, "new_path": new_path }),
+                            )
+                            .await
+                        } else {
+                            run(
+                                handle,
+                                editor_id,
+                                "directory.rename_with_type_change",
+                                block_id,
+                                json!({
+                                    "old_path": path,
+                                    "new_path": new_path,
+                                    "file_extension": file.extension,
+                                }),
+                            )
+                            .await
+                        };
+                        if let Err(e) = renamed {
+                            report.skipped.push(format!("{}: {}", path, e));
+                            continue;
+                        }
+                        report.renamed.push(LinkRename {
+                            from: path,
+                            to: new_path.clone(),
+                        });
+                        synced.insert(new_path.clone(), (new_external.clone(), file.hash.clone()));
+                        (new_path, new_external, file)
+                    }
+                    None if edited && options.prefer != Some(LinkSide::Disk) => {
+                        report.conflicts.push(LinkConflict {
+                            path,
+                            reason: "deleted on disk, edited in Elfiee".to_string(),
+                        });
+                        continue;
+                    }
+                    None => {
+                        match run(handle, editor_id, "core.delete", &child_id, json!({})).await {
+                            Ok(()) => report.deleted.push(path),
+                            Err(e) => report.skipped.push(format!("{}: {}", path, e)),
+                        }
+                        continue;
+                    }
+                }
+            }
+        };
+
+        let changed = Some(&file.hash) != base.as_ref();
+        let side = match (changed, edited) {
+            _ if Some(&file.hash) == current.as_ref() => {
+                // Same bytes on both sides (possibly edited the same way)
+                if changed {
+                    synced.insert(path, (external, file.hash.clone()));
+                }
+                continue;
+            }
+            (true, false) => LinkSide::Disk,
+            (false, true) if options.push => LinkSide::Elfiee,
+            (false, _) => continue,
+            (true, true) => match options.prefer {
+                Some(side) if side == LinkSide::Disk || may_write_files => side,
+                _ => {
+                    report.conflicts.push(LinkConflict {
+                        path,
+                        reason: "changed on disk and in Elfiee".to_string(),
+                    });
+                    continue;
+                }
+            },
+        };
+
+        match side {
+            LinkSide::Disk => match pull(handle, editor_id, &block, file).await {
+                Ok(()) => {
+                    report.changed.push(path.clone());
+                    synced.insert(path, (external, file.hash.clone()));
+                }
+                Err(e) => report.skipped.push(format!("{}: {}", path, e)),
+            },
+            LinkSide::Elfiee => match push(handle, editor_id, &block, Path::new(&external)).await {
+                Ok(hash) => {
+                    report.pushed.push(path.clone());
+                    synced.insert(path, (external, hash));
+                }
+                Err(e) => report.skipped.push(format!("{}: {}", path, e)),
+            },
+        }
+    }
+
+    // Step 4: New files
+    for (external, file) in unmatched {
+        let path = linked_path(&target_prefix, &file.relative_path);
+        if entries.contains_key(&path) {
+            report
+                .skipped
+                .push(format!("{}: an entry with this path already exists", path));
+            continue;
+        }
+        let block_type = match infer_block_type(&file.extension) {
+            Some(block_type) if block_type != "asset" => block_type,
+            Some(_) => {
+                report.skipped.push(format!(
+                    "{}: binary files are only read by directory.import",
+                    path
+                ));
+                continue;
+            }
+            None => {
+                report
+                    .skipped
+                    .push(format!("{}: unsupported file type", path));
+                continue;
+            }
+        };
+        let Ok(content) = std::str::from_utf8(&file.bytes) else {
+            report.skipped.push(format!("{}: not UTF-8 text", path));
+            continue;
+        };
+        let created = run(
+            handle,
+            editor_id,
+            "directory.create",
+            block_id,
+            json!({
+                "path": path,
+                "type": "file",
+                "source": "linked",
+                "content": content,
+                "block_type": block_type,
+            }),
+        )
+        .await;
+        match created {
+            Ok(()) => {
+                report.added.push(path.clone());
+                synced.insert(path, (external.clone(), file.hash.clone()));
+            }
+            Err(e) => report.skipped.push(format!("{}: {}", path, e)),
+        }
+    }
+
+    // Step 5: Record the synced hashes and the folder structure in the entries
+    let dir = handle
+        .get_block(block_id.to_string())
+        .await
+        .ok_or_else(|| format!("Directory block '{}' not found", block_id))?;
+    let mut entries = dir.contents["entries"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    let before = entries.clone();
+    for (path, (external, hash)) in synced {
+        if let Some(entry) = entries.get_mut(&path).and_then(|e| e.as_object_mut()) {
+            entry.insert("source".to_string(), json!("linked"));
+            entry.insert("external_path".to_string(), json!(external));
+            entry.insert("hash".to_string(), json!(hash));
+            entry.insert("updated_at".to_string(), json!(now_utc()));
+        }
+    }
+    for folder in &folders {
+        if !entries.contains_key(folder) {
+            entries.insert(
+                folder.clone(),
+                json!({
+                    "id": format!("dir-{}", uuid::Uuid::new_v4()),
+                    "type": "directory",
+                    "source": "linked",
+                    "updated_at": now_utc()
+                }),
+            );
+        }
+    }
+    let prefix = match target_prefix.as_str() {
+        "" | "/" => String::new(),
+        prefix => format!("{}/", prefix),
+    };
+    let vanished: Vec<String> = entries
+        .iter()
+        .filter(|(path, entry)| {
+            entry["type"] == "directory"
+                && entry["source"] == "linked"
+                && path.starts_with(&prefix)
+                && !folders.contains(*path)
+        })
+        .map(|(path, _)| path.clone())
+        .filter(|folder| {
+            let inside = format!("{}/", folder);
+            !entries
+                .iter()
+                .any(|(path, entry)| entry["type"] == "file" && path.starts_with(&inside))
+        })
+        .collect();
+    for folder in vanished {
+        entries.remove(&folder);
+    }
+    if entries != before {
+        run(
+            handle,
+            editor_id,
+            "directory.write",
+            block_id,
+            json!({ "entries": entries, "source": "linked" }),
+        )
+        .await?;
+    }
+
+    Ok(report)
+}
+
+/// Rewrite a block from its file.
+async fn pull(
+    handle: &EngineHandle,
+    editor_id: &str,
+    block: &Block,
+    file: &DiskFile,
+) -> Result<(), String> {
+    if block.block_type != "markdown" && block.block_type != "code" {
+        return Err("binary files are only read by directory.import".to_string());
+    }
+    let content = std::str::from_utf8(&file.bytes).map_err(|_| "not UTF-8 text".to_string())?;
+    run(
+        handle,
+        editor_id,
+        &format!("{}.write", block.block_type),
+        &block.block_id,
+        json!({ "content": content }),
+    )
+    .await
+}
+
+/// Rewrite a file from its block and return the hash of what was written.
+///
+/// Needs the read capability of the block, as for a checkout.
+async fn push(
+    handle: &EngineHandle,
+    editor_id: &str,
+    block: &Block,
+    external: &Path,
+) -> Result<String, String> {
+    let authorized = match block.block_type.as_str() {
+        "markdown" | "code" => {
+            handle
+                .check_grant(
+                    editor_id.to_string(),
+                    format!("{}.read", block.block_type),
+                    block.block_id.clone(),
+                )
+                .await
+        }
+        _ => block.owner == editor_id,
+    };
+    if !authorized {
+        return Err("no read permission".to_string());
+    }
+
+    let bytes = if block.block_type == "asset" {
+        let archive_dir = block.contents["_block_dir"]
+            .as_str()
+            .and_then(|dir| Path::new(dir).parent())
+            .ok_or("asset has no archive directory")?;
+        read_asset(archive_dir, &block.contents)?
+    } else {
+        block_text(block).unwrap_or_default().as_bytes().to_vec()
+    };
+    fs::write(external, &bytes).map_err(|e| format!("Failed to write {:?}: {}", external, e))?;
+    Ok(BlobStore::hash(&bytes))
+}
+
+/// Keeps a linked directory in sync by refreshing it periodically.
+///
+/// The folder is rescanned every `interval`; the watcher stops when dropped.
+#[derive(Debug)]
+pub struct LinkWatcher {
+    task: JoinHandle<()>,
+}
+
+impl LinkWatcher {
+    /// Start refreshing `block_id` as `editor_id`, calling `on_change` with
+    /// every report that isn't empty. A report equal to the previous one (the
+    /// same conflicts still unresolved, ...) is not passed on again.
+    pub fn start<F>(
+        handle: EngineHandle,
+        editor_id: String,
+        block_id: String,
+        options: LinkSyncOptions,
+        interval: Duration,
+        on_change: F,
+    ) -> Self
+    where
+        F: Fn(LinkSyncReport) + Send + 'static,
+    {
+        let task = tokio::spawn(async move {
+            let start = tokio::time::Instant::now() + interval;
+            let mut ticks = tokio::time::interval_at(start, interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut last = LinkSyncReport::default();
+            loop {
+                ticks.tick().await;
+                match refresh_linked(&handle, &editor_id, &block_id, &options).await {
+                    Ok(report) if report != last => {
+                        last = report.clone();
+                        if !report.is_empty() {
+                            on_change(report);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Refresh of linked directory {} failed: {}", block_id, e),
+                }
+            }
+        });
+        Self { task }
+    }
+}
+
+impl Drop for LinkWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
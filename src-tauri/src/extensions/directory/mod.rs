@@ -31,6 +31,8 @@ pub use directory_rename_with_type_change::*;
 
 pub mod elf_meta;
 
+pub mod linked;
+
 // ============================================================================
 // Payload Definitions
 // ============================================================================
@@ -41,6 +43,62 @@ pub struct DirectoryImportPayload {
     pub source_path: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target_path: Option<String>,
+    /// Which files to scan; defaults to `ScanOptions::default()`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<DirectoryScanOptions>,
+}
+
+/// Scan options for DirectoryImport. Unset fields keep the scanner defaults.
+///
+/// Globs without a `/` match file and folder names at any depth; globs with
+/// one match the path from the imported folder (`src/**/*.rs`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct DirectoryScanOptions {
+    /// Folder levels to descend into (1 = only the folder's own entries)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<u32>,
+    /// Files larger than this many bytes are skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_file_size: Option<u64>,
+    /// Fail the import if the folder has more entries than this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<u32>,
+    /// Only import files matching one of these globs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Skip files and folders matching any of these globs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Import dotfiles and dot-folders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_hidden: Option<bool>,
+    /// Honour `.gitignore`, `.ignore` and `.elfignore` files (default true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub respect_ignore_files: Option<bool>,
+}
+
+impl DirectoryScanOptions {
+    /// The scanner options these stand for.
+    pub fn to_scan_options(&self) -> crate::utils::ScanOptions {
+        let defaults = crate::utils::ScanOptions::default();
+        crate::utils::ScanOptions {
+            max_depth: self
+                .max_depth
+                .map_or(defaults.max_depth, |depth| depth as usize),
+            max_file_size: self.max_file_size.unwrap_or(defaults.max_file_size),
+            max_files: self
+                .max_files
+                .map_or(defaults.max_files, |count| count as usize),
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            ignore_hidden: !self.include_hidden.unwrap_or(!defaults.ignore_hidden),
+            respect_ignore_files: self
+                .respect_ignore_files
+                .unwrap_or(defaults.respect_ignore_files),
+            ..defaults
+        }
+    }
 }
 
 /// Payload for DirectoryExport
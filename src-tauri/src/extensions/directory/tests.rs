@@ -31,6 +31,34 @@ fn test_import_payload_deserialize() {
     let payload = result.unwrap();
     assert_eq!(payload.source_path, "/Users/me/projects/my-app");
     assert_eq!(payload.target_path, Some("libs/external".to_string()));
+    assert!(payload.options.is_none());
+}
+
+#[test]
+fn test_import_payload_scan_options() {
+    let json = serde_json::json!({
+        "source_path": "/Users/me/projects/my-app",
+        "options": {
+            "max_depth": 3,
+            "exclude": ["*.lock"],
+            "include_hidden": true
+        }
+    });
+
+    let payload: DirectoryImportPayload = serde_json::from_value(json).unwrap();
+    let options = payload.options.unwrap();
+    assert_eq!(options.max_depth, Some(3));
+    assert!(options.include.is_empty());
+
+    // Unset fields keep the scanner defaults
+    let scan = options.to_scan_options();
+    let defaults = crate::utils::ScanOptions::default();
+    assert_eq!(scan.max_depth, 3);
+    assert_eq!(scan.exclude, vec!["*.lock".to_string()]);
+    assert!(!scan.ignore_hidden);
+    assert!(scan.respect_ignore_files);
+    assert_eq!(scan.max_file_size, defaults.max_file_size);
+    assert_eq!(scan.ignore_patterns, defaults.ignore_patterns);
 }
 
 // ============================================
@@ -118,6 +146,11 @@ fn test_import_basic() {
         "Should have file1.md entry"
     );
     assert_eq!(entries["file1.md"]["source"], "linked");
+    // The hash of the imported bytes is the base for later refreshes
+    assert_eq!(
+        entries["file1.md"]["hash"],
+        crate::elf::BlobStore::hash(b"# Test File 1")
+    );
 
     // Verify metadata update event
     let metadata_event = events
@@ -132,6 +165,139 @@ fn test_import_basic() {
     assert_eq!(metadata["external_root_path"], temp_path.to_str().unwrap());
 }
 
+#[test]
+fn test_import_binary_files_as_assets() {
+    let registry = CapabilityRegistry::new();
+    let cap = registry.get("directory.import").unwrap();
+
+    use std::fs;
+    use tempfile::TempDir;
+
+    let source = TempDir::new().unwrap();
+    let png = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0xff];
+    fs::write(source.path().join("mock.png"), png).unwrap();
+
+    // The archive directory, with the directory block's own block dir in it
+    let archive = TempDir::new().unwrap();
+    let mut block = Block::new(
+        "Test Block".to_string(),
+        "directory".to_string(),
+        "alice".to_string(),
+    );
+    let block_dir = archive.path().join(format!("block-{}", block.block_id));
+    block.contents = serde_json::json!({
+        "entries": {},
+        "_block_dir": block_dir.to_string_lossy(),
+    });
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "directory.import".to_string(),
+        block.block_id.clone(),
+        serde_json::json!({ "source_path": source.path().to_str().unwrap() }),
+    );
+    let events = cap.handler(&cmd, Some(&block)).unwrap();
+
+    let create = events
+        .iter()
+        .find(|e| e.attribute.ends_with("/core.create"))
+        .unwrap();
+    assert_eq!(create.value["type"], "asset");
+    let contents = &create.value["contents"];
+    assert_eq!(contents["mime"], "image/png");
+    assert_eq!(contents["size"], png.len());
+    assert_eq!(contents["source"], "linked");
+
+    // The bytes live in the archive's blob store, not in the event
+    let hash = contents["hash"].as_str().unwrap();
+    assert_eq!(
+        fs::read(archive.path().join("blobs").join(hash)).unwrap(),
+        png
+    );
+    assert_eq!(
+        crate::utils::read_asset(archive.path(), contents).unwrap(),
+        png.to_vec()
+    );
+
+    // Without an archive directory the asset is skipped with a warning
+    block.contents = serde_json::json!({ "entries": {} });
+    let events = cap.handler(&cmd, Some(&block)).unwrap();
+    assert!(!events.iter().any(|e| e.attribute.ends_with("/core.create")));
+    let write = events
+        .iter()
+        .find(|e| e.attribute.ends_with("/directory.write"))
+        .unwrap();
+    assert_eq!(write.value["warnings"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_import_reports_skipped_files() {
+    let registry = CapabilityRegistry::new();
+    let cap = registry.get("directory.import").unwrap();
+
+    use std::fs;
+    use tempfile::TempDir;
+
+    let source = TempDir::new().unwrap();
+    fs::create_dir(source.path().join("drafts")).unwrap();
+    fs::write(source.path().join(".elfignore"), "drafts/\n").unwrap();
+    fs::write(source.path().join("drafts/wip.md"), "# WIP").unwrap();
+    fs::write(source.path().join("notes.md"), "# Notes").unwrap();
+    fs::write(source.path().join("Cargo.lock"), "lock").unwrap();
+    fs::write(source.path().join("data.db"), "sqlite").unwrap();
+
+    let mut block = Block::new(
+        "Test Block".to_string(),
+        "directory".to_string(),
+        "alice".to_string(),
+    );
+    block.contents = serde_json::json!({ "entries": {} });
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "directory.import".to_string(),
+        block.block_id.clone(),
+        serde_json::json!({
+            "source_path": source.path().to_str().unwrap(),
+            "options": { "exclude": ["*.lock"] }
+        }),
+    );
+    let events = cap.handler(&cmd, Some(&block)).unwrap();
+
+    let write = events
+        .iter()
+        .find(|e| e.attribute.ends_with("/directory.write"))
+        .unwrap();
+    let entries = write.value["contents"]["entries"].as_object().unwrap();
+    assert_eq!(entries.keys().collect::<Vec<_>>(), vec!["notes.md"]);
+
+    let skipped: Vec<(&str, &str)> = write.value["skipped"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| (s["path"].as_str().unwrap(), s["reason"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        skipped,
+        vec![
+            (".elfignore", "hidden"),
+            ("Cargo.lock", "excluded by '*.lock'"),
+            ("drafts", "ignored by .elfignore ('drafts/')"),
+            ("data.db", "unsupported file type"),
+        ]
+    );
+
+    // The options are kept for refreshes of the linked folder
+    let metadata = events
+        .iter()
+        .find(|e| e.attribute.ends_with("/core.update_metadata"))
+        .unwrap();
+    assert_eq!(
+        metadata.value["metadata"]["scan_options"],
+        serde_json::json!({ "exclude": ["*.lock"] })
+    );
+}
+
 // ============================================
 // DirectoryImport - Authorization Tests
 // ============================================
@@ -3,10 +3,12 @@ pub mod commands;
 pub mod config;
 pub mod elf;
 pub mod engine;
+pub mod export;
 pub mod extensions;
 pub mod mcp;
 pub mod models;
 pub mod state;
+pub mod sync;
 pub mod utils;
 
 use state::AppState;
@@ -47,6 +49,9 @@ pub fn run() {
                 // File operations
                 commands::file::create_file,
                 commands::file::open_file,
+                commands::file::is_file_encrypted,
+                commands::file::open_encrypted_file,
+                commands::file::set_file_encryption,
                 commands::file::save_file,
                 commands::file::close_file,
                 commands::file::list_open_files,
@@ -55,16 +60,37 @@ pub fn run() {
                 commands::file::rename_file,
                 commands::file::duplicate_file,
                 commands::file::get_system_editor_id_from_config,
+                commands::file::check_integrity,
+                commands::file::enable_event_signing,
+                commands::file::verify_events,
+                commands::file::get_editor_public_key,
+                commands::file::trust_editor_key,
+                commands::file::get_recovery_info,
+                commands::file::restore_recovery,
+                commands::file::discard_recovery,
+                commands::file::get_autosave_policy,
+                commands::file::set_autosave_policy,
+                commands::file::check_external_changes,
+                commands::file::merge_external_changes,
+                commands::file::merge_files,
                 // Event operations (Timeline feature)
                 commands::event::get_state_at_event,
+                // Sync between running instances
+                commands::sync::start_sync_server,
+                commands::sync::stop_sync_server,
+                commands::sync::sync_with_peer,
                 // Block operations (core)
                 commands::block::execute_command,
+                commands::block::dry_run_command,
                 commands::block::get_block,
                 commands::block::get_all_blocks,
                 commands::block::update_block_metadata,
                 commands::block::rename_block,
                 commands::block::change_block_type,
                 commands::block::check_permission,
+                commands::block::explain_permission,
+                commands::block::validate_references,
+                commands::block::list_trash,
                 // Editor operations
                 commands::editor::create_editor,
                 commands::editor::delete_editor,
@@ -75,8 +101,18 @@ pub fn run() {
                 // Grant operations
                 commands::editor::list_grants,
                 commands::editor::get_block_grants,
+                commands::editor::list_denies,
+                commands::editor::list_roles,
+                commands::editor::list_role_assignments,
                 // Workspace/Checkout operations
                 commands::checkout::checkout_workspace,
+                commands::linked::refresh_linked_directory,
+                commands::linked::watch_linked_directory,
+                commands::linked::unwatch_linked_directory,
+                // Export operations
+                commands::export::export_site,
+                commands::export::export_git,
+                commands::export::import_git,
                 // Terminal operations (from extensions/terminal/commands.rs)
                 // Note: These are high-frequency "patch" operations that don't record Events.
                 // Event-producing operations use capabilities via execute_command.
@@ -99,12 +135,21 @@ pub fn run() {
             .typ::<extensions::directory::DirectoryCreatePayload>()
             .typ::<extensions::directory::DirectoryExportPayload>()
             .typ::<extensions::directory::DirectoryImportPayload>()
+            .typ::<extensions::directory::DirectoryScanOptions>()
             .typ::<extensions::directory::DirectoryWritePayload>()
             .typ::<models::CreateBlockPayload>()
+            .typ::<models::DeleteBlockPayload>()
+            .typ::<models::RestoreBlockPayload>()
             .typ::<models::LinkBlockPayload>()
             .typ::<models::UnlinkBlockPayload>()
             .typ::<models::GrantPayload>()
             .typ::<models::RevokePayload>()
+            .typ::<models::DenyPayload>()
+            .typ::<models::UndenyPayload>()
+            .typ::<models::DefineRolePayload>()
+            .typ::<models::DeleteRolePayload>()
+            .typ::<models::GrantRolePayload>()
+            .typ::<models::RevokeRolePayload>()
             .typ::<models::UpdateMetadataPayload>()
             .typ::<models::EditorCreatePayload>()
             .typ::<models::EditorDeletePayload>()
@@ -118,7 +163,17 @@ pub fn run() {
             // Block metadata types
             .typ::<models::BlockMetadata>()
             // Event types
-            .typ::<commands::event::StateSnapshot>();
+            .typ::<commands::event::StateSnapshot>()
+            // Engine report types
+            .typ::<engine::DeleteReport>()
+            .typ::<engine::TrashedBlock>()
+            .typ::<elf::FsckReport>()
+            .typ::<engine::VerifyReport>()
+            .typ::<elf::RecoveryInfo>()
+            .typ::<elf::AutosavePolicy>()
+            .typ::<engine::ExternalChange>()
+            .typ::<elf::MergeReport>()
+            .typ::<sync::SyncReport>();
 
         // Export TypeScript bindings on app startup
         #[cfg(debug_assertions)]
@@ -140,6 +195,9 @@ pub fn run() {
         // File operations
         commands::file::create_file,
         commands::file::open_file,
+        commands::file::is_file_encrypted,
+        commands::file::open_encrypted_file,
+        commands::file::set_file_encryption,
         commands::file::save_file,
         commands::file::close_file,
         commands::file::list_open_files,
@@ -148,16 +206,37 @@ pub fn run() {
         commands::file::rename_file,
         commands::file::duplicate_file,
         commands::file::get_system_editor_id_from_config,
+        commands::file::check_integrity,
+        commands::file::enable_event_signing,
+        commands::file::verify_events,
+        commands::file::get_editor_public_key,
+        commands::file::trust_editor_key,
+        commands::file::get_recovery_info,
+        commands::file::restore_recovery,
+        commands::file::discard_recovery,
+        commands::file::get_autosave_policy,
+        commands::file::set_autosave_policy,
+        commands::file::check_external_changes,
+        commands::file::merge_external_changes,
+        commands::file::merge_files,
         // Event operations (Timeline feature)
         commands::event::get_state_at_event,
+        // Sync between running instances
+        commands::sync::start_sync_server,
+        commands::sync::stop_sync_server,
+        commands::sync::sync_with_peer,
         // Block operations (core)
         commands::block::execute_command,
+        commands::block::dry_run_command,
         commands::block::get_block,
         commands::block::get_all_blocks,
         commands::block::update_block_metadata,
         commands::block::rename_block,
         commands::block::change_block_type,
         commands::block::check_permission,
+        commands::block::explain_permission,
+        commands::block::validate_references,
+        commands::block::list_trash,
         // Editor operations
         commands::editor::create_editor,
         commands::editor::delete_editor,
@@ -168,8 +247,18 @@ pub fn run() {
         // Grant operations
         commands::editor::list_grants,
         commands::editor::get_block_grants,
+        commands::editor::list_denies,
+        commands::editor::list_roles,
+        commands::editor::list_role_assignments,
         // Workspace/Checkout operations
         commands::checkout::checkout_workspace,
+        commands::linked::refresh_linked_directory,
+        commands::linked::watch_linked_directory,
+        commands::linked::unwatch_linked_directory,
+        // Export operations
+        commands::export::export_site,
+        commands::export::export_git,
+        commands::export::import_git,
         // Terminal operations (from extensions/terminal/commands.rs)
         extensions::terminal::commands::init_pty_session,
         extensions::terminal::commands::write_to_pty,
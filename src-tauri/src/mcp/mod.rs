@@ -30,7 +30,12 @@
 //! - `elfiee_block_list` - List blocks in a project
 //! - `elfiee_block_get` - Get block details
 //! - `elfiee_block_create` - Create new block
-//! - `elfiee_block_delete` - Delete block
+//! - `elfiee_block_delete` - Delete block (detach / refuse / cascade)
+//! - `elfiee_trash_list` - List deleted blocks in the trash
+//! - `elfiee_block_restore` - Restore a block from the trash
+//! - `elfiee_trash_purge` - Permanently remove a block from the trash
+//! - `elfiee_validate_references` - Find dangling block references
+//! - `elfiee_check_integrity` - Check (and optionally repair) project integrity
 //! - `elfiee_block_rename` - Rename block
 //! - `elfiee_block_link` - Add block relation
 //! - `elfiee_block_unlink` - Remove block relation
@@ -40,7 +45,11 @@
 //! - `elfiee_code_read/write` - Read/write code
 //! - `elfiee_directory_create/delete/rename/write/import/export` - Directory operations
 //! - `elfiee_terminal_init/execute/save/close` - Terminal operations
-//! - `elfiee_grant/revoke` - Permission operations
+//! - `elfiee_grant/revoke` - Permission operations (capability patterns, subtree/descendants scopes, delegation)
+//! - `elfiee_deny/undeny` - Deny rules (take precedence over grants and roles)
+//! - `elfiee_role_define/delete/grant/revoke/list` - Role operations
+//! - `elfiee_explain_authorization` - Trace the rules behind an authorization check
+//! - `elfiee_dry_run` - Validate a capability call without committing events
 //! - `elfiee_editor_create/delete` - Editor operations
 //! - `elfiee_exec` - Execute any capability
 
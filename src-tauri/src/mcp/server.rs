@@ -4,8 +4,12 @@
 //! All tools call EngineManager directly, no intermediate layers.
 
 use crate::mcp;
-use crate::models::Command;
+use crate::models::{
+    Command, Grant, PROJECT_SCOPE, SCOPE_DESCENDANTS_PREFIX, SCOPE_SUBTREE_PREFIX,
+};
 use crate::state::AppState;
+use crate::utils::read_asset;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use rmcp::{
     handler::server::{router::tool::ToolRouter, tool::Parameters},
     model::{
@@ -21,6 +25,7 @@ use schemars::JsonSchema;
 use serde::Deserialize;
 use serde_json::json;
 use std::future::Future;
+use std::path::Path;
 use std::sync::Arc;
 
 /// Elfiee MCP Server
@@ -63,6 +68,36 @@ pub struct BlockCreateInput {
     pub parent_id: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlockDeleteInput {
+    /// Path to the .elf project file
+    pub project: String,
+    /// ID of the block to delete
+    pub block_id: String,
+    /// Delete mode: 'detach' (default), 'refuse' or 'cascade'
+    pub mode: Option<String>,
+    /// Maximum relation depth for 'cascade' mode (omit for unlimited)
+    pub depth: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlockRestoreInput {
+    /// Path to the .elf project file
+    pub project: String,
+    /// ID of the trashed block to restore
+    pub block_id: String,
+    /// Also restore blocks deleted by a cascade from this block (default: false)
+    pub include_cascaded: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct IntegrityCheckInput {
+    /// Path to the .elf project file
+    pub project: String,
+    /// Repair the problems found (default: false, report only)
+    pub repair: Option<bool>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct BlockRenameInput {
     /// Path to the .elf project file
@@ -238,12 +273,60 @@ pub struct TerminalSaveInput {
 pub struct GrantInput {
     /// Path to the .elf project file
     pub project: String,
-    /// Block ID to grant permission on
+    /// Block ID to grant permission on (empty with scope 'project' for project-level
+    /// capabilities such as core.create, editor.create and editor.delete)
     pub block_id: String,
     /// Editor ID to grant permission to
     pub editor_id: String,
-    /// Capability ID to grant (e.g., 'markdown.write')
+    /// Capability ID to grant (e.g., 'markdown.write'), or a pattern such as 'markdown.*'
     pub cap_id: String,
+    /// Which blocks the grant covers: 'block' (default, only block_id), 'subtree'
+    /// (block_id is a directory; covers every block its entries point to),
+    /// 'descendants' (block_id and its implement descendants), 'all' (every block)
+    /// or 'project' (project-level capabilities)
+    pub scope: Option<String>,
+    /// Grant only: RFC 3339 timestamp after which the grant no longer applies
+    pub expires_at: Option<String>,
+    /// Grant only: maximum number of times the grant may be used
+    pub max_uses: Option<u32>,
+    /// Grant only: let the editor grant this capability on to others
+    pub grantable: Option<bool>,
+    /// Revoke only: also revoke every grant delegated from this one
+    pub cascade: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RoleDefineInput {
+    /// Path to the .elf project file
+    pub project: String,
+    /// Role name (e.g., 'reviewer')
+    pub name: String,
+    /// Capabilities the role bundles (e.g., ['markdown.read', 'code.read'])
+    pub capabilities: Vec<String>,
+    /// Optional block ID the role is limited to (omit for any block)
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RoleDeleteInput {
+    /// Path to the .elf project file
+    pub project: String,
+    /// Role name
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RoleGrantInput {
+    /// Path to the .elf project file
+    pub project: String,
+    /// Block ID to give the role on
+    pub block_id: String,
+    /// Editor ID to give the role to
+    pub editor_id: String,
+    /// Role name
+    pub role: String,
+    /// Give the role on all blocks instead of only block_id (default: false)
+    pub all_blocks: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -256,6 +339,18 @@ pub struct EditorInput {
     pub name: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExplainAuthorizationInput {
+    /// Path to the .elf project file
+    pub project: String,
+    /// Capability ID (e.g., 'markdown.write')
+    pub capability: String,
+    /// Target block ID (omit for create-style capabilities)
+    pub block_id: Option<String>,
+    /// Editor to check (defaults to the active editor)
+    pub editor_id: Option<String>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ExecInput {
     /// Path to the .elf project file
@@ -312,6 +407,48 @@ impl ElfieeMcpServer {
             .ok_or_else(|| mcp::engine_not_found(file_id))
     }
 
+    /// Build a core.grant/core.revoke target from a block ID and scope name
+    fn grant_target(block_id: &str, scope: Option<&str>) -> Result<String, String> {
+        match scope.unwrap_or("block") {
+            "block" => Ok(block_id.to_string()),
+            "subtree" => Ok(format!("{}{}", SCOPE_SUBTREE_PREFIX, block_id)),
+            "descendants" => Ok(format!("{}{}", SCOPE_DESCENDANTS_PREFIX, block_id)),
+            "all" => Ok("*".to_string()),
+            "project" => Ok(PROJECT_SCOPE.to_string()),
+            other => Err(format!(
+                "Unknown scope '{}'. Valid scopes: block, subtree, descendants, all, project",
+                other
+            )),
+        }
+    }
+
+    /// Execute core.deny / core.undeny from a GrantInput
+    async fn execute_deny_rule(
+        &self,
+        capability: &str,
+        input: GrantInput,
+    ) -> Result<CallToolResult, McpError> {
+        let target_block = match Self::grant_target(&input.block_id, input.scope.as_deref()) {
+            Ok(target) => target,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    serde_json::to_string_pretty(&json!({ "ok": false, "error": e })).unwrap(),
+                )]))
+            }
+        };
+        self.execute_capability(
+            &input.project,
+            capability,
+            Some(input.block_id),
+            json!({
+                "target_editor": input.editor_id,
+                "capability": input.cap_id,
+                "target_block": target_block,
+            }),
+        )
+        .await
+    }
+
     /// Execute a capability and return rich result with updated state
     async fn execute_capability(
         &self,
@@ -458,7 +595,7 @@ impl ElfieeMcpServer {
         let lower = error.to_lowercase();
         if lower.contains("not authorized") || lower.contains("permission") {
             return format!(
-                "The current editor lacks '{}' permission. Use elfiee_grant to grant it first.",
+                "The current editor lacks '{}' permission. Use elfiee_explain_authorization to see why, or elfiee_grant to grant it.",
                 capability
             );
         }
@@ -623,10 +760,26 @@ impl ElfieeMcpServer {
                 // Grants on this block
                 let grants = handle.get_block_grants(block.block_id.clone()).await;
                 if !grants.is_empty() {
+                    let limits = handle.get_grant_limits().await;
+                    let delegations = handle.get_grant_delegations().await;
+                    let now = chrono::Utc::now();
                     let grant_list: Vec<serde_json::Value> = grants
-                        .iter()
-                        .map(|(editor_id, cap_id, _)| {
-                            json!({ "editor": editor_id, "capability": cap_id })
+                        .into_iter()
+                        .map(|key| {
+                            let grant_limits = limits.get(&key).cloned();
+                            let delegation = delegations.get(&key).cloned();
+                            let (editor_id, cap_id, target) = key;
+                            let grant = Grant::new(editor_id, cap_id, target)
+                                .with_limits(grant_limits, now)
+                                .with_delegation(delegation);
+                            json!({
+                                "editor": grant.editor_id,
+                                "capability": grant.cap_id,
+                                "scope": grant.scope,
+                                "limits": grant.limits,
+                                "expired": grant.expired,
+                                "delegation": grant.delegation,
+                            })
                         })
                         .collect();
                     result["grants"] = json!(grant_list);
@@ -671,21 +824,136 @@ impl ElfieeMcpServer {
 
     /// Delete a block from the project
     #[tool(
-        description = "Soft-delete a block. The block is marked as deleted but its history is preserved in the event store."
+        description = "Soft-delete a block. The block moves to the trash (see elfiee_trash_list) and can be restored with elfiee_block_restore until it is purged. Mode 'detach' (default) deletes only this block, 'refuse' fails if the block has children or directory entries, 'cascade' also deletes implement descendants up to 'depth'. Links and directory entries pointing at deleted blocks are removed; the committed core.delete event lists every affected block."
     )]
     async fn elfiee_block_delete(
+        &self,
+        Parameters(input): Parameters<BlockDeleteInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut payload = json!({});
+        if let Some(mode) = input.mode {
+            payload["mode"] = json!(mode);
+        }
+        if let Some(depth) = input.depth {
+            payload["depth"] = json!(depth);
+        }
+
+        self.execute_capability(&input.project, "core.delete", Some(input.block_id), payload)
+            .await
+    }
+
+    /// List blocks in the trash
+    #[tool(
+        description = "List deleted blocks in the trash, most recently deleted first. Each entry includes the block, the parents and directory entries it had when deleted, who deleted it and when."
+    )]
+    async fn elfiee_trash_list(
+        &self,
+        Parameters(input): Parameters<ProjectInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let file_id = self.get_file_id(&input.project)?;
+        let handle = self.get_engine(&file_id)?;
+
+        let trash = handle.get_trash().await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&json!({
+                "project": input.project,
+                "trash": trash,
+                "count": trash.len(),
+            }))
+            .unwrap(),
+        )]))
+    }
+
+    /// Restore a block from the trash
+    #[tool(
+        description = "Restore a deleted block from the trash with its contents intact. It is re-linked to parents and re-added to directories that still exist. Set include_cascaded to also restore blocks deleted by a cascade from it."
+    )]
+    async fn elfiee_block_restore(
+        &self,
+        Parameters(input): Parameters<BlockRestoreInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let payload = json!({ "include_cascaded": input.include_cascaded.unwrap_or(false) });
+        self.execute_capability(
+            &input.project,
+            "core.restore",
+            Some(input.block_id),
+            payload,
+        )
+        .await
+    }
+
+    /// Permanently remove a block from the trash
+    #[tool(
+        description = "Permanently purge a deleted block from the trash. It can no longer be restored; its event history is kept."
+    )]
+    async fn elfiee_trash_purge(
         &self,
         Parameters(input): Parameters<BlockInput>,
     ) -> Result<CallToolResult, McpError> {
         self.execute_capability(
             &input.project,
-            "core.delete",
+            "core.purge",
             Some(input.block_id),
             json!({}),
         )
         .await
     }
 
+    /// Find references to blocks that no longer exist
+    #[tool(
+        description = "Validate a project's references. Returns implement links and directory entries that point at blocks which no longer exist."
+    )]
+    async fn elfiee_validate_references(
+        &self,
+        Parameters(input): Parameters<ProjectInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let file_id = self.get_file_id(&input.project)?;
+        let handle = self.get_engine(&file_id)?;
+
+        let dangling = handle.find_dangling_references().await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&json!({
+                "project": input.project,
+                "ok": dangling.is_empty(),
+                "dangling": dangling,
+                "count": dangling.len(),
+            }))
+            .unwrap(),
+        )]))
+    }
+
+    /// Check project integrity (fsck)
+    #[tool(
+        description = "Check a project for integrity problems: orphan block directories, snapshots that don't match contents, directory entries and relations pointing at missing blocks, and grants for deleted editors or blocks. Set repair=true to fix them with corrective events; save the file afterwards."
+    )]
+    async fn elfiee_check_integrity(
+        &self,
+        Parameters(input): Parameters<IntegrityCheckInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let file_id = self.get_file_id(&input.project)?;
+        let editor_id = self.get_editor_id(&file_id)?;
+        let handle = self.get_engine(&file_id)?;
+
+        match handle
+            .check_integrity(input.repair.unwrap_or(false), editor_id)
+            .await
+        {
+            Ok(report) => Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&json!({
+                    "project": input.project,
+                    "ok": report.is_clean(),
+                    "report": report,
+                }))
+                .unwrap(),
+            )])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(
+                serde_json::to_string_pretty(&json!({ "ok": false, "error": e })).unwrap(),
+            )])),
+        }
+    }
+
     /// Rename a block
     #[tool(description = "Rename a block")]
     async fn elfiee_block_rename(
@@ -1111,19 +1379,31 @@ impl ElfieeMcpServer {
 
     /// Grant a capability to an editor on a block
     #[tool(
-        description = "Grant a capability (e.g. 'markdown.write', 'code.write') to an editor on a specific block. The block owner can always perform all operations without explicit grants."
+        description = "Grant a capability (e.g. 'markdown.write', or a pattern like 'markdown.*') to an editor on a block. Set scope='subtree' to cover a directory block and every block its entries point to, 'descendants' for a block and its implement descendants, or 'all' for every block. Project-level capabilities (core.create, editor.create, editor.delete, role definitions, and grant management without a block) need an empty block_id with scope='project'; grants on 'all' don't reach the project. Use expires_at (RFC 3339) and/or max_uses for temporary access that lapses on its own. Non-owners can only grant what they hold through a grant made with grantable=true. The block owner can always perform all operations without explicit grants."
     )]
     async fn elfiee_grant(
         &self,
         Parameters(input): Parameters<GrantInput>,
     ) -> Result<CallToolResult, McpError> {
+        let target_block = match Self::grant_target(&input.block_id, input.scope.as_deref()) {
+            Ok(target) => target,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    serde_json::to_string_pretty(&json!({ "ok": false, "error": e })).unwrap(),
+                )]))
+            }
+        };
         self.execute_capability(
             &input.project,
             "core.grant",
             Some(input.block_id),
             json!({
-                "editor_id": input.editor_id,
-                "cap_id": input.cap_id
+                "target_editor": input.editor_id,
+                "capability": input.cap_id,
+                "target_block": target_block,
+                "expires_at": input.expires_at,
+                "max_uses": input.max_uses,
+                "grantable": input.grantable.unwrap_or(false),
             }),
         )
         .await
@@ -1131,52 +1411,299 @@ impl ElfieeMcpServer {
 
     /// Revoke a capability from an editor on a block
     #[tool(
-        description = "Revoke a previously granted capability from an editor on a specific block."
+        description = "Revoke a previously granted capability from an editor. Pass the same cap_id, block_id and scope used to grant it. Set cascade=true to also revoke every grant the editor delegated from it."
     )]
     async fn elfiee_revoke(
         &self,
         Parameters(input): Parameters<GrantInput>,
     ) -> Result<CallToolResult, McpError> {
+        let target_block = match Self::grant_target(&input.block_id, input.scope.as_deref()) {
+            Ok(target) => target,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    serde_json::to_string_pretty(&json!({ "ok": false, "error": e })).unwrap(),
+                )]))
+            }
+        };
         self.execute_capability(
             &input.project,
             "core.revoke",
             Some(input.block_id),
             json!({
-                "editor_id": input.editor_id,
-                "cap_id": input.cap_id
+                "target_editor": input.editor_id,
+                "capability": input.cap_id,
+                "target_block": target_block,
+                "cascade": input.cascade.unwrap_or(false),
+            }),
+        )
+        .await
+    }
+
+    /// Deny a capability to an editor on a block
+    #[tool(
+        description = "Forbid an editor (or '*' for everyone) from using a capability (or pattern like 'markdown.*') on a block, even where a grant, wildcard grant or role would allow it. Supports the same scopes as elfiee_grant. Block owners are not affected."
+    )]
+    async fn elfiee_deny(
+        &self,
+        Parameters(input): Parameters<GrantInput>,
+    ) -> Result<CallToolResult, McpError> {
+        self.execute_deny_rule("core.deny", input).await
+    }
+
+    /// Remove a deny rule
+    #[tool(
+        description = "Remove a deny rule added with elfiee_deny. Pass the same cap_id, block_id and scope used to deny it."
+    )]
+    async fn elfiee_undeny(
+        &self,
+        Parameters(input): Parameters<GrantInput>,
+    ) -> Result<CallToolResult, McpError> {
+        self.execute_deny_rule("core.undeny", input).await
+    }
+
+    /// Define or redefine a role
+    #[tool(
+        description = "Define a named role bundling capabilities (e.g. 'reviewer' = markdown.read + code.read), optionally limited to one block. Redefining an existing role changes it for every editor holding it."
+    )]
+    async fn elfiee_role_define(
+        &self,
+        Parameters(input): Parameters<RoleDefineInput>,
+    ) -> Result<CallToolResult, McpError> {
+        self.execute_capability(
+            &input.project,
+            "core.define_role",
+            None,
+            json!({
+                "name": input.name,
+                "capabilities": input.capabilities,
+                "scope": input.scope,
+            }),
+        )
+        .await
+    }
+
+    /// Delete a role
+    #[tool(description = "Delete a role. Every editor holding it loses the role.")]
+    async fn elfiee_role_delete(
+        &self,
+        Parameters(input): Parameters<RoleDeleteInput>,
+    ) -> Result<CallToolResult, McpError> {
+        self.execute_capability(
+            &input.project,
+            "core.delete_role",
+            None,
+            json!({ "name": input.name }),
+        )
+        .await
+    }
+
+    /// Give a role to an editor
+    #[tool(
+        description = "Give a role to an editor on a block (or on all blocks with all_blocks=true). The editor gets every capability the role bundles."
+    )]
+    async fn elfiee_role_grant(
+        &self,
+        Parameters(input): Parameters<RoleGrantInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let target_block = if input.all_blocks.unwrap_or(false) {
+            "*".to_string()
+        } else {
+            input.block_id.clone()
+        };
+        self.execute_capability(
+            &input.project,
+            "core.grant_role",
+            Some(input.block_id),
+            json!({
+                "target_editor": input.editor_id,
+                "role": input.role,
+                "target_block": target_block,
             }),
         )
         .await
     }
 
+    /// Take a role from an editor
+    #[tool(description = "Take a previously given role from an editor on a block (or all_blocks).")]
+    async fn elfiee_role_revoke(
+        &self,
+        Parameters(input): Parameters<RoleGrantInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let target_block = if input.all_blocks.unwrap_or(false) {
+            "*".to_string()
+        } else {
+            input.block_id.clone()
+        };
+        self.execute_capability(
+            &input.project,
+            "core.revoke_role",
+            Some(input.block_id),
+            json!({
+                "target_editor": input.editor_id,
+                "role": input.role,
+                "target_block": target_block,
+            }),
+        )
+        .await
+    }
+
+    /// List roles and role assignments
+    #[tool(
+        description = "List role definitions and which editors hold which roles on which blocks."
+    )]
+    async fn elfiee_role_list(
+        &self,
+        Parameters(input): Parameters<ProjectInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let file_id = self.get_file_id(&input.project)?;
+        let handle = self.get_engine(&file_id)?;
+
+        let roles = handle.get_roles().await;
+        let assignments = handle.get_role_assignments().await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&json!({
+                "project": input.project,
+                "roles": roles,
+                "assignments": assignments,
+            }))
+            .unwrap(),
+        )]))
+    }
+
+    // ========================================================================
+    // Authorization Diagnostics
+    // ========================================================================
+
+    /// Explain why an editor may or may not use a capability on a block
+    #[tool(
+        description = "Explain an authorization check: block owner, matching deny rules, grants (including wildcards and expired ones) and roles, and the rule that decides. Create-style capabilities report that no check applies."
+    )]
+    async fn elfiee_explain_authorization(
+        &self,
+        Parameters(input): Parameters<ExplainAuthorizationInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let file_id = self.get_file_id(&input.project)?;
+        let editor_id = match input.editor_id {
+            Some(editor_id) => editor_id,
+            None => self.get_editor_id(&file_id)?,
+        };
+        let handle = self.get_engine(&file_id)?;
+
+        match handle
+            .explain_authorization(
+                editor_id,
+                input.capability,
+                input.block_id.unwrap_or_default(),
+            )
+            .await
+        {
+            Ok(trace) => Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&json!({
+                    "project": input.project,
+                    "authorization": trace,
+                }))
+                .unwrap(),
+            )])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(
+                serde_json::to_string_pretty(&json!({ "ok": false, "error": e })).unwrap(),
+            )])),
+        }
+    }
+
+    /// Validate a capability call without committing anything
+    #[tool(
+        description = "Dry-run a capability as the active editor: validates the payload and authorization and returns the events that would be committed, without persisting them. On failure, includes the authorization trace."
+    )]
+    async fn elfiee_dry_run(
+        &self,
+        Parameters(input): Parameters<ExecInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let file_id = self.get_file_id(&input.project)?;
+        let editor_id = self.get_editor_id(&file_id)?;
+        let handle = self.get_engine(&file_id)?;
+
+        let block_id = input.block_id.unwrap_or_default();
+        let cmd = Command::new(
+            editor_id.clone(),
+            input.capability.clone(),
+            block_id.clone(),
+            input.payload.unwrap_or(json!({})),
+        );
+
+        match handle.dry_run_command(cmd).await {
+            Ok(events) => {
+                let events: Vec<serde_json::Value> = events
+                    .iter()
+                    .map(|e| {
+                        json!({
+                            "entity": e.entity,
+                            "attribute": e.attribute,
+                            "value": e.value,
+                        })
+                    })
+                    .collect();
+                Ok(CallToolResult::success(vec![Content::text(
+                    serde_json::to_string_pretty(&json!({
+                        "ok": true,
+                        "capability": input.capability,
+                        "editor": editor_id,
+                        "events_would_commit": events.len(),
+                        "events": events,
+                    }))
+                    .unwrap(),
+                )]))
+            }
+            Err(e) => {
+                let authorization = handle
+                    .explain_authorization(editor_id, input.capability.clone(), block_id)
+                    .await
+                    .ok();
+                Ok(CallToolResult::error(vec![Content::text(
+                    serde_json::to_string_pretty(&json!({
+                        "ok": false,
+                        "capability": input.capability,
+                        "error": e,
+                        "hint": Self::error_hint(&input.capability, &e),
+                        "authorization": authorization,
+                    }))
+                    .unwrap(),
+                )]))
+            }
+        }
+    }
+
     // ========================================================================
     // Editor Operations
     // ========================================================================
 
     /// Create a new editor in the project
-    #[tool(description = "Create a new editor in the project")]
+    #[tool(
+        description = "Create a new editor in the project. Requires the project owner or a grant of editor.create on all blocks."
+    )]
     async fn elfiee_editor_create(
         &self,
         Parameters(input): Parameters<EditorInput>,
     ) -> Result<CallToolResult, McpError> {
-        let mut payload = json!({ "editor_id": input.editor_id });
-        if let Some(name) = input.name {
-            payload["name"] = json!(name);
-        }
+        // editor.create requires a display name; default to the editor ID
+        let name = input.name.unwrap_or_else(|| input.editor_id.clone());
+        let payload = json!({ "editor_id": input.editor_id, "name": name });
 
-        self.execute_capability(&input.project, "core.editor_create", None, payload)
+        self.execute_capability(&input.project, "editor.create", None, payload)
             .await
     }
 
     /// Delete an editor from the project
-    #[tool(description = "Delete an editor from the project")]
+    #[tool(
+        description = "Delete an editor from the project. Requires the project owner or a grant of editor.delete on all blocks. The project owner can't be deleted."
+    )]
     async fn elfiee_editor_delete(
         &self,
         Parameters(input): Parameters<EditorInput>,
     ) -> Result<CallToolResult, McpError> {
         self.execute_capability(
             &input.project,
-            "core.editor_delete",
+            "editor.delete",
             None,
             json!({ "editor_id": input.editor_id }),
         )
@@ -1289,14 +1816,23 @@ impl rmcp::handler::server::ServerHandler for ElfieeMcpServer {
                         let mime = match block.block_type.as_str() {
                             "markdown" => "text/markdown",
                             "code" => "text/plain",
+                            "asset" => block
+                                .contents
+                                .get("mime")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("application/octet-stream"),
                             _ => "application/json",
                         };
+                        let size = (block.block_type == "asset")
+                            .then(|| block.contents.get("size").and_then(|v| v.as_u64()))
+                            .flatten()
+                            .and_then(|size| u32::try_from(size).ok());
                         resources.push(resource(RawResource {
                             uri: format!("elfiee://{}/block/{}", path, block.block_id),
                             name: block.name.clone(),
                             description: Some(format!("[{}] {}", block.block_type, block.name)),
                             mime_type: Some(mime.to_string()),
-                            size: None,
+                            size,
                         }));
                     }
                 }
@@ -1432,15 +1968,43 @@ impl rmcp::handler::server::ServerHandler for ElfieeMcpServer {
                 // elfiee://{project}/grants
                 "grants" => {
                     let blocks = handle.get_all_blocks().await;
+                    let limits = handle.get_grant_limits().await;
+                    let delegations = handle.get_grant_delegations().await;
+                    let denies: Vec<serde_json::Value> = handle
+                        .get_all_denies()
+                        .await
+                        .into_iter()
+                        .flat_map(|(editor_id, rules)| {
+                            rules.into_iter().map(move |(cap_id, target)| {
+                                let deny = Grant::new(editor_id.clone(), cap_id, target);
+                                json!({
+                                    "editor": deny.editor_id,
+                                    "capability": deny.cap_id,
+                                    "scope": deny.scope,
+                                })
+                            })
+                        })
+                        .collect();
+                    let now = chrono::Utc::now();
                     let mut all_grants = Vec::new();
                     for block in blocks.values() {
                         let grants = handle.get_block_grants(block.block_id.clone()).await;
-                        for (editor_id, cap_id, _) in &grants {
+                        for key in grants {
+                            let grant_limits = limits.get(&key).cloned();
+                            let delegation = delegations.get(&key).cloned();
+                            let (editor_id, cap_id, target) = key;
+                            let grant = Grant::new(editor_id, cap_id, target)
+                                .with_limits(grant_limits, now)
+                                .with_delegation(delegation);
                             all_grants.push(json!({
                                 "block_id": block.block_id,
                                 "block_name": block.name,
-                                "editor": editor_id,
-                                "capability": cap_id,
+                                "editor": grant.editor_id,
+                                "capability": grant.cap_id,
+                                "scope": grant.scope,
+                                "limits": grant.limits,
+                                "expired": grant.expired,
+                                "delegation": grant.delegation,
                             }));
                         }
                     }
@@ -1453,6 +2017,7 @@ impl rmcp::handler::server::ServerHandler for ElfieeMcpServer {
                                 "project": project,
                                 "grants": all_grants,
                                 "count": all_grants.len(),
+                                "denies": denies,
                             }))
                             .unwrap(),
                         }],
@@ -1497,6 +2062,32 @@ impl rmcp::handler::server::ServerHandler for ElfieeMcpServer {
                 rest if rest.starts_with("block/") => {
                     let block_id = rest.strip_prefix("block/").unwrap();
                     match handle.get_block(block_id.to_string()).await {
+                        Some(block) if block.block_type == "asset" => {
+                            // Binary asset: serve its bytes base64-encoded
+                            let archive_dir = block
+                                .contents
+                                .get("_block_dir")
+                                .and_then(|v| v.as_str())
+                                .and_then(|dir| Path::new(dir).parent())
+                                .ok_or_else(|| {
+                                    mcp::invalid_payload("Asset block has no archive directory")
+                                })?;
+                            let bytes = read_asset(archive_dir, &block.contents)
+                                .map_err(mcp::invalid_payload)?;
+                            let mime = block
+                                .contents
+                                .get("mime")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("application/octet-stream");
+
+                            Ok(ReadResourceResult {
+                                contents: vec![ResourceContents::BlobResourceContents {
+                                    uri: uri.clone(),
+                                    mime_type: Some(mime.to_string()),
+                                    blob: BASE64.encode(bytes),
+                                }],
+                            })
+                        }
                         Some(block) => {
                             // Return raw content for content-type blocks
                             let (text, mime) = match block.block_type.as_str() {
@@ -10,6 +10,57 @@ pub struct Event {
     pub value: serde_json::Value,
     pub timestamp: HashMap<String, i64>, // Vector clock
     pub created_at: String,              // Wall clock time (ISO 8601)
+    /// Hash chain link and signature (only on projects with signing enabled)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<EventSignature>,
+}
+
+/// Tamper-evidence data attached to a signed event.
+///
+/// `hash` covers the event's fields and `prev_hash`, chaining every signed
+/// event to the one before it; `signature` is the signer's ed25519 signature
+/// of `hash`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct EventSignature {
+    /// Hash of the previous signed event ("" for the first one)
+    pub prev_hash: String,
+    /// SHA-256 of this event (hex)
+    pub hash: String,
+    /// Editor whose key signed the event
+    pub signer: String,
+    /// The signer's ed25519 public key (hex)
+    pub public_key: String,
+    /// ed25519 signature of `hash` (hex)
+    pub signature: String,
+    /// The author's own signature, for events created in another copy of the
+    /// project; `signer` is then the editor that chained the event into this log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<OriginSignature>,
+}
+
+/// Signature an event's author made in the copy of the project it was created in.
+///
+/// `hash` chains the event to `prev_hash` of that copy's log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct OriginSignature {
+    pub prev_hash: String,
+    pub hash: String,
+    pub signer: String,
+    pub public_key: String,
+    pub signature: String,
+}
+
+impl From<EventSignature> for OriginSignature {
+    /// The author's signature of an event: its origin if it has one, else itself.
+    fn from(signature: EventSignature) -> Self {
+        signature.origin.unwrap_or(Self {
+            prev_hash: signature.prev_hash,
+            hash: signature.hash,
+            signer: signature.signer,
+            public_key: signature.public_key,
+            signature: signature.signature,
+        })
+    }
 }
 
 impl Event {
@@ -26,6 +77,7 @@ impl Event {
             value,
             timestamp,
             created_at: crate::utils::time::now_utc(),
+            signature: None,
         }
     }
 }
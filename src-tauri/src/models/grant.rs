@@ -1,5 +1,180 @@
+use crate::utils::time;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::collections::HashSet;
+
+/// Prefix of a grant target covering a directory block and every block its entries point to.
+pub const SCOPE_SUBTREE_PREFIX: &str = "subtree:";
+
+/// Prefix of a grant target covering a block and its `implement` descendants.
+pub const SCOPE_DESCENDANTS_PREFIX: &str = "descendants:";
+
+/// Grant target of project-level authority (creating blocks, managing editors
+/// and roles). Not a block ID, and not covered by `"*"`, so grants on every
+/// block don't also give authority over the project.
+pub const PROJECT_SCOPE: &str = "@project";
+
+/// The set of blocks a grant target covers.
+///
+/// Grant targets are stored as strings: `"*"`, [`PROJECT_SCOPE`], a block ID,
+/// `"subtree:{dir_id}"` or `"descendants:{block_id}"`. Hierarchical scopes are resolved against the
+/// current structure, so blocks added to a directory later are covered too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(tag = "kind", content = "root", rename_all = "snake_case")]
+pub enum GrantScope {
+    /// All blocks (`"*"`)
+    All,
+    /// The project itself ([`PROJECT_SCOPE`])
+    Project,
+    /// A single block
+    Block(String),
+    /// A directory block and every block its entries point to
+    Subtree(String),
+    /// A block and every block it reaches through `implement` relations
+    Descendants(String),
+}
+
+impl GrantScope {
+    /// Parse a stored grant target.
+    pub fn parse(target: &str) -> Self {
+        if target == "*" {
+            GrantScope::All
+        } else if target == PROJECT_SCOPE {
+            GrantScope::Project
+        } else if let Some(root) = target.strip_prefix(SCOPE_SUBTREE_PREFIX) {
+            GrantScope::Subtree(root.to_string())
+        } else if let Some(root) = target.strip_prefix(SCOPE_DESCENDANTS_PREFIX) {
+            GrantScope::Descendants(root.to_string())
+        } else {
+            GrantScope::Block(target.to_string())
+        }
+    }
+
+    /// The block the scope is anchored at (None for `All` and `Project`).
+    pub fn root(&self) -> Option<&str> {
+        match self {
+            GrantScope::All | GrantScope::Project => None,
+            GrantScope::Block(id) | GrantScope::Subtree(id) | GrantScope::Descendants(id) => {
+                Some(id)
+            }
+        }
+    }
+
+    /// Check if the scope covers a block.
+    ///
+    /// `containers` lists the structural ancestors of `block_id`; pass
+    /// `BlockContainers::default()` to match flat scopes only.
+    pub fn covers(&self, block_id: &str, containers: &BlockContainers) -> bool {
+        match self {
+            GrantScope::All => block_id != PROJECT_SCOPE,
+            GrantScope::Project => block_id == PROJECT_SCOPE,
+            GrantScope::Block(id) => id == block_id,
+            GrantScope::Subtree(root) => root == block_id || containers.directories.contains(root),
+            GrantScope::Descendants(root) => {
+                root == block_id || containers.implement_ancestors.contains(root)
+            }
+        }
+    }
+
+    /// Check if every block `other` covers is also covered by this scope.
+    ///
+    /// `containers` lists the structural ancestors of `other`'s root. Hierarchical
+    /// scopes only include themselves (or single blocks they cover), since the
+    /// blocks under them can change.
+    pub fn includes(&self, other: &GrantScope, containers: &BlockContainers) -> bool {
+        match (self, other) {
+            (GrantScope::All, GrantScope::Project) => false,
+            (GrantScope::All, _) => true,
+            (_, GrantScope::Block(id)) => self.covers(id, containers),
+            _ => self == other,
+        }
+    }
+}
+
+/// Structural ancestors of a block, used to resolve hierarchical grant scopes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockContainers {
+    /// Directory blocks whose entries (directly or through nested directory blocks) point to the block
+    pub directories: HashSet<String>,
+
+    /// Blocks that reach the block through `implement` relations
+    pub implement_ancestors: HashSet<String>,
+}
+
+/// Check if a granted capability pattern matches a capability ID.
+///
+/// Patterns are an exact cap_id, `"*"` for every capability, or a namespace
+/// wildcard such as `"markdown.*"` (matches `markdown.write` but not `markdown`).
+pub fn capability_matches(pattern: &str, cap_id: &str) -> bool {
+    if pattern == cap_id || pattern == "*" {
+        return true;
+    }
+    match pattern.strip_suffix('*') {
+        Some(prefix) if prefix.ends_with('.') => cap_id.starts_with(prefix),
+        _ => false,
+    }
+}
+
+/// Expiry and usage limits attached to a grant.
+///
+/// Limits are set by the optional `expires_at` / `max_uses` fields of a
+/// `core.grant` event. `uses` counts the commands the grant authorized, one
+/// per command however many events it produced: the engine marks the first
+/// event with `grant_use`. It is rebuilt from those markers on replay, so the
+/// state stays deterministic.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct GrantLimits {
+    /// RFC 3339 timestamp after which the grant no longer applies
+    pub expires_at: Option<String>,
+
+    /// Maximum number of times the grant may be used
+    pub max_uses: Option<u32>,
+
+    /// Number of times the grant has been used
+    pub uses: u32,
+}
+
+impl GrantLimits {
+    /// Check if the grant has expired at `now`.
+    ///
+    /// An unparseable `expires_at` is treated as expired.
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        match self.expires_at.as_deref() {
+            None => false,
+            Some(expires_at) => time::parse_to_utc(expires_at)
+                .map(|expires_at| now >= expires_at)
+                .unwrap_or(true),
+        }
+    }
+
+    /// Check if every allowed use has been consumed.
+    pub fn is_exhausted(&self) -> bool {
+        self.max_uses.is_some_and(|max| self.uses >= max)
+    }
+
+    /// Check if the grant can still be used at `now`.
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        !self.is_expired_at(now) && !self.is_exhausted()
+    }
+}
+
+/// Who handed out a grant, and whether it may be handed on.
+///
+/// Read from a `core.grant` event: the issuing editor comes from the event
+/// attribute, `grantable` and `delegated_from` from its value.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct Delegation {
+    /// The editor who issued the grant
+    pub granted_by: String,
+
+    /// Whether the grantee may grant the capability on to other editors
+    pub grantable: bool,
+
+    /// The grantable grant (editor_id, cap_id, block_id) the issuer delegated from,
+    /// or None if the issuer owns the block (or the project)
+    pub delegated_from: Option<(String, String, String)>,
+}
 
 /// Represents a capability grant in the CBAC system.
 ///
@@ -10,25 +185,138 @@ pub struct Grant {
     /// The editor who has been granted the capability
     pub editor_id: String,
 
-    /// The capability that has been granted (e.g., "markdown.write", "core.delete")
+    /// The capability that has been granted (e.g., "markdown.write", "core.delete"),
+    /// or a pattern such as "markdown.*"
     pub cap_id: String,
 
-    /// The target block ID, or "*" for wildcard (all blocks)
+    /// The grant target as stored: a block ID, "*" for wildcard (all blocks),
+    /// "subtree:{dir_id}" or "descendants:{block_id}"
     pub block_id: String,
+
+    /// The blocks the target covers, parsed from `block_id`
+    pub scope: GrantScope,
+
+    /// Expiry and usage limits (None for unlimited grants)
+    pub limits: Option<GrantLimits>,
+
+    /// Whether the grant has expired or used up its allowed uses
+    pub expired: bool,
+
+    /// Who issued the grant and whether it may be delegated (None if unknown)
+    pub delegation: Option<Delegation>,
 }
 
 impl Grant {
     /// Create a new Grant
     pub fn new(editor_id: String, cap_id: String, block_id: String) -> Self {
+        let scope = GrantScope::parse(&block_id);
         Self {
             editor_id,
             cap_id,
             block_id,
+            scope,
+            limits: None,
+            expired: false,
+            delegation: None,
         }
     }
 
+    /// Attach limits, marking the grant expired if it can no longer be used at `now`
+    pub fn with_limits(mut self, limits: Option<GrantLimits>, now: DateTime<Utc>) -> Self {
+        self.expired = limits.as_ref().is_some_and(|l| !l.is_active_at(now));
+        self.limits = limits;
+        self
+    }
+
+    /// Attach delegation details
+    pub fn with_delegation(mut self, delegation: Option<Delegation>) -> Self {
+        self.delegation = delegation;
+        self
+    }
+
     /// Check if this grant applies to a specific block
-    pub fn applies_to_block(&self, block_id: &str) -> bool {
-        self.block_id == block_id || self.block_id == "*"
+    pub fn applies_to_block(&self, block_id: &str, containers: &BlockContainers) -> bool {
+        self.scope.covers(block_id, containers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capability_patterns() {
+        assert!(capability_matches("markdown.write", "markdown.write"));
+        assert!(capability_matches("markdown.*", "markdown.write"));
+        assert!(capability_matches("*", "core.delete"));
+        assert!(!capability_matches("markdown.*", "markdown"));
+        assert!(!capability_matches("markdown.*", "code.write"));
+        assert!(!capability_matches("mark*", "markdown.write"));
+    }
+
+    #[test]
+    fn test_parse_scopes() {
+        assert_eq!(GrantScope::parse("*"), GrantScope::All);
+        assert_eq!(GrantScope::parse(PROJECT_SCOPE), GrantScope::Project);
+        assert_eq!(GrantScope::parse("b1"), GrantScope::Block("b1".into()));
+        assert_eq!(
+            GrantScope::parse("subtree:dir"),
+            GrantScope::Subtree("dir".into())
+        );
+        assert_eq!(
+            GrantScope::parse("descendants:b1"),
+            GrantScope::Descendants("b1".into())
+        );
+    }
+
+    #[test]
+    fn test_hierarchical_scope_covers() {
+        let mut containers = BlockContainers::default();
+        containers.directories.insert("dir".to_string());
+
+        let subtree = GrantScope::parse("subtree:dir");
+        assert!(subtree.covers("dir", &BlockContainers::default()));
+        assert!(subtree.covers("file", &containers));
+        assert!(!subtree.covers("file", &BlockContainers::default()));
+        assert!(!GrantScope::parse("descendants:dir").covers("file", &containers));
+
+        let subtree_includes = |other: &str, containers: &BlockContainers| {
+            subtree.includes(&GrantScope::parse(other), containers)
+        };
+        assert!(subtree_includes("file", &containers));
+        assert!(subtree_includes("subtree:dir", &BlockContainers::default()));
+        assert!(!subtree_includes("*", &BlockContainers::default()));
+        assert!(!subtree_includes("descendants:dir", &containers));
+        assert!(GrantScope::All.includes(&subtree, &BlockContainers::default()));
+    }
+
+    #[test]
+    fn test_project_scope_is_not_a_block() {
+        let none = BlockContainers::default();
+        assert!(GrantScope::All.covers("b1", &none));
+        assert!(!GrantScope::All.covers(PROJECT_SCOPE, &none));
+        assert!(GrantScope::Project.covers(PROJECT_SCOPE, &none));
+        assert!(!GrantScope::Project.covers("b1", &none));
+        assert!(!GrantScope::All.includes(&GrantScope::Project, &none));
+        assert!(GrantScope::Project.includes(&GrantScope::Project, &none));
+    }
+
+    #[test]
+    fn test_grant_limits() {
+        let now = time::parse_to_utc("2025-06-01T00:00:00Z").unwrap();
+        let limits = GrantLimits {
+            expires_at: Some("2025-06-02T00:00:00Z".to_string()),
+            max_uses: Some(2),
+            uses: 1,
+        };
+        assert!(limits.is_active_at(now));
+        assert!(limits.is_expired_at(time::parse_to_utc("2025-06-02T00:00:00Z").unwrap()));
+
+        let used_up = GrantLimits { uses: 2, ..limits };
+        assert!(used_up.is_exhausted());
+
+        let grant = Grant::new("bob".into(), "markdown.write".into(), "b1".into())
+            .with_limits(Some(used_up), now);
+        assert!(grant.expired);
     }
 }
@@ -6,12 +6,17 @@ mod event;
 mod grant;
 pub mod metadata;
 pub mod payloads;
+mod role;
 
 pub use block::{Block, RELATION_IMPLEMENT};
 pub use capability::Capability;
 pub use command::Command;
 pub use editor::{Editor, EditorType};
-pub use event::Event;
-pub use grant::Grant;
+pub use event::{Event, EventSignature, OriginSignature};
+pub use grant::{
+    capability_matches, BlockContainers, Delegation, Grant, GrantLimits, GrantScope, PROJECT_SCOPE,
+    SCOPE_DESCENDANTS_PREFIX, SCOPE_SUBTREE_PREFIX,
+};
 pub use metadata::BlockMetadata;
 pub use payloads::*;
+pub use role::{Role, RoleAssignment};
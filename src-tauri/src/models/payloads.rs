@@ -50,6 +50,44 @@ pub struct UnlinkBlockPayload {
     pub target_id: String,
 }
 
+/// How core.delete treats blocks that still reference or depend on the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteMode {
+    /// Reject the delete if the block has `implement` children or is referenced
+    /// by a directory entry.
+    Refuse,
+    /// Delete the block together with its `implement` descendants, up to `depth`.
+    /// Descendants that still have a parent outside the deleted set are kept.
+    Cascade,
+    /// Delete only this block; links and directory entries pointing at it are removed.
+    #[default]
+    Detach,
+}
+
+/// Payload for core.delete capability
+///
+/// An empty payload (`{}`) deletes the block in `detach` mode.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct DeleteBlockPayload {
+    /// Delete strategy (defaults to `detach`)
+    #[serde(default)]
+    pub mode: DeleteMode,
+    /// Maximum relation depth for `cascade` mode (None = unlimited, 0 = only this block)
+    #[serde(default)]
+    pub depth: Option<u32>,
+}
+
+/// Payload for core.restore capability
+///
+/// An empty payload (`{}`) restores only the target block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct RestoreBlockPayload {
+    /// Also restore blocks that were deleted by a cascade from the target
+    #[serde(default)]
+    pub include_cascaded: bool,
+}
+
 /// Payload for core.grant capability
 ///
 /// This payload is used to grant a capability to an editor for a specific block.
@@ -57,11 +95,22 @@ pub struct UnlinkBlockPayload {
 pub struct GrantPayload {
     /// The editor ID to grant the capability to
     pub target_editor: String,
-    /// The capability ID to grant (e.g., "markdown.write", "core.delete")
+    /// The capability ID to grant (e.g., "markdown.write", "core.delete"),
+    /// or a pattern such as "markdown.*"
     pub capability: String,
-    /// The block ID to grant access to, or "*" for all blocks (wildcard)
+    /// The block ID to grant access to, "*" for all blocks (wildcard),
+    /// "subtree:{dir_id}" or "descendants:{block_id}"
     #[serde(default = "default_wildcard")]
     pub target_block: String,
+    /// Optional RFC 3339 timestamp after which the grant no longer applies
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// Optional maximum number of times the grant may be used
+    #[serde(default)]
+    pub max_uses: Option<u32>,
+    /// Whether the target editor may grant the capability on to others
+    #[serde(default)]
+    pub grantable: bool,
 }
 
 /// Payload for core.revoke capability
@@ -71,9 +120,90 @@ pub struct GrantPayload {
 pub struct RevokePayload {
     /// The editor ID to revoke the capability from
     pub target_editor: String,
-    /// The capability ID to revoke
+    /// The capability ID (or pattern) to revoke, as it was granted
+    pub capability: String,
+    /// The grant target to revoke, as it was granted (block ID, "*",
+    /// "subtree:{dir_id}" or "descendants:{block_id}")
+    #[serde(default = "default_wildcard")]
+    pub target_block: String,
+    /// Also revoke every grant delegated from this one, directly or indirectly
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+/// Payload for core.deny capability
+///
+/// Forbids an editor from using a capability on a block, even if a grant
+/// (including a wildcard grant) or role would allow it.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct DenyPayload {
+    /// The editor ID to deny, or "*" for all editors
+    pub target_editor: String,
+    /// The capability ID to deny (e.g., "markdown.write"), or a pattern such as "markdown.*"
+    pub capability: String,
+    /// The block ID to deny access to, "*" for all blocks (wildcard),
+    /// "subtree:{dir_id}" or "descendants:{block_id}"
+    #[serde(default = "default_wildcard")]
+    pub target_block: String,
+}
+
+/// Payload for core.undeny capability
+///
+/// Removes a deny rule previously added with core.deny.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct UndenyPayload {
+    /// The editor ID of the deny rule
+    pub target_editor: String,
+    /// The capability ID (or pattern) of the deny rule
     pub capability: String,
-    /// The block ID to revoke access from, or "*" for all blocks (wildcard)
+    /// The target of the deny rule, as it was denied
+    #[serde(default = "default_wildcard")]
+    pub target_block: String,
+}
+
+/// Payload for core.define_role capability
+///
+/// Creates a role or replaces the definition of an existing one.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct DefineRolePayload {
+    /// The role name (e.g., "reviewer")
+    pub name: String,
+    /// Capabilities the role bundles (e.g., ["markdown.read", "code.read"])
+    pub capabilities: Vec<String>,
+    /// Optional block the role is limited to (omit for any block)
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// Payload for core.delete_role capability
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct DeleteRolePayload {
+    /// The role name to delete
+    pub name: String,
+}
+
+/// Payload for core.grant_role capability
+///
+/// This payload is used to give a role to an editor for a specific block.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct GrantRolePayload {
+    /// The editor ID to give the role to
+    pub target_editor: String,
+    /// The role name
+    pub role: String,
+    /// The block ID the role applies to, or "*" for all blocks (wildcard)
+    #[serde(default = "default_wildcard")]
+    pub target_block: String,
+}
+
+/// Payload for core.revoke_role capability
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct RevokeRolePayload {
+    /// The editor ID to take the role from
+    pub target_editor: String,
+    /// The role name
+    pub role: String,
+    /// The block ID the role was given on, or "*" for all blocks (wildcard)
     #[serde(default = "default_wildcard")]
     pub target_block: String,
 }
@@ -203,6 +333,45 @@ mod tests {
         assert_eq!(payload.target_id, "block-789");
     }
 
+    #[test]
+    fn test_delete_block_payload_defaults_to_detach() {
+        let payload: DeleteBlockPayload = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(payload.mode, DeleteMode::Detach);
+        assert!(payload.depth.is_none());
+    }
+
+    #[test]
+    fn test_delete_block_payload_cascade_with_depth() {
+        let json = serde_json::json!({ "mode": "cascade", "depth": 2 });
+        let payload: DeleteBlockPayload = serde_json::from_value(json).unwrap();
+        assert_eq!(payload.mode, DeleteMode::Cascade);
+        assert_eq!(payload.depth, Some(2));
+    }
+
+    #[test]
+    fn test_restore_block_payload_defaults() {
+        let payload: RestoreBlockPayload = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(!payload.include_cascaded);
+    }
+
+    #[test]
+    fn test_define_role_payload_scope_defaults_to_none() {
+        let json = serde_json::json!({
+            "name": "reviewer",
+            "capabilities": ["markdown.read", "code.read"]
+        });
+        let payload: DefineRolePayload = serde_json::from_value(json).unwrap();
+        assert_eq!(payload.capabilities.len(), 2);
+        assert!(payload.scope.is_none());
+    }
+
+    #[test]
+    fn test_grant_role_payload_with_wildcard_default() {
+        let json = serde_json::json!({ "target_editor": "bob", "role": "reviewer" });
+        let payload: GrantRolePayload = serde_json::from_value(json).unwrap();
+        assert_eq!(payload.target_block, "*");
+    }
+
     #[test]
     fn test_grant_payload_with_wildcard_default() {
         let json = serde_json::json!({
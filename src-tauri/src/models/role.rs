@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// A named bundle of capabilities.
+///
+/// Roles are defined by `core.define_role` events and given to editors with
+/// `core.grant_role`. Redefining a role changes what every holder can do.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct Role {
+    /// Unique role name (e.g., "reviewer")
+    pub name: String,
+
+    /// Capabilities the role bundles (e.g., "markdown.read", "code.read")
+    pub capabilities: Vec<String>,
+
+    /// Optional block the role is limited to (None = any block)
+    pub scope: Option<String>,
+}
+
+impl Role {
+    /// Check if the role's scope covers a specific block
+    pub fn applies_to_block(&self, block_id: &str) -> bool {
+        match self.scope.as_deref() {
+            None | Some("*") => true,
+            Some(scope) => scope == block_id,
+        }
+    }
+}
+
+/// An editor holding a role on a block (or "*" for all blocks).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct RoleAssignment {
+    /// The editor holding the role
+    pub editor_id: String,
+
+    /// The role name
+    pub role: String,
+
+    /// The target block ID, or "*" for wildcard (all blocks)
+    pub block_id: String,
+}
+
+impl RoleAssignment {
+    /// Create a new RoleAssignment
+    pub fn new(editor_id: String, role: String, block_id: String) -> Self {
+        Self {
+            editor_id,
+            role,
+            block_id,
+        }
+    }
+}
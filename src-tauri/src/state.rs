@@ -1,5 +1,7 @@
 use crate::elf::ElfArchive;
 use crate::engine::EngineManager;
+use crate::extensions::directory::linked::LinkWatcher;
+use crate::sync::SyncServer;
 use dashmap::DashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -28,6 +30,12 @@ pub struct AppState {
     /// This is UI state and is NOT persisted to .elf file
     /// Using DashMap for thread-safe concurrent access
     pub active_editors: Arc<DashMap<String, String>>,
+
+    /// Map of file_id -> sync server accepting peers for that file
+    pub sync_servers: Arc<DashMap<String, SyncServer>>,
+
+    /// Map of (file_id, block_id) -> watcher keeping a linked directory in sync
+    pub link_watchers: Arc<DashMap<(String, String), LinkWatcher>>,
 }
 
 impl AppState {
@@ -37,6 +45,8 @@ impl AppState {
             engine_manager: EngineManager::new(),
             files: Arc::new(DashMap::new()),
             active_editors: Arc::new(DashMap::new()),
+            sync_servers: Arc::new(DashMap::new()),
+            link_watchers: Arc::new(DashMap::new()),
         }
     }
 
@@ -0,0 +1,203 @@
+//! Peer authentication and sealed messages for sync sessions.
+//!
+//! Both peers know a shared secret. After exchanging random nonces, each side
+//! proves it knows the secret with an HMAC over both nonces, its own first, so
+//! a proof can't be reflected back at its sender. Nothing about the project is
+//! sent before the peer's proof checks out. Every later message is sealed with
+//! AES-256-GCM under a key per direction, derived from the secret and the
+//! nonces, with a message counter as the AES nonce.
+
+use super::{read_message, write_message, SyncMessage, PROTOCOL_VERSION};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::io::{AsyncRead, AsyncWrite, BufReader, Lines};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shortest secret peers may sync with
+pub const MIN_SECRET_LEN: usize = 8;
+
+const AUTH_LABEL: &[u8] = b"elfiee-sync auth";
+const KEY_LABEL: &[u8] = b"elfiee-sync key";
+
+/// HMAC-SHA256 keyed with the secret over a label and two nonces.
+fn mac(secret: &str, label: &[u8], first: &[u8], second: &[u8]) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(label);
+    mac.update(first);
+    mac.update(second);
+    mac
+}
+
+/// Authenticate the peer on a fresh connection.
+///
+/// Returns the sealer for the messages to send and the opener for the
+/// messages received for the rest of the session.
+pub async fn handshake<R, W>(
+    lines: &mut Lines<BufReader<R>>,
+    writer: &mut W,
+    secret: &str,
+) -> Result<(Sealer, Opener), String>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    if secret.chars().count() < MIN_SECRET_LEN {
+        return Err(format!(
+            "The sync secret must be at least {} characters",
+            MIN_SECRET_LEN
+        ));
+    }
+
+    let nonce: [u8; 32] = rand::random();
+    write_message(
+        writer,
+        &SyncMessage::Hello {
+            version: PROTOCOL_VERSION,
+            nonce: hex::encode(nonce),
+        },
+    )
+    .await?;
+
+    let peer_nonce = match read_message(lines).await? {
+        SyncMessage::Hello { version, nonce } => {
+            if version != PROTOCOL_VERSION {
+                return Err(format!(
+                    "Peer speaks sync protocol version {}, expected {}",
+                    version, PROTOCOL_VERSION
+                ));
+            }
+            hex::decode(&nonce)
+                .ok()
+                .filter(|nonce| nonce.len() == 32)
+                .ok_or("Invalid hello from peer")?
+        }
+        other => return Err(format!("Expected hello from peer, got {:?}", other)),
+    };
+    if peer_nonce == nonce {
+        return Err("Peer sent back our own nonce".to_string());
+    }
+
+    let proof = mac(secret, AUTH_LABEL, &nonce, &peer_nonce).finalize();
+    write_message(
+        writer,
+        &SyncMessage::Auth {
+            proof: hex::encode(proof.into_bytes()),
+        },
+    )
+    .await?;
+
+    match read_message(lines).await? {
+        SyncMessage::Auth { proof } => {
+            let proof = hex::decode(&proof).map_err(|_| "Invalid proof from peer")?;
+            mac(secret, AUTH_LABEL, &peer_nonce, &nonce)
+                .verify_slice(&proof)
+                .map_err(|_| "Peer does not know the sync secret")?;
+        }
+        other => return Err(format!("Expected proof from peer, got {:?}", other)),
+    }
+
+    let send_key = mac(secret, KEY_LABEL, &nonce, &peer_nonce).finalize();
+    let receive_key = mac(secret, KEY_LABEL, &peer_nonce, &nonce).finalize();
+    Ok((
+        Sealer::new(&send_key.into_bytes()),
+        Opener::new(&receive_key.into_bytes()),
+    ))
+}
+
+/// AES nonce of the `counter`-th message in one direction.
+fn counter_nonce(counter: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
+
+/// Seals the messages sent to the peer.
+pub struct Sealer {
+    cipher: Aes256Gcm,
+    counter: u64,
+}
+
+impl Sealer {
+    fn new(key: &[u8]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new_from_slice(key).expect("keys are 32 bytes"),
+            counter: 0,
+        }
+    }
+
+    /// Seal a message and send it.
+    pub async fn send<W>(&mut self, writer: &mut W, message: &SyncMessage) -> Result<(), String>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let plain = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+        let data = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&counter_nonce(self.counter)),
+                plain.as_slice(),
+            )
+            .map_err(|_| "Failed to seal message".to_string())?;
+        self.counter += 1;
+        write_message(
+            writer,
+            &SyncMessage::Sealed {
+                data: BASE64.encode(data),
+            },
+        )
+        .await
+    }
+}
+
+/// Opens the messages received from the peer.
+pub struct Opener {
+    cipher: Aes256Gcm,
+    counter: u64,
+}
+
+impl Opener {
+    fn new(key: &[u8]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new_from_slice(key).expect("keys are 32 bytes"),
+            counter: 0,
+        }
+    }
+
+    /// Receive the next message and open it.
+    ///
+    /// Messages that weren't sealed with the session key, or arrive out of
+    /// order, are refused.
+    pub async fn receive<R>(
+        &mut self,
+        lines: &mut Lines<BufReader<R>>,
+    ) -> Result<SyncMessage, String>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let SyncMessage::Sealed { data } = read_message(lines).await? else {
+            return Err("Expected a sealed message from peer".to_string());
+        };
+        let data = BASE64
+            .decode(data)
+            .map_err(|_| "Invalid sealed message from peer")?;
+        let plain = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&counter_nonce(self.counter)),
+                data.as_slice(),
+            )
+            .map_err(|_| "Sealed message from peer does not open")?;
+        self.counter += 1;
+
+        match serde_json::from_slice(&plain) {
+            Ok(SyncMessage::Sealed { .. }) => Err("Nested sealed message from peer".to_string()),
+            Ok(message) => Ok(message),
+            Err(e) => Err(format!("Invalid message from peer: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Peer-to-peer sync of event logs between running instances of a project.
+//!
+//! Two engines (or an engine and a relay process, see `elfiee-cli relay`)
+//! exchange the events the other is missing over a byte stream, usually TCP.
+//! Messages are JSON objects, one per line. A session is symmetric:
+//!
+//! ```text
+//! A -> B  hello     { version, nonce }
+//! B -> A  hello     { version, nonce }
+//! A -> B  auth      { proof over both nonces }
+//! B -> A  auth      { proof over both nonces }
+//! A -> B  inventory { genesis, ids of all events }      (sealed)
+//! B -> A  inventory { genesis, ids of all events }      (sealed)
+//! A -> B  events    { events B doesn't have }           (sealed)
+//! B -> A  events    { events A doesn't have }           (sealed)
+//! ```
+//!
+//! Both peers must know the same sync secret; see [`handshake()`] for how it is
+//! proven and how the rest of the session is sealed. Nothing from the log is
+//! sent to a peer that hasn't proven it. An event is missing on the other side
+//! if its id isn't in the other side's inventory, so events written
+//! independently on both sides are always exchanged. `genesis` is the first
+//! event of the log, so that instances of different projects refuse to sync;
+//! peers start from copies of the same file.
+//!
+//! Inventories list event ids rather than the vector clocks events carry
+//! (`Event::timestamp`), because a clock can't tell what a peer is missing:
+//!
+//! - An editor id isn't bound to one instance. The same editor can write in
+//!   both copies, and each copy then counts its own events from the same
+//!   number, so two different events carry the same count for that editor.
+//! - A peer's clock is the maximum of the clocks of the events it applied. An
+//!   event it refused (say, before the grant that authorizes it arrived) is
+//!   still covered once a later event that saw it is applied, and would never
+//!   be offered again.
+//!
+//! An id list grows with the log, but it never leaves an event out.
+//!
+//! Only signed files sync. Received events go through
+//! [`EngineHandle::receive_events`], which verifies each against the trusted
+//! key of its editor, runs the events of each command through the same checks
+//! as a local command (grants, checked at the time of arrival, and the command
+//! validators), and links them into the local signature chain in the name of
+//! the receiving editor. A command is taken in or refused as a whole. Unsigned
+//! events are refused.
+
+mod handshake;
+mod server;
+
+pub use handshake::{handshake, Opener, Sealer, MIN_SECRET_LEN};
+pub use server::{connect, SyncServer};
+
+use crate::engine::{EngineHandle, RejectedEvent};
+use crate::models::Event;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashSet;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+
+/// Version of the sync protocol; peers with another version refuse to sync
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// A message of the sync protocol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncMessage {
+    /// Protocol version and a fresh random nonce (hex)
+    Hello { version: u32, nonce: String },
+    /// Proof that the sender knows the sync secret (hex HMAC)
+    Auth { proof: String },
+    /// Another message, sealed with the session key (base64)
+    Sealed { data: String },
+    /// What the sender has: its first event and the ids of all its events
+    Inventory {
+        genesis: Option<String>,
+        event_ids: Vec<String>,
+    },
+    /// Events the receiver is missing, in the sender's log order
+    Events { events: Vec<Event> },
+}
+
+/// Result of a sync session, from the local side.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct SyncReport {
+    /// Events sent to the peer
+    pub sent: usize,
+    /// Events received from the peer
+    pub received: usize,
+    /// Received events appended to the local log
+    pub applied: usize,
+    /// Received events refused: bad signature, missing capability or failed check
+    pub rejected: Vec<RejectedEvent>,
+}
+
+/// Run a sync session with a peer over `stream`.
+///
+/// The peer must know `secret`. Received events are linked into the local
+/// signature chain by `receiver`, an editor with an enrolled key.
+pub async fn sync_session<S>(
+    handle: &EngineHandle,
+    stream: S,
+    secret: &str,
+    receiver: &str,
+) -> Result<SyncReport, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    let (mut sealer, mut opener) = handshake::handshake(&mut lines, &mut writer, secret).await?;
+
+    let events = handle.get_all_events().await?;
+    let genesis = events.first().map(|event| event.event_id.clone());
+    sealer
+        .send(
+            &mut writer,
+            &SyncMessage::Inventory {
+                genesis: genesis.clone(),
+                event_ids: events.iter().map(|event| event.event_id.clone()).collect(),
+            },
+        )
+        .await?;
+
+    let (peer_ids, peer_genesis) = match opener.receive(&mut lines).await? {
+        SyncMessage::Inventory { genesis, event_ids } => {
+            (event_ids.into_iter().collect::<HashSet<_>>(), genesis)
+        }
+        other => return Err(format!("Expected inventory from peer, got {:?}", other)),
+    };
+    if let (Some(ours), Some(theirs)) = (&genesis, &peer_genesis) {
+        if ours != theirs {
+            return Err("Peer has a different project".to_string());
+        }
+    }
+
+    // Send and receive at the same time, so large batches can't block each other
+    let missing = missing_events(events, &peer_ids);
+    let sent = missing.len();
+    let send = async {
+        sealer
+            .send(&mut writer, &SyncMessage::Events { events: missing })
+            .await?;
+        writer
+            .shutdown()
+            .await
+            .map_err(|e| format!("Failed to send to peer: {}", e))
+    };
+    let receive = async {
+        match opener.receive(&mut lines).await? {
+            SyncMessage::Events { events } => Ok(events),
+            other => Err(format!("Expected events from peer, got {:?}", other)),
+        }
+    };
+    let (sent_result, received) = tokio::join!(send, receive);
+    sent_result?;
+    let received = received?;
+
+    let report = handle.receive_events(received, receiver).await?;
+    Ok(SyncReport {
+        sent,
+        received: report.received,
+        applied: report.applied,
+        rejected: report.rejected,
+    })
+}
+
+/// Events whose ids aren't in `peer_ids`, in log order.
+pub fn missing_events(events: Vec<Event>, peer_ids: &HashSet<String>) -> Vec<Event> {
+    events
+        .into_iter()
+        .filter(|event| !peer_ids.contains(&event.event_id))
+        .collect()
+}
+
+/// Write one message as a line of JSON.
+pub async fn write_message<W>(writer: &mut W, message: &SyncMessage) -> Result<(), String>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to send to peer: {}", e))?;
+    writer
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to send to peer: {}", e))
+}
+
+/// Read the next message.
+pub async fn read_message<R>(lines: &mut Lines<BufReader<R>>) -> Result<SyncMessage, String>
+where
+    R: AsyncRead + Unpin,
+{
+    let line = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("Failed to read from peer: {}", e))?
+        .ok_or("Peer closed the connection")?;
+    serde_json::from_str(&line).map_err(|e| format!("Invalid message from peer: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_events_by_id() {
+        let event = |editor_id: &str, clock: &[(&str, i64)]| {
+            Event::new(
+                "block1".to_string(),
+                format!("{}/markdown.write", editor_id),
+                serde_json::json!({}),
+                clock.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+            )
+        };
+        let events = vec![
+            event("alice", &[("alice", 1)]),
+            event("alice", &[("alice", 2)]),
+            event("bob", &[("alice", 2), ("bob", 1)]),
+        ];
+
+        // The peer wrote its own event at alice's second count: ours is still missing there
+        let peer_ids = HashSet::from([events[0].event_id.clone(), "theirs".to_string()]);
+        let missing = missing_events(events, &peer_ids);
+        assert_eq!(missing.len(), 2);
+        assert_eq!(missing[0].timestamp["alice"], 2);
+        assert!(missing[1].attribute.starts_with("bob/"));
+    }
+}
@@ -0,0 +1,95 @@
+//! TCP transport for sync sessions.
+
+use super::{sync_session, SyncReport, MIN_SECRET_LEN};
+use crate::engine::EngineHandle;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Accepts sync sessions from peers for one open project.
+///
+/// Each connection is one session; the server stops when dropped.
+#[derive(Debug)]
+pub struct SyncServer {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl SyncServer {
+    /// Listen on `addr` (port 0 picks a free port).
+    ///
+    /// Peers must know `secret`; their events are taken in by `receiver`.
+    pub async fn start(
+        handle: EngineHandle,
+        addr: SocketAddr,
+        secret: String,
+        receiver: String,
+    ) -> Result<Self, String> {
+        if secret.chars().count() < MIN_SECRET_LEN {
+            return Err(format!(
+                "The sync secret must be at least {} characters",
+                MIN_SECRET_LEN
+            ));
+        }
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("Sync: Failed to bind to {}: {}", addr, e))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| format!("Sync: Failed to get local address: {}", e))?;
+
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        log::error!("Sync: Failed to accept connection: {}", e);
+                        continue;
+                    }
+                };
+                let handle = handle.clone();
+                let secret = secret.clone();
+                let receiver = receiver.clone();
+                tokio::spawn(async move {
+                    match sync_session(&handle, stream, &secret, &receiver).await {
+                        Ok(report) => log::info!(
+                            "Sync with {}: sent {}, applied {} of {}, rejected {}",
+                            peer,
+                            report.sent,
+                            report.applied,
+                            report.received,
+                            report.rejected.len()
+                        ),
+                        Err(e) => log::warn!("Sync with {} failed: {}", peer, e),
+                    }
+                });
+            }
+        });
+
+        Ok(Self { local_addr, task })
+    }
+
+    /// Address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for SyncServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Sync with the peer listening on `addr`, which must know `secret`.
+pub async fn connect(
+    handle: &EngineHandle,
+    addr: &str,
+    secret: &str,
+    receiver: &str,
+) -> Result<SyncReport, String> {
+    let stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| format!("Sync: Failed to connect to {}: {}", addr, e))?;
+    sync_session(handle, stream, secret, receiver).await
+}
@@ -0,0 +1,116 @@
+/// Asset block utilities.
+///
+/// An `asset` block holds a binary file (image, PDF, archive, ...). Its bytes
+/// live in the archive's blob store (`blobs/{sha256}`), not in the event log.
+/// The block contents only describe them:
+///
+/// ```json
+/// { "hash": "<sha256 hex>", "mime": "image/png", "size": 1234 }
+/// ```
+use crate::elf::BlobStore;
+use std::path::Path;
+
+/// Guess the MIME type of a file from its extension.
+///
+/// Unknown extensions are `application/octet-stream`.
+pub fn infer_mime_type(extension: &str) -> &'static str {
+    match extension.to_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "avi" => "video/x-msvideo",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "tar" => "application/x-tar",
+        "gz" => "application/gzip",
+        "7z" => "application/x-7z-compressed",
+        "rar" => "application/vnd.rar",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Store an asset's bytes in the archive's blob store and return the block contents.
+///
+/// # Arguments
+/// - `archive_dir`: Directory the archive is extracted to (parent of the block directories)
+/// - `block_name`: Block name (used for the MIME type)
+/// - `bytes`: The asset's bytes
+pub fn store_asset(
+    archive_dir: &Path,
+    block_name: &str,
+    bytes: &[u8],
+) -> Result<serde_json::Value, String> {
+    let hash = BlobStore::new(archive_dir)
+        .put(bytes)
+        .map_err(|e| format!("Failed to store asset {}: {}", block_name, e))?;
+
+    let extension = Path::new(block_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    Ok(serde_json::json!({
+        "hash": hash,
+        "mime": infer_mime_type(extension),
+        "size": bytes.len(),
+    }))
+}
+
+/// Read an asset's bytes from the archive's blob store.
+///
+/// Fails if the blob is missing or no longer matches the recorded hash.
+pub fn read_asset(archive_dir: &Path, contents: &serde_json::Value) -> Result<Vec<u8>, String> {
+    let hash = contents
+        .get("hash")
+        .and_then(|v| v.as_str())
+        .ok_or("Asset block has no content hash")?;
+    BlobStore::new(archive_dir)
+        .get(hash)
+        .map_err(|e| format!("Failed to read asset {}: {}", hash, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_mime_type() {
+        assert_eq!(infer_mime_type("png"), "image/png");
+        assert_eq!(infer_mime_type("JPG"), "image/jpeg");
+        assert_eq!(infer_mime_type("pdf"), "application/pdf");
+        assert_eq!(infer_mime_type("xyz"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_store_and_read_asset() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let bytes = [0x89, b'P', b'N', b'G', 0, 1, 2, 3];
+        let contents = store_asset(temp.path(), "mock.png", &bytes).unwrap();
+
+        assert_eq!(contents["mime"], "image/png");
+        assert_eq!(contents["size"], bytes.len());
+        let hash = contents["hash"].as_str().unwrap();
+        assert_eq!(read_asset(temp.path(), &contents).unwrap(), bytes);
+
+        // Changed bytes no longer match the recorded hash
+        std::fs::write(temp.path().join("blobs").join(hash), b"other").unwrap();
+        let err = read_asset(temp.path(), &contents).unwrap_err();
+        assert!(err.contains("corrupted"), "{}", err);
+    }
+}
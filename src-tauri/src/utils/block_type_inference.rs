@@ -1,26 +1,34 @@
 /// Infer Block Type from file extension.
 ///
 /// Strategy:
-/// 1. Known markdown extensions -> "markdown"
-/// 2. Known code/config extensions -> "code"
-/// 3. Known binary extensions (images, executables, etc.) -> None (Skip)
-/// 4. Unknown extensions -> Some("code") (Treat as plain text fallback)
+/// 1. Known binary document/media extensions (images, PDFs, archives, ...) -> "asset"
+/// 2. Known build artifacts and databases -> None (Skip)
+/// 3. Known markdown extensions -> "markdown"
+/// 4. Known code/config extensions -> "code"
+/// 5. Unknown extensions -> Some("code") (Treat as plain text fallback)
 pub fn infer_block_type(extension: &str) -> Option<String> {
     let ext = extension.to_lowercase();
 
-    // 1. Explicit Binary Blacklist - Do NOT import these into DB as text
+    // 1. Binary files worth keeping - stored as bytes in the block directory
     match ext.as_str() {
         "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "ico" | // Images
-        "mp4" | "mov" | "avi" | "mkv" |                          // Video
+        "bmp" | "tif" | "tiff" |
+        "mp4" | "mov" | "avi" | "mkv" | "webm" |                 // Video
         "mp3" | "wav" | "ogg" | "flac" |                         // Audio
-        "pdf" | "zip" | "tar" | "gz" | "7z" | "rar" |            // Archives
+        "pdf" | "zip" | "tar" | "gz" | "7z" | "rar" |            // Documents/Archives
+        "woff" | "woff2" | "ttf" | "otf" => return Some("asset".to_string()), // Fonts
+        _ => {}
+    }
+
+    // 2. Build artifacts and databases - Do NOT import these
+    match ext.as_str() {
         "exe" | "dll" | "so" | "dylib" | "bin" | "obj" | "o" |   // Binary/Compiled
         "pyc" | "class" | "wasm" |                               // Bytecode
         "db" | "sqlite" | "sqlite3" => return None,              // Databases
         _ => {}
     }
 
-    // 2. Specific Type Mapping
+    // 3. Specific Type Mapping
     match ext.as_str() {
         // Markdown
         "md" | "markdown" => Some("markdown".to_string()),
@@ -31,7 +39,7 @@ pub fn infer_block_type(extension: &str) -> Option<String> {
         | "xml" | "ini" | "conf" | "sh" | "bash" | "zsh" | "fish" | "html" | "htm" | "css"
         | "scss" | "sass" | "less" | "sql" => Some("code".to_string()),
 
-        // 3. Fallback: Treat everything else as plain text 'code' block
+        // 4. Fallback: Treat everything else as plain text 'code' block
         // This ensures we don't miss .env, .gitignore, license files, etc.
         _ => {
             log::debug!("Unknown extension '{}', defaulting to code block type", ext);
@@ -56,9 +64,15 @@ mod tests {
         assert_eq!(infer_block_type("json"), Some("code".to_string()));
     }
 
+    #[test]
+    fn test_binary_assets() {
+        assert_eq!(infer_block_type("png"), Some("asset".to_string()));
+        assert_eq!(infer_block_type("PDF"), Some("asset".to_string()));
+        assert_eq!(infer_block_type("zip"), Some("asset".to_string()));
+    }
+
     #[test]
     fn test_binary_blacklist() {
-        assert_eq!(infer_block_type("png"), None);
         assert_eq!(infer_block_type("exe"), None);
         assert_eq!(infer_block_type("wasm"), None);
         assert_eq!(infer_block_type("db"), None);
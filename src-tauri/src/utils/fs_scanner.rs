@@ -1,5 +1,7 @@
+use super::ignore_rules::{glob_match, IgnoreFile, IGNORE_FILE_NAMES};
+use serde::{Deserialize, Serialize};
+use std::fs;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
 
 /// Information about a scanned file.
 ///
@@ -37,6 +39,12 @@ pub struct ScanOptions {
     pub max_file_size: u64,
     /// Maximum number of files to scan
     pub max_files: usize,
+    /// Globs a file must match one of to be included (empty includes every file)
+    pub include: Vec<String>,
+    /// Globs of files and directories to leave out
+    pub exclude: Vec<String>,
+    /// Whether to honour `.gitignore`, `.ignore` and `.elfignore` files at any level
+    pub respect_ignore_files: bool,
 }
 
 impl Default for ScanOptions {
@@ -55,89 +63,225 @@ impl Default for ScanOptions {
             ],
             max_file_size: 10 * 1024 * 1024, // 10 MB
             max_files: 10_000,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_ignore_files: true,
         }
     }
 }
 
+/// A file or directory left out of a scan, with the reason.
+///
+/// A skipped directory stands for everything in it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedFile {
+    /// Path relative to the scan root
+    pub path: String,
+    pub reason: String,
+}
+
+/// Files found by a scan and the ones left out.
+#[derive(Debug, Clone, Default)]
+pub struct ScanResult {
+    pub files: Vec<FileInfo>,
+    pub skipped: Vec<SkippedFile>,
+}
+
 /// Scan a directory and return a list of files
 pub fn scan_directory(root: &Path, options: &ScanOptions) -> Result<Vec<FileInfo>, String> {
-    let mut files = Vec::new();
-    let mut count = 0;
+    scan_directory_with_skipped(root, options).map(|result| result.files)
+}
+
+/// Scan a directory and return the files found and the ones left out.
+///
+/// Entries are checked in this order, and the first reason to leave one out
+/// is reported: hidden names, `ignore_patterns`, ignore files (the deepest
+/// one with a matching rule decides), `exclude`, then for files `include`
+/// and `max_file_size`. Directories that are left out are not descended into.
+pub fn scan_directory_with_skipped(
+    root: &Path,
+    options: &ScanOptions,
+) -> Result<ScanResult, String> {
+    let mut scan = Scan {
+        options,
+        ignore_files: Vec::new(),
+        result: ScanResult::default(),
+        count: 0,
+    };
+    scan.walk(root, "", 1)?;
+    Ok(scan.result)
+}
+
+/// State of a scan in progress.
+struct Scan<'a> {
+    options: &'a ScanOptions,
+    /// Ignore files of the folders being walked, outermost first
+    ignore_files: Vec<IgnoreFile>,
+    result: ScanResult,
+    count: usize,
+}
 
-    let walker = WalkDir::new(root)
-        .max_depth(options.max_depth)
-        .follow_links(options.follow_symlinks);
+impl Scan<'_> {
+    /// Scan the entries of `dir` (`relative` to the root) at `depth`.
+    fn walk(&mut self, dir: &Path, relative: &str, depth: usize) -> Result<(), String> {
+        let pushed = self.read_ignore_files(dir, relative);
 
-    for entry in walker {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let path = entry.path();
+        let mut entries = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read directory {:?}: {}", dir, e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        entries.sort_by_key(|entry| entry.file_name());
 
-        // Check file count limit
-        count += 1;
-        if count > options.max_files {
-            return Err(format!("Too many files (limit: {})", options.max_files));
-        }
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative_path = if relative.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", relative, name)
+            };
+
+            let metadata = if self.options.follow_symlinks {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            }
+            .map_err(|e| format!("Failed to read metadata: {}", e))?;
+            if metadata.file_type().is_symlink() {
+                self.skip(relative_path, "symbolic link".to_string());
+                continue;
+            }
+            let is_directory = metadata.is_dir();
 
-        // Skip hidden files
-        if options.ignore_hidden {
-            if let Some(name) = path.file_name() {
-                if name.to_string_lossy().starts_with('.') {
+            if let Some(reason) = self.excluded(&name, &relative_path, is_directory) {
+                self.skip(relative_path, reason);
+                continue;
+            }
+            if !is_directory {
+                if let Some(reason) = self.not_wanted(&relative_path, metadata.len()) {
+                    self.skip(relative_path, reason);
                     continue;
                 }
             }
-        }
 
-        // Skip ignored directories
-        let should_skip = options.ignore_patterns.iter().any(|pattern| {
-            path.components()
-                .any(|c| c.as_os_str().to_string_lossy() == *pattern)
-        });
-        if should_skip {
-            continue;
-        }
+            // Check file count limit
+            self.count += 1;
+            if self.count > self.options.max_files {
+                return Err(format!(
+                    "Too many files (limit: {})",
+                    self.options.max_files
+                ));
+            }
 
-        let metadata = entry
-            .metadata()
-            .map_err(|e| format!("Failed to read metadata: {}", e))?;
+            self.result.files.push(FileInfo {
+                absolute_path: path.clone(),
+                relative_path: relative_path.clone(),
+                file_name: name,
+                extension: path
+                    .extension()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                size: metadata.len(),
+                is_directory,
+            });
 
-        // Skip large files
-        if metadata.is_file() && metadata.len() > options.max_file_size {
-            log::warn!("Skipping large file: {:?} ({} bytes)", path, metadata.len());
-            continue;
+            if is_directory {
+                if depth < self.options.max_depth {
+                    self.walk(&path, &relative_path, depth + 1)?;
+                } else if fs::read_dir(&path).is_ok_and(|mut d| d.next().is_some()) {
+                    let reason = format!("contents deeper than {} levels", self.options.max_depth);
+                    self.skip(relative_path, reason);
+                }
+            }
         }
 
-        let relative_path = path
-            .strip_prefix(root)
-            .map_err(|e| format!("Failed to strip prefix: {}", e))?
-            .to_string_lossy()
-            .to_string();
+        self.ignore_files.truncate(self.ignore_files.len() - pushed);
+        Ok(())
+    }
 
-        // Skip root directory itself
-        if relative_path.is_empty() {
-            continue;
+    /// Read the ignore files of `dir`, returning how many were added.
+    fn read_ignore_files(&mut self, dir: &Path, relative: &str) -> usize {
+        if !self.options.respect_ignore_files {
+            return 0;
         }
+        let mut pushed = 0;
+        for name in IGNORE_FILE_NAMES {
+            let Ok(text) = fs::read_to_string(dir.join(name)) else {
+                continue;
+            };
+            let source = if relative.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", relative, name)
+            };
+            self.ignore_files
+                .push(IgnoreFile::parse(&source, relative, &text));
+            pushed += 1;
+        }
+        pushed
+    }
 
-        let file_info = FileInfo {
-            absolute_path: path.to_path_buf(),
-            relative_path: relative_path.clone(),
-            file_name: path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            extension: path
-                .extension()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            size: metadata.len(),
-            is_directory: metadata.is_dir(),
-        };
+    /// Why an entry is left out by name or by rule, if it is.
+    fn excluded(&self, name: &str, relative_path: &str, is_directory: bool) -> Option<String> {
+        if self.options.ignore_hidden && name.starts_with('.') {
+            return Some("hidden".to_string());
+        }
+        if self.options.ignore_patterns.iter().any(|p| p == name) {
+            return Some(format!("ignored by default pattern '{}'", name));
+        }
+        // Deeper ignore files override outer ones; within a file the last rule wins
+        for file in self.ignore_files.iter().rev() {
+            match file.check(relative_path, is_directory) {
+                Some((true, rule)) => {
+                    return Some(format!("ignored by {} ('{}')", file.source, rule))
+                }
+                Some((false, _)) => break,
+                None => {}
+            }
+        }
+        self.options
+            .exclude
+            .iter()
+            .find(|glob| matches_glob(glob, name, relative_path))
+            .map(|glob| format!("excluded by '{}'", glob))
+    }
+
+    /// Why a file is left out by `include` or its size, if it is.
+    fn not_wanted(&self, relative_path: &str, size: u64) -> Option<String> {
+        let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+        if !self.options.include.is_empty()
+            && !self
+                .options
+                .include
+                .iter()
+                .any(|glob| matches_glob(glob, name, relative_path))
+        {
+            return Some("not matched by any include pattern".to_string());
+        }
+        if size > self.options.max_file_size {
+            log::warn!("Skipping large file: {} ({} bytes)", relative_path, size);
+            return Some(format!(
+                "larger than {} bytes ({} bytes)",
+                self.options.max_file_size, size
+            ));
+        }
+        None
+    }
 
-        files.push(file_info);
+    fn skip(&mut self, path: String, reason: String) {
+        self.result.skipped.push(SkippedFile { path, reason });
     }
+}
 
-    Ok(files)
+/// Match an include or exclude glob: against the name if it has no `/`,
+/// otherwise against the path from the scan root.
+fn matches_glob(glob: &str, name: &str, relative_path: &str) -> bool {
+    if glob.contains('/') {
+        glob_match(glob.trim_start_matches('/'), relative_path)
+    } else {
+        glob_match(glob, name)
+    }
 }
 
 #[cfg(test)]
@@ -195,4 +339,104 @@ mod tests {
         assert_eq!(files.len(), 1);
         assert_eq!(files[0].file_name, "main.js");
     }
+
+    fn paths(files: &[FileInfo]) -> Vec<&str> {
+        files.iter().map(|f| f.relative_path.as_str()).collect()
+    }
+
+    fn reason<'a>(result: &'a ScanResult, path: &str) -> &'a str {
+        result
+            .skipped
+            .iter()
+            .find(|s| s.path == path)
+            .map(|s| s.reason.as_str())
+            .unwrap_or_else(|| panic!("{} not skipped: {:?}", path, result.skipped))
+    }
+
+    #[test]
+    fn test_scan_honours_ignore_files_at_any_level() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/gen")).unwrap();
+        fs::create_dir_all(root.join("logs")).unwrap();
+        fs::write(root.join(".gitignore"), "*.log\nlogs/\n").unwrap();
+        fs::write(root.join("src/.elfignore"), "gen/\n!keep.log\n").unwrap();
+        fs::write(root.join("a.log"), "x").unwrap();
+        fs::write(root.join("logs/b.txt"), "x").unwrap();
+        fs::write(root.join("src/main.rs"), "x").unwrap();
+        fs::write(root.join("src/keep.log"), "x").unwrap();
+        fs::write(root.join("src/gen/out.rs"), "x").unwrap();
+
+        let result = scan_directory_with_skipped(root, &ScanOptions::default()).unwrap();
+        assert_eq!(
+            paths(&result.files),
+            vec!["src", "src/keep.log", "src/main.rs"]
+        );
+        assert_eq!(reason(&result, "a.log"), "ignored by .gitignore ('*.log')");
+        assert_eq!(reason(&result, "logs"), "ignored by .gitignore ('logs/')");
+        assert_eq!(
+            reason(&result, "src/gen"),
+            "ignored by src/.elfignore ('gen/')"
+        );
+        // Skipped folders are reported once, not file by file
+        assert!(!result.skipped.iter().any(|s| s.path == "logs/b.txt"));
+
+        let options = ScanOptions {
+            respect_ignore_files: false,
+            ignore_hidden: false,
+            ..ScanOptions::default()
+        };
+        let files = scan_directory(root, &options).unwrap();
+        assert!(paths(&files).contains(&"src/gen/out.rs"));
+        assert!(paths(&files).contains(&".gitignore"));
+    }
+
+    #[test]
+    fn test_scan_include_and_exclude() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("docs/drafts")).unwrap();
+        fs::write(root.join("README.md"), "x").unwrap();
+        fs::write(root.join("notes.txt"), "x").unwrap();
+        fs::write(root.join("docs/guide.md"), "x").unwrap();
+        fs::write(root.join("docs/drafts/wip.md"), "x").unwrap();
+
+        let options = ScanOptions {
+            include: vec!["*.md".to_string()],
+            exclude: vec!["docs/drafts".to_string()],
+            ..ScanOptions::default()
+        };
+        let result = scan_directory_with_skipped(root, &options).unwrap();
+        assert_eq!(
+            paths(&result.files),
+            vec!["README.md", "docs", "docs/guide.md"]
+        );
+        assert_eq!(
+            reason(&result, "notes.txt"),
+            "not matched by any include pattern"
+        );
+        assert_eq!(reason(&result, "docs/drafts"), "excluded by 'docs/drafts'");
+    }
+
+    #[test]
+    fn test_scan_reports_hidden_depth_and_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join(".vscode")).unwrap();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join(".vscode/settings.json"), "{}").unwrap();
+        fs::write(root.join("a/b/deep.md"), "x").unwrap();
+        fs::write(root.join("big.txt"), "0123456789").unwrap();
+
+        let options = ScanOptions {
+            max_depth: 2,
+            max_file_size: 5,
+            ..ScanOptions::default()
+        };
+        let result = scan_directory_with_skipped(root, &options).unwrap();
+        assert_eq!(paths(&result.files), vec!["a", "a/b"]);
+        assert_eq!(reason(&result, ".vscode"), "hidden");
+        assert_eq!(reason(&result, "a/b"), "contents deeper than 2 levels");
+        assert_eq!(reason(&result, "big.txt"), "larger than 5 bytes (10 bytes)");
+    }
 }
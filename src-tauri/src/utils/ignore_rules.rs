@@ -0,0 +1,207 @@
+/// Gitignore-style rules for directory scans.
+///
+/// Supports what ignore files are usually written with: `*`, `?`, `[a-z]`
+/// and `**` globs, `!` negation, a trailing `/` for directories only, and
+/// a leading or inner `/` anchoring the pattern to the ignore file's folder.
+/// Patterns without a `/` match the name at any depth.
+
+/// Ignore files a scan honours in every folder, checked in this order.
+pub const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".ignore", ".elfignore"];
+
+/// Match `path` (with `/` separators) against a glob.
+///
+/// `*` and `?` don't match `/`; `**` matches any number of folders.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&pattern, &path)
+}
+
+fn matches(p: &[char], s: &[char]) -> bool {
+    match p.first() {
+        None => s.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            let rest = &p[2..];
+            match rest.split_first() {
+                // `**/`: zero or more whole folders
+                Some(('/', rest)) => {
+                    matches(rest, s)
+                        || (0..s.len()).any(|i| s[i] == '/' && matches(rest, &s[i + 1..]))
+                }
+                _ => (0..=s.len()).any(|i| matches(rest, &s[i..])),
+            }
+        }
+        Some('*') => (0..=s.len())
+            .take_while(|&i| i == 0 || s[i - 1] != '/')
+            .any(|i| matches(&p[1..], &s[i..])),
+        Some('?') => s.first().is_some_and(|&c| c != '/') && matches(&p[1..], &s[1..]),
+        Some('[') => match (class(p), s.first()) {
+            (Some((set, negated, len)), Some(&c)) => {
+                c != '/' && in_class(set, c) != negated && matches(&p[len..], &s[1..])
+            }
+            (Some(_), None) => false,
+            // No closing `]`: a literal `[`
+            (None, first) => first == Some(&'[') && matches(&p[1..], &s[1..]),
+        },
+        Some('\\') if p.len() > 1 => s.first() == Some(&p[1]) && matches(&p[2..], &s[1..]),
+        Some(&c) => s.first() == Some(&c) && matches(&p[1..], &s[1..]),
+    }
+}
+
+/// Parse a `[...]` class at the start of `p`: its set, whether it is negated, and its length.
+fn class(p: &[char]) -> Option<(&[char], bool, usize)> {
+    let negated = matches!(p.get(1), Some('!') | Some('^'));
+    let start = if negated { 2 } else { 1 };
+    // A `]` right after the opening bracket is part of the set
+    let close = start + 1 + p.get(start + 1..)?.iter().position(|&c| c == ']')?;
+    Some((&p[start..close], negated, close + 1))
+}
+
+/// Whether `c` is in a class set such as `a-z0-9_`.
+fn in_class(set: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            if set[i] <= c && c <= set[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if set[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+/// One line of an ignore file.
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// The line as written, for reports
+    line: String,
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the path below the ignore file's folder, not the name
+    anchored: bool,
+}
+
+/// The rules of one ignore file.
+#[derive(Debug, Clone)]
+pub struct IgnoreFile {
+    /// Path of the ignore file relative to the scan root, for reports
+    pub source: String,
+    /// Folder of the ignore file relative to the scan root ("" for the root)
+    base: String,
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+    /// Parse the text of the ignore file at `source`, which applies to `base`.
+    pub fn parse(source: &str, base: &str, text: &str) -> Self {
+        let rules = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let (negated, glob) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                // `\#` and `\!` start patterns that begin with those characters
+                let glob = match glob.strip_prefix('\\') {
+                    Some(rest) if rest.starts_with(['#', '!']) => rest,
+                    _ => glob,
+                };
+                let (dir_only, glob) = match glob.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, glob),
+                };
+                let anchored = glob.contains('/');
+                let glob = glob.strip_prefix('/').unwrap_or(glob);
+                (!glob.is_empty()).then(|| IgnoreRule {
+                    line: line.to_string(),
+                    glob: glob.to_string(),
+                    negated,
+                    dir_only,
+                    anchored,
+                })
+            })
+            .collect();
+
+        Self {
+            source: source.to_string(),
+            base: base.to_string(),
+            rules,
+        }
+    }
+
+    /// Whether the file ignores `path` (relative to the scan root): the last
+    /// matching rule decides. Returns the rule, or None if no rule matches.
+    pub fn check(&self, path: &str, is_dir: bool) -> Option<(bool, &str)> {
+        let below = if self.base.is_empty() {
+            path
+        } else {
+            path.strip_prefix(&self.base)?.strip_prefix('/')?
+        };
+        let name = below.rsplit('/').next().unwrap_or(below);
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only)
+                    && glob_match(&rule.glob, if rule.anchored { below } else { name })
+            })
+            .map(|rule| (!rule.negated, rule.line.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.log", "debug.log"));
+        assert!(!glob_match("*.log", "logs/debug.log"));
+        assert!(glob_match("**/*.log", "logs/debug.log"));
+        assert!(glob_match("**/*.log", "debug.log"));
+        assert!(glob_match("docs/**", "docs/a/b.md"));
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(glob_match("[a-c]*.rs", "build.rs"));
+        assert!(!glob_match("[!a-c]*.rs", "build.rs"));
+        assert!(glob_match("\\*.md", "*.md"));
+        assert!(glob_match("[x", "[x"));
+    }
+
+    #[test]
+    fn test_ignore_file_rules() {
+        let file = IgnoreFile::parse(
+            "src/.gitignore",
+            "src",
+            "# comment\n*.log\n!keep.log\n/build/\ngen/*.rs\n",
+        );
+        assert_eq!(file.check("src/a.log", false), Some((true, "*.log")));
+        assert_eq!(file.check("src/deep/a.log", false), Some((true, "*.log")));
+        assert_eq!(
+            file.check("src/keep.log", false),
+            Some((false, "!keep.log"))
+        );
+        assert_eq!(file.check("src/build", true), Some((true, "/build/")));
+        // `build/` only applies to folders, and `/build` only next to the ignore file
+        assert_eq!(file.check("src/build", false), None);
+        assert_eq!(file.check("src/deep/build", true), None);
+        assert_eq!(file.check("src/gen/a.rs", false), Some((true, "gen/*.rs")));
+        // Paths outside the ignore file's folder
+        assert_eq!(file.check("a.log", false), None);
+        assert_eq!(file.check("srcx/a.log", false), None);
+    }
+}
@@ -1,5 +1,7 @@
+pub mod asset;
 pub mod block_type_inference;
 pub mod fs_scanner;
+pub mod ignore_rules;
 pub mod path_validator;
 pub mod pty;
 pub mod snapshot;
@@ -17,8 +19,13 @@ pub use time::{
 /// Infers the block type based on file extension.
 pub use block_type_inference::infer_block_type;
 
+/// Asset block utilities for binary files stored in block directories.
+pub use asset::{infer_mime_type, read_asset, store_asset};
+
 /// Scans directories recursively with security limits and filtering.
-pub use fs_scanner::{scan_directory, FileInfo, ScanOptions};
+pub use fs_scanner::{
+    scan_directory, scan_directory_with_skipped, FileInfo, ScanOptions, ScanResult, SkippedFile,
+};
 
 /// Validates file paths to prevent traversal attacks and access to sensitive directories.
 pub use path_validator::{is_safe_path, validate_virtual_path};
@@ -53,6 +53,18 @@ fn extract_content(block_type: &str, contents: &serde_json::Value) -> Option<Str
     }
 }
 
+/// Get the snapshot a block should have: `(filename, content)`.
+///
+/// Returns `None` for blocks without snapshot content.
+pub(crate) fn expected_snapshot(
+    block_type: &str,
+    block_name: &str,
+    contents: &serde_json::Value,
+) -> Option<(String, String)> {
+    extract_content(block_type, contents)
+        .map(|content| (snapshot_filename(block_type, block_name), content))
+}
+
 /// Write a snapshot file for a block to its `block-{uuid}/` directory.
 ///
 /// Creates the block directory if it doesn't exist, then writes the
@@ -81,9 +93,8 @@ pub fn write_block_snapshot(
     fs::create_dir_all(&block_dir)
         .map_err(|e| format!("Failed to create block directory: {}", e))?;
 
-    let filename = snapshot_filename(block_type, block_name);
-    let content = match extract_content(block_type, contents) {
-        Some(c) => c,
+    let (filename, content) = match expected_snapshot(block_type, block_name, contents) {
+        Some(snapshot) => snapshot,
         None => return Ok(()), // No content to snapshot, skip silently
     };
 
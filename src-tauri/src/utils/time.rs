@@ -49,8 +49,7 @@ pub(crate) fn now_local() -> String {
 
 /// Parse RFC 3339 timestamp and convert to UTC DateTime (internal use).
 ///
-/// **CAUTION**: Only for testing. Production code should work with string timestamps.
-#[cfg_attr(not(test), allow(dead_code))]
+/// Used where timestamps must be compared, e.g. grant expiry checks.
 pub(crate) fn parse_to_utc(timestamp: &str) -> Result<DateTime<Utc>, String> {
     DateTime::parse_from_rfc3339(timestamp)
         .map(|dt| dt.with_timezone(&Utc))
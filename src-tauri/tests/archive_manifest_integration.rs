@@ -0,0 +1,213 @@
+/// 集成测试：.elf 的按条目压缩策略和 manifest.json
+///
+/// 验证：
+/// - events.db 用 zstd，已压缩的 asset blob 不再压缩，文本用 deflate
+/// - manifest.json 记录格式版本、创建者版本和每个条目的 hash
+/// - 更新的格式版本被拒绝并给出明确提示
+/// - 内容与 manifest 不符的文件被拒绝；没有 manifest 的旧文件照常打开
+use elfiee_lib::elf::manifest::{FORMAT_VERSION, MANIFEST_NAME};
+use elfiee_lib::elf::{BlobStore, ElfArchive, EntryCompression, Manifest};
+use elfiee_lib::engine::spawn_engine;
+use elfiee_lib::models::Command;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use tempfile::TempDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// 辅助函数：读取 zip 中的 manifest
+fn read_manifest(path: &Path) -> Manifest {
+    let mut zip = ZipArchive::new(File::open(path).unwrap()).unwrap();
+    let mut json = String::new();
+    zip.by_name(MANIFEST_NAME)
+        .unwrap()
+        .read_to_string(&mut json)
+        .unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+/// 辅助函数：按给定条目重写 zip（用于构造旧文件、新格式文件和损坏文件）
+fn rewrite_zip(path: &Path, edit: impl Fn(&str, Vec<u8>) -> Option<Vec<u8>>) {
+    let mut source = ZipArchive::new(File::open(path).unwrap()).unwrap();
+    let mut entries = Vec::new();
+    for i in 0..source.len() {
+        let mut file = source.by_index(i).unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        entries.push((file.name().to_string(), data));
+    }
+    let mut zip = ZipWriter::new(File::create(path).unwrap());
+    for (name, data) in entries {
+        if let Some(data) = edit(&name, data) {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(&data).unwrap();
+        }
+    }
+    zip.finish().unwrap();
+}
+
+/// 辅助函数：保存一个包含 markdown block 和 png asset 的项目，返回 block id
+async fn create_project(path: &Path) -> String {
+    ElfArchive::new().await.unwrap().save(path).await.unwrap();
+    let archive = ElfArchive::open(path).unwrap();
+    let handle = spawn_engine(
+        "test_manifest".to_string(),
+        archive.event_pool().await.unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "notes.md", "block_type": "markdown" }),
+    );
+    let block_id = handle.process_command(cmd).await.unwrap()[0].entity.clone();
+    let cmd = Command::new(
+        "alice".to_string(),
+        "markdown.write".to_string(),
+        block_id.clone(),
+        serde_json::json!({ "content": "# Notes\n".repeat(50) }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    png.resize(png.len() + 1024, 0);
+    let source = TempDir::new().unwrap();
+    std::fs::write(source.path().join("logo.png"), &png).unwrap();
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "assets", "block_type": "directory" }),
+    );
+    let dir_id = handle.process_command(cmd).await.unwrap()[0].entity.clone();
+    let cmd = Command::new(
+        "alice".to_string(),
+        "directory.import".to_string(),
+        dir_id,
+        serde_json::json!({ "source_path": source.path().to_str().unwrap() }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    handle.shutdown().await;
+    archive.save(path).await.unwrap();
+    block_id
+}
+
+#[tokio::test]
+async fn test_save_compresses_by_file_type_and_writes_manifest() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    let block_id = create_project(&path).await;
+
+    let manifest = read_manifest(&path);
+    assert_eq!(manifest.format_version, FORMAT_VERSION);
+    assert_eq!(manifest.creator_version, env!("CARGO_PKG_VERSION"));
+
+    let mut zip = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+    let method = |zip: &mut ZipArchive<File>, name: &str| zip.by_name(name).unwrap().compression();
+    assert_eq!(method(&mut zip, "events.db"), CompressionMethod::Zstd);
+    assert_eq!(
+        manifest.entries["events.db"].compression,
+        EntryCompression::Zstd
+    );
+
+    let body = format!("block-{}/body.md", block_id);
+    assert_eq!(method(&mut zip, &body), CompressionMethod::Deflated);
+
+    let blob = manifest
+        .entries
+        .keys()
+        .find(|name| name.starts_with("blobs/"))
+        .unwrap()
+        .clone();
+    assert_eq!(method(&mut zip, &blob), CompressionMethod::Stored);
+
+    // 每个文件条目都在 manifest 中，hash 与内容一致
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i).unwrap();
+        if file.is_dir() || file.name() == MANIFEST_NAME {
+            continue;
+        }
+        let name = file.name().to_string();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        let entry = &manifest.entries[&name];
+        assert_eq!(entry.size, data.len() as u64, "{}", name);
+        assert_eq!(entry.sha256, BlobStore::hash(&data), "{}", name);
+    }
+
+    // 未解压的 block 目录原样复制，manifest 条目保留
+    let reopened = ElfArchive::open(&path).unwrap();
+    let resaved = dir.path().join("resaved.elf");
+    reopened.save(&resaved).await.unwrap();
+    assert_eq!(
+        read_manifest(&resaved).entries[&body],
+        manifest.entries[&body]
+    );
+}
+
+#[tokio::test]
+async fn test_open_refuses_newer_format() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    create_project(&path).await;
+
+    rewrite_zip(&path, |name, data| {
+        if name != MANIFEST_NAME {
+            return Some(data);
+        }
+        let mut manifest: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        manifest["format_version"] = serde_json::json!(FORMAT_VERSION + 1);
+        manifest["creator_version"] = serde_json::json!("9.0.0");
+        manifest["entries"] = serde_json::json!("layout changed");
+        Some(serde_json::to_vec(&manifest).unwrap())
+    });
+
+    let err = ElfArchive::open(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    let message = err.to_string();
+    assert!(message.contains("9.0.0"), "{}", message);
+    assert!(message.contains("Update Elfiee"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_open_checks_entry_hashes_and_accepts_legacy_files() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    let block_id = create_project(&path).await;
+    let body = format!("block-{}/body.md", block_id);
+    let legacy = dir.path().join("legacy.elf");
+    std::fs::copy(&path, &legacy).unwrap();
+
+    // 篡改一个 block 文件：在按需解压时被发现
+    rewrite_zip(&path, |name, data| {
+        Some(if name == body {
+            b"tampered".to_vec()
+        } else {
+            data
+        })
+    });
+    let archive = ElfArchive::open(&path).unwrap();
+    let err = archive
+        .load_block_dir(&format!("block-{}", block_id))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // 没有 manifest 的旧文件照常打开，保存后补上 manifest
+    rewrite_zip(&legacy, |name, data| {
+        (name != MANIFEST_NAME).then_some(data)
+    });
+    let archive = ElfArchive::open(&legacy).unwrap();
+    archive.load_all().unwrap();
+    let pool = archive.event_pool().await.unwrap();
+    assert!(!elfiee_lib::engine::EventStore::get_all_events(&pool.pool)
+        .await
+        .unwrap()
+        .is_empty());
+    archive.save(&legacy).await.unwrap();
+    assert!(read_manifest(&legacy).entries.contains_key(&body));
+}
@@ -0,0 +1,344 @@
+/// 集成测试：自动保存与崩溃恢复
+///
+/// 验证 engine 驱动的自动保存与恢复日志：
+/// - 未保存就结束的会话，下次打开时可以恢复其事件
+/// - 放弃恢复后，事件不会再被提供
+/// - 加密文件的恢复日志用文件的密钥加密，不以明文落盘
+/// - 按事件数 / 按秒数的自动保存会把变更写入 .elf 文件
+/// - 文件在打开期间被其他程序改写时，保存会被拒绝，合并后才能保存；只修改时间戳不算改写
+/// - 合并时磁盘上的事件按本地授权检查，签名文件中的事件由合并者接入签名链
+use elfiee_lib::elf::{ArchiveKey, AutosavePolicy, ElfArchive, RecoveryJournal};
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore, KeyStore};
+use elfiee_lib::models::{Command, Event};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tempfile::TempDir;
+
+/// 辅助函数：把恢复日志放进临时配置目录（整个测试进程共用一个）
+fn use_temp_config() {
+    static CONFIG_DIR: OnceLock<TempDir> = OnceLock::new();
+    let config_dir = CONFIG_DIR.get_or_init(|| TempDir::new().unwrap());
+    unsafe {
+        std::env::set_var(
+            "ELF_TEST_CONFIG_PATH",
+            config_dir.path().join("config.json"),
+        );
+    }
+}
+
+/// 辅助函数：在临时目录中创建一个空的 .elf 文件
+async fn create_elf(dir: &TempDir) -> PathBuf {
+    let path = dir.path().join("project.elf");
+    ElfArchive::new().await.unwrap().save(&path).await.unwrap();
+    path
+}
+
+/// 辅助函数：打开 .elf 文件并把它挂到新的 engine 上
+async fn open_attached(
+    path: &Path,
+    policy: AutosavePolicy,
+) -> (EngineHandle, Option<elfiee_lib::elf::RecoveryInfo>) {
+    let archive = Arc::new(ElfArchive::open(path).unwrap());
+    let event_pool = archive.event_pool().await.unwrap();
+    let handle = spawn_engine("test_autosave".to_string(), event_pool)
+        .await
+        .unwrap();
+    let info = handle
+        .attach_file(archive, path.to_path_buf(), policy)
+        .await
+        .unwrap();
+    (handle, info)
+}
+
+/// 辅助函数：打开加密的 .elf 文件并把它挂到新的 engine 上
+async fn open_encrypted(
+    path: &Path,
+    key: &ArchiveKey,
+) -> (EngineHandle, Option<elfiee_lib::elf::RecoveryInfo>) {
+    let archive = Arc::new(ElfArchive::open_with_key(path, Some(key)).unwrap());
+    let event_pool = archive.event_pool().await.unwrap();
+    let handle = spawn_engine("test_autosave".to_string(), event_pool)
+        .await
+        .unwrap();
+    let info = handle
+        .attach_file(archive, path.to_path_buf(), AutosavePolicy::default())
+        .await
+        .unwrap();
+    (handle, info)
+}
+
+/// 辅助函数：以 alice 创建一个 markdown block
+async fn create_block(handle: &EngineHandle, name: &str) -> String {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": name, "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    events[0].entity.clone()
+}
+
+/// 未保存就崩溃的会话在下次打开时可以恢复
+#[tokio::test]
+async fn test_unsaved_session_is_restored_on_open() {
+    use_temp_config();
+    let dir = TempDir::new().unwrap();
+    let path = create_elf(&dir).await;
+
+    // 第一次会话：做了变更但没有保存，进程"崩溃"（不 detach）
+    let (handle, info) = open_attached(&path, AutosavePolicy::default()).await;
+    assert!(info.is_none());
+    let block_id = create_block(&handle, "draft.md").await;
+    handle.shutdown().await;
+
+    // 第二次会话：文件里没有变更，但可以恢复
+    let (handle, info) = open_attached(&path, AutosavePolicy::default()).await;
+    let info = info.expect("unsaved session should be offered for recovery");
+    assert_eq!(info.events, 1);
+    assert_eq!(info.editors, vec!["alice".to_string()]);
+    assert!(handle.get_block(block_id.clone()).await.is_none());
+
+    assert_eq!(handle.restore_recovery().await.unwrap(), 1);
+    assert!(handle.get_recovery_info().await.is_none());
+    assert_eq!(
+        handle.get_block(block_id.clone()).await.unwrap().name,
+        "draft.md"
+    );
+
+    // 保存后再打开：变更已在文件中，没有可恢复的内容
+    handle.save_file().await.unwrap();
+    handle.detach_file().await.unwrap();
+    handle.shutdown().await;
+
+    let (handle, info) = open_attached(&path, AutosavePolicy::default()).await;
+    assert!(info.is_none());
+    assert!(handle.get_block(block_id).await.is_some());
+    handle.detach_file().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// 放弃恢复后不会再次提供；在决定之前编辑也会放弃恢复
+#[tokio::test]
+async fn test_discarded_or_superseded_recovery_is_gone() {
+    use_temp_config();
+    let dir = TempDir::new().unwrap();
+    let path = create_elf(&dir).await;
+
+    let (handle, _) = open_attached(&path, AutosavePolicy::default()).await;
+    create_block(&handle, "lost.md").await;
+    handle.shutdown().await;
+
+    let (handle, info) = open_attached(&path, AutosavePolicy::default()).await;
+    assert_eq!(info.unwrap().events, 1);
+    assert_eq!(handle.discard_recovery().await.unwrap(), 1);
+    // 崩溃前又产生了新的未保存事件
+    create_block(&handle, "second.md").await;
+    handle.shutdown().await;
+
+    let (handle, info) = open_attached(&path, AutosavePolicy::default()).await;
+    assert_eq!(info.unwrap().events, 1);
+    // 直接编辑：旧会话的事件不再能接上事件日志，被丢弃
+    create_block(&handle, "third.md").await;
+    assert!(handle.get_recovery_info().await.is_none());
+    assert_eq!(handle.restore_recovery().await.unwrap(), 0);
+    handle.detach_file().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// 加密文件的恢复日志是加密的，用同一个密钥打开时仍然可以恢复
+#[tokio::test]
+async fn test_journal_of_encrypted_file_is_encrypted() {
+    use_temp_config();
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("secret.elf");
+    let key = ArchiveKey::Passphrase("correct horse".to_string());
+    let archive = ElfArchive::new().await.unwrap();
+    archive.set_encryption(Some(key.clone()));
+    archive.save(&path).await.unwrap();
+
+    let (handle, _) = open_encrypted(&path, &key).await;
+    let block_id = create_block(&handle, "acquisition-plan.md").await;
+    handle.shutdown().await;
+
+    let journal = std::fs::read_to_string(RecoveryJournal::location(&path).unwrap()).unwrap();
+    assert!(!journal.contains("acquisition-plan"));
+    assert!(!journal.contains("core.create"));
+
+    // 没有密钥读不出日志；用密钥打开文件后可以恢复
+    assert!(RecoveryJournal::read(&path, None).unwrap().is_empty());
+    let (handle, info) = open_encrypted(&path, &key).await;
+    assert_eq!(info.unwrap().events, 1);
+    assert_eq!(handle.restore_recovery().await.unwrap(), 1);
+    assert!(handle.get_block(block_id).await.is_some());
+    handle.detach_file().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// 按事件数和按秒数的自动保存都会把变更写入 .elf 文件
+#[tokio::test]
+async fn test_autosave_by_events_and_by_time() {
+    use_temp_config();
+    let dir = TempDir::new().unwrap();
+    let path = create_elf(&dir).await;
+
+    // 每 2 个事件保存一次
+    let policy = AutosavePolicy {
+        every_events: Some(2),
+        every_seconds: None,
+    };
+    let (handle, _) = open_attached(&path, policy).await;
+    let first = create_block(&handle, "one.md").await;
+    let second = create_block(&handle, "two.md").await;
+    handle.shutdown().await;
+
+    let (handle, info) = open_attached(&path, AutosavePolicy::default()).await;
+    assert!(info.is_none(), "autosaved events must not need recovery");
+    assert!(handle.get_block(first).await.is_some());
+    assert!(handle.get_block(second).await.is_some());
+
+    // 第一个未保存变更 1 秒后保存
+    handle
+        .set_autosave_policy(AutosavePolicy {
+            every_events: None,
+            every_seconds: Some(1),
+        })
+        .await
+        .unwrap();
+    let third = create_block(&handle, "three.md").await;
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    handle.shutdown().await;
+
+    let (handle, info) = open_attached(&path, AutosavePolicy::default()).await;
+    assert!(info.is_none());
+    assert!(handle.get_block(third).await.is_some());
+    handle.detach_file().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// 文件被其他会话改写后，保存被拒绝；合并对方的事件后双方的变更都会保留
+#[tokio::test]
+async fn test_external_modification_is_detected_and_merged() {
+    use_temp_config();
+    let dir = TempDir::new().unwrap();
+    let path = create_elf(&dir).await;
+
+    let (ours, _) = open_attached(&path, AutosavePolicy::default()).await;
+    let (theirs, _) = open_attached(&path, AutosavePolicy::default()).await;
+    assert!(ours.check_external_change().await.unwrap().is_none());
+
+    // 只是时间戳变了，内容没变，不算被改写
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+        .unwrap();
+    assert!(ours.check_external_change().await.unwrap().is_none());
+
+    // 另一个会话先保存了自己的变更
+    let their_block = create_block(&theirs, "theirs.md").await;
+    theirs.save_file().await.unwrap();
+    theirs.shutdown().await;
+
+    // 我们的保存不能覆盖对方的工作
+    let our_block = create_block(&ours, "ours.md").await;
+    let err = ours.save_file().await.unwrap_err();
+    assert!(err.contains("modified by another program"), "{}", err);
+
+    let change = ours.check_external_change().await.unwrap().unwrap();
+    assert_eq!(change.new_events, 1);
+    assert_eq!(change.editors, vec!["alice".to_string()]);
+
+    // 合并后可以保存，文件中同时包含双方的变更
+    let report = ours.merge_external_changes("alice").await.unwrap();
+    assert_eq!(report.applied, 1);
+    assert!(report.rejected.is_empty());
+    assert!(ours.check_external_change().await.unwrap().is_none());
+    assert!(ours.get_block(their_block.clone()).await.is_some());
+    ours.save_file().await.unwrap();
+    ours.detach_file().await.unwrap();
+    ours.shutdown().await;
+
+    let (handle, _) = open_attached(&path, AutosavePolicy::default()).await;
+    assert!(handle.get_block(their_block).await.is_some());
+    assert!(handle.get_block(our_block).await.is_some());
+    handle.detach_file().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// 其他程序写进文件的事件按本地授权检查，没有权限的 editor 的事件在合并时被拒绝
+#[tokio::test]
+async fn test_merge_rejects_unauthorized_external_events() {
+    use_temp_config();
+    let dir = TempDir::new().unwrap();
+    let path = create_elf(&dir).await;
+
+    let (ours, _) = open_attached(&path, AutosavePolicy::default()).await;
+    let block_id = create_block(&ours, "notes.md").await;
+    ours.save_file().await.unwrap();
+
+    // 绕过 engine 往文件里写入 mallory 的事件
+    let archive = ElfArchive::open(&path).unwrap();
+    let pool = archive.event_pool().await.unwrap();
+    let forged = Event::new(
+        block_id.clone(),
+        "mallory/markdown.write".to_string(),
+        serde_json::json!({ "contents": { "markdown": "pwned" } }),
+        HashMap::from([("mallory".to_string(), 1)]),
+    );
+    EventStore::append_events(&pool.pool, std::slice::from_ref(&forged))
+        .await
+        .unwrap();
+    archive.save(&path).await.unwrap();
+
+    let report = ours.merge_external_changes("alice").await.unwrap();
+    assert_eq!(report.received, 1);
+    assert_eq!(report.applied, 0);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].event_id, forged.event_id);
+    let block = ours.get_block(block_id).await.unwrap();
+    assert_ne!(
+        block.contents.get("markdown"),
+        Some(&serde_json::json!("pwned"))
+    );
+
+    // 合并后可以保存，被拒绝的事件不会留在文件中
+    ours.save_file().await.unwrap();
+    let events = ours.get_all_events().await.unwrap();
+    assert!(events.iter().all(|event| event.event_id != forged.event_id));
+    ours.detach_file().await.unwrap();
+    ours.shutdown().await;
+}
+
+/// 签名文件合并另一个会话的事件后，签名链仍然完整
+#[tokio::test]
+async fn test_merge_keeps_signed_chain_intact() {
+    use_temp_config();
+    let dir = TempDir::new().unwrap();
+    let keys_dir = TempDir::new().unwrap();
+    let keys = KeyStore::new(keys_dir.path().to_path_buf());
+    keys.enrol("alice").unwrap();
+    let path = create_elf(&dir).await;
+
+    let (ours, _) = open_attached(&path, AutosavePolicy::default()).await;
+    let (theirs, _) = open_attached(&path, AutosavePolicy::default()).await;
+    ours.enable_signing(keys.clone()).await.unwrap();
+    theirs.enable_signing(keys.clone()).await.unwrap();
+
+    let their_block = create_block(&theirs, "theirs.md").await;
+    theirs.save_file().await.unwrap();
+    theirs.shutdown().await;
+    create_block(&ours, "ours.md").await;
+
+    let report = ours.merge_external_changes("alice").await.unwrap();
+    assert_eq!(report.applied, 1);
+    assert!(ours.get_block(their_block).await.is_some());
+
+    let verify = ours.verify_events().await.unwrap();
+    assert!(verify.is_valid(), "{:?}", verify.issues);
+    assert_eq!(verify.signed_events, verify.total_events);
+    ours.detach_file().await.unwrap();
+    ours.shutdown().await;
+}
@@ -0,0 +1,193 @@
+/// 集成测试：.elf 内的内容寻址 blob 存储
+///
+/// 验证：
+/// - 大段文本存入 blobs/<sha256>，events.db 中只保留引用；读取事件时还原
+/// - 相同内容只存一份
+/// - 导入的二进制文件（asset）按 hash 引用 blob
+/// - 保存时清理没有事件引用的 blob
+/// - 用户内容中形如引用的值被转义，blob 丢失只影响对应的 block
+use elfiee_lib::elf::blobs::INLINE_LIMIT;
+use elfiee_lib::elf::{BlobStore, ElfArchive};
+use elfiee_lib::engine::{spawn_engine, EngineHandle};
+use elfiee_lib::models::Command;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// 辅助函数：打开 .elf 文件并启动 engine
+async fn open_engine(path: &Path) -> (ElfArchive, EngineHandle) {
+    let archive = ElfArchive::open(path).unwrap();
+    let event_pool = archive.event_pool().await.unwrap();
+    let handle = spawn_engine("test_blobs".to_string(), event_pool)
+        .await
+        .unwrap();
+    (archive, handle)
+}
+
+/// 辅助函数：以 alice 创建 block
+async fn create_block(handle: &EngineHandle, name: &str, block_type: &str) -> String {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": name, "block_type": block_type }),
+    );
+    handle.process_command(cmd).await.unwrap()[0].entity.clone()
+}
+
+/// 大段 markdown 存成 blob，两个 block 的相同内容共用一个 blob
+#[tokio::test]
+async fn test_large_text_is_stored_once_as_blob() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    ElfArchive::new().await.unwrap().save(&path).await.unwrap();
+
+    let (archive, handle) = open_engine(&path).await;
+    let spec = "# Spec\n".to_string() + &"lorem ipsum ".repeat(INLINE_LIMIT);
+    let mut block_ids = Vec::new();
+    for name in ["a.md", "b.md"] {
+        let block_id = create_block(&handle, name, "markdown").await;
+        let cmd = Command::new(
+            "alice".to_string(),
+            "markdown.write".to_string(),
+            block_id.clone(),
+            serde_json::json!({ "content": spec }),
+        );
+        handle.process_command(cmd).await.unwrap();
+        block_ids.push(block_id);
+    }
+
+    // 一个 blob，且 events.db 里没有正文
+    let store = BlobStore::new(archive.temp_path());
+    assert_eq!(
+        store.list().unwrap(),
+        vec![BlobStore::hash(spec.as_bytes())]
+    );
+    handle.shutdown().await;
+    archive.save(&path).await.unwrap();
+    let db = std::fs::read(archive.temp_path().join("events.db")).unwrap();
+    assert!(!db.windows(64).any(|w| w == &spec.as_bytes()[..64]));
+
+    // 重新打开后事件和 block 内容完整
+    let (_archive, handle) = open_engine(&path).await;
+    for block_id in &block_ids {
+        let block = handle.get_block(block_id.clone()).await.unwrap();
+        assert_eq!(block.contents["markdown"], spec.as_str());
+    }
+    let events = handle.get_all_events().await.unwrap();
+    let write = events
+        .iter()
+        .find(|e| e.attribute.ends_with("/markdown.write"))
+        .unwrap();
+    assert_eq!(write.value["contents"]["markdown"], spec.as_str());
+    handle.shutdown().await;
+}
+
+/// 导入的二进制文件按 hash 引用 blob；未被引用的 blob 在保存时被清理
+#[tokio::test]
+async fn test_assets_reference_blobs_and_unreferenced_blobs_are_collected() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    ElfArchive::new().await.unwrap().save(&path).await.unwrap();
+
+    let source = TempDir::new().unwrap();
+    let png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    std::fs::write(source.path().join("mock.png"), &png).unwrap();
+    std::fs::write(source.path().join("copy.png"), &png).unwrap();
+
+    let (archive, handle) = open_engine(&path).await;
+    let dir_id = create_block(&handle, "assets", "directory").await;
+    let cmd = Command::new(
+        "alice".to_string(),
+        "directory.import".to_string(),
+        dir_id,
+        serde_json::json!({ "source_path": source.path().to_str().unwrap() }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    let assets: Vec<_> = events
+        .iter()
+        .filter(|e| e.attribute.ends_with("/core.create"))
+        .collect();
+    assert_eq!(assets.len(), 2);
+    let hash = BlobStore::hash(&png);
+    for asset in &assets {
+        assert_eq!(asset.value["contents"]["hash"], hash.as_str());
+    }
+
+    // 一个没有事件引用的 blob
+    let store = BlobStore::new(archive.temp_path());
+    let orphan = store.put(b"left over from an aborted import").unwrap();
+    assert_eq!(store.list().unwrap().len(), 2);
+
+    handle.shutdown().await;
+    archive.save(&path).await.unwrap();
+    assert_eq!(store.list().unwrap(), vec![hash.clone()]);
+
+    let reopened = ElfArchive::open(&path).unwrap();
+    let store = BlobStore::new(reopened.temp_path());
+    assert_eq!(store.get(&hash).unwrap(), png);
+    assert!(store.get(&orphan).is_err());
+}
+
+/// 用户写入形如 blob 引用的内容（{"$blob": "x"}）原样保存，文件可以重新打开
+#[tokio::test]
+async fn test_literal_blob_reference_survives_reopen() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    ElfArchive::new().await.unwrap().save(&path).await.unwrap();
+
+    let (archive, handle) = open_engine(&path).await;
+    let dir_id = create_block(&handle, "docs", "directory").await;
+    let cmd = Command::new(
+        "alice".to_string(),
+        "directory.write".to_string(),
+        dir_id.clone(),
+        serde_json::json!({ "entries": { "$blob": "x" } }),
+    );
+    handle.process_command(cmd).await.unwrap();
+    handle.shutdown().await;
+    archive.save(&path).await.unwrap();
+
+    let (_archive, handle) = open_engine(&path).await;
+    assert!(handle.get_all_events().await.is_ok());
+    let block = handle.get_block(dir_id).await.unwrap();
+    assert_eq!(
+        block.contents["entries"],
+        serde_json::json!({ "$blob": "x" })
+    );
+    handle.shutdown().await;
+}
+
+/// blob 丢失只影响引用它的 block，其余事件照常读取
+#[tokio::test]
+async fn test_missing_blob_only_affects_its_block() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    ElfArchive::new().await.unwrap().save(&path).await.unwrap();
+
+    let (archive, handle) = open_engine(&path).await;
+    let spec = "x".repeat(INLINE_LIMIT + 1);
+    let mut block_ids = Vec::new();
+    for (name, content) in [("big.md", spec.as_str()), ("small.md", "small")] {
+        let block_id = create_block(&handle, name, "markdown").await;
+        let cmd = Command::new(
+            "alice".to_string(),
+            "markdown.write".to_string(),
+            block_id.clone(),
+            serde_json::json!({ "content": content }),
+        );
+        handle.process_command(cmd).await.unwrap();
+        block_ids.push(block_id);
+    }
+    handle.shutdown().await;
+    let store = BlobStore::new(archive.temp_path());
+    std::fs::remove_file(store.path(&BlobStore::hash(spec.as_bytes())).unwrap()).unwrap();
+    archive.save(&path).await.unwrap();
+
+    let (_archive, handle) = open_engine(&path).await;
+    assert!(handle.get_all_events().await.is_ok());
+    let big = handle.get_block(block_ids[0].clone()).await.unwrap();
+    assert!(big.contents["markdown"].as_str().is_none());
+    let small = handle.get_block(block_ids[1].clone()).await.unwrap();
+    assert_eq!(small.contents["markdown"], "small");
+    handle.shutdown().await;
+}
@@ -0,0 +1,167 @@
+/// 集成测试：授权委托 (grantable grant 与级联撤销)
+///
+/// 验证 engine 层面的委托规则：
+/// - 非 owner 即使持有 core.grant，也只能授予自己以 grantable 方式持有的能力
+/// - 委托产生的 grant 在列表中记录授予者与来源 grant
+/// - cascade 撤销会同时撤销由该 grant 委托出去的所有 grant
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore};
+use elfiee_lib::models::{Command, Event};
+
+/// 辅助函数：创建内存 engine，alice 自举并创建 bob、carol，再创建一个 markdown block
+async fn setup_engine() -> (EngineHandle, String) {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_delegation".to_string(), event_pool)
+        .await
+        .unwrap();
+
+    for editor_id in ["alice", "bob", "carol"] {
+        let cmd = Command::new(
+            "alice".to_string(),
+            "editor.create".to_string(),
+            "".to_string(),
+            serde_json::json!({
+                "editor_id": editor_id,
+                "name": editor_id,
+                "editor_type": "Human",
+            }),
+        );
+        handle.process_command(cmd).await.unwrap();
+    }
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "prd.md", "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    let block_id = events[0].entity.clone();
+
+    (handle, block_id)
+}
+
+/// 辅助函数：以 granter 身份授予 target 在 block 上的能力
+async fn grant(
+    handle: &EngineHandle,
+    granter: &str,
+    target: &str,
+    capability: &str,
+    block_id: &str,
+    grantable: bool,
+) -> Result<Vec<Event>, String> {
+    let cmd = Command::new(
+        granter.to_string(),
+        "core.grant".to_string(),
+        block_id.to_string(),
+        serde_json::json!({
+            "target_editor": target,
+            "capability": capability,
+            "target_block": block_id,
+            "grantable": grantable,
+        }),
+    );
+    handle.process_command(cmd).await
+}
+
+/// 非 owner 只能转授自己以 grantable 方式持有的能力
+#[tokio::test]
+async fn test_only_grantable_grants_can_be_delegated() {
+    let (handle, block_id) = setup_engine().await;
+
+    grant(&handle, "alice", "bob", "core.grant", &block_id, false)
+        .await
+        .unwrap();
+    grant(&handle, "alice", "bob", "markdown.write", &block_id, false)
+        .await
+        .unwrap();
+
+    // bob 持有 core.grant 和 markdown.write，但后者不可转授
+    let err = grant(&handle, "bob", "carol", "markdown.write", &block_id, false)
+        .await
+        .unwrap_err();
+    assert!(err.contains("Delegation failed"), "got: {}", err);
+    let err = grant(&handle, "bob", "carol", "markdown.read", &block_id, false)
+        .await
+        .unwrap_err();
+    assert!(err.contains("Delegation failed"), "got: {}", err);
+
+    grant(&handle, "alice", "bob", "markdown.write", &block_id, true)
+        .await
+        .unwrap();
+    grant(&handle, "bob", "carol", "markdown.write", &block_id, false)
+        .await
+        .unwrap();
+
+    let delegations = handle.get_grant_delegations().await;
+    let carol = delegations
+        .get(&(
+            "carol".to_string(),
+            "markdown.write".to_string(),
+            block_id.clone(),
+        ))
+        .unwrap();
+    assert_eq!(carol.granted_by, "bob");
+    assert!(!carol.grantable);
+    assert_eq!(
+        carol.delegated_from,
+        Some((
+            "bob".to_string(),
+            "markdown.write".to_string(),
+            block_id.clone()
+        ))
+    );
+
+    // carol 的 grant 不可转授
+    grant(&handle, "alice", "carol", "core.grant", &block_id, false)
+        .await
+        .unwrap();
+    assert!(grant(
+        &handle,
+        "carol",
+        "alice",
+        "markdown.write",
+        &block_id,
+        false
+    )
+    .await
+    .is_err());
+
+    handle.shutdown().await;
+}
+
+/// cascade 撤销同时撤销由该 grant 委托出去的 grant
+#[tokio::test]
+async fn test_cascade_revoke_removes_delegated_grants() {
+    let (handle, block_id) = setup_engine().await;
+
+    grant(&handle, "alice", "bob", "core.grant", &block_id, false)
+        .await
+        .unwrap();
+    grant(&handle, "alice", "bob", "markdown.write", &block_id, true)
+        .await
+        .unwrap();
+    grant(&handle, "bob", "carol", "markdown.write", &block_id, false)
+        .await
+        .unwrap();
+
+    let revoke = Command::new(
+        "alice".to_string(),
+        "core.revoke".to_string(),
+        block_id.clone(),
+        serde_json::json!({
+            "target_editor": "bob",
+            "capability": "markdown.write",
+            "target_block": block_id,
+            "cascade": true,
+        }),
+    );
+    let events = handle.process_command(revoke).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].value["editor"], "carol");
+
+    let grants = handle.get_block_grants(block_id.clone()).await;
+    assert!(!grants.iter().any(|(_, cap, _)| cap == "markdown.write"));
+    assert_eq!(handle.get_grant_delegations().await.len(), 1);
+
+    handle.shutdown().await;
+}
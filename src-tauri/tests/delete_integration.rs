@@ -0,0 +1,304 @@
+/// 集成测试：core.delete 的级联与孤儿处理
+///
+/// 验证删除模式（refuse / cascade / detach）在 engine 层面的行为：
+/// - 删除后 parents 的 implement 关系与目录 entries 不再悬空
+/// - core.delete 事件记录全部受影响的 block
+/// - 级联删除需要对每个被删除 block 的 core.delete 权限
+/// - 删除的 block 进入回收站，可 restore 或 purge
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore};
+use elfiee_lib::models::{Command, RELATION_IMPLEMENT};
+
+/// 辅助函数：创建内存 engine
+async fn setup_engine() -> EngineHandle {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    spawn_engine("test_delete".to_string(), event_pool)
+        .await
+        .unwrap()
+}
+
+/// 辅助函数：以指定 editor 创建 block，返回 block_id
+async fn create_block(handle: &EngineHandle, editor: &str, name: &str, block_type: &str) -> String {
+    let cmd = Command::new(
+        editor.to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": name, "block_type": block_type }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    events[0].entity.clone()
+}
+
+/// 辅助函数：source → target 建立 implement 关系
+async fn link_blocks(handle: &EngineHandle, source_id: &str, target_id: &str) {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.link".to_string(),
+        source_id.to_string(),
+        serde_json::json!({ "relation": RELATION_IMPLEMENT, "target_id": target_id }),
+    );
+    handle.process_command(cmd).await.unwrap();
+}
+
+/// 辅助函数：以指定模式删除 block
+async fn delete_block(
+    handle: &EngineHandle,
+    editor: &str,
+    block_id: &str,
+    payload: serde_json::Value,
+) -> Result<Vec<elfiee_lib::models::Event>, String> {
+    let cmd = Command::new(
+        editor.to_string(),
+        "core.delete".to_string(),
+        block_id.to_string(),
+        payload,
+    );
+    handle.process_command(cmd).await
+}
+
+/// 默认 detach 模式：父 block 的 implement 关系与目录 entry 被清理
+#[tokio::test]
+async fn test_detach_cleans_parents_and_directory_entries() {
+    let handle = setup_engine().await;
+    let a = create_block(&handle, "alice", "A", "markdown").await;
+    let b = create_block(&handle, "alice", "B", "markdown").await;
+    let dir = create_block(&handle, "alice", "Dir", "directory").await;
+    link_blocks(&handle, &a, &b).await;
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "directory.write".to_string(),
+        dir.clone(),
+        serde_json::json!({
+            "entries": { "b.md": { "id": b, "type": "file", "source": "outline" } }
+        }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    let events = delete_block(&handle, "alice", &b, serde_json::json!({}))
+        .await
+        .unwrap();
+
+    // core.delete + core.unlink(A) + directory.write(Dir)
+    assert_eq!(events.len(), 3);
+    let affected = &events[0].value["affected"];
+    assert_eq!(affected["mode"], "detach");
+    assert_eq!(affected["unlinked_parents"][0], a.as_str());
+    assert_eq!(affected["directory_entries"][0]["path"], "b.md");
+
+    let block_a = handle.get_block(a).await.unwrap();
+    assert!(!block_a.children.contains_key(RELATION_IMPLEMENT));
+    let dir_block = handle.get_block(dir).await.unwrap();
+    assert!(dir_block.contents["entries"]
+        .as_object()
+        .unwrap()
+        .is_empty());
+    assert!(handle.find_dangling_references().await.is_empty());
+
+    handle.shutdown().await;
+}
+
+/// refuse 模式：存在 children 时拒绝删除
+#[tokio::test]
+async fn test_refuse_with_children_rejected() {
+    let handle = setup_engine().await;
+    let a = create_block(&handle, "alice", "A", "markdown").await;
+    let b = create_block(&handle, "alice", "B", "markdown").await;
+    link_blocks(&handle, &a, &b).await;
+
+    let result = delete_block(
+        &handle,
+        "alice",
+        &a,
+        serde_json::json!({ "mode": "refuse" }),
+    )
+    .await;
+    assert!(result.unwrap_err().contains("Cannot delete block"));
+    assert!(handle.get_block(a).await.is_some());
+
+    handle.shutdown().await;
+}
+
+/// cascade 模式：按深度删除下游 block
+#[tokio::test]
+async fn test_cascade_deletes_descendants() {
+    let handle = setup_engine().await;
+    let a = create_block(&handle, "alice", "A", "markdown").await;
+    let b = create_block(&handle, "alice", "B", "markdown").await;
+    let c = create_block(&handle, "alice", "C", "markdown").await;
+    link_blocks(&handle, &a, &b).await;
+    link_blocks(&handle, &b, &c).await;
+
+    let events = delete_block(
+        &handle,
+        "alice",
+        &a,
+        serde_json::json!({ "mode": "cascade", "depth": 1 }),
+    )
+    .await
+    .unwrap();
+
+    let deleted = events[0].value["affected"]["deleted"].as_array().unwrap();
+    assert_eq!(deleted.len(), 2);
+    assert!(handle.get_block(a).await.is_none());
+    assert!(handle.get_block(b).await.is_none());
+    assert!(handle.get_block(c).await.is_some());
+    assert!(handle.find_dangling_references().await.is_empty());
+
+    handle.shutdown().await;
+}
+
+/// cascade 模式：对下游 block 没有 core.delete 权限时整体拒绝
+#[tokio::test]
+async fn test_cascade_requires_permission_on_descendants() {
+    let handle = setup_engine().await;
+    let a = create_block(&handle, "bob", "A", "markdown").await;
+    let b = create_block(&handle, "alice", "B", "markdown").await;
+
+    let cmd = Command::new(
+        "bob".to_string(),
+        "core.link".to_string(),
+        a.clone(),
+        serde_json::json!({ "relation": RELATION_IMPLEMENT, "target_id": b }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    let result = delete_block(&handle, "bob", &a, serde_json::json!({ "mode": "cascade" })).await;
+    assert!(result.unwrap_err().contains("Authorization failed"));
+    assert!(handle.get_block(a).await.is_some());
+    assert!(handle.get_block(b).await.is_some());
+
+    handle.shutdown().await;
+}
+
+/// 辅助函数：以指定 editor 执行 trash 相关 capability
+async fn trash_command(
+    handle: &EngineHandle,
+    editor: &str,
+    cap_id: &str,
+    block_id: &str,
+    payload: serde_json::Value,
+) -> Result<Vec<elfiee_lib::models::Event>, String> {
+    let cmd = Command::new(
+        editor.to_string(),
+        cap_id.to_string(),
+        block_id.to_string(),
+        payload,
+    );
+    handle.process_command(cmd).await
+}
+
+/// 删除进入回收站，restore 后内容、父关系与目录 entry 全部恢复
+#[tokio::test]
+async fn test_restore_from_trash_reattaches_block() {
+    let handle = setup_engine().await;
+    let a = create_block(&handle, "alice", "A", "markdown").await;
+    let b = create_block(&handle, "alice", "B", "markdown").await;
+    let dir = create_block(&handle, "alice", "Dir", "directory").await;
+    link_blocks(&handle, &a, &b).await;
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "directory.write".to_string(),
+        dir.clone(),
+        serde_json::json!({
+            "entries": { "b.md": { "id": b, "type": "file", "source": "outline" } }
+        }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "markdown.write".to_string(),
+        b.clone(),
+        serde_json::json!({ "content": "# kept" }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    delete_block(&handle, "alice", &b, serde_json::json!({}))
+        .await
+        .unwrap();
+    let trash = handle.get_trash().await;
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].block.block_id, b);
+    assert_eq!(trash[0].parents, vec![a.clone()]);
+
+    let events = trash_command(&handle, "alice", "core.restore", &b, serde_json::json!({}))
+        .await
+        .unwrap();
+    // core.restore + core.link(A) + directory.write(Dir)
+    assert_eq!(events.len(), 3);
+
+    let block_b = handle.get_block(b.clone()).await.unwrap();
+    assert_eq!(block_b.contents["markdown"], "# kept");
+    let block_a = handle.get_block(a).await.unwrap();
+    assert_eq!(block_a.children[RELATION_IMPLEMENT], vec![b.clone()]);
+    let dir_block = handle.get_block(dir).await.unwrap();
+    assert_eq!(dir_block.contents["entries"]["b.md"]["id"], b.as_str());
+    assert!(handle.get_trash().await.is_empty());
+    assert!(handle.find_dangling_references().await.is_empty());
+
+    handle.shutdown().await;
+}
+
+/// cascade 删除的下游 block 可以随目标一起恢复
+#[tokio::test]
+async fn test_restore_include_cascaded() {
+    let handle = setup_engine().await;
+    let a = create_block(&handle, "alice", "A", "markdown").await;
+    let b = create_block(&handle, "alice", "B", "markdown").await;
+    link_blocks(&handle, &a, &b).await;
+
+    delete_block(
+        &handle,
+        "alice",
+        &a,
+        serde_json::json!({ "mode": "cascade" }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(handle.get_trash().await.len(), 2);
+
+    trash_command(
+        &handle,
+        "alice",
+        "core.restore",
+        &a,
+        serde_json::json!({ "include_cascaded": true }),
+    )
+    .await
+    .unwrap();
+
+    let block_a = handle.get_block(a).await.unwrap();
+    assert_eq!(block_a.children[RELATION_IMPLEMENT], vec![b.clone()]);
+    assert!(handle.get_block(b).await.is_some());
+    assert!(handle.get_trash().await.is_empty());
+
+    handle.shutdown().await;
+}
+
+/// purge 后无法再恢复；未进入回收站的 block 不能 restore
+#[tokio::test]
+async fn test_purge_is_permanent() {
+    let handle = setup_engine().await;
+    let a = create_block(&handle, "alice", "A", "markdown").await;
+
+    let result = trash_command(&handle, "alice", "core.restore", &a, serde_json::json!({})).await;
+    assert!(result.unwrap_err().contains("not in trash"));
+
+    delete_block(&handle, "alice", &a, serde_json::json!({}))
+        .await
+        .unwrap();
+    let result = trash_command(&handle, "bob", "core.purge", &a, serde_json::json!({})).await;
+    assert!(result.unwrap_err().contains("Authorization failed"));
+
+    trash_command(&handle, "alice", "core.purge", &a, serde_json::json!({}))
+        .await
+        .unwrap();
+    assert!(handle.get_trash().await.is_empty());
+
+    let result = trash_command(&handle, "alice", "core.restore", &a, serde_json::json!({})).await;
+    assert!(result.is_err());
+    assert!(handle.get_block(a).await.is_none());
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,76 @@
+/// 集成测试：拒绝规则 (core.deny / core.undeny)
+///
+/// 验证拒绝规则在 engine 层面优先于通配符授权：
+/// - bot 拥有所有 block 的写权限，但被拒绝写 PRD
+/// - 移除拒绝规则后恢复写权限
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore};
+use elfiee_lib::models::Command;
+
+/// 辅助函数：以 alice 创建 markdown block，返回 block_id
+async fn create_block(handle: &EngineHandle, name: &str) -> String {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": name, "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    events[0].entity.clone()
+}
+
+/// 辅助函数：以 alice 执行授权类命令（grant / deny / undeny）
+async fn alice_rule(handle: &EngineHandle, cap_id: &str, block_id: &str, target: &str) {
+    let cmd = Command::new(
+        "alice".to_string(),
+        cap_id.to_string(),
+        block_id.to_string(),
+        serde_json::json!({
+            "target_editor": "bot",
+            "capability": "markdown.*",
+            "target_block": target,
+        }),
+    );
+    handle.process_command(cmd).await.unwrap();
+}
+
+/// 辅助函数：以 bot 写入 markdown
+async fn bot_writes(
+    handle: &EngineHandle,
+    block_id: &str,
+) -> Result<Vec<elfiee_lib::models::Event>, String> {
+    let cmd = Command::new(
+        "bot".to_string(),
+        "markdown.write".to_string(),
+        block_id.to_string(),
+        serde_json::json!({ "content": "generated" }),
+    );
+    handle.process_command(cmd).await
+}
+
+/// 拒绝规则优先于通配符授权，undeny 后恢复
+#[tokio::test]
+async fn test_deny_overrides_wildcard_grant() {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_deny".to_string(), event_pool)
+        .await
+        .unwrap();
+
+    let notes = create_block(&handle, "notes.md").await;
+    let prd = create_block(&handle, "prd.md").await;
+
+    alice_rule(&handle, "core.grant", &notes, "*").await;
+    alice_rule(&handle, "core.deny", &prd, &prd).await;
+
+    bot_writes(&handle, &notes).await.unwrap();
+    let err = bot_writes(&handle, &prd).await.unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+
+    let denies = handle.get_all_denies().await;
+    assert_eq!(denies["bot"], vec![("markdown.*".to_string(), prd.clone())]);
+
+    alice_rule(&handle, "core.undeny", &prd, &prd).await;
+    bot_writes(&handle, &prd).await.unwrap();
+    assert!(handle.get_all_denies().await.is_empty());
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,131 @@
+/// 集成测试：授权解释与命令试运行 (dry-run)
+///
+/// 验证 engine 层面的诊断接口：
+/// - dry-run 返回将要产生的事件，但不持久化、不改变状态
+/// - dry-run 对未授权命令返回与 process_command 相同的错误
+/// - 授权解释列出所有匹配的授权（含通配符），create 类能力按项目级授权检查，
+///   对所有 block 的 "*" 授权不覆盖项目
+use elfiee_lib::engine::{spawn_engine, AuthorizationRule, EngineHandle, EventStore};
+use elfiee_lib::models::Command;
+
+/// 辅助函数：创建内存 engine，并以 alice 创建一个 markdown block
+async fn setup_engine() -> (EngineHandle, String) {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_dry_run".to_string(), event_pool)
+        .await
+        .unwrap();
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "prd.md", "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    let block_id = events[0].entity.clone();
+
+    (handle, block_id)
+}
+
+/// 辅助函数：构造 markdown.write 命令
+fn write_cmd(editor_id: &str, block_id: &str) -> Command {
+    Command::new(
+        editor_id.to_string(),
+        "markdown.write".to_string(),
+        block_id.to_string(),
+        serde_json::json!({ "content": "# Draft" }),
+    )
+}
+
+/// dry-run 不持久化事件，也不改变 block 内容
+#[tokio::test]
+async fn test_dry_run_does_not_persist() {
+    let (handle, block_id) = setup_engine().await;
+    let before = handle.get_all_events().await.unwrap().len();
+
+    let events = handle
+        .dry_run_command(write_cmd("alice", &block_id))
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].attribute, "alice/markdown.write");
+
+    assert_eq!(handle.get_all_events().await.unwrap().len(), before);
+    let block = handle.get_block(block_id.clone()).await.unwrap();
+    assert!(block.contents.get("markdown").is_none());
+
+    // 非法 payload 同样在 dry-run 中被拒绝
+    let bad = Command::new(
+        "alice".to_string(),
+        "markdown.write".to_string(),
+        block_id,
+        serde_json::json!({ "text": 1 }),
+    );
+    assert!(handle.dry_run_command(bad).await.is_err());
+
+    handle.shutdown().await;
+}
+
+/// dry-run 的授权失败与授权解释一致
+#[tokio::test]
+async fn test_dry_run_and_explain_authorization() {
+    let (handle, block_id) = setup_engine().await;
+
+    let err = handle
+        .dry_run_command(write_cmd("bob", &block_id))
+        .await
+        .unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+
+    let trace = handle
+        .explain_authorization(
+            "bob".to_string(),
+            "markdown.write".to_string(),
+            block_id.clone(),
+        )
+        .await
+        .unwrap();
+    assert!(!trace.decision.allowed);
+    assert_eq!(trace.decision.rule, AuthorizationRule::NoMatch);
+    assert_eq!(trace.block_owner.as_deref(), Some("alice"));
+
+    let grant = Command::new(
+        "alice".to_string(),
+        "core.grant".to_string(),
+        block_id.clone(),
+        serde_json::json!({
+            "target_editor": "*",
+            "capability": "markdown.*",
+            "target_block": "*",
+        }),
+    );
+    handle.process_command(grant).await.unwrap();
+
+    let trace = handle
+        .explain_authorization(
+            "bob".to_string(),
+            "markdown.write".to_string(),
+            block_id.clone(),
+        )
+        .await
+        .unwrap();
+    assert!(trace.decision.allowed);
+    assert_eq!(trace.matching_grants.len(), 1);
+    assert_eq!(trace.matching_grants[0].editor_id, "*");
+    handle
+        .dry_run_command(write_cmd("bob", &block_id))
+        .await
+        .unwrap();
+
+    let trace = handle
+        .explain_authorization("bob".to_string(), "core.create".to_string(), "".into())
+        .await
+        .unwrap();
+    // Grants on every block ("*") don't reach the project, which alice owns
+    assert!(trace.project_level);
+    assert!(!trace.decision.allowed);
+    assert_eq!(trace.decision.rule, AuthorizationRule::NoMatch);
+    assert_eq!(trace.block_owner.as_deref(), Some("alice"));
+
+    handle.shutdown().await;
+}
@@ -17,9 +17,11 @@ async fn setup_engine() -> elfiee_lib::engine::EngineHandle {
 }
 
 /// 辅助函数：创建 editor
+///
+/// 第一个 editor（system）自举为项目 owner，其余 editor 由 system 创建
 async fn create_editor(handle: &elfiee_lib::engine::EngineHandle, editor_id: &str) -> String {
     let cmd = Command::new(
-        editor_id.to_string(),
+        "system".to_string(),
         "editor.create".to_string(),
         "".to_string(),
         serde_json::json!({
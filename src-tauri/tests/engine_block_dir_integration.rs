@@ -22,7 +22,7 @@ async fn test_end_to_end_block_dir_persistence() {
     let elf_path = temp_elf.path();
 
     let archive = ElfArchive::new().await.unwrap();
-    archive.save(elf_path).unwrap();
+    archive.save(elf_path).await.unwrap();
 
     // ========== 步骤2: 打开 archive 并启动 engine ==========
     let archive = ElfArchive::open(elf_path).unwrap();
@@ -103,7 +103,7 @@ async fn test_end_to_end_block_dir_persistence() {
     println!("✓ Created test files in block directory");
 
     // ========== 步骤6: 保存 elf ==========
-    archive.save(elf_path).unwrap();
+    archive.save(elf_path).await.unwrap();
     println!("✓ Saved elf archive");
 
     // 关闭 engine
@@ -177,7 +177,7 @@ async fn test_multiple_blocks_with_files() {
     let elf_path = temp_elf.path();
 
     let archive = ElfArchive::new().await.unwrap();
-    archive.save(elf_path).unwrap();
+    archive.save(elf_path).await.unwrap();
 
     let archive = ElfArchive::open(elf_path).unwrap();
     let event_pool_with_path = archive.event_pool().await.unwrap();
@@ -233,7 +233,7 @@ async fn test_multiple_blocks_with_files() {
     }
 
     // 保存并重新打开
-    archive.save(elf_path).unwrap();
+    archive.save(elf_path).await.unwrap();
     handle.shutdown().await;
 
     let reopened_archive = ElfArchive::open(elf_path).unwrap();
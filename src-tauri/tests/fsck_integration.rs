@@ -0,0 +1,141 @@
+/// 集成测试：项目完整性检查 (fsck)
+///
+/// 验证 ElfArchive + StateProjector 上的完整性检查与修复：
+/// - 孤立的 block-* 目录、过期快照、授予不存在 editor 的权限被按类别报告
+/// - repair 模式提交修正事件并修复文件，保存后重新打开仍然干净
+use elfiee_lib::elf::{ElfArchive, FsckCategory};
+use elfiee_lib::engine::spawn_engine;
+use elfiee_lib::models::Command;
+use std::fs;
+use tempfile::NamedTempFile;
+
+/// 辅助函数：创建 archive、engine 和 system editor
+async fn setup_engine() -> (
+    ElfArchive,
+    elfiee_lib::engine::EngineHandle,
+    std::path::PathBuf,
+) {
+    let temp_elf = NamedTempFile::new().unwrap();
+    let elf_path = temp_elf.path().to_path_buf();
+
+    let archive = ElfArchive::new().await.unwrap();
+    archive.save(&elf_path).await.unwrap();
+
+    let archive = ElfArchive::open(&elf_path).unwrap();
+    let event_pool = archive.event_pool().await.unwrap();
+    let handle = spawn_engine("test".to_string(), event_pool).await.unwrap();
+
+    let cmd = Command::new(
+        "system".to_string(),
+        "editor.create".to_string(),
+        "system".to_string(),
+        serde_json::json!({ "editor_id": "system", "name": "System" }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    (archive, handle, elf_path)
+}
+
+/// 干净的项目没有问题
+#[tokio::test]
+async fn test_fsck_clean_project() {
+    let (_archive, handle, _elf_path) = setup_engine().await;
+
+    let cmd = Command::new(
+        "system".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "README.md", "block_type": "markdown" }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    let report = handle
+        .check_integrity(false, "system".to_string())
+        .await
+        .unwrap();
+    assert!(report.is_clean(), "unexpected issues: {:?}", report.issues);
+
+    handle.shutdown().await;
+}
+
+/// 检查报告各类问题，repair 后保存并重新打开仍然干净
+#[tokio::test]
+async fn test_fsck_reports_and_repairs() {
+    let (archive, handle, elf_path) = setup_engine().await;
+
+    let cmd = Command::new(
+        "system".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "README.md", "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    let block_id = events[0].entity.clone();
+
+    let cmd = Command::new(
+        "system".to_string(),
+        "markdown.write".to_string(),
+        block_id.clone(),
+        serde_json::json!({ "content": "# Hello" }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    // 授权给一个不存在的 editor
+    let cmd = Command::new(
+        "system".to_string(),
+        "core.grant".to_string(),
+        block_id.clone(),
+        serde_json::json!({
+            "target_editor": "ghost",
+            "capability": "markdown.read",
+            "target_block": block_id,
+        }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    // 破坏快照，并制造一个孤立目录
+    let temp = archive.temp_path();
+    fs::write(temp.join(format!("block-{}/body.md", block_id)), "# Stale").unwrap();
+    fs::create_dir_all(temp.join("block-orphan")).unwrap();
+
+    let report = handle
+        .check_integrity(false, "system".to_string())
+        .await
+        .unwrap();
+    let grouped = report.by_category();
+    assert_eq!(grouped[&FsckCategory::OrphanBlockDir][0].subject, "orphan");
+    assert_eq!(
+        grouped[&FsckCategory::SnapshotMismatch][0].subject,
+        block_id
+    );
+    assert_eq!(grouped[&FsckCategory::OrphanGrant][0].subject, "ghost");
+    assert!(!report.repaired);
+
+    let report = handle
+        .check_integrity(true, "system".to_string())
+        .await
+        .unwrap();
+    assert!(report.repaired);
+    assert_eq!(report.repair_events, 1);
+
+    let report = handle
+        .check_integrity(false, "system".to_string())
+        .await
+        .unwrap();
+    assert!(report.is_clean(), "unexpected issues: {:?}", report.issues);
+    handle.shutdown().await;
+
+    archive.save(&elf_path).await.unwrap();
+    let reopened = ElfArchive::open(&elf_path).unwrap();
+    let event_pool = reopened.event_pool().await.unwrap();
+    let handle = spawn_engine("reopened".to_string(), event_pool)
+        .await
+        .unwrap();
+    let report = handle
+        .check_integrity(false, "system".to_string())
+        .await
+        .unwrap();
+    assert!(report.is_clean(), "unexpected issues: {:?}", report.issues);
+
+    handle.shutdown().await;
+}
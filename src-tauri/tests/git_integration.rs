@@ -0,0 +1,193 @@
+/// 集成测试：git 仓库的导出与导入
+///
+/// 验证：
+/// - 导入：本地仓库的每个提交按作者重放为命令，增删改文件对应 block 的创建、写入和删除
+/// - 导入：二进制文件跳过并报告原因
+/// - 导入：提交作者只在导入的目录及其文件上获得权限
+/// - 导出：每个命令一个提交，作者为命令的 editor，文件布局与 checkout 一致
+/// - 导出：只包含导出者能读的 block 及其历史
+/// - 导入再导出后，最后一个提交的文件与原仓库一致
+use elfiee_lib::engine::{spawn_engine, EventStore};
+use elfiee_lib::export;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// 辅助函数：在仓库中运行 git 并返回标准输出
+fn git(repo: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["-c", "commit.gpgsign=false"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?}: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// 辅助函数：以指定作者提交当前工作区
+fn commit(repo: &Path, author: &str, email: &str, message: &str) {
+    let author = format!("{} <{}>", author, email);
+    git(repo, &["add", "-A"]);
+    git(
+        repo,
+        &[
+            "-c",
+            "user.name=committer",
+            "-c",
+            "user.email=committer@example.com",
+            "commit",
+            "-q",
+            "--author",
+            &author,
+            "-m",
+            message,
+        ],
+    );
+}
+
+#[tokio::test]
+async fn test_import_then_export_git() {
+    // 源仓库：三个提交，两个作者
+    let source_dir = TempDir::new().unwrap();
+    let source = source_dir.path().join("notes");
+    fs::create_dir(&source).unwrap();
+    git(&source, &["init", "-q"]);
+
+    fs::write(source.join("README.md"), "# Notes\n").unwrap();
+    fs::create_dir_all(source.join("src/bin")).unwrap();
+    fs::write(source.join("src/bin/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(source.join("logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+    commit(&source, "Alice", "alice@example.com", "initial");
+
+    fs::write(source.join("README.md"), "# Notes\n\nMore.\n").unwrap();
+    fs::write(source.join("todo.md"), "- write\n").unwrap();
+    commit(&source, "Bob", "bob@example.com", "edit");
+
+    fs::remove_file(source.join("todo.md")).unwrap();
+    commit(&source, "Alice", "alice@example.com", "done");
+
+    // 导入
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_git".to_string(), event_pool)
+        .await
+        .unwrap();
+    let report = export::import_git(&handle, &source, "owner").await.unwrap();
+    assert_eq!(report.commits, 3);
+    assert_eq!(report.blocks, 3);
+    assert_eq!(report.skipped, vec!["logo.png: binary file"]);
+
+    let editors = handle.get_all_editors().await;
+    assert_eq!(editors["alice@example.com"].name, "Alice");
+    assert_eq!(editors["bob@example.com"].name, "Bob");
+
+    let blocks = handle.get_all_blocks().await;
+    let dir = blocks
+        .values()
+        .find(|b| b.block_type == "directory")
+        .unwrap();
+    assert_eq!(dir.name, "notes");
+    let entries = dir.contents["entries"].as_object().unwrap();
+    assert_eq!(entries["src"]["type"], "directory");
+    assert_eq!(entries["src/bin"]["type"], "directory");
+    assert!(!entries.contains_key("todo.md"));
+
+    let readme = &blocks[entries["README.md"]["id"].as_str().unwrap()];
+    assert_eq!(readme.block_type, "markdown");
+    assert_eq!(readme.contents["markdown"], "# Notes\n\nMore.\n");
+    let main = &blocks[entries["src/bin/main.rs"]["id"].as_str().unwrap()];
+    assert_eq!(main.block_type, "code");
+    assert_eq!(main.owner, "alice@example.com");
+
+    // 每个命令由提交的作者发出
+    let events = handle.get_all_events().await.unwrap();
+    assert!(events
+        .iter()
+        .any(|e| e.attribute == "bob@example.com/markdown.write"));
+    assert!(events
+        .iter()
+        .any(|e| e.attribute == "alice@example.com/core.delete"));
+
+    // 导出：每个命令一个提交
+    let target_dir = TempDir::new().unwrap();
+    let target = target_dir.path().join("export");
+    let exported = export::export_git(&handle, "owner", None, &target)
+        .await
+        .unwrap();
+    assert_eq!(exported.files, 2);
+
+    let log = git(&target, &["log", "--reverse", "--format=%an <%ae>%x09%s"]);
+    let log: Vec<&str> = log.lines().collect();
+    assert_eq!(log.len(), exported.commits);
+    assert_eq!(log[0], "owner <owner@elfiee>\teditor.create owner");
+    assert!(log.contains(&"Bob <bob@example.com>\tmarkdown.write README.md"));
+
+    let body = git(&target, &["log", "-1", "--format=%b"]);
+    assert!(body.contains("Elfiee-Editor: alice@example.com"));
+    assert!(body.contains("Elfiee-Event: "));
+
+    // 最后一个提交的文件布局：{directory block 名称}/{entry 路径}
+    let files = git(&target, &["ls-files"]);
+    assert_eq!(
+        files.lines().collect::<Vec<_>>(),
+        vec!["notes/README.md", "notes/src/bin/main.rs"]
+    );
+    assert_eq!(
+        fs::read_to_string(target.join("notes/README.md")).unwrap(),
+        "# Notes\n\nMore.\n"
+    );
+
+    // 再导入导出的仓库：作者映射回同一批 editor
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let again_handle = spawn_engine("test_git_again".to_string(), event_pool)
+        .await
+        .unwrap();
+    let again = export::import_git(&again_handle, &target, "owner")
+        .await
+        .unwrap();
+    let editors = again_handle.get_all_editors().await;
+    again_handle.shutdown().await;
+    assert_eq!(again.commits, exported.commits);
+    assert_eq!(again.blocks, 3);
+    assert!(editors.contains_key("bob@example.com"));
+
+    // 目标目录必须为空
+    assert!(export::export_git(&handle, "owner", None, &target)
+        .await
+        .is_err());
+
+    // 作者只在导入的目录及其文件上有权限
+    let cmd = elfiee_lib::models::Command::new(
+        "owner".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "private.md", "block_type": "markdown" }),
+    );
+    let private = handle.process_command(cmd).await.unwrap()[0].entity.clone();
+    let cmd = elfiee_lib::models::Command::new(
+        "bob@example.com".to_string(),
+        "markdown.write".to_string(),
+        private,
+        serde_json::json!({ "content": "bob was here" }),
+    );
+    assert!(handle.process_command(cmd).await.is_err());
+
+    // 只导出 editor 能读的 block：bob 读不到任何 block，也看不到它们的历史
+    let bob_target = target_dir.path().join("bob");
+    let bob_export = export::export_git(&handle, "bob@example.com", None, &bob_target)
+        .await
+        .unwrap();
+    handle.shutdown().await;
+    assert_eq!(bob_export.files, 0);
+    assert!(bob_export.skipped.contains(&"notes".to_string()));
+    assert!(bob_export.skipped.contains(&"private.md".to_string()));
+    let bob_log = git(&bob_target, &["log", "--format=%s"]);
+    assert!(!bob_log.contains("private.md"), "{}", bob_log);
+}
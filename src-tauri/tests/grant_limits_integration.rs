@@ -0,0 +1,211 @@
+/// 集成测试：限时 / 限次授权
+///
+/// 验证 core.grant 的 expires_at 与 max_uses 在 engine 层面的行为：
+/// - 次数用尽后授权失败，但授权仍保留（可在列表中标记为过期）
+/// - 已过期的授权不再生效，即使命令的 timestamp 被回拨
+/// - 一条命令只消耗一次，无论它产生多少事件
+/// - 非法的 expires_at / max_uses 被拒绝
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore};
+use elfiee_lib::models::Command;
+
+/// 辅助函数：创建内存 engine，并以 alice 创建一个 markdown block
+async fn setup_engine() -> (EngineHandle, String) {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_grant_limits".to_string(), event_pool)
+        .await
+        .unwrap();
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "task.md", "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    let block_id = events[0].entity.clone();
+
+    (handle, block_id)
+}
+
+/// 辅助函数：以 alice 授予 bob 带限制的 markdown.write
+async fn grant_write(
+    handle: &EngineHandle,
+    block_id: &str,
+    limits: serde_json::Value,
+) -> Result<Vec<elfiee_lib::models::Event>, String> {
+    let mut payload = serde_json::json!({
+        "target_editor": "bob",
+        "capability": "markdown.write",
+        "target_block": block_id,
+    });
+    for (key, value) in limits.as_object().unwrap() {
+        payload[key] = value.clone();
+    }
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.grant".to_string(),
+        block_id.to_string(),
+        payload,
+    );
+    handle.process_command(cmd).await
+}
+
+/// 辅助函数：以 bob 写入 markdown
+async fn bob_writes(
+    handle: &EngineHandle,
+    block_id: &str,
+) -> Result<Vec<elfiee_lib::models::Event>, String> {
+    let cmd = Command::new(
+        "bob".to_string(),
+        "markdown.write".to_string(),
+        block_id.to_string(),
+        serde_json::json!({ "content": "# Done" }),
+    );
+    handle.process_command(cmd).await
+}
+
+/// 限次授权：用完即失效，但仍保留在授权表中
+#[tokio::test]
+async fn test_usage_limited_grant() {
+    let (handle, block_id) = setup_engine().await;
+
+    grant_write(&handle, &block_id, serde_json::json!({ "max_uses": 1 }))
+        .await
+        .unwrap();
+    bob_writes(&handle, &block_id).await.unwrap();
+
+    let err = bob_writes(&handle, &block_id).await.unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+
+    let limits = handle.get_grant_limits().await;
+    let key = (
+        "bob".to_string(),
+        "markdown.write".to_string(),
+        block_id.clone(),
+    );
+    assert_eq!(limits[&key].uses, 1);
+    assert_eq!(handle.get_block_grants(block_id).await.len(), 1);
+
+    handle.shutdown().await;
+}
+
+/// 限时授权：过期后不再生效
+#[tokio::test]
+async fn test_time_limited_grant() {
+    let (handle, block_id) = setup_engine().await;
+
+    grant_write(
+        &handle,
+        &block_id,
+        serde_json::json!({ "expires_at": "2000-01-01T00:00:00Z" }),
+    )
+    .await
+    .unwrap();
+    assert!(bob_writes(&handle, &block_id).await.is_err());
+
+    grant_write(
+        &handle,
+        &block_id,
+        serde_json::json!({ "expires_at": "2999-01-01T00:00:00Z" }),
+    )
+    .await
+    .unwrap();
+    bob_writes(&handle, &block_id).await.unwrap();
+
+    handle.shutdown().await;
+}
+
+/// 过期按 engine 的时钟判断，回拨命令的 timestamp 无法绕过
+#[tokio::test]
+async fn test_backdated_command_cannot_use_expired_grant() {
+    let (handle, block_id) = setup_engine().await;
+
+    grant_write(
+        &handle,
+        &block_id,
+        serde_json::json!({ "expires_at": "2000-01-01T00:00:00Z" }),
+    )
+    .await
+    .unwrap();
+
+    let mut cmd = Command::new(
+        "bob".to_string(),
+        "markdown.write".to_string(),
+        block_id.clone(),
+        serde_json::json!({ "content": "# Done" }),
+    );
+    cmd.timestamp = "1999-01-01T00:00:00Z".parse().unwrap();
+    let err = handle.process_command(cmd).await.unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+
+    handle.shutdown().await;
+}
+
+/// 一条产生多个事件的命令只消耗一次
+#[tokio::test]
+async fn test_command_with_many_events_uses_grant_once() {
+    let (handle, _) = setup_engine().await;
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "docs", "block_type": "directory" }),
+    );
+    let dir_id = handle.process_command(cmd).await.unwrap()[0].entity.clone();
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.grant".to_string(),
+        dir_id.clone(),
+        serde_json::json!({
+            "target_editor": "bob",
+            "capability": "directory.create",
+            "target_block": dir_id,
+            "max_uses": 2,
+        }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    // directory.create 产生 core.create 与 directory.write 两个事件
+    let cmd = Command::new(
+        "bob".to_string(),
+        "directory.create".to_string(),
+        dir_id.clone(),
+        serde_json::json!({ "path": "a.md", "type": "file", "source": "outline" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    assert!(events.len() > 1);
+
+    let key = ("bob".to_string(), "directory.create".to_string(), dir_id);
+    assert_eq!(handle.get_grant_limits().await[&key].uses, 1);
+
+    // 重放得到同样的次数
+    let events = handle.get_all_events().await.unwrap();
+    let mut state = elfiee_lib::engine::StateProjector::new();
+    state.replay(events);
+    assert_eq!(state.grants.all_limits()[&key].uses, 1);
+
+    handle.shutdown().await;
+}
+
+/// 非法的限制参数被拒绝
+#[tokio::test]
+async fn test_invalid_limits_rejected() {
+    let (handle, block_id) = setup_engine().await;
+
+    let err = grant_write(
+        &handle,
+        &block_id,
+        serde_json::json!({ "expires_at": "tomorrow" }),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.contains("Invalid expires_at"), "got: {}", err);
+
+    let err = grant_write(&handle, &block_id, serde_json::json!({ "max_uses": 0 }))
+        .await
+        .unwrap_err();
+    assert!(err.contains("max_uses"), "got: {}", err);
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,172 @@
+/// 集成测试：导出静态 HTML 站点
+///
+/// 验证：
+/// - markdown 渲染为 HTML，code 带行号且转义
+/// - directory 的 entries 成为导航，implement 关系成为双向链接
+/// - 每个 block 有历史页，列出它的事件
+/// - 导出者没有读权限的 block 不导出
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore};
+use elfiee_lib::export;
+use elfiee_lib::models::{Command, RELATION_IMPLEMENT};
+use std::fs;
+use tempfile::TempDir;
+
+/// 辅助函数：执行命令并返回第一个事件的 entity
+async fn run(
+    handle: &EngineHandle,
+    editor: &str,
+    cap_id: &str,
+    block_id: &str,
+    payload: serde_json::Value,
+) -> String {
+    let cmd = Command::new(
+        editor.to_string(),
+        cap_id.to_string(),
+        block_id.to_string(),
+        payload,
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    events
+        .iter()
+        .find(|e| e.attribute.ends_with("/core.create"))
+        .unwrap_or(&events[0])
+        .entity
+        .clone()
+}
+
+#[tokio::test]
+async fn test_export_site() {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_html".to_string(), event_pool)
+        .await
+        .unwrap();
+    for (editor_id, name) in [("alice", "Alice"), ("bob", "Bob")] {
+        run(
+            &handle,
+            "alice",
+            "editor.create",
+            "",
+            serde_json::json!({ "editor_id": editor_id, "name": name }),
+        )
+        .await;
+    }
+
+    // docs/spec.md (markdown) 和 main.rs (code)，spec implement main.rs
+    let dir_id = run(
+        &handle,
+        "alice",
+        "core.create",
+        "",
+        serde_json::json!({ "name": "docs", "block_type": "directory" }),
+    )
+    .await;
+    let spec_id = run(
+        &handle,
+        "alice",
+        "directory.create",
+        &dir_id,
+        serde_json::json!({
+            "path": "spec.md",
+            "type": "file",
+            "source": "outline",
+            "content": "# Spec\n\nHandle errors with **care**.",
+            "block_type": "markdown"
+        }),
+    )
+    .await;
+    let code_id = run(
+        &handle,
+        "alice",
+        "core.create",
+        "",
+        serde_json::json!({ "name": "main.rs", "block_type": "code" }),
+    )
+    .await;
+    run(
+        &handle,
+        "alice",
+        "code.write",
+        &code_id,
+        serde_json::json!({ "content": "fn main() {\n    println!(\"<hi>\");\n}" }),
+    )
+    .await;
+    run(
+        &handle,
+        "alice",
+        "core.link",
+        &spec_id,
+        serde_json::json!({ "relation": RELATION_IMPLEMENT, "target_id": code_id }),
+    )
+    .await;
+    run(
+        &handle,
+        "alice",
+        "core.create",
+        "",
+        serde_json::json!({ "name": "secret.md", "block_type": "markdown" }),
+    )
+    .await;
+
+    // bob 只能读 docs、spec.md 和 main.rs
+    for (cap, block_id) in [
+        ("core.read", &dir_id),
+        ("markdown.read", &spec_id),
+        ("code.read", &code_id),
+    ] {
+        run(
+            &handle,
+            "alice",
+            "core.grant",
+            block_id,
+            serde_json::json!({
+                "target_editor": "bob",
+                "capability": cap,
+                "target_block": block_id,
+            }),
+        )
+        .await;
+    }
+
+    let target = TempDir::new().unwrap();
+    let report = export::export_site(&handle, "bob", target.path())
+        .await
+        .unwrap();
+    handle.shutdown().await;
+    assert_eq!(report.blocks, 3);
+    assert_eq!(report.skipped, vec!["secret.md"]);
+
+    let read = |path: String| fs::read_to_string(target.path().join(path)).unwrap();
+    let index = read("index.html".to_string());
+    assert!(index.contains(&format!("href=\"blocks/{}.html\">spec.md</a>", spec_id)));
+    assert!(!index.contains("secret.md"));
+    assert!(target.path().join("style.css").exists());
+
+    // markdown 页：渲染结果、导航和 implement 链接
+    let spec = read(format!("blocks/{}.html", spec_id));
+    assert!(spec.contains("<h1>Spec</h1>"), "{}", spec);
+    assert!(spec.contains("<strong>care</strong>"));
+    assert!(spec.contains(&format!(
+        "<a href=\"../blocks/{}.html\">spec.md</a>",
+        spec_id
+    )));
+    assert!(spec.contains("<h2>Implements</h2>"));
+    assert!(spec.contains(&format!(
+        "<a href=\"../blocks/{}.html\">main.rs</a>",
+        code_id
+    )));
+    assert!(spec.contains(&format!("../history/{}.html", spec_id)));
+
+    // code 页：行号、转义和反向链接
+    let code = read(format!("blocks/{}.html", code_id));
+    assert!(code.contains("<span class=\"ln\">2</span>    println!(&quot;&lt;hi&gt;&quot;);"));
+    assert!(code.contains("<h2>Implemented by</h2>"));
+
+    // 历史页：事件、editor 名称和能力
+    let history = read(format!("history/{}.html", code_id));
+    assert!(
+        history.contains("<td>Alice</td><td>code.write</td>"),
+        "{}",
+        history
+    );
+    assert!(history.contains("<td>core.create</td>"));
+}
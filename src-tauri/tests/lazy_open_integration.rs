@@ -0,0 +1,133 @@
+/// 集成测试：按需解压 .elf 中的 block 目录
+///
+/// 验证：
+/// - 打开文件时只解压 events.db，block 目录留在 zip 中
+/// - engine 访问 block 时解压它的目录
+/// - 保存时未解压的 block 目录原样从旧文件复制，内容不丢失
+/// - block_dir 只解压一个 block 的目录，temp_path 解压其余全部
+/// - 被 purge 的 block 目录不会在保存时被复制回来
+use elfiee_lib::elf::ElfArchive;
+use elfiee_lib::engine::{spawn_engine, EngineHandle};
+use elfiee_lib::models::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// 辅助函数：打开 .elf 文件并启动 engine
+async fn open_engine(path: &Path) -> (ElfArchive, EngineHandle) {
+    let archive = ElfArchive::open(path).unwrap();
+    let event_pool = archive.event_pool().await.unwrap();
+    let handle = spawn_engine("test_lazy".to_string(), event_pool)
+        .await
+        .unwrap();
+    (archive, handle)
+}
+
+/// 辅助函数：以 system 执行命令
+async fn run(handle: &EngineHandle, cap_id: &str, block_id: &str, payload: serde_json::Value) {
+    let cmd = Command::new(
+        "system".to_string(),
+        cap_id.to_string(),
+        block_id.to_string(),
+        payload,
+    );
+    handle.process_command(cmd).await.unwrap();
+}
+
+/// 辅助函数：创建两个带内容的 markdown block，保存后返回它们的 id
+async fn create_project(path: &Path) -> (String, String) {
+    ElfArchive::new().await.unwrap().save(path).await.unwrap();
+    let (archive, handle) = open_engine(path).await;
+    run(
+        &handle,
+        "editor.create",
+        "system",
+        serde_json::json!({ "editor_id": "system", "name": "System" }),
+    )
+    .await;
+
+    let mut ids = Vec::new();
+    for name in ["a.md", "b.md"] {
+        let cmd = Command::new(
+            "system".to_string(),
+            "core.create".to_string(),
+            "".to_string(),
+            serde_json::json!({ "name": name, "block_type": "markdown" }),
+        );
+        let block_id = handle.process_command(cmd).await.unwrap()[0].entity.clone();
+        run(
+            &handle,
+            "markdown.write",
+            &block_id,
+            serde_json::json!({ "content": format!("# {}", name) }),
+        )
+        .await;
+        ids.push(block_id);
+    }
+    handle.shutdown().await;
+    archive.save(path).await.unwrap();
+    (ids.remove(0), ids.remove(0))
+}
+
+#[tokio::test]
+async fn test_block_dirs_are_extracted_on_access_and_copied_on_save() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    let (a, b) = create_project(&path).await;
+
+    let (archive, handle) = open_engine(&path).await;
+    let temp = archive.extracted_path().to_path_buf();
+    assert!(temp.join("events.db").exists());
+    assert!(!temp.join(format!("block-{}", a)).exists());
+    assert!(!temp.join(format!("block-{}", b)).exists());
+
+    // Accessing a block extracts its directory, and only that one
+    handle.get_block(a.clone()).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(temp.join(format!("block-{}/body.md", a))).unwrap(),
+        "# a.md"
+    );
+    assert!(!temp.join(format!("block-{}", b)).exists());
+
+    // Saving keeps the block that was never extracted
+    archive.save(&path).await.unwrap();
+    handle.shutdown().await;
+
+    let reopened = ElfArchive::open(&path).unwrap();
+    assert!(reopened.contains(&format!("block-{}", b)));
+    assert!(!reopened
+        .extracted_path()
+        .join(format!("block-{}", a))
+        .exists());
+    let block_dir = reopened.block_dir(&b).unwrap();
+    assert_eq!(
+        fs::read_to_string(block_dir.join("body.md")).unwrap(),
+        "# b.md"
+    );
+    assert!(!reopened
+        .extracted_path()
+        .join(format!("block-{}", a))
+        .exists());
+
+    // temp_path extracts whatever is still zipped, for callers that read it directly
+    assert_eq!(
+        fs::read_to_string(reopened.temp_path().join(format!("block-{}/body.md", a))).unwrap(),
+        "# a.md"
+    );
+}
+
+#[tokio::test]
+async fn test_purged_block_dir_is_not_copied_back() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("project.elf");
+    let (a, _b) = create_project(&path).await;
+
+    let (archive, handle) = open_engine(&path).await;
+    run(&handle, "core.delete", &a, serde_json::json!({})).await;
+    run(&handle, "core.purge", &a, serde_json::json!({})).await;
+    archive.save(&path).await.unwrap();
+    handle.shutdown().await;
+
+    let reopened = ElfArchive::open(&path).unwrap();
+    assert!(!reopened.contains(&format!("block-{}", a)));
+}
@@ -0,0 +1,360 @@
+/// 集成测试：directory block 与链接文件夹的双向同步
+///
+/// 验证：
+/// - 刷新：磁盘上新增、修改、重命名、删除的文件映射为对应的命令
+/// - 推送：只有开启 push 时，Elfiee 中的修改才写回文件
+/// - 冲突：两边都修改时报告冲突，指定 prefer 后按该侧解决
+/// - 监听：LinkWatcher 定期刷新，并在有变化时回调
+/// - 扫描选项：刷新沿用导入时的扫描选项和忽略文件
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore};
+use elfiee_lib::extensions::directory::linked::{
+    refresh_linked, LinkRename, LinkSide, LinkSyncOptions, LinkWatcher,
+};
+use elfiee_lib::models::Command;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// 辅助函数：执行命令并返回第一个事件的 entity
+async fn run(
+    handle: &EngineHandle,
+    cap_id: &str,
+    block_id: &str,
+    payload: serde_json::Value,
+) -> String {
+    let cmd = Command::new(
+        "alice".to_string(),
+        cap_id.to_string(),
+        block_id.to_string(),
+        payload,
+    );
+    handle.process_command(cmd).await.unwrap()[0].entity.clone()
+}
+
+/// 辅助函数：创建 engine，并把 folder 导入为 directory block
+async fn setup(folder: &Path) -> (EngineHandle, String) {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_linked".to_string(), event_pool)
+        .await
+        .unwrap();
+    run(
+        &handle,
+        "editor.create",
+        "",
+        serde_json::json!({ "editor_id": "alice", "name": "Alice" }),
+    )
+    .await;
+    let dir_id = run(
+        &handle,
+        "core.create",
+        "",
+        serde_json::json!({ "name": "project", "block_type": "directory" }),
+    )
+    .await;
+    run(
+        &handle,
+        "directory.import",
+        &dir_id,
+        serde_json::json!({ "source_path": folder.to_string_lossy() }),
+    )
+    .await;
+    (handle, dir_id)
+}
+
+/// 辅助函数：按虚拟路径读取 block 的文本
+async fn read(handle: &EngineHandle, dir_id: &str, path: &str) -> Option<String> {
+    let dir = handle.get_block(dir_id.to_string()).await.unwrap();
+    let id = dir.contents["entries"][path]["id"].as_str()?.to_string();
+    let block = handle.get_block(id).await?;
+    block.contents["text"]
+        .as_str()
+        .or(block.contents["markdown"].as_str())
+        .map(str::to_string)
+}
+
+#[tokio::test]
+async fn test_refresh_picks_up_disk_changes() {
+    let folder = TempDir::new().unwrap();
+    fs::write(folder.path().join("a.md"), "# A").unwrap();
+    fs::write(folder.path().join("old.md"), "# Old").unwrap();
+    fs::create_dir(folder.path().join("src")).unwrap();
+    fs::write(folder.path().join("src/main.rs"), "fn main() {}").unwrap();
+    let (handle, dir_id) = setup(folder.path()).await;
+    let options = LinkSyncOptions::default();
+
+    // 没有变化时什么都不做
+    let report = refresh_linked(&handle, "alice", &dir_id, &options)
+        .await
+        .unwrap();
+    assert!(report.is_empty(), "{:?}", report);
+
+    fs::write(folder.path().join("a.md"), "# A v2").unwrap();
+    fs::rename(
+        folder.path().join("src/main.rs"),
+        folder.path().join("src/app.rs"),
+    )
+    .unwrap();
+    fs::remove_file(folder.path().join("old.md")).unwrap();
+    fs::create_dir(folder.path().join("docs")).unwrap();
+    fs::write(folder.path().join("docs/b.md"), "# B").unwrap();
+    fs::write(folder.path().join("data.db"), "sqlite").unwrap();
+
+    let report = refresh_linked(&handle, "alice", &dir_id, &options)
+        .await
+        .unwrap();
+    assert_eq!(report.changed, vec!["a.md"]);
+    assert_eq!(
+        report.renamed,
+        vec![LinkRename {
+            from: "src/main.rs".to_string(),
+            to: "src/app.rs".to_string(),
+        }]
+    );
+    assert_eq!(report.deleted, vec!["old.md"]);
+    assert_eq!(report.added, vec!["docs/b.md"]);
+    assert_eq!(report.skipped, vec!["data.db: unsupported file type"]);
+
+    assert_eq!(read(&handle, &dir_id, "a.md").await.unwrap(), "# A v2");
+    assert_eq!(
+        read(&handle, &dir_id, "src/app.rs").await.unwrap(),
+        "fn main() {}"
+    );
+    assert_eq!(read(&handle, &dir_id, "docs/b.md").await.unwrap(), "# B");
+
+    let dir = handle.get_block(dir_id.clone()).await.unwrap();
+    let entries = dir.contents["entries"].as_object().unwrap();
+    assert!(!entries.contains_key("old.md"));
+    assert_eq!(entries["docs"]["type"], "directory");
+    assert_eq!(entries["docs/b.md"]["source"], "linked");
+    assert!(entries["docs/b.md"]["hash"].is_string());
+    let renamed = entries["src/app.rs"]["id"].as_str().unwrap();
+    let block = handle.get_block(renamed.to_string()).await.unwrap();
+    assert_eq!(block.name, "app.rs");
+
+    // 删除的 block 进入回收站
+    assert!(!handle
+        .get_all_blocks()
+        .await
+        .values()
+        .any(|b| b.name == "old.md"));
+
+    // 再次刷新没有变化（hash 已记录）
+    let report = refresh_linked(&handle, "alice", &dir_id, &options)
+        .await
+        .unwrap();
+    assert_eq!(report.skipped, vec!["data.db: unsupported file type"]);
+    assert!(report.changed.is_empty() && report.added.is_empty());
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_push_and_conflicts() {
+    let folder = TempDir::new().unwrap();
+    fs::write(folder.path().join("a.md"), "# A").unwrap();
+    let (handle, dir_id) = setup(folder.path()).await;
+    let dir = handle.get_block(dir_id.clone()).await.unwrap();
+    let a_id = dir.contents["entries"]["a.md"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // 不开启 push：Elfiee 中的修改不写回
+    run(
+        &handle,
+        "markdown.write",
+        &a_id,
+        serde_json::json!({ "content": "# A edited" }),
+    )
+    .await;
+    let report = refresh_linked(&handle, "alice", &dir_id, &LinkSyncOptions::default())
+        .await
+        .unwrap();
+    assert!(report.is_empty(), "{:?}", report);
+    assert_eq!(
+        fs::read_to_string(folder.path().join("a.md")).unwrap(),
+        "# A"
+    );
+
+    // 开启 push
+    let push = LinkSyncOptions {
+        push: true,
+        prefer: None,
+    };
+    let report = refresh_linked(&handle, "alice", &dir_id, &push)
+        .await
+        .unwrap();
+    assert_eq!(report.pushed, vec!["a.md"]);
+    assert_eq!(
+        fs::read_to_string(folder.path().join("a.md")).unwrap(),
+        "# A edited"
+    );
+
+    // 两边都修改：冲突，两边都不动
+    fs::write(folder.path().join("a.md"), "# A on disk").unwrap();
+    run(
+        &handle,
+        "markdown.write",
+        &a_id,
+        serde_json::json!({ "content": "# A in Elfiee" }),
+    )
+    .await;
+    let report = refresh_linked(&handle, "alice", &dir_id, &push)
+        .await
+        .unwrap();
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].path, "a.md");
+    assert_eq!(
+        read(&handle, &dir_id, "a.md").await.unwrap(),
+        "# A in Elfiee"
+    );
+    assert_eq!(
+        fs::read_to_string(folder.path().join("a.md")).unwrap(),
+        "# A on disk"
+    );
+
+    // 指定磁盘一侧获胜
+    let prefer_disk = LinkSyncOptions {
+        push: true,
+        prefer: Some(LinkSide::Disk),
+    };
+    let report = refresh_linked(&handle, "alice", &dir_id, &prefer_disk)
+        .await
+        .unwrap();
+    assert_eq!(report.changed, vec!["a.md"]);
+    assert!(report.conflicts.is_empty());
+    assert_eq!(read(&handle, &dir_id, "a.md").await.unwrap(), "# A on disk");
+
+    // 磁盘删除、Elfiee 中修改：冲突，block 保留
+    run(
+        &handle,
+        "markdown.write",
+        &a_id,
+        serde_json::json!({ "content": "# A again" }),
+    )
+    .await;
+    fs::remove_file(folder.path().join("a.md")).unwrap();
+    let report = refresh_linked(&handle, "alice", &dir_id, &push)
+        .await
+        .unwrap();
+    assert_eq!(
+        report.conflicts[0].reason,
+        "deleted on disk, edited in Elfiee"
+    );
+    assert!(handle.get_block(a_id.clone()).await.is_some());
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_refresh_keeps_import_scan_options() {
+    let folder = TempDir::new().unwrap();
+    fs::write(folder.path().join("a.md"), "# A").unwrap();
+    fs::write(folder.path().join(".gitignore"), "*.tmp.md\n").unwrap();
+
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_linked_options".to_string(), event_pool)
+        .await
+        .unwrap();
+    run(
+        &handle,
+        "editor.create",
+        "",
+        serde_json::json!({ "editor_id": "alice", "name": "Alice" }),
+    )
+    .await;
+    let dir_id = run(
+        &handle,
+        "core.create",
+        "",
+        serde_json::json!({ "name": "project", "block_type": "directory" }),
+    )
+    .await;
+    run(
+        &handle,
+        "directory.import",
+        &dir_id,
+        serde_json::json!({
+            "source_path": folder.path().to_string_lossy(),
+            "options": { "exclude": ["drafts"] }
+        }),
+    )
+    .await;
+
+    // 被排除或被忽略的文件在刷新时也不会被当作新增
+    fs::create_dir(folder.path().join("drafts")).unwrap();
+    fs::write(folder.path().join("drafts/wip.md"), "# WIP").unwrap();
+    fs::write(folder.path().join("scratch.tmp.md"), "# tmp").unwrap();
+    fs::write(folder.path().join("b.md"), "# B").unwrap();
+
+    let report = refresh_linked(&handle, "alice", &dir_id, &LinkSyncOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(report.added, vec!["b.md"]);
+    let dir = handle.get_block(dir_id.clone()).await.unwrap();
+    let entries = dir.contents["entries"].as_object().unwrap();
+    assert!(!entries.contains_key("drafts"));
+    assert!(!entries.contains_key("scratch.tmp.md"));
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_refresh_requires_linked_folder() {
+    let folder = TempDir::new().unwrap();
+    let (handle, dir_id) = setup(folder.path()).await;
+    let outline_id = run(
+        &handle,
+        "core.create",
+        "",
+        serde_json::json!({ "name": "notes", "block_type": "directory" }),
+    )
+    .await;
+
+    let options = LinkSyncOptions::default();
+    let err = refresh_linked(&handle, "alice", &outline_id, &options)
+        .await
+        .unwrap_err();
+    assert!(err.contains("not linked"), "{}", err);
+
+    // 文件夹不见了不等于所有文件被删除
+    let path = folder.path().to_path_buf();
+    drop(folder);
+    let err = refresh_linked(&handle, "alice", &dir_id, &options)
+        .await
+        .unwrap_err();
+    assert!(err.contains(&path.to_string_lossy().to_string()), "{}", err);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_watcher_reports_changes() {
+    let folder = TempDir::new().unwrap();
+    fs::write(folder.path().join("a.md"), "# A").unwrap();
+    let (handle, dir_id) = setup(folder.path()).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = LinkWatcher::start(
+        handle.clone(),
+        "alice".to_string(),
+        dir_id.clone(),
+        LinkSyncOptions::default(),
+        Duration::from_millis(50),
+        move |report| {
+            let _ = tx.send(report);
+        },
+    );
+
+    fs::write(folder.path().join("b.md"), "# B").unwrap();
+    let report = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(report.added, vec!["b.md"]);
+    assert_eq!(read(&handle, &dir_id, "b.md").await.unwrap(), "# B");
+
+    drop(watcher);
+    handle.shutdown().await;
+}
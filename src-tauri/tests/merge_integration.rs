@@ -0,0 +1,187 @@
+/// 集成测试：合并同一项目的两个分叉副本
+///
+/// 验证 merge_archives 对两个 .elf 副本的合并：
+/// - 双方各自新增的 block 都出现在合并结果中
+/// - 双方都写过的 block 被报告为冲突，保留优先一方的内容（包括快照文件）
+/// - 已签名项目合并后，签名链仍然完整（合并者把分叉后的事件接入链中）
+/// - 没有共同历史的文件不能合并
+use elfiee_lib::elf::merge::merge_archives;
+use elfiee_lib::elf::{ConflictKind, ElfArchive, MergeSide};
+use elfiee_lib::engine::{spawn_engine, EngineHandle, KeyStore};
+use elfiee_lib::models::Command;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tempfile::TempDir;
+
+/// 辅助函数：把密钥放进临时配置目录（整个测试进程共用一个），并为 alice 登记密钥
+fn use_temp_keys() -> KeyStore {
+    static CONFIG_DIR: OnceLock<TempDir> = OnceLock::new();
+    let config_dir = CONFIG_DIR.get_or_init(|| TempDir::new().unwrap());
+    unsafe {
+        std::env::set_var(
+            "ELF_TEST_CONFIG_PATH",
+            config_dir.path().join("config.json"),
+        );
+    }
+    let keys = KeyStore::default_location().unwrap();
+    keys.enrol("alice").unwrap();
+    keys
+}
+
+/// 辅助函数：打开 .elf 文件并启动 engine
+async fn open_engine(path: &Path) -> (ElfArchive, EngineHandle) {
+    let archive = ElfArchive::open(path).unwrap();
+    let event_pool = archive.event_pool().await.unwrap();
+    let handle = spawn_engine("test_merge".to_string(), event_pool)
+        .await
+        .unwrap();
+    (archive, handle)
+}
+
+/// 辅助函数：以 alice 创建一个 markdown block
+async fn create_block(handle: &EngineHandle, name: &str) -> String {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": name, "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    events[0].entity.clone()
+}
+
+/// 辅助函数：以 alice 写入 markdown 内容
+async fn write_markdown(handle: &EngineHandle, block_id: &str, content: &str) {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "markdown.write".to_string(),
+        block_id.to_string(),
+        serde_json::json!({ "content": content }),
+    );
+    handle.process_command(cmd).await.unwrap();
+}
+
+/// 辅助函数：创建一个带共享 block 的项目（可选签名），并复制成 ours / theirs 两个副本
+async fn create_copies(dir: &TempDir, keys: &KeyStore, signed: bool) -> (PathBuf, PathBuf, String) {
+    let base = dir.path().join("base.elf");
+    ElfArchive::new().await.unwrap().save(&base).await.unwrap();
+
+    let (archive, handle) = open_engine(&base).await;
+    if signed {
+        handle.enable_signing(keys.clone()).await.unwrap();
+    }
+    let shared = create_block(&handle, "shared.md").await;
+    write_markdown(&handle, &shared, "base").await;
+    handle.shutdown().await;
+    archive.save(&base).await.unwrap();
+
+    let ours = dir.path().join("ours.elf");
+    let theirs = dir.path().join("theirs.elf");
+    std::fs::copy(&base, &ours).unwrap();
+    std::fs::copy(&base, &theirs).unwrap();
+    (ours, theirs, shared)
+}
+
+/// 辅助函数：在副本上新建一个 block 并改写共享 block，然后保存
+async fn edit_copy(path: &Path, shared: &str, new_block: &str, content: &str) -> String {
+    let (archive, handle) = open_engine(path).await;
+    let block_id = create_block(&handle, new_block).await;
+    write_markdown(&handle, shared, content).await;
+    handle.shutdown().await;
+    archive.save(path).await.unwrap();
+    block_id
+}
+
+/// 双方的新 block 都被保留；共享 block 的冲突按优先方解决
+#[tokio::test]
+async fn test_merge_divergent_copies() {
+    let keys = use_temp_keys();
+    let dir = TempDir::new().unwrap();
+    let (ours_path, theirs_path, shared) = create_copies(&dir, &keys, false).await;
+    let ours_block = edit_copy(&ours_path, &shared, "ours.md", "ours").await;
+    let theirs_block = edit_copy(&theirs_path, &shared, "theirs.md", "theirs").await;
+
+    for (prefer, expected) in [(MergeSide::Ours, "ours"), (MergeSide::Theirs, "theirs")] {
+        let ours = ElfArchive::open(&ours_path).unwrap();
+        let theirs = ElfArchive::open(&theirs_path).unwrap();
+        let (merged, report) = merge_archives(&ours, &theirs, prefer, "alice", &keys)
+            .await
+            .unwrap();
+
+        assert_eq!(report.common_events, 2);
+        assert_eq!(report.ours_events, 2);
+        assert_eq!(report.theirs_events, 2);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].kind, ConflictKind::Write);
+        assert_eq!(report.conflicts[0].entity, shared);
+        assert_eq!(report.conflicts[0].capability, "markdown.write");
+        assert_eq!(report.conflicts[0].winner, prefer);
+
+        let merged_path = dir.path().join(format!("merged-{:?}.elf", prefer));
+        merged.save(&merged_path).await.unwrap();
+
+        let (archive, handle) = open_engine(&merged_path).await;
+        assert!(handle.get_block(ours_block.clone()).await.is_some());
+        assert!(handle.get_block(theirs_block.clone()).await.is_some());
+        let block = handle.get_block(shared.clone()).await.unwrap();
+        assert_eq!(block.contents["markdown"], expected);
+        let snapshot = archive
+            .temp_path()
+            .join(format!("block-{}", shared))
+            .join("body.md");
+        assert_eq!(std::fs::read_to_string(snapshot).unwrap(), expected);
+        handle.shutdown().await;
+    }
+}
+
+/// 已签名项目的两个副本合并后，合并结果的签名链可以通过验证
+#[tokio::test]
+async fn test_merge_of_signed_copies_keeps_chain_intact() {
+    let keys = use_temp_keys();
+    let dir = TempDir::new().unwrap();
+    let (ours_path, theirs_path, shared) = create_copies(&dir, &keys, true).await;
+    edit_copy(&ours_path, &shared, "ours.md", "ours").await;
+    edit_copy(&theirs_path, &shared, "theirs.md", "theirs").await;
+
+    let ours = ElfArchive::open(&ours_path).unwrap();
+    let theirs = ElfArchive::open(&theirs_path).unwrap();
+    let (merged, report) = merge_archives(&ours, &theirs, MergeSide::Ours, "alice", &keys)
+        .await
+        .unwrap();
+    assert!(report.relinked_events > 0);
+
+    let merged_path = dir.path().join("merged.elf");
+    merged.save(&merged_path).await.unwrap();
+    let (_archive, handle) = open_engine(&merged_path).await;
+    let verify = handle.verify_events().await.unwrap();
+    assert!(verify.is_valid(), "{:?}", verify.issues);
+    assert_eq!(verify.signed_events, verify.total_events);
+    let block = handle.get_block(shared).await.unwrap();
+    assert_eq!(block.contents["markdown"], "ours");
+    handle.shutdown().await;
+}
+
+/// 没有共同历史的两个项目不能合并
+#[tokio::test]
+async fn test_merge_rejects_unrelated_projects() {
+    let keys = use_temp_keys();
+    let dir = TempDir::new().unwrap();
+    let mut paths = Vec::new();
+    for name in ["a.elf", "b.elf"] {
+        let path = dir.path().join(name);
+        ElfArchive::new().await.unwrap().save(&path).await.unwrap();
+        let (archive, handle) = open_engine(&path).await;
+        create_block(&handle, name).await;
+        handle.shutdown().await;
+        archive.save(&path).await.unwrap();
+        paths.push(path);
+    }
+
+    let a = ElfArchive::open(&paths[0]).unwrap();
+    let b = ElfArchive::open(&paths[1]).unwrap();
+    let err = merge_archives(&a, &b, MergeSide::Ours, "alice", &keys)
+        .await
+        .err()
+        .unwrap();
+    assert!(err.contains("share no history"), "{}", err);
+}
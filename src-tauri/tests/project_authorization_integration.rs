@@ -0,0 +1,188 @@
+/// 集成测试：项目级授权 (core.create / editor.create / editor.delete)
+///
+/// 验证项目级能力在 engine 层面受 CBAC 约束：
+/// - 第一个 editor 自举为项目 owner
+/// - Bot 没有项目级授权时不能创建 block、创建或删除 editor
+/// - owner 通过项目级 grant（block_id 为空，目标 PROJECT_SCOPE）授权后 Bot 才能删除 editor；
+///   旧的目标为 "*" 的项目能力授权按项目级读取
+/// - 对所有 block 的 "*" 授权不带来项目级权限
+/// - 没有 editor.create 的旧日志归第一个事件的作者所有，其他人不能自举
+/// - 项目 owner 本身不能被删除
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore};
+use elfiee_lib::models::PROJECT_SCOPE;
+use elfiee_lib::models::{Command, Event};
+
+/// 辅助函数：创建内存 engine，owner 自举，并由 owner 创建 bot 与 carol
+async fn setup_engine() -> EngineHandle {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_project_auth".to_string(), event_pool)
+        .await
+        .unwrap();
+
+    for (editor_id, editor_type) in [("owner", "Human"), ("bot", "Bot"), ("carol", "Human")] {
+        create_editor(&handle, "owner", editor_id, editor_type)
+            .await
+            .unwrap();
+    }
+
+    handle
+}
+
+/// 辅助函数：以 creator 身份创建 editor
+async fn create_editor(
+    handle: &EngineHandle,
+    creator: &str,
+    editor_id: &str,
+    editor_type: &str,
+) -> Result<Vec<Event>, String> {
+    let cmd = Command::new(
+        creator.to_string(),
+        "editor.create".to_string(),
+        "".to_string(),
+        serde_json::json!({
+            "editor_id": editor_id,
+            "name": editor_id,
+            "editor_type": editor_type,
+        }),
+    );
+    handle.process_command(cmd).await
+}
+
+/// 辅助函数：以 actor 身份删除 editor
+async fn delete_editor(
+    handle: &EngineHandle,
+    actor: &str,
+    editor_id: &str,
+) -> Result<Vec<Event>, String> {
+    let cmd = Command::new(
+        actor.to_string(),
+        "editor.delete".to_string(),
+        "".to_string(),
+        serde_json::json!({ "editor_id": editor_id }),
+    );
+    handle.process_command(cmd).await
+}
+
+/// Bot 没有项目级授权时不能管理 editor 或创建 block
+#[tokio::test]
+async fn test_bot_cannot_manage_project_without_grant() {
+    let handle = setup_engine().await;
+
+    let err = delete_editor(&handle, "bot", "carol").await.unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+    let err = delete_editor(&handle, "bot", "owner").await.unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+
+    let err = create_editor(&handle, "bot", "mallory", "Bot")
+        .await
+        .unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+
+    let create = Command::new(
+        "bot".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "notes.md", "block_type": "markdown" }),
+    );
+    assert!(handle.process_command(create).await.is_err());
+
+    assert_eq!(handle.get_all_editors().await.len(), 3);
+
+    handle.shutdown().await;
+}
+
+/// owner 授予项目级 editor.delete 后 Bot 可以删除 editor，但不能删除项目 owner
+#[tokio::test]
+async fn test_project_grant_allows_editor_delete() {
+    let handle = setup_engine().await;
+
+    let grant = Command::new(
+        "owner".to_string(),
+        "core.grant".to_string(),
+        "".to_string(),
+        serde_json::json!({
+            "target_editor": "bot",
+            "capability": "editor.delete",
+            "target_block": "*",
+        }),
+    );
+    handle.process_command(grant).await.unwrap();
+
+    // 项目级 grant 只覆盖被授予的能力
+    assert!(create_editor(&handle, "bot", "mallory", "Bot")
+        .await
+        .is_err());
+
+    delete_editor(&handle, "bot", "carol").await.unwrap();
+    assert!(!handle.get_all_editors().await.contains_key("carol"));
+
+    let err = delete_editor(&handle, "bot", "owner").await.unwrap_err();
+    assert!(err.contains("project owner"), "got: {}", err);
+
+    handle.shutdown().await;
+}
+
+/// 对所有 block 的授权（目标 "*"）不覆盖项目，项目级授权需要 PROJECT_SCOPE
+#[tokio::test]
+async fn test_wildcard_block_grant_is_not_project_authority() {
+    let handle = setup_engine().await;
+
+    let grant = |capability: &str, target: &str| {
+        Command::new(
+            "owner".to_string(),
+            "core.grant".to_string(),
+            "".to_string(),
+            serde_json::json!({
+                "target_editor": "bot",
+                "capability": capability,
+                "target_block": target,
+            }),
+        )
+    };
+    handle
+        .process_command(grant("editor.*", "*"))
+        .await
+        .unwrap();
+    let err = create_editor(&handle, "bot", "mallory", "Bot")
+        .await
+        .unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+
+    handle
+        .process_command(grant("editor.create", PROJECT_SCOPE))
+        .await
+        .unwrap();
+    create_editor(&handle, "bot", "mallory", "Bot")
+        .await
+        .unwrap();
+
+    handle.shutdown().await;
+}
+
+/// 没有 editor.create 事件的日志归第一个事件的作者所有
+#[tokio::test]
+async fn test_log_without_editors_is_owned_by_first_author() {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_legacy_owner".to_string(), event_pool)
+        .await
+        .unwrap();
+
+    let create = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "notes.md", "block_type": "markdown" }),
+    );
+    handle.process_command(create).await.unwrap();
+
+    // 其他人不能再借自举创建 editor 并给自己授权
+    let err = create_editor(&handle, "mallory", "mallory", "Human")
+        .await
+        .unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+    create_editor(&handle, "alice", "alice", "Human")
+        .await
+        .unwrap();
+
+    handle.shutdown().await;
+}
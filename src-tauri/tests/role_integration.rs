@@ -0,0 +1,112 @@
+/// 集成测试：基于角色的授权
+///
+/// 验证 core.define_role / core.grant_role 在 engine 层面的行为：
+/// - 被授予角色的 editor 获得角色包含的全部 capability
+/// - 重新定义角色会立即影响所有持有者
+/// - 不能授予未定义的角色
+use elfiee_lib::engine::{spawn_engine, EngineHandle, EventStore};
+use elfiee_lib::models::Command;
+
+/// 辅助函数：创建内存 engine，并以 alice 创建一个 markdown block
+async fn setup_engine() -> (EngineHandle, String) {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine("test_roles".to_string(), event_pool)
+        .await
+        .unwrap();
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "notes.md", "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    let block_id = events[0].entity.clone();
+
+    (handle, block_id)
+}
+
+/// 辅助函数：以 alice 定义角色
+async fn define_role(handle: &EngineHandle, name: &str, capabilities: &[&str]) {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.define_role".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": name, "capabilities": capabilities }),
+    );
+    handle.process_command(cmd).await.unwrap();
+}
+
+/// 辅助函数：以 alice 在 block 上授予角色
+async fn grant_role(
+    handle: &EngineHandle,
+    block_id: &str,
+    editor: &str,
+    role: &str,
+) -> Result<Vec<elfiee_lib::models::Event>, String> {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.grant_role".to_string(),
+        block_id.to_string(),
+        serde_json::json!({
+            "target_editor": editor,
+            "role": role,
+            "target_block": block_id,
+        }),
+    );
+    handle.process_command(cmd).await
+}
+
+/// 辅助函数：以 bob 写入 markdown
+async fn bob_writes(
+    handle: &EngineHandle,
+    block_id: &str,
+) -> Result<Vec<elfiee_lib::models::Event>, String> {
+    let cmd = Command::new(
+        "bob".to_string(),
+        "markdown.write".to_string(),
+        block_id.to_string(),
+        serde_json::json!({ "content": "# From bob" }),
+    );
+    handle.process_command(cmd).await
+}
+
+/// 授予角色后 editor 获得角色中的 capability，重新定义角色后随之变化
+#[tokio::test]
+async fn test_role_grant_and_redefine() {
+    let (handle, block_id) = setup_engine().await;
+
+    define_role(&handle, "writer", &["markdown.write"]).await;
+    assert!(bob_writes(&handle, &block_id).await.is_err());
+
+    grant_role(&handle, &block_id, "bob", "writer")
+        .await
+        .unwrap();
+    bob_writes(&handle, &block_id).await.unwrap();
+
+    // 重新定义角色，去掉写权限
+    define_role(&handle, "writer", &["markdown.read"]).await;
+    let err = bob_writes(&handle, &block_id).await.unwrap_err();
+    assert!(err.contains("Authorization failed"), "got: {}", err);
+
+    let assignments = handle.get_role_assignments().await;
+    assert_eq!(assignments.len(), 1);
+    assert_eq!(assignments[0].editor_id, "bob");
+    assert_eq!(assignments[0].role, "writer");
+
+    handle.shutdown().await;
+}
+
+/// 不能授予未定义的角色
+#[tokio::test]
+async fn test_grant_unknown_role_fails() {
+    let (handle, block_id) = setup_engine().await;
+
+    let err = grant_role(&handle, &block_id, "bob", "ghost")
+        .await
+        .unwrap_err();
+    assert!(err.contains("Unknown role"), "got: {}", err);
+    assert!(handle.get_role_assignments().await.is_empty());
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,166 @@
+/// 集成测试：签名事件 (hash chain + per-editor 签名)
+///
+/// 验证 engine 层面的防篡改历史：
+/// - 启用签名后，每个持久化的事件都链接上一个事件的 hash 并带有签名
+/// - 启用签名后创建的 editor 自动登记密钥，未登记的 editor 不能签名
+/// - 直接修改 events.db 中的事件会被 verify 报告
+/// - 重新打开已签名的文件后继续签名，链不中断
+use elfiee_lib::engine::{
+    spawn_engine, EngineHandle, EventPoolWithPath, EventStore, KeyStore, VerifyIssueKind,
+};
+use elfiee_lib::models::Command;
+use tempfile::TempDir;
+
+/// 辅助函数：在临时目录中创建基于文件的 engine
+async fn open_engine(dir: &TempDir) -> (EngineHandle, EventPoolWithPath) {
+    let db_path = dir.path().join("events.db");
+    let event_pool = EventStore::create(db_path.to_str().unwrap()).await.unwrap();
+    let handle = spawn_engine("test_signed_events".to_string(), event_pool.clone())
+        .await
+        .unwrap();
+    (handle, event_pool)
+}
+
+/// 辅助函数：alice 自举为项目 owner
+async fn create_alice(handle: &EngineHandle) {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "editor.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "editor_id": "alice", "name": "Alice" }),
+    );
+    handle.process_command(cmd).await.unwrap();
+}
+
+/// 辅助函数：以 alice 创建一个 markdown block 并写入内容
+async fn create_and_write(handle: &EngineHandle, content: &str) -> String {
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "prd.md", "block_type": "markdown" }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    let block_id = events[0].entity.clone();
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "markdown.write".to_string(),
+        block_id.clone(),
+        serde_json::json!({ "content": content }),
+    );
+    let events = handle.process_command(cmd).await.unwrap();
+    assert!(events[0].signature.is_some());
+
+    block_id
+}
+
+/// 篡改 events.db 中已签名的事件会被 verify 发现
+#[tokio::test]
+async fn test_tampered_event_is_reported() {
+    let dir = TempDir::new().unwrap();
+    let keys_dir = TempDir::new().unwrap();
+    let (handle, event_pool) = open_engine(&dir).await;
+
+    handle
+        .enable_signing(KeyStore::new(keys_dir.path().to_path_buf()))
+        .await
+        .unwrap();
+    create_alice(&handle).await;
+    create_and_write(&handle, "# Approved").await;
+    assert!(keys_dir.path().join("alice.key").exists());
+
+    let report = handle.verify_events().await.unwrap();
+    assert!(report.is_valid(), "{:?}", report.issues);
+    assert_eq!(report.signed_events, report.total_events);
+
+    // 绕过 engine 直接改写 markdown.write 事件
+    sqlx::query(
+        "UPDATE events SET value = '{\"contents\":{\"markdown\":\"# Rejected\"}}'
+         WHERE attribute = 'alice/markdown.write'",
+    )
+    .execute(&event_pool.pool)
+    .await
+    .unwrap();
+
+    let report = handle.verify_events().await.unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].kind, VerifyIssueKind::HashMismatch);
+
+    handle.shutdown().await;
+}
+
+/// 重新打开已签名的文件后继续签名，使用 ~/.elf/keys 中的同一把密钥
+#[tokio::test]
+async fn test_reopened_file_keeps_signing() {
+    let dir = TempDir::new().unwrap();
+    let config_dir = TempDir::new().unwrap();
+    unsafe {
+        std::env::set_var(
+            "ELF_TEST_CONFIG_PATH",
+            config_dir.path().join("config.json"),
+        );
+    }
+
+    let (handle, _) = open_engine(&dir).await;
+    handle
+        .enable_signing(KeyStore::default_location().unwrap())
+        .await
+        .unwrap();
+    create_alice(&handle).await;
+    create_and_write(&handle, "# v1").await;
+    handle.shutdown().await;
+    assert!(config_dir.path().join("keys").join("alice.key").exists());
+
+    let (handle, _) = open_engine(&dir).await;
+    create_and_write(&handle, "# v2").await;
+
+    let report = handle.verify_events().await.unwrap();
+    assert!(report.is_valid(), "{:?}", report.issues);
+    assert_eq!(report.signed_events, 5);
+
+    handle.shutdown().await;
+}
+
+/// 只有已登记密钥的 editor 能签名，签名时不会悄悄生成密钥
+#[tokio::test]
+async fn test_unenrolled_editor_cannot_sign() {
+    let dir = TempDir::new().unwrap();
+    let keys_dir = TempDir::new().unwrap();
+    let (handle, _) = open_engine(&dir).await;
+
+    create_alice(&handle).await;
+    handle
+        .enable_signing(KeyStore::new(keys_dir.path().to_path_buf()))
+        .await
+        .unwrap();
+    assert!(keys_dir.path().join("alice.key").exists());
+
+    // mallory 有项目级 core.create 授权，但没有登记密钥
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.grant".to_string(),
+        "".to_string(),
+        serde_json::json!({
+            "target_editor": "mallory",
+            "capability": "core.create",
+            "target_block": elfiee_lib::models::PROJECT_SCOPE,
+        }),
+    );
+    handle.process_command(cmd).await.unwrap();
+
+    let cmd = Command::new(
+        "mallory".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "x.md", "block_type": "markdown" }),
+    );
+    let err = handle.process_command(cmd).await.unwrap_err();
+    assert!(err.contains("no enrolled signing key"), "got: {}", err);
+    assert!(!keys_dir.path().join("mallory.key").exists());
+
+    let report = handle.verify_events().await.unwrap();
+    assert!(report.is_valid(), "{:?}", report.issues);
+
+    handle.shutdown().await;
+}
@@ -21,7 +21,7 @@ async fn setup_engine() -> (
     let elf_path = temp_elf.path().to_path_buf();
 
     let archive = ElfArchive::new().await.unwrap();
-    archive.save(&elf_path).unwrap();
+    archive.save(&elf_path).await.unwrap();
 
     let archive = ElfArchive::open(&elf_path).unwrap();
     let event_pool = archive.event_pool().await.unwrap();
@@ -449,7 +449,7 @@ async fn test_snapshot_persists_after_save_and_reopen() {
     handle.process_command(cmd).await.unwrap();
 
     // 保存并关闭
-    archive.save(&elf_path).unwrap();
+    archive.save(&elf_path).await.unwrap();
     handle.shutdown().await;
 
     // 重新打开
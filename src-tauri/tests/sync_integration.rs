@@ -0,0 +1,366 @@
+/// 集成测试：运行中实例之间的事件同步
+///
+/// 验证同一进程内两个已签名的 engine 通过回环地址同步：
+/// - 只有知道同步密钥的对端才能同步
+/// - 新加入的实例收到全部事件，之后只交换对方缺少的事件（按事件 id）
+/// - 收到的事件按作者的可信密钥验证签名，按本地授权重新检查（CBAC），
+///   并由接收方接入本地签名链
+/// - 没有权限、签名无效或没有签名的事件被拒绝
+/// - 一条命令的事件一起检查：删除带出的解除链接随删除一起被接受
+/// - 不同项目的实例拒绝同步
+use elfiee_lib::engine::{
+    spawn_engine, EngineHandle, EventSigner, EventStore, KeyStore, TrashedBlock,
+};
+use elfiee_lib::models::{Command, Event};
+use elfiee_lib::sync::{self, SyncMessage, SyncServer};
+use std::collections::HashMap;
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+const SECRET: &str = "correct horse battery";
+
+/// 辅助函数：创建内存 engine，用 keys 签名
+async fn new_engine(name: &str, keys: &TempDir) -> EngineHandle {
+    let event_pool = EventStore::create(":memory:").await.unwrap();
+    let handle = spawn_engine(name.to_string(), event_pool).await.unwrap();
+    handle.enable_signing(key_store(keys)).await.unwrap();
+    handle
+}
+
+/// 辅助函数：临时目录中的密钥库
+fn key_store(dir: &TempDir) -> KeyStore {
+    KeyStore::new(dir.path().to_path_buf())
+}
+
+/// 辅助函数：editor 在 from 中登记密钥，to 信任它的公钥
+fn trust(from: &TempDir, to: &TempDir, editor_id: &str) {
+    key_store(from).enrol(editor_id).unwrap();
+    let public_key = key_store(from).public_key(editor_id).unwrap().unwrap();
+    key_store(to).trust(editor_id, &public_key).unwrap();
+}
+
+/// 辅助函数：alice 自举并创建 bob，创建 markdown block 并授予 bob 写权限
+async fn setup_project(handle: &EngineHandle) -> String {
+    for editor_id in ["alice", "bob"] {
+        let cmd = Command::new(
+            "alice".to_string(),
+            "editor.create".to_string(),
+            "".to_string(),
+            serde_json::json!({ "editor_id": editor_id, "name": editor_id }),
+        );
+        handle.process_command(cmd).await.unwrap();
+    }
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.create".to_string(),
+        "".to_string(),
+        serde_json::json!({ "name": "notes.md", "block_type": "markdown" }),
+    );
+    let block_id = handle.process_command(cmd).await.unwrap()[0].entity.clone();
+
+    let cmd = Command::new(
+        "alice".to_string(),
+        "core.grant".to_string(),
+        block_id.clone(),
+        serde_json::json!({
+            "target_editor": "bob",
+            "capability": "markdown.write",
+            "target_block": block_id,
+        }),
+    );
+    handle.process_command(cmd).await.unwrap();
+    block_id
+}
+
+/// 辅助函数：写入 markdown 内容
+async fn write_markdown(
+    handle: &EngineHandle,
+    editor_id: &str,
+    block_id: &str,
+    content: &str,
+) -> Result<Vec<Event>, String> {
+    let cmd = Command::new(
+        editor_id.to_string(),
+        "markdown.write".to_string(),
+        block_id.to_string(),
+        serde_json::json!({ "content": content }),
+    );
+    handle.process_command(cmd).await
+}
+
+/// 两个 engine 通过回环地址双向同步，签名链在两边都保持完整
+#[tokio::test]
+async fn test_sync_between_two_engines_over_loopback() {
+    // alice 在 A 上签名，bob 在 B 上签名，双方互相信任对方的公钥
+    let keys_a = TempDir::new().unwrap();
+    let keys_b = TempDir::new().unwrap();
+    trust(&keys_a, &keys_b, "alice");
+    trust(&keys_b, &keys_a, "bob");
+
+    let a = new_engine("a", &keys_a).await;
+    let b = new_engine("b", &keys_b).await;
+    let block_id = setup_project(&a).await;
+    let a_events = a.get_all_events().await.unwrap().len();
+
+    let server = SyncServer::start(
+        a.clone(),
+        "127.0.0.1:0".parse().unwrap(),
+        SECRET.to_string(),
+        "alice".to_string(),
+    )
+    .await
+    .unwrap();
+    let addr = server.local_addr().to_string();
+
+    // 不知道密钥的对端什么也拿不到
+    let err = sync::connect(&b, &addr, "wrong secret", "bob")
+        .await
+        .unwrap_err();
+    assert!(err.contains("sync secret"), "{}", err);
+    assert!(b.get_all_events().await.unwrap().is_empty());
+
+    // B 加入：收到 A 的全部事件
+    let report = sync::connect(&b, &addr, SECRET, "bob").await.unwrap();
+    assert_eq!(report.sent, 0);
+    assert_eq!(report.received, a_events);
+    assert_eq!(report.applied, a_events);
+    assert!(report.rejected.is_empty(), "{:?}", report.rejected);
+    assert_eq!(b.get_all_events().await.unwrap().len(), a_events);
+
+    // bob 在 B 上写入（授权来自同步过来的 grant），再同步给 A
+    write_markdown(&b, "bob", &block_id, "from bob")
+        .await
+        .unwrap();
+    let report = sync::connect(&b, &addr, SECRET, "bob").await.unwrap();
+    assert_eq!(report.sent, 1);
+    assert_eq!(report.received, 0);
+
+    // 服务端在后台处理会话，等它把事件应用完
+    for _ in 0..50 {
+        if a.get_all_events().await.unwrap().len() > a_events {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let block = a.get_block(block_id.clone()).await.unwrap();
+    assert_eq!(block.contents["markdown"], "from bob");
+    let last = a.get_all_events().await.unwrap().pop().unwrap();
+    assert!(last.attribute.starts_with("bob/"), "{}", last.attribute);
+
+    // 两边的签名链都完整：收到的事件由接收方接入链中，保留作者的签名
+    for handle in [&a, &b] {
+        let report = handle.verify_events().await.unwrap();
+        assert!(report.is_valid(), "{:?}", report.issues);
+        assert_eq!(report.signed_events, report.total_events);
+    }
+    let signature = last.signature.unwrap();
+    assert_eq!(signature.signer, "alice");
+    assert_eq!(signature.origin.unwrap().signer, "bob");
+
+    // 已经同步过的事件不会再发送
+    let report = sync::connect(&b, &addr, SECRET, "bob").await.unwrap();
+    assert_eq!(report.sent, 0);
+    assert_eq!(report.received, 0);
+}
+
+/// 对端发来的事件按签名和本地授权检查：没有权限、被篡改或没有签名的事件被拒绝
+#[tokio::test]
+async fn test_sync_rejects_unauthorized_and_unsigned_events() {
+    let keys_a = TempDir::new().unwrap();
+    let keys_peer = TempDir::new().unwrap();
+    trust(&keys_peer, &keys_a, "bob");
+    trust(&keys_peer, &keys_a, "mallory");
+
+    let a = new_engine("a", &keys_a).await;
+    let block_id = setup_project(&a).await;
+
+    let content = |text: &str| serde_json::json!({ "contents": { "markdown": text } });
+    // 每个事件是 bob 的一条单独命令（向量时钟不同）
+    let write = |editor_id: &str, text: &str, count: i64| {
+        Event::new(
+            block_id.clone(),
+            format!("{}/markdown.write", editor_id),
+            content(text),
+            HashMap::from([(editor_id.to_string(), count)]),
+        )
+    };
+    // mallory 的密钥可信，但在项目中没有写权限
+    let mut forged = write("mallory", "pwned", 1);
+    // bob 签名后内容被改写
+    let mut tampered = write("bob", "from bob", 1);
+    // 没有签名的事件
+    let unsigned = write("bob", "unsigned", 2);
+    let mut allowed = write("bob", "from bob", 3);
+    let mut signer = EventSigner::new(key_store(&keys_peer), String::new());
+    signer.sign(std::slice::from_mut(&mut forged)).unwrap();
+    signer.sign(std::slice::from_mut(&mut tampered)).unwrap();
+    signer.sign(std::slice::from_mut(&mut allowed)).unwrap();
+    tampered.value = content("pwned");
+
+    let rejected_ids = vec![
+        forged.event_id.clone(),
+        tampered.event_id.clone(),
+        unsigned.event_id.clone(),
+    ];
+
+    // 手写协议的对端：握手，交换清单，再发送四个事件
+    let (local, remote) = tokio::io::duplex(64 * 1024);
+    let peer = tokio::spawn(async move {
+        let (reader, mut writer) = tokio::io::split(remote);
+        let mut lines = BufReader::new(reader).lines();
+        let (mut sealer, mut opener) = sync::handshake(&mut lines, &mut writer, SECRET)
+            .await
+            .unwrap();
+        let inventory = SyncMessage::Inventory {
+            genesis: None,
+            event_ids: Vec::new(),
+        };
+        sealer.send(&mut writer, &inventory).await.unwrap();
+        opener.receive(&mut lines).await.unwrap();
+        let events = SyncMessage::Events {
+            events: vec![forged, tampered, unsigned, allowed],
+        };
+        sealer.send(&mut writer, &events).await.unwrap();
+        opener.receive(&mut lines).await.unwrap();
+        writer.shutdown().await.unwrap();
+    });
+
+    let report = sync::sync_session(&a, local, SECRET, "alice")
+        .await
+        .unwrap();
+    peer.await.unwrap();
+
+    assert_eq!(report.received, 4);
+    assert_eq!(report.applied, 1);
+    let rejected: Vec<_> = report
+        .rejected
+        .iter()
+        .map(|rejected| rejected.event_id.clone())
+        .collect();
+    assert_eq!(rejected, rejected_ids);
+    let block = a.get_block(block_id).await.unwrap();
+    assert_eq!(block.contents["markdown"], "from bob");
+    assert!(a.verify_events().await.unwrap().is_valid());
+}
+
+/// 只有子 block 的 core.delete 权限的 bob 删除它，父 block 的解除链接随删除同步，两边投影一致
+#[tokio::test]
+async fn test_sync_accepts_delete_followups_with_the_delete() {
+    let keys_a = TempDir::new().unwrap();
+    let keys_b = TempDir::new().unwrap();
+    trust(&keys_a, &keys_b, "alice");
+    trust(&keys_b, &keys_a, "bob");
+
+    let a = new_engine("a", &keys_a).await;
+    let b = new_engine("b", &keys_b).await;
+    setup_project(&a).await;
+
+    // alice 创建 parent → child，只授予 bob child 上的 core.delete
+    let mut ids = Vec::new();
+    for name in ["parent.md", "child.md"] {
+        let cmd = Command::new(
+            "alice".to_string(),
+            "core.create".to_string(),
+            "".to_string(),
+            serde_json::json!({ "name": name, "block_type": "markdown" }),
+        );
+        ids.push(a.process_command(cmd).await.unwrap()[0].entity.clone());
+    }
+    let (parent, child) = (ids[0].clone(), ids[1].clone());
+    for (cap_id, block_id, payload) in [
+        (
+            "core.link",
+            &parent,
+            serde_json::json!({ "relation": "implement", "target_id": child }),
+        ),
+        (
+            "core.grant",
+            &child,
+            serde_json::json!({
+                "target_editor": "bob",
+                "capability": "core.delete",
+                "target_block": child,
+            }),
+        ),
+    ] {
+        let cmd = Command::new(
+            "alice".to_string(),
+            cap_id.to_string(),
+            block_id.clone(),
+            payload,
+        );
+        a.process_command(cmd).await.unwrap();
+    }
+
+    let server = SyncServer::start(
+        a.clone(),
+        "127.0.0.1:0".parse().unwrap(),
+        SECRET.to_string(),
+        "alice".to_string(),
+    )
+    .await
+    .unwrap();
+    let addr = server.local_addr().to_string();
+    sync::connect(&b, &addr, SECRET, "bob").await.unwrap();
+
+    // bob 在 B 上删除 child：core.delete 加上 parent 的 core.unlink
+    let cmd = Command::new(
+        "bob".to_string(),
+        "core.delete".to_string(),
+        child.clone(),
+        serde_json::json!({}),
+    );
+    let events = b.process_command(cmd).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(events[1].attribute.ends_with("/core.unlink"));
+
+    let a_events = a.get_all_events().await.unwrap().len();
+    let report = sync::connect(&b, &addr, SECRET, "bob").await.unwrap();
+    assert_eq!(report.sent, 2);
+    for _ in 0..50 {
+        if a.get_all_events().await.unwrap().len() >= a_events + 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    // 两边的投影一致：child 在回收站，parent 不再链接它
+    assert_eq!(
+        a.get_all_events().await.unwrap().len(),
+        b.get_all_events().await.unwrap().len()
+    );
+    let blocks_a = serde_json::to_value(a.get_all_blocks().await).unwrap();
+    let blocks_b = serde_json::to_value(b.get_all_blocks().await).unwrap();
+    assert_eq!(blocks_a, blocks_b);
+    assert!(blocks_a.get(&child).is_none());
+    assert!(blocks_a[&parent]["children"].get("implement").is_none());
+    let trash = |trash: Vec<TrashedBlock>| -> Vec<String> {
+        trash.into_iter().map(|t| t.block.block_id).collect()
+    };
+    assert_eq!(trash(a.get_trash().await), trash(b.get_trash().await));
+    assert_eq!(trash(a.get_trash().await), vec![child]);
+}
+
+/// 不同项目的实例拒绝同步
+#[tokio::test]
+async fn test_sync_refuses_a_different_project() {
+    let keys = TempDir::new().unwrap();
+    let a = new_engine("a", &keys).await;
+    let b = new_engine("b", &keys).await;
+    setup_project(&a).await;
+    setup_project(&b).await;
+
+    let server = SyncServer::start(
+        a.clone(),
+        "127.0.0.1:0".parse().unwrap(),
+        SECRET.to_string(),
+        "alice".to_string(),
+    )
+    .await
+    .unwrap();
+    let err = sync::connect(&b, &server.local_addr().to_string(), SECRET, "alice")
+        .await
+        .unwrap_err();
+    assert!(err.contains("different project"), "{}", err);
+}